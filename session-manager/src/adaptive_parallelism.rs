@@ -0,0 +1,127 @@
+use log::debug;
+use std::time::Duration;
+
+/// Hill-climbing concurrency controller for file batch processing.
+///
+/// Starts at a small concurrency and after each measurement window compares
+/// the observed throughput (bytes/sec) against the previous window: if
+/// throughput improved, it keeps moving in the same direction; if it got
+/// worse, it reverses. The concurrency is always clamped to [min, max].
+///
+/// The controller is deliberately decoupled from wall-clock time: callers
+/// feed it measured byte counts and elapsed durations, which keeps the
+/// logic deterministic and testable.
+#[derive(Debug)]
+pub struct AdaptiveConcurrency {
+    min: usize,
+    max: usize,
+    current: usize,
+    step: usize,
+    last_throughput: Option<f64>,
+    // +1 when the last adjustment increased concurrency, -1 when it decreased
+    direction: i64,
+}
+
+impl AdaptiveConcurrency {
+    pub fn new(min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+        Self {
+            min,
+            max,
+            current: min,
+            step: 1,
+            last_throughput: None,
+            direction: 1,
+        }
+    }
+
+    /// Current number of concurrent file operations to use
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Record a completed measurement window and adjust the concurrency.
+    /// Returns the concurrency to use for the next window.
+    pub fn record_window(&mut self, bytes: u64, elapsed: Duration) -> usize {
+        let secs = elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return self.current;
+        }
+
+        let throughput = bytes as f64 / secs;
+
+        if let Some(previous) = self.last_throughput {
+            // Reverse direction when throughput regressed, otherwise keep climbing
+            if throughput < previous {
+                self.direction = -self.direction;
+            }
+
+            let next = self.current as i64 + self.direction * self.step as i64;
+            self.current = next.clamp(self.min as i64, self.max as i64) as usize;
+        } else {
+            // First window: ramp up from the starting concurrency
+            self.current = (self.current + self.step).min(self.max);
+        }
+
+        debug!(
+            "Adaptive concurrency window: {:.0} bytes/sec, concurrency -> {}",
+            throughput, self.current
+        );
+
+        self.last_throughput = Some(throughput);
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrency_ramps_up_with_improving_throughput() {
+        let mut controller = AdaptiveConcurrency::new(2, 16);
+        assert_eq!(controller.current(), 2);
+
+        // Deterministic fake clock/byte counter: each window is faster than
+        // the last, so the controller should keep ramping up
+        let mut bytes = 1_000_000u64;
+        for _ in 0..6 {
+            controller.record_window(bytes, Duration::from_secs(1));
+            bytes *= 2;
+        }
+
+        assert!(controller.current() > 2, "concurrency should have increased");
+        assert!(controller.current() <= 16);
+    }
+
+    #[test]
+    fn test_concurrency_backs_off_on_regression() {
+        let mut controller = AdaptiveConcurrency::new(2, 16);
+
+        // Improve, then regress: the controller should reverse direction
+        controller.record_window(10_000_000, Duration::from_secs(1));
+        controller.record_window(20_000_000, Duration::from_secs(1));
+        let before = controller.current();
+        controller.record_window(1_000_000, Duration::from_secs(1));
+
+        assert!(controller.current() < before, "concurrency should back off");
+    }
+
+    #[test]
+    fn test_concurrency_respects_bounds() {
+        let mut controller = AdaptiveConcurrency::new(1, 3);
+
+        // Many improving windows cannot push past the max
+        for i in 1..20u64 {
+            controller.record_window(i * 1_000_000, Duration::from_secs(1));
+        }
+        assert!(controller.current() <= 3);
+
+        // Many regressing windows cannot drop below the min
+        for i in (1..20u64).rev() {
+            controller.record_window(i * 1_000, Duration::from_secs(1));
+        }
+        assert!(controller.current() >= 1);
+    }
+}
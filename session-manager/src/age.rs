@@ -0,0 +1,145 @@
+//! Age-based file exclusion for `--exclude-older-than`.
+//!
+//! Stale caches untouched for months are rarely worth the backup bytes;
+//! the cutoff installed here skips any file whose mtime predates it.
+//! The cutoff is computed once at install time so a long walk applies
+//! one consistent threshold instead of a sliding "now". Directories are
+//! never excluded by age — children can be newer than their parent.
+
+use anyhow::{bail, Result};
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use crate::clock::{Clock, SYSTEM_CLOCK};
+
+/// Parse a human-readable relative duration: a number followed by `s`,
+/// `m`, `h`, `d` or `w` (`30d`, `12h`); a bare number means seconds
+pub fn parse_human_duration(spec: &str) -> Result<Duration> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        bail!("Empty duration");
+    }
+    let (digits, unit) = match spec.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => spec.split_at(split),
+        None => (spec, "s"),
+    };
+    if digits.is_empty() {
+        bail!("Duration has no numeric part: '{}'", spec);
+    }
+    let value: u64 = digits
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid duration '{}': {}", spec, e))?;
+    let seconds_per_unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        other => bail!("Unknown duration unit '{}' in '{}' (expected s, m, h, d or w)", other, spec),
+    };
+    Ok(Duration::from_secs(value * seconds_per_unit))
+}
+
+/// Globally installed mtime cutoff, set once at binary startup from
+/// `--exclude-older-than`
+static AGE_CUTOFF: Lazy<RwLock<Option<SystemTime>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install the process-wide age cutoff: files last modified more than
+/// `max_age` before now are excluded from the transfer
+pub fn install_max_age(max_age: Duration) {
+    let cutoff = SYSTEM_CLOCK
+        .now()
+        .checked_sub(max_age)
+        .unwrap_or(std::time::UNIX_EPOCH);
+    debug!("Installing age cutoff: files older than {:?} are excluded", max_age);
+    *AGE_CUTOFF.write() = Some(cutoff);
+}
+
+/// Remove the installed cutoff, re-admitting files of any age
+pub fn clear_max_age() {
+    *AGE_CUTOFF.write() = None;
+}
+
+/// The installed cutoff, if `--exclude-older-than` was given
+pub fn installed_cutoff() -> Option<SystemTime> {
+    *AGE_CUTOFF.read()
+}
+
+/// Whether the file behind `metadata` was last modified before `cutoff`.
+/// A file whose mtime cannot be read is kept: backing up too much is the
+/// safe failure mode.
+pub fn is_stale(metadata: &fs::Metadata, cutoff: SystemTime) -> bool {
+    metadata.modified().map(|mtime| mtime < cutoff).unwrap_or(false)
+}
+
+/// Relative paths (rsync-escaped, `/`-anchored) of the files under
+/// `source` older than `cutoff`; rsync has no age filter of its own, so
+/// the stale set is enumerated up front and fed through an exclude file
+pub fn stale_relative_paths(source: &std::path::Path, cutoff: SystemTime) -> Vec<String> {
+    let mut patterns = Vec::new();
+    for entry in walkdir::WalkDir::new(source).into_iter().flatten() {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !is_stale(&metadata, cutoff) {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(source) else {
+            continue;
+        };
+        let mut pattern = String::new();
+        for component in relative.components() {
+            pattern.push('/');
+            pattern.push_str(&crate::escape_rsync_pattern(
+                &component.as_os_str().to_string_lossy(),
+            ));
+        }
+        patterns.push(pattern);
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_human_duration_accepts_suffixed_and_bare_forms() {
+        assert_eq!(parse_human_duration("7d").unwrap(), Duration::from_secs(7 * 86400));
+        assert_eq!(parse_human_duration("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_human_duration("45m").unwrap(), Duration::from_secs(45 * 60));
+        assert_eq!(parse_human_duration("2w").unwrap(), Duration::from_secs(14 * 86400));
+        assert_eq!(parse_human_duration("90").unwrap(), Duration::from_secs(90));
+
+        assert!(parse_human_duration("").is_err());
+        assert!(parse_human_duration("d").is_err());
+        assert!(parse_human_duration("30x").is_err());
+        assert!(parse_human_duration("1.5h").is_err());
+    }
+
+    #[test]
+    fn test_stale_detection_and_enumeration() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("cache").join("stale.bin");
+        let new = temp_dir.path().join("cache").join("fresh.bin");
+        fs::create_dir_all(old.parent().unwrap()).unwrap();
+        fs::write(&old, b"old").unwrap();
+        fs::write(&new, b"new").unwrap();
+        let forty_days_ago = SYSTEM_CLOCK.unix_seconds() - 40 * 86400;
+        filetime::set_file_mtime(&old, filetime::FileTime::from_unix_time(forty_days_ago as i64, 0)).unwrap();
+
+        let cutoff = SYSTEM_CLOCK.now() - Duration::from_secs(30 * 86400);
+        assert!(is_stale(&fs::metadata(&old).unwrap(), cutoff));
+        assert!(!is_stale(&fs::metadata(&new).unwrap(), cutoff));
+
+        let stale = stale_relative_paths(temp_dir.path(), cutoff);
+        assert_eq!(stale, vec!["/cache/stale.bin".to_string()]);
+    }
+}
@@ -0,0 +1,256 @@
+//! Backup content sampling for observability.
+//!
+//! Before approving retention changes, operators want a quick sense of
+//! what a session actually contains: which files dominate the bytes,
+//! what kinds of files they are, and which top-level directories carry
+//! the weight. [`analyze_tree`] walks a session source or a backup the
+//! same way a backup would -- honoring mount exclusions, the installed
+//! exclude/include filter and internal artifact skipping -- so the
+//! numbers match what a backup would capture. Memory stays bounded: the
+//! largest files and directories are tracked in fixed-size heaps and
+//! everything else aggregates into per-key counters.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::filter::TransferFilter;
+use crate::mount_index::MountIndex;
+
+/// How many of the largest files and directories the report keeps
+pub const TOP_ENTRIES: usize = 20;
+
+/// One path with its size; directories carry their cumulative bytes.
+/// Ordering is by bytes, with the path as a stable tie-breaker
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LargeEntry {
+    pub bytes: u64,
+    /// Relative to the analyzed root
+    pub path: PathBuf,
+}
+
+/// Aggregate usage for one file extension
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ExtensionUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// What a tree contains, sampled with bounded memory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AnalyzeReport {
+    pub total_files: usize,
+    pub total_directories: usize,
+    pub total_bytes: u64,
+    /// The [`TOP_ENTRIES`] largest files, biggest first
+    pub largest_files: Vec<LargeEntry>,
+    /// The [`TOP_ENTRIES`] largest directories by cumulative size,
+    /// biggest first; nested directories each appear with their own total
+    pub largest_directories: Vec<LargeEntry>,
+    /// File count and bytes per extension; extensionless files are
+    /// grouped under `(none)`
+    pub by_extension: BTreeMap<String, ExtensionUsage>,
+    /// Cumulative bytes per top-level directory; files directly at the
+    /// root are grouped under `.`
+    pub by_top_level: BTreeMap<String, u64>,
+    /// Entries skipped as mounted or explicitly excluded paths
+    pub skipped_mounts: usize,
+    /// Entries skipped by the exclude/include filter
+    pub excluded_by_pattern: usize,
+}
+
+/// Walk `root` and aggregate what it contains. The walk mirrors the
+/// backup's own skipping: `excluded_paths` (typically the mounted paths)
+/// are bypassed, `filter` applies with the native transfer's semantics
+/// (excluded directories are still entered when an include could match
+/// beneath them), and this tool's own artifacts are never counted
+pub fn analyze_tree(
+    root: &Path,
+    filter: Option<&TransferFilter>,
+    excluded_paths: &HashSet<PathBuf>,
+) -> Result<AnalyzeReport> {
+    let mounts = MountIndex::new(excluded_paths);
+    let mut report = AnalyzeReport::default();
+    let mut files_heap: BinaryHeap<Reverse<LargeEntry>> = BinaryHeap::new();
+    let mut dirs_heap: BinaryHeap<Reverse<LargeEntry>> = BinaryHeap::new();
+
+    report.total_bytes = analyze_dir(root, root, filter, &mounts, &mut report, &mut files_heap, &mut dirs_heap)?;
+
+    report.largest_files = drain_descending(files_heap);
+    report.largest_directories = drain_descending(dirs_heap);
+    Ok(report)
+}
+
+/// Recursive walk returning the directory's cumulative admitted bytes,
+/// mirroring the size estimation walk in [`crate::quota`]
+fn analyze_dir(
+    dir: &Path,
+    root: &Path,
+    filter: Option<&TransferFilter>,
+    mounts: &MountIndex,
+    report: &mut AnalyzeReport,
+    files_heap: &mut BinaryHeap<Reverse<LargeEntry>>,
+    dirs_heap: &mut BinaryHeap<Reverse<LargeEntry>>,
+) -> Result<u64> {
+    let mut total = 0u64;
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory for analysis: {}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if crate::is_internal_artifact(&entry.file_name()) {
+            continue;
+        }
+        if crate::is_path_excluded(&path, root, mounts) {
+            report.skipped_mounts += 1;
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if let Some(filter) = filter {
+            let keep = if metadata.is_dir() {
+                filter.should_descend(relative)
+            } else {
+                filter.admits(relative)
+            };
+            if !keep {
+                report.excluded_by_pattern += 1;
+                continue;
+            }
+        }
+        if metadata.is_dir() {
+            let bytes = analyze_dir(&path, root, filter, mounts, report, files_heap, dirs_heap)?;
+            report.total_directories += 1;
+            note_top(dirs_heap, LargeEntry { bytes, path: relative.to_path_buf() });
+            total += bytes;
+        } else if metadata.is_file() {
+            let bytes = metadata.len();
+            report.total_files += 1;
+            note_top(files_heap, LargeEntry { bytes, path: relative.to_path_buf() });
+
+            let extension = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "(none)".to_string());
+            let usage = report.by_extension.entry(extension).or_default();
+            usage.files += 1;
+            usage.bytes += bytes;
+
+            let top_level = match relative.components().next() {
+                Some(component) if relative.components().count() > 1 => {
+                    component.as_os_str().to_string_lossy().into_owned()
+                }
+                _ => ".".to_string(),
+            };
+            *report.by_top_level.entry(top_level).or_default() += bytes;
+
+            total += bytes;
+        }
+        // Symlinks and special files carry no content bytes to sample
+    }
+    Ok(total)
+}
+
+/// Keep the heap at [`TOP_ENTRIES`]: a min-heap of the biggest entries
+/// seen so far, evicting its smallest when a bigger one arrives
+fn note_top(heap: &mut BinaryHeap<Reverse<LargeEntry>>, entry: LargeEntry) {
+    if heap.len() < TOP_ENTRIES {
+        heap.push(Reverse(entry));
+    } else if heap.peek().is_some_and(|smallest| entry > smallest.0) {
+        heap.pop();
+        heap.push(Reverse(entry));
+    }
+}
+
+fn drain_descending(heap: BinaryHeap<Reverse<LargeEntry>>) -> Vec<LargeEntry> {
+    let mut entries: Vec<LargeEntry> = heap.into_iter().map(|entry| entry.0).collect();
+    entries.sort_by(|a, b| b.cmp(a));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn file(base: &Path, relative: &str, bytes: usize) {
+        let path = base.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; bytes]).unwrap();
+    }
+
+    #[test]
+    fn test_report_aggregates_sizes_extensions_and_top_level_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        file(temp_dir.path(), "root/model.bin", 500);
+        file(temp_dir.path(), "root/notes.txt", 50);
+        file(temp_dir.path(), "home/user/data.bin", 300);
+        file(temp_dir.path(), "README", 10);
+
+        let report = analyze_tree(temp_dir.path(), None, &HashSet::new()).unwrap();
+        assert_eq!(report.total_files, 4);
+        assert_eq!(report.total_directories, 3);
+        assert_eq!(report.total_bytes, 860);
+
+        // Largest first, paths relative to the root
+        assert_eq!(report.largest_files[0], LargeEntry { bytes: 500, path: "root/model.bin".into() });
+        assert_eq!(report.largest_files[1], LargeEntry { bytes: 300, path: "home/user/data.bin".into() });
+
+        // Nested directories each carry their own cumulative total
+        assert_eq!(report.largest_directories[0], LargeEntry { bytes: 550, path: "root".into() });
+        assert!(report
+            .largest_directories
+            .iter()
+            .any(|entry| entry == &LargeEntry { bytes: 300, path: "home/user".into() }));
+
+        assert_eq!(report.by_extension["bin"].files, 2);
+        assert_eq!(report.by_extension["bin"].bytes, 800);
+        assert_eq!(report.by_extension["(none)"].files, 1);
+
+        assert_eq!(report.by_top_level["root"], 550);
+        assert_eq!(report.by_top_level["home"], 300);
+        assert_eq!(report.by_top_level["."], 10);
+    }
+
+    #[test]
+    fn test_filter_and_exclusions_keep_numbers_matching_a_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        file(temp_dir.path(), "root/keep.txt", 100);
+        file(temp_dir.path(), "root/.cache/blob", 400);
+        file(temp_dir.path(), "mnt/volume/huge.bin", 900);
+        file(temp_dir.path(), "stale.lock", 10);
+
+        let filter = TransferFilter::new(&["**/.cache".to_string()], &[]);
+        let mut excluded = HashSet::new();
+        excluded.insert(PathBuf::from("/mnt/volume"));
+
+        let report = analyze_tree(temp_dir.path(), Some(&filter), &excluded).unwrap();
+        // Only keep.txt counts: the cache is pattern-excluded, the mounted
+        // volume is bypassed and the lock file is an internal artifact
+        assert_eq!(report.total_files, 1);
+        assert_eq!(report.total_bytes, 100);
+        assert_eq!(report.excluded_by_pattern, 1);
+        assert_eq!(report.skipped_mounts, 1);
+        assert!(!report.by_top_level.contains_key("mnt"));
+    }
+
+    #[test]
+    fn test_top_entries_stay_bounded() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..TOP_ENTRIES + 10 {
+            file(temp_dir.path(), &format!("f{:03}.dat", i), i + 1);
+        }
+
+        let report = analyze_tree(temp_dir.path(), None, &HashSet::new()).unwrap();
+        assert_eq!(report.largest_files.len(), TOP_ENTRIES);
+        // The smallest files fell out of the bounded heap
+        assert_eq!(report.largest_files[0].bytes, (TOP_ENTRIES + 10) as u64);
+        assert!(report.largest_files.iter().all(|entry| entry.bytes > 10));
+    }
+}
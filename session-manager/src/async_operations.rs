@@ -1,10 +1,32 @@
 use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
 use std::path::Path;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tokio::fs;
 use log::{debug, info, warn};
 
-use crate::{PathMapping, PathMappings, SessionInfo, PodInfo};
+use crate::{match_container_name, ContainerNameMatch, PathMapping, PathMappings, PodInfo, SessionInfo, DEFAULT_CONTAINER_NAME_TRANSFORMS};
+
+/// Per-key single-flight guards: concurrent first lookups for the same
+/// key serialize here so only the winner loads and parses the mappings
+/// file; the coalesced waiters find its cache entry on the re-check.
+/// Entries are bounded by the number of distinct pod identities the
+/// process ever looks up.
+static LOOKUP_FLIGHTS: Lazy<parking_lot::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(|| parking_lot::Mutex::new(HashMap::new()));
+
+/// How many times the mappings file has been read and parsed, for
+/// instrumentation (see [`crate::optimized_io::read_syscall_count`] for
+/// the same pattern)
+static FILE_LOADS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of mapping-file loads performed by this process
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn mapping_file_loads() -> u64 {
+    FILE_LOADS.load(Ordering::Relaxed)
+}
 
 /// Cached path mapping loader with async support
 pub async fn find_current_session_cached(
@@ -13,7 +35,7 @@ pub async fn find_current_session_cached(
 ) -> Result<Option<SessionInfo>> {
     // Try cache first
     let cache_key = format!("{}:{}:{}", pod_info.namespace, pod_info.pod_name, pod_info.container_name);
-    
+
     {
         let cache = crate::PATH_MAPPING_CACHE.read();
         if let Some(cached_mapping) = cache.peek(&cache_key) {
@@ -21,32 +43,56 @@ pub async fn find_current_session_cached(
             return Ok(Some(create_session_info_from_mapping(cached_mapping)?));
         }
     }
-    
+
+    // Single-flight: take this key's guard, then re-check the cache.
+    // Every concurrent first lookup for the same key queues here while
+    // the winner loads the file; the rest then hit its cache entry.
+    let flight = LOOKUP_FLIGHTS
+        .lock()
+        .entry(cache_key.clone())
+        .or_default()
+        .clone();
+    let _guard = flight.lock().await;
+
+    {
+        let cache = crate::PATH_MAPPING_CACHE.read();
+        if let Some(cached_mapping) = cache.peek(&cache_key) {
+            debug!("Coalesced into a concurrent load for: {}", cache_key);
+            return Ok(Some(create_session_info_from_mapping(cached_mapping)?));
+        }
+    }
+
     // Load from file if not in cache
     let path_mappings = load_path_mappings_async(mappings_file).await?;
     
     // Find the most recent matching entry
-    let mut best_match: Option<(String, PathMapping)> = None;
+    let mut best_match: Option<(String, PathMapping, ContainerNameMatch)> = None;
     let mut latest_time: Option<chrono::DateTime<chrono::Utc>> = None;
 
     for (path_key, mapping) in path_mappings.mappings {
-        if mapping.namespace == pod_info.namespace
-            && mapping.pod_name == pod_info.pod_name
-            && mapping.container_name == pod_info.container_name
-        {
+        if mapping.namespace != pod_info.namespace || mapping.pod_name != pod_info.pod_name {
+            continue;
+        }
+
+        if let Some(name_match) = match_container_name(
+            &mapping.container_name,
+            &pod_info.container_name,
+            &pod_info.pod_name,
+            DEFAULT_CONTAINER_NAME_TRANSFORMS,
+        ) {
             let created_at = chrono::DateTime::parse_from_rfc3339(&mapping.created_at)
                 .with_context(|| format!("Invalid created_at timestamp: {} for mapping {}", mapping.created_at, path_key))?
                 .with_timezone(&chrono::Utc);
 
-            if latest_time.map_or(true, |t| created_at > t) {
+            if latest_time.is_none_or(|t| created_at > t) {
                 latest_time = Some(created_at);
-                best_match = Some((path_key, mapping));
+                best_match = Some((path_key, mapping, name_match));
             }
         }
     }
 
     match best_match {
-        Some((path_key, mapping)) => {
+        Some((path_key, mapping, name_match)) => {
             // Cache the result
             {
                 let mut cache = crate::PATH_MAPPING_CACHE.write();
@@ -54,7 +100,13 @@ pub async fn find_current_session_cached(
             }
             
             info!("Found matching session mapping: {}", path_key);
-            Ok(Some(create_session_info_from_mapping(&mapping)?))
+            if let Some(alias) = name_match.alias_description() {
+                info!("Container name matched via alias: {}", alias);
+            }
+
+            let mut session_info = create_session_info_from_mapping(&mapping)?;
+            session_info.matched_alias = name_match.alias_description();
+            Ok(Some(session_info))
         }
         None => {
             info!("No matching session found for namespace={}, pod={}, container={}", 
@@ -75,6 +127,7 @@ async fn load_path_mappings_async(mappings_file: &Path) -> Result<PathMappings>
 
     let content = fs::read_to_string(mappings_file).await
         .with_context(|| format!("Failed to read mappings file: {}", mappings_file.display()))?;
+    FILE_LOADS.fetch_add(1, Ordering::Relaxed);
 
     if content.trim().is_empty() {
         warn!("Path mappings file is empty: {}", mappings_file.display());
@@ -115,6 +168,65 @@ fn create_session_info_from_mapping(mapping: &PathMapping) -> Result<SessionInfo
     Ok(SessionInfo {
         pod_hash: mapping.pod_hash.clone(),
         snapshot_hash: mapping.snapshot_hash.clone(),
+        snapshot_id: mapping.snapshot_id.clone(),
         created_at,
+        matched_alias: None,
     })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_cache_misses_coalesce_into_one_file_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let mappings_file = temp_dir.path().join("path-mappings.json");
+        // Identity unique to this test: the cache and load counter are
+        // process-wide and other tests share them
+        std::fs::write(
+            &mappings_file,
+            r#"{
+                "mappings": {
+                    "f1a2b3c4/d5e6f7a8": {
+                        "namespace": "teco-flight",
+                        "pod_name": "nb-flight-0",
+                        "container_name": "inference",
+                        "created_at": "2026-01-01T00:00:00Z",
+                        "pod_hash": "f1a2b3c4",
+                        "snapshot_hash": "d5e6f7a8"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let pod_info = PodInfo {
+            namespace: "teco-flight".to_string(),
+            pod_name: "nb-flight-0".to_string(),
+            container_name: "inference".to_string(),
+        };
+
+        let loads_before = mapping_file_loads();
+        let mut handles = Vec::new();
+        for _ in 0..16 {
+            let mappings_file = mappings_file.clone();
+            let pod_info = pod_info.clone();
+            handles.push(tokio::spawn(async move {
+                find_current_session_cached(&mappings_file, &pod_info).await
+            }));
+        }
+        for handle in handles {
+            let session = handle.await.unwrap().unwrap().expect("session should match");
+            assert_eq!(session.pod_hash, "f1a2b3c4");
+            assert_eq!(session.snapshot_hash, "d5e6f7a8");
+        }
+
+        assert_eq!(
+            mapping_file_loads() - loads_before,
+            1,
+            "concurrent lookups for one key must coalesce into a single file load"
+        );
+    }
 }
\ No newline at end of file
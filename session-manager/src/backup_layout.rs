@@ -0,0 +1,249 @@
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::lockless_backup::create_directory_simple;
+use crate::PodInfo;
+
+/// Identity record written at the root of each derived backup directory,
+/// used to refuse accidental sharing of one directory by different pods
+pub const BACKUP_META_FILE_NAME: &str = ".backup-meta.json";
+
+/// The pod identity a backup directory belongs to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupMeta {
+    pub namespace: String,
+    pub pod_name: String,
+    pub container_name: String,
+}
+
+impl BackupMeta {
+    pub fn from_pod_info(pod_info: &PodInfo) -> Self {
+        Self {
+            namespace: pod_info.namespace.clone(),
+            pod_name: pod_info.pod_name.clone(),
+            container_name: pod_info.container_name.clone(),
+        }
+    }
+
+    fn load(dir: &Path) -> Result<Option<Self>> {
+        let meta_path = dir.join(BACKUP_META_FILE_NAME);
+        if !meta_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&meta_path)
+            .with_context(|| format!("Failed to read backup metadata: {}", meta_path.display()))?;
+        let meta = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse backup metadata: {}", meta_path.display()))?;
+        Ok(Some(meta))
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let meta_path = dir.join(BACKUP_META_FILE_NAME);
+        let json = crate::result_envelope::render_json(self).context("Failed to serialize backup metadata")?;
+        fs::write(&meta_path, json)
+            .with_context(|| format!("Failed to write backup metadata: {}", meta_path.display()))?;
+        Ok(())
+    }
+}
+
+/// Short stable hash of the pod identity, mirroring the hash-based short
+/// directory names the snapshotter uses
+pub fn pod_hash(namespace: &str, pod_name: &str) -> String {
+    let digest = blake3::hash(format!("{}/{}", namespace, pod_name).as_bytes());
+    digest.to_hex()[..8].to_string()
+}
+
+/// The derived per-container backup directory:
+/// `<base>/<namespace>/<pod_hash>/<container_name>/`
+pub fn derive_backup_dir(base: &Path, pod_info: &PodInfo) -> PathBuf {
+    base.join(&pod_info.namespace)
+        .join(pod_hash(&pod_info.namespace, &pod_info.pod_name))
+        .join(&pod_info.container_name)
+}
+
+/// Refuse a directory whose metadata names a different pod; `force`
+/// downgrades the refusal to a warning and rewrites the metadata
+fn check_collision(dir: &Path, expected: &BackupMeta, force: bool) -> Result<()> {
+    match BackupMeta::load(dir)? {
+        Some(existing) if existing != *expected => {
+            if force {
+                warn!(
+                    "Backup directory {} belongs to {}/{}/{} but --force was given; taking it over",
+                    dir.display(), existing.namespace, existing.pod_name, existing.container_name
+                );
+                Ok(())
+            } else {
+                bail!(
+                    "Backup directory {} already belongs to {}/{}/{} (current pod is {}/{}/{}); \
+                     refusing to proceed without --force",
+                    dir.display(),
+                    existing.namespace, existing.pod_name, existing.container_name,
+                    expected.namespace, expected.pod_name, expected.container_name
+                )
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether `base` holds a legacy flat backup: session data written
+/// directly at the root instead of under the derived layout. The derived
+/// layout only ever creates namespace directories at the root, so any
+/// plain file there marks the old layout.
+fn is_legacy_flat_backup(base: &Path, derived: &Path) -> bool {
+    if derived.exists() {
+        return false;
+    }
+    let Ok(entries) = fs::read_dir(base) else {
+        return false;
+    };
+    entries
+        .flatten()
+        .any(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+}
+
+/// Move a legacy flat backup's contents into the derived directory
+fn migrate_legacy_backup(base: &Path, derived: &Path) -> Result<usize> {
+    info!(
+        "Detected legacy flat backup at {}; migrating into {}",
+        base.display(), derived.display()
+    );
+    create_directory_simple(derived)
+        .with_context(|| format!("Failed to create derived backup directory: {}", derived.display()))?;
+
+    let mut moved = 0;
+    for entry in fs::read_dir(base)
+        .with_context(|| format!("Failed to read backup directory: {}", base.display()))?
+        .flatten()
+    {
+        let entry_path = entry.path();
+        // The derived layout's namespace directory may already exist next
+        // to the legacy data; everything else is legacy content
+        if derived.starts_with(&entry_path) {
+            continue;
+        }
+        let target = derived.join(entry.file_name());
+        fs::rename(&entry_path, &target).with_context(|| {
+            format!("Failed to migrate {} to {}", entry_path.display(), target.display())
+        })?;
+        moved += 1;
+    }
+    info!("Migrated {} entries into the namespace-scoped layout", moved);
+    Ok(moved)
+}
+
+/// Resolve (and for the backup side create) the effective backup
+/// directory for a pod, running the collision check and legacy migration.
+///
+/// With `flat_layout` the base path is used as-is, preserving the old
+/// behavior where operators point every pod at its own directory.
+pub fn resolve_backup_dir(
+    base: &Path,
+    pod_info: &PodInfo,
+    flat_layout: bool,
+    force: bool,
+    create: bool,
+) -> Result<PathBuf> {
+    if flat_layout {
+        debug!("Using legacy flat backup layout at {}", base.display());
+        return Ok(base.to_path_buf());
+    }
+
+    let derived = derive_backup_dir(base, pod_info);
+    let expected = BackupMeta::from_pod_info(pod_info);
+
+    if base.exists() && is_legacy_flat_backup(base, &derived) {
+        migrate_legacy_backup(base, &derived)?;
+    }
+
+    if create {
+        create_directory_simple(&derived)
+            .with_context(|| format!("Failed to create backup directory: {}", derived.display()))?;
+    }
+    if derived.exists() {
+        check_collision(&derived, &expected, force)?;
+        if create {
+            expected.save(&derived)?;
+        }
+    }
+
+    info!("Using namespace-scoped backup directory: {}", derived.display());
+    Ok(derived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn pod(namespace: &str, pod_name: &str, container_name: &str) -> PodInfo {
+        PodInfo {
+            namespace: namespace.to_string(),
+            pod_name: pod_name.to_string(),
+            container_name: container_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_derived_layout_is_stable_and_scoped() {
+        let base = Path::new("/backup");
+        let info = pod("teco", "nb-test-0", "inference");
+        let dir = derive_backup_dir(base, &info);
+
+        assert!(dir.starts_with("/backup/teco"));
+        assert!(dir.ends_with("inference"));
+        // Same identity derives the same path; a different pod does not
+        assert_eq!(dir, derive_backup_dir(base, &info));
+        assert_ne!(dir, derive_backup_dir(base, &pod("teco", "nb-test-1", "inference")));
+    }
+
+    #[test]
+    fn test_collision_refused_without_force_and_allowed_with() {
+        let temp = TempDir::new().unwrap();
+        let base = temp.path().join("backup");
+        let first = pod("teco", "nb-a-0", "inference");
+        let second = pod("teco", "nb-b-0", "inference");
+
+        let first_dir = resolve_backup_dir(&base, &first, false, false, true).unwrap();
+        assert!(first_dir.join(BACKUP_META_FILE_NAME).exists());
+
+        // Re-resolving for the same pod is fine
+        assert_eq!(resolve_backup_dir(&base, &first, false, false, true).unwrap(), first_dir);
+
+        // Another pod steered into the same directory is refused
+        fs::create_dir_all(derive_backup_dir(&base, &second).parent().unwrap()).unwrap();
+        fs::rename(&first_dir, derive_backup_dir(&base, &second)).unwrap();
+        let err = resolve_backup_dir(&base, &second, false, false, true).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+
+        // --force takes the directory over and rewrites the metadata
+        let forced = resolve_backup_dir(&base, &second, false, true, true).unwrap();
+        let meta = BackupMeta::load(&forced).unwrap().unwrap();
+        assert_eq!(meta.pod_name, "nb-b-0");
+    }
+
+    #[test]
+    fn test_legacy_flat_backup_is_migrated_into_derived_dir() {
+        let temp = TempDir::new().unwrap();
+        let base = temp.path().join("backup");
+        fs::create_dir_all(base.join("home/user")).unwrap();
+        fs::write(base.join("sessions.db"), b"legacy flat data").unwrap();
+        fs::write(base.join("home/user/notes.txt"), b"more legacy data").unwrap();
+
+        let info = pod("teco", "nb-test-0", "inference");
+        let derived = resolve_backup_dir(&base, &info, false, false, true).unwrap();
+
+        assert_eq!(fs::read(derived.join("sessions.db")).unwrap(), b"legacy flat data");
+        assert_eq!(fs::read(derived.join("home/user/notes.txt")).unwrap(), b"more legacy data");
+        assert!(!base.join("sessions.db").exists());
+
+        // Flat layout opt-out leaves the base untouched
+        assert_eq!(
+            resolve_backup_dir(&base, &info, true, false, false).unwrap(),
+            base
+        );
+    }
+}
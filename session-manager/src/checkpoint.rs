@@ -0,0 +1,268 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::direct_restore::{DirectRestoreEngine, DirectRestoreResult};
+
+/// Directory under the backup root holding all named checkpoints
+pub const CHECKPOINTS_DIR_NAME: &str = "checkpoints";
+
+/// Identity record written at the root of each checkpoint directory
+pub const CHECKPOINT_META_FILE_NAME: &str = ".checkpoint-meta.json";
+
+/// Names other layout features own at the backup root; a checkpoint must
+/// never shadow them
+const RESERVED_NAMES: &[&str] = &["checkpoints", "generations", "latest", "current"];
+
+/// Who created a checkpoint, when, and from which session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointMeta {
+    pub name: String,
+    pub creator: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The session directory the checkpoint was taken from
+    pub source_session: PathBuf,
+}
+
+impl CheckpointMeta {
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let meta_path = dir.join(CHECKPOINT_META_FILE_NAME);
+        if !meta_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&meta_path)
+            .with_context(|| format!("Failed to read checkpoint metadata: {}", meta_path.display()))?;
+        let meta = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse checkpoint metadata: {}", meta_path.display()))?;
+        Ok(Some(meta))
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let meta_path = dir.join(CHECKPOINT_META_FILE_NAME);
+        let json = crate::result_envelope::render_json(self)
+            .context("Failed to serialize checkpoint metadata")?;
+        fs::write(&meta_path, json)
+            .with_context(|| format!("Failed to write checkpoint metadata: {}", meta_path.display()))?;
+        Ok(())
+    }
+}
+
+/// One checkpoint directory found under the backup root; metadata is
+/// absent for directories created by hand or by older builds
+#[derive(Debug)]
+pub struct CheckpointEntry {
+    pub name: String,
+    pub meta: Option<CheckpointMeta>,
+}
+
+/// Checkpoint names become directory names, so anything that could walk
+/// out of the checkpoints directory or shadow other layout features is
+/// refused. Generation directories use the `gen-<number>` pattern; that
+/// namespace stays disjoint from checkpoint names.
+pub fn validate_checkpoint_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("Checkpoint name must not be empty");
+    }
+    if name.starts_with('.') {
+        bail!("Checkpoint name must not start with '.': {}", name);
+    }
+    if name.contains('/') || name.contains('\\') {
+        bail!("Checkpoint name must not contain path separators: {}", name);
+    }
+    if RESERVED_NAMES.contains(&name) {
+        bail!("Checkpoint name is reserved by the backup layout: {}", name);
+    }
+    if name
+        .strip_prefix("gen-")
+        .is_some_and(|rest| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()))
+    {
+        bail!("Checkpoint name collides with the generation directory namespace: {}", name);
+    }
+    Ok(())
+}
+
+/// The directory a named checkpoint lives in, after name validation
+pub fn checkpoint_dir(backup_root: &Path, name: &str) -> Result<PathBuf> {
+    validate_checkpoint_name(name)?;
+    Ok(backup_root.join(CHECKPOINTS_DIR_NAME).join(name))
+}
+
+/// The user running the tool, for the checkpoint's creator field
+fn current_creator() -> String {
+    std::env::var("SUDO_USER")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Snapshot `source_session` into `<backup_root>/checkpoints/<name>/`
+/// through the regular transfer engine with manifest verification.
+/// Refuses to overwrite an existing checkpoint; delete it first.
+pub fn create_checkpoint(
+    backup_root: &Path,
+    name: &str,
+    label: Option<&str>,
+    source_session: &Path,
+    opts: &crate::BackupVerifyOptions,
+) -> Result<crate::BackupReport> {
+    let dir = checkpoint_dir(backup_root, name)?;
+    if dir.exists() {
+        bail!(
+            "Checkpoint {} already exists at {}; delete it first",
+            name, dir.display()
+        );
+    }
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create checkpoint directory: {}", dir.display()))?;
+
+    info!("Creating checkpoint {} from {}", name, source_session.display());
+    let report = crate::backup_and_verify(source_session, &dir, opts)
+        .with_context(|| format!("Failed to create checkpoint {}", name))?;
+
+    let meta = CheckpointMeta {
+        name: name.to_string(),
+        creator: current_creator(),
+        created_at: Utc::now(),
+        label: label.map(str::to_string),
+        source_session: source_session.to_path_buf(),
+    };
+    meta.save(&dir)?;
+    info!(
+        "Checkpoint {} created: {} files, {} verified",
+        name, report.transfer.success_count, report.verified_files
+    );
+    Ok(report)
+}
+
+/// All checkpoints under the backup root, sorted by name
+pub fn list_checkpoints(backup_root: &Path) -> Result<Vec<CheckpointEntry>> {
+    let base = backup_root.join(CHECKPOINTS_DIR_NAME);
+    if !base.exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(&base)
+        .with_context(|| format!("Failed to read checkpoints directory: {}", base.display()))?
+        .flatten()
+    {
+        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let meta = CheckpointMeta::load(&entry.path()).unwrap_or(None);
+        entries.push(CheckpointEntry { name, meta });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Remove a named checkpoint and everything in it
+pub fn delete_checkpoint(backup_root: &Path, name: &str) -> Result<()> {
+    let dir = checkpoint_dir(backup_root, name)?;
+    if !dir.exists() {
+        bail!("Checkpoint {} does not exist at {}", name, dir.display());
+    }
+    fs::remove_dir_all(&dir)
+        .with_context(|| format!("Failed to delete checkpoint: {}", dir.display()))?;
+    info!("Deleted checkpoint {}", name);
+    Ok(())
+}
+
+/// Restore a named checkpoint through the given engine. The engine is
+/// forced into preserve mode: a checkpoint stays restorable afterwards
+/// instead of being consumed by the usual backup-file cleanup.
+pub fn restore_checkpoint(
+    backup_root: &Path,
+    name: &str,
+    engine: DirectRestoreEngine,
+) -> Result<DirectRestoreResult> {
+    let dir = checkpoint_dir(backup_root, name)?;
+    if !dir.exists() {
+        bail!("Checkpoint {} does not exist at {}", name, dir.display());
+    }
+    if let Some(meta) = CheckpointMeta::load(&dir)? {
+        info!(
+            "Restoring checkpoint {} (created {} by {}{})",
+            meta.name,
+            meta.created_at.to_rfc3339(),
+            meta.creator,
+            meta.label.as_deref().map(|l| format!(", label \"{}\"", l)).unwrap_or_default()
+        );
+    }
+    engine
+        .with_preserve_backup(true)
+        .restore_to_container_root(&dir)
+        .with_context(|| format!("Failed to restore checkpoint {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_validation_rejects_unsafe_and_reserved_names() {
+        for bad in ["", "a/b", "a\\b", ".hidden", "..", "checkpoints",
+                    "generations", "gen-3", "gen-0017"] {
+            assert!(validate_checkpoint_name(bad).is_err(), "{:?} should be rejected", bad);
+        }
+        for good in ["before-upgrade", "gen-", "gen-abc", "v1.2_final"] {
+            assert!(validate_checkpoint_name(good).is_ok(), "{:?} should be accepted", good);
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_create_restore_preserves_the_checkpoint() {
+        let staging = tempfile::TempDir::new().unwrap();
+        // The restore engine writes to container-absolute paths, so the
+        // checkpointed session mirrors a scratch directory under /tmp
+        let scratch = tempfile::Builder::new()
+            .prefix("checkpoint-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+
+        let session = staging.path().join("session");
+        fs::create_dir_all(session.join(relative)).unwrap();
+        fs::write(session.join(relative).join("notes.txt"), b"checkpoint me").unwrap();
+
+        let backup_root = staging.path().join("backup");
+        fs::create_dir_all(&backup_root).unwrap();
+        let opts = crate::BackupVerifyOptions { timeout_secs: 60, ..Default::default() };
+        let report = create_checkpoint(&backup_root, "before-upgrade", Some("pre upgrade"),
+                                       &session, &opts).unwrap();
+        assert_eq!(report.transfer.success_count, 1);
+        assert!(report.mismatches.is_empty());
+
+        // Creating over an existing checkpoint is refused
+        let err = create_checkpoint(&backup_root, "before-upgrade", None, &session, &opts)
+            .unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+
+        let listed = list_checkpoints(&backup_root).unwrap();
+        assert_eq!(listed.len(), 1);
+        let meta = listed[0].meta.as_ref().expect("metadata written");
+        assert_eq!(meta.name, "before-upgrade");
+        assert_eq!(meta.label.as_deref(), Some("pre upgrade"));
+        assert_eq!(meta.source_session, session);
+
+        // Restore twice: the checkpoint must survive its own restore
+        for _ in 0..2 {
+            fs::remove_file(scratch.path().join("notes.txt")).ok();
+            let result = restore_checkpoint(&backup_root, "before-upgrade",
+                                            DirectRestoreEngine::new(false, 60)).unwrap();
+            assert_eq!(result.failed_files, 0);
+            assert_eq!(result.cleaned_files, 0, "checkpoint restore must not clean backup files");
+            assert_eq!(fs::read(scratch.path().join("notes.txt")).unwrap(), b"checkpoint me");
+        }
+        let dir = checkpoint_dir(&backup_root, "before-upgrade").unwrap();
+        assert!(dir.join(relative).join("notes.txt").exists());
+
+        delete_checkpoint(&backup_root, "before-upgrade").unwrap();
+        assert!(!dir.exists());
+        assert!(delete_checkpoint(&backup_root, "before-upgrade").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Pluggable time source for deterministic tests.
+//!
+//! The cleanup safety window, backup metadata age and the wall-clock
+//! timeout all compare against "now"; reading `SystemTime::now()` or
+//! `Instant::now()` inline made those thresholds untestable without
+//! sleeping through them. Time-based logic takes a `&'static dyn Clock`
+//! instead — [`SYSTEM_CLOCK`] by default, a leaked [`MockClock`] in
+//! tests — so a test can place "now" exactly on either side of a
+//! threshold.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A source of wall-clock and monotonic time
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Current wall-clock time
+    fn now(&self) -> SystemTime;
+
+    /// Monotonic reading for timeout arithmetic; only differences
+    /// between readings of the same clock are meaningful
+    fn monotonic(&self) -> Duration;
+
+    /// Whole seconds since the Unix epoch
+    fn unix_seconds(&self) -> u64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+}
+
+/// The process clock; the default at every injection point
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+/// Shared instance for `&'static dyn Clock` fields
+pub static SYSTEM_CLOCK: SystemClock = SystemClock;
+
+/// Anchor for the monotonic reading, taken on first use
+static PROCESS_START: Lazy<Instant> = Lazy::new(Instant::now);
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        PROCESS_START.elapsed()
+    }
+}
+
+/// Test clock advanced by hand; wall-clock and monotonic time move
+/// together
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<SystemTime>,
+}
+
+impl MockClock {
+    pub fn at_unix_seconds(seconds: u64) -> Self {
+        Self {
+            now: Mutex::new(UNIX_EPOCH + Duration::from_secs(seconds)),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock() += by;
+    }
+
+    /// Leak a clock for injection points that keep a `&'static dyn
+    /// Clock`; the few bytes live until the test process exits
+    pub fn leaked(seconds: u64) -> &'static MockClock {
+        Box::leak(Box::new(Self::at_unix_seconds(seconds)))
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_moves_only_when_advanced() {
+        let clock = MockClock::at_unix_seconds(1_000_000);
+        assert_eq!(clock.unix_seconds(), 1_000_000);
+        let before = clock.monotonic();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.monotonic(), before);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.unix_seconds(), 1_000_090);
+        assert_eq!(clock.monotonic() - before, Duration::from_secs(90));
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs;
+use std::path::Path;
+
+/// Extensions whose content is already compressed; recompressing them
+/// wastes CPU for no gain, so they are always stored raw
+pub const INCOMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    "gz", "zst", "xz", "bz2", "lz4", "br", "zip", "7z", "rar", "png", "jpg", "jpeg", "gif",
+    "webp", "mp3", "mp4", "mkv", "avi", "parquet", "orc", "woff", "woff2",
+];
+
+/// Default minimum file size for per-file compression; smaller files gain
+/// little and pay the per-file overhead
+pub const DEFAULT_COMPRESSION_MIN_SIZE: u64 = 256 * 1024;
+
+/// Zstd compression level used for backup files
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Policy deciding which files in a backup are stored zstd-compressed
+#[derive(Debug, Clone)]
+pub struct CompressionPolicy {
+    /// Minimum original size in bytes before a file is compressed
+    pub min_size: u64,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_COMPRESSION_MIN_SIZE,
+        }
+    }
+}
+
+impl CompressionPolicy {
+    /// Whether a file should be stored compressed: large enough and not of
+    /// a known-incompressible type
+    pub fn should_compress(&self, path: &Path, size: u64) -> bool {
+        if size < self.min_size {
+            return false;
+        }
+        match path.extension() {
+            Some(ext) => {
+                let ext = ext.to_string_lossy().to_lowercase();
+                !INCOMPRESSIBLE_EXTENSIONS.contains(&ext.as_str())
+            }
+            None => true,
+        }
+    }
+}
+
+/// Compress `source` into `target` with zstd, returning the stored size
+pub fn compress_file(source: &Path, target: &Path) -> Result<u64> {
+    let input = fs::File::open(source)
+        .with_context(|| format!("Failed to open file for compression: {}", source.display()))?;
+    let output = fs::File::create(target)
+        .with_context(|| format!("Failed to create compressed file: {}", target.display()))?;
+
+    let mut encoder = zstd::stream::Encoder::new(output, COMPRESSION_LEVEL)
+        .context("Failed to create zstd encoder")?;
+    let mut reader = std::io::BufReader::new(input);
+    std::io::copy(&mut reader, &mut encoder)
+        .with_context(|| format!("Failed to compress {}", source.display()))?;
+    encoder
+        .finish()
+        .with_context(|| format!("Failed to finish compressing {}", source.display()))?;
+
+    let stored_size = fs::metadata(target)
+        .with_context(|| format!("Failed to stat compressed file: {}", target.display()))?
+        .len();
+    debug!(
+        "Compressed {} -> {} ({} bytes stored)",
+        source.display(),
+        target.display(),
+        stored_size
+    );
+    Ok(stored_size)
+}
+
+/// Decompress a zstd-stored backup file into `target`
+pub fn decompress_file(source: &Path, target: &Path) -> Result<()> {
+    let input = fs::File::open(source)
+        .with_context(|| format!("Failed to open compressed file: {}", source.display()))?;
+    let mut output = fs::File::create(target)
+        .with_context(|| format!("Failed to create decompressed file: {}", target.display()))?;
+
+    let mut decoder = zstd::stream::Decoder::new(input)
+        .with_context(|| format!("Failed to create zstd decoder for {}", source.display()))?;
+    std::io::copy(&mut decoder, &mut output)
+        .with_context(|| format!("Failed to decompress {}", source.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compress_decompress_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("train.csv");
+        let contents = "epoch,loss\n".repeat(50_000);
+        fs::write(&source, &contents).unwrap();
+
+        let compressed = temp.path().join("train.csv.zst");
+        let stored_size = compress_file(&source, &compressed).unwrap();
+        assert!(stored_size < contents.len() as u64 / 2, "text should compress well");
+
+        let restored = temp.path().join("restored.csv");
+        decompress_file(&compressed, &restored).unwrap();
+        assert_eq!(fs::read_to_string(&restored).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_policy_skips_small_and_incompressible_files() {
+        let policy = CompressionPolicy { min_size: 1024 };
+
+        // Below the threshold
+        assert!(!policy.should_compress(Path::new("notes.txt"), 512));
+        // Known-incompressible extensions, regardless of size
+        assert!(!policy.should_compress(Path::new("layer.tar.gz"), 10 << 20));
+        assert!(!policy.should_compress(Path::new("photo.PNG"), 10 << 20));
+        assert!(!policy.should_compress(Path::new("data.parquet"), 10 << 20));
+        // Large compressible artifacts
+        assert!(policy.should_compress(Path::new("training.log"), 10 << 20));
+        assert!(policy.should_compress(Path::new("notebook.ipynb"), 1 << 20));
+        assert!(policy.should_compress(Path::new("no-extension"), 1 << 20));
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::PodInfo;
+
+/// Default containerd CRI socket path on the node
+pub const DEFAULT_CRI_SOCKET: &str = "/run/containerd/containerd.sock";
+
+/// Kubernetes labels the kubelet attaches to every CRI container
+const LABEL_POD_NAMESPACE: &str = "io.kubernetes.pod.namespace";
+const LABEL_POD_NAME: &str = "io.kubernetes.pod.name";
+const LABEL_CONTAINER_NAME: &str = "io.kubernetes.container.name";
+
+/// gRPC method for fetching a container's status from the runtime
+const CONTAINER_STATUS_METHOD: &str = "/runtime.v1.RuntimeService/ContainerStatus";
+
+/// Minimal mirror of `runtime.v1.ContainerStatusRequest`; field tags
+/// match the CRI proto so the encoding stays wire-compatible
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ContainerStatusRequest {
+    #[prost(string, tag = "1")]
+    pub container_id: String,
+    #[prost(bool, tag = "2")]
+    pub verbose: bool,
+}
+
+/// Minimal mirror of `runtime.v1.ContainerStatusResponse`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ContainerStatusResponse {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<ContainerStatus>,
+}
+
+/// Only the fields we consume from `runtime.v1.ContainerStatus`; prost
+/// skips the many fields this mirror leaves out
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ContainerStatus {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(message, optional, tag = "2")]
+    pub metadata: Option<ContainerMetadata>,
+    #[prost(map = "string, string", tag = "12")]
+    pub labels: HashMap<String, String>,
+}
+
+/// Minimal mirror of `runtime.v1.ContainerMetadata`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ContainerMetadata {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(uint32, tag = "2")]
+    pub attempt: u32,
+}
+
+/// Extract the pod identity from a CRI container status response
+pub fn pod_info_from_response(response: &ContainerStatusResponse) -> Result<PodInfo> {
+    let status = response
+        .status
+        .as_ref()
+        .ok_or_else(|| anyhow!("CRI response carries no container status"))?;
+
+    let label = |key: &str| status.labels.get(key).cloned();
+
+    let namespace = label(LABEL_POD_NAMESPACE)
+        .ok_or_else(|| anyhow!("Container {} has no {} label", status.id, LABEL_POD_NAMESPACE))?;
+    let pod_name = label(LABEL_POD_NAME)
+        .ok_or_else(|| anyhow!("Container {} has no {} label", status.id, LABEL_POD_NAME))?;
+    // The container name label is authoritative; metadata.name is the
+    // fallback for runtimes that omit it
+    let container_name = label(LABEL_CONTAINER_NAME)
+        .or_else(|| status.metadata.as_ref().map(|m| m.name.clone()))
+        .ok_or_else(|| anyhow!("Container {} has no {} label", status.id, LABEL_CONTAINER_NAME))?;
+
+    Ok(PodInfo {
+        namespace,
+        pod_name,
+        container_name,
+    })
+}
+
+/// Query the containerd CRI socket for a container's status
+pub async fn container_status(socket: &Path, container_id: &str) -> Result<ContainerStatusResponse> {
+    use hyper_util::rt::TokioIo;
+    use tonic::transport::{Endpoint, Uri};
+    use tower::service_fn;
+
+    let socket_path = socket.to_path_buf();
+    debug!("Connecting to CRI socket: {}", socket_path.display());
+
+    // The endpoint URI is a placeholder; the connector always dials the
+    // unix socket
+    let channel = Endpoint::try_from("http://[::]:50051")?
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let socket_path = socket_path.clone();
+            async move {
+                Ok::<_, std::io::Error>(TokioIo::new(
+                    tokio::net::UnixStream::connect(socket_path).await?,
+                ))
+            }
+        }))
+        .await
+        .with_context(|| format!("Failed to connect to CRI socket: {}", socket.display()))?;
+
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready()
+        .await
+        .map_err(|e| anyhow!("CRI service not ready: {}", e))?;
+
+    let request = tonic::Request::new(ContainerStatusRequest {
+        container_id: container_id.to_string(),
+        verbose: false,
+    });
+    let response = grpc
+        .unary(
+            request,
+            tonic::codegen::http::uri::PathAndQuery::from_static(CONTAINER_STATUS_METHOD),
+            tonic::codec::ProstCodec::default(),
+        )
+        .await
+        .map_err(|status| anyhow!("CRI ContainerStatus({}) failed: {}", container_id, status))?;
+
+    Ok(response.into_inner())
+}
+
+/// Resolve pod identity from a container ID over the CRI socket;
+/// blocking wrapper for the synchronous binary entry points
+pub fn resolve_pod_info(socket: &Path, container_id: &str) -> Result<PodInfo> {
+    let runtime = tokio::runtime::Runtime::new().context("Failed to create async runtime")?;
+    let response = runtime.block_on(container_status(socket, container_id))?;
+    let pod_info = pod_info_from_response(&response)?;
+    info!(
+        "Resolved container {} via CRI: namespace={}, pod={}, container={}",
+        container_id, pod_info.namespace, pod_info.pod_name, pod_info.container_name
+    );
+    Ok(pod_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    fn mocked_response() -> ContainerStatusResponse {
+        let labels = HashMap::from([
+            (LABEL_POD_NAMESPACE.to_string(), "teco".to_string()),
+            (LABEL_POD_NAME.to_string(), "nb-test-0".to_string()),
+            (LABEL_CONTAINER_NAME.to_string(), "inference".to_string()),
+        ]);
+        ContainerStatusResponse {
+            status: Some(ContainerStatus {
+                id: "abc123".to_string(),
+                metadata: Some(ContainerMetadata {
+                    name: "inference".to_string(),
+                    attempt: 0,
+                }),
+                labels,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_pod_info_extracted_from_mocked_response() {
+        // Decode from wire bytes, as a real CRI response would arrive
+        let encoded = mocked_response().encode_to_vec();
+        let decoded = ContainerStatusResponse::decode(encoded.as_slice()).unwrap();
+
+        let pod_info = pod_info_from_response(&decoded).unwrap();
+        assert_eq!(pod_info.namespace, "teco");
+        assert_eq!(pod_info.pod_name, "nb-test-0");
+        assert_eq!(pod_info.container_name, "inference");
+    }
+
+    #[test]
+    fn test_missing_labels_fall_back_to_metadata_then_error() {
+        let mut response = mocked_response();
+
+        // Without the container name label, metadata.name is used
+        response.status.as_mut().unwrap().labels.remove(LABEL_CONTAINER_NAME);
+        assert_eq!(pod_info_from_response(&response).unwrap().container_name, "inference");
+
+        // A missing namespace label is unrecoverable
+        response.status.as_mut().unwrap().labels.remove(LABEL_POD_NAMESPACE);
+        let err = pod_info_from_response(&response).unwrap_err();
+        assert!(err.to_string().contains(LABEL_POD_NAMESPACE));
+
+        // So is an empty response
+        let empty = ContainerStatusResponse { status: None };
+        assert!(pod_info_from_response(&empty).is_err());
+    }
+}
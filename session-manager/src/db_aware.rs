@@ -0,0 +1,197 @@
+use anyhow::Result;
+use log::{debug, warn};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// First bytes of every SQLite 3 database file
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+/// How many times a unit is re-copied waiting for it to hold still
+const STABILITY_MAX_ATTEMPTS: usize = 5;
+
+/// Pause between stability attempts, giving in-flight transactions a
+/// chance to commit
+const STABILITY_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Whether the file starts with the SQLite magic header
+pub fn is_sqlite_file(path: &Path) -> bool {
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut header = [0u8; 16];
+    file.read_exact(&mut header).is_ok() && &header == SQLITE_MAGIC
+}
+
+/// Whether the path is an SQLite sidecar (`-wal` journal or `-shm` index)
+/// whose main database file exists next to it
+pub fn sqlite_sidecar_main(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let main_name = name
+        .strip_suffix("-wal")
+        .or_else(|| name.strip_suffix("-shm"))?;
+    let main_path = path.with_file_name(main_name);
+    if is_sqlite_file(&main_path) {
+        Some(main_path)
+    } else {
+        None
+    }
+}
+
+/// The database file plus whichever of its `-wal`/`-shm` sidecars exist;
+/// the trio must be captured together or the restored copy is corrupt
+pub fn sqlite_unit_members(db_path: &Path) -> Vec<PathBuf> {
+    let mut members = vec![db_path.to_path_buf()];
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar_name = db_path.file_name().unwrap_or_default().to_os_string();
+        sidecar_name.push(suffix);
+        let sidecar = db_path.with_file_name(sidecar_name);
+        if sidecar.exists() {
+            members.push(sidecar);
+        }
+    }
+    members
+}
+
+/// Whether the directory looks like a LevelDB/RocksDB store: it has a
+/// CURRENT file and at least one MANIFEST-* file
+pub fn is_leveldb_dir(path: &Path) -> bool {
+    if !path.join("CURRENT").is_file() {
+        return false;
+    }
+    fs::read_dir(path).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with("MANIFEST-"))
+        })
+    })
+}
+
+/// Size and mtime of every member; files that vanished read as None so
+/// deletions also count as instability
+fn snapshot_members(members: &[PathBuf]) -> Vec<Option<(u64, SystemTime)>> {
+    members
+        .iter()
+        .map(|member| {
+            fs::symlink_metadata(member)
+                .ok()
+                .map(|m| (m.len(), m.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+        })
+        .collect()
+}
+
+/// Copy a multi-file database unit, retrying until its members' sizes and
+/// mtimes are identical across two passes. Returns whether stability was
+/// reached; on exhaustion the last (possibly inconsistent) copy is kept
+/// and the caller should mark the entries unstable.
+pub fn copy_unit_stable<F>(members: &[PathBuf], mut copy_member: F) -> Result<bool>
+where
+    F: FnMut(&Path) -> Result<()>,
+{
+    for attempt in 1..=STABILITY_MAX_ATTEMPTS {
+        let before = snapshot_members(members);
+        for member in members {
+            if member.exists() {
+                copy_member(member)?;
+            }
+        }
+        let after = snapshot_members(members);
+        if before == after {
+            debug!("Database unit stable after {} attempt(s): {}", attempt, members[0].display());
+            return Ok(true);
+        }
+        warn!(
+            "Database unit changed during copy (attempt {}/{}): {}",
+            attempt, STABILITY_MAX_ATTEMPTS, members[0].display()
+        );
+        std::thread::sleep(STABILITY_RETRY_DELAY);
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_detection_and_unit_membership() {
+        let temp = TempDir::new().unwrap();
+        let db = temp.path().join("workspace.db");
+
+        let conn = rusqlite::Connection::open(&db).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)", []).unwrap();
+        conn.execute("INSERT INTO notes (body) VALUES ('hello')", []).unwrap();
+
+        assert!(is_sqlite_file(&db));
+        assert!(!is_sqlite_file(&temp.path().join("missing.db")));
+
+        // With the connection open the WAL sidecars exist and resolve back
+        let members = sqlite_unit_members(&db);
+        assert_eq!(members.len(), 3);
+        assert_eq!(sqlite_sidecar_main(&temp.path().join("workspace.db-wal")), Some(db.clone()));
+        assert_eq!(sqlite_sidecar_main(&temp.path().join("unrelated-wal")), None);
+
+        let plain = temp.path().join("plain.txt");
+        fs::write(&plain, b"not a database").unwrap();
+        assert!(!is_sqlite_file(&plain));
+    }
+
+    #[test]
+    fn test_open_wal_database_copy_passes_integrity_check() {
+        let temp = TempDir::new().unwrap();
+        let db = temp.path().join("sessions.db");
+        let target_dir = temp.path().join("backup");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        // Leave the WAL open with uncheckpointed pages, as JupyterLab does
+        let conn = rusqlite::Connection::open(&db).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.execute("CREATE TABLE cells (id INTEGER PRIMARY KEY, source TEXT)", []).unwrap();
+        for i in 0..50 {
+            conn.execute("INSERT INTO cells (source) VALUES (?1)", [format!("print({})", i)]).unwrap();
+        }
+
+        let members = sqlite_unit_members(&db);
+        assert!(members.len() >= 2, "expected WAL sidecar to exist");
+        let stable = copy_unit_stable(&members, |member| {
+            let target = target_dir.join(member.file_name().unwrap());
+            fs::copy(member, target)?;
+            Ok(())
+        })
+        .unwrap();
+        assert!(stable, "quiescent database must stabilize");
+
+        // The trio restored together passes SQLite's own consistency check
+        let copied = rusqlite::Connection::open(target_dir.join("sessions.db")).unwrap();
+        let verdict: String = copied
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(verdict, "ok");
+        let rows: i64 = copied
+            .query_row("SELECT COUNT(*) FROM cells", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 50);
+    }
+
+    #[test]
+    fn test_leveldb_dir_detection() {
+        let temp = TempDir::new().unwrap();
+        let store = temp.path().join("leveldb");
+        fs::create_dir_all(&store).unwrap();
+        fs::write(store.join("CURRENT"), b"MANIFEST-000003\n").unwrap();
+        fs::write(store.join("MANIFEST-000003"), b"manifest contents").unwrap();
+        fs::write(store.join("000004.log"), b"log contents").unwrap();
+        assert!(is_leveldb_dir(&store));
+
+        // A directory without the marker files is ordinary data
+        let plain = temp.path().join("plain");
+        fs::create_dir_all(&plain).unwrap();
+        fs::write(plain.join("CURRENT"), b"not a store without a manifest").unwrap();
+        assert!(!is_leveldb_dir(&plain));
+    }
+}
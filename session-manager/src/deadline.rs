@@ -0,0 +1,88 @@
+use crate::clock::{Clock, SYSTEM_CLOCK};
+use std::time::Duration;
+
+/// A single wall-clock budget shared by every phase of a run.
+///
+/// Created once at startup from `--timeout` and passed through the transfer
+/// and restore paths, so a run configured with 300 seconds really finishes
+/// (or is cancelled) within 300 seconds instead of each phase getting its
+/// own fresh timeout. Subprocess timeouts are computed from the remaining
+/// budget via [`Deadline::remaining_secs`].
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    /// Monotonic reading of `clock` at which the budget runs out
+    deadline: Duration,
+    clock: &'static dyn Clock,
+}
+
+impl Deadline {
+    /// Create a deadline `timeout_secs` seconds from now
+    pub fn from_secs(timeout_secs: u64) -> Self {
+        Self::from_secs_with_clock(timeout_secs, &SYSTEM_CLOCK)
+    }
+
+    /// Create a deadline measured against an injected clock; tests use a
+    /// mock to place "now" exactly at the budget boundary
+    pub fn from_secs_with_clock(timeout_secs: u64, clock: &'static dyn Clock) -> Self {
+        Self {
+            deadline: clock.monotonic() + Duration::from_secs(timeout_secs),
+            clock,
+        }
+    }
+
+    /// Remaining budget, zero once the deadline has passed
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_sub(self.clock.monotonic())
+    }
+
+    /// Remaining budget in whole seconds, suitable for subprocess `timeout`
+    /// arguments. Returns 0 once the deadline has passed; callers should
+    /// check [`Deadline::expired`] first.
+    pub fn remaining_secs(&self) -> u64 {
+        self.remaining().as_secs()
+    }
+
+    /// Whether the wall-clock budget is exhausted
+    pub fn expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_zero_budget_is_immediately_expired() {
+        let deadline = Deadline::from_secs(0);
+        assert!(deadline.expired());
+        assert_eq!(deadline.remaining_secs(), 0);
+    }
+
+    #[test]
+    fn test_remaining_decreases() {
+        let deadline = Deadline::from_secs(3600);
+        assert!(!deadline.expired());
+        let first = deadline.remaining();
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(deadline.remaining() < first);
+    }
+
+    #[test]
+    fn test_budget_boundary_is_exact_under_a_mock_clock() {
+        let clock = MockClock::leaked(1_000_000);
+        let deadline = Deadline::from_secs_with_clock(300, clock);
+
+        clock.advance(Duration::from_secs(299));
+        assert!(!deadline.expired());
+        assert_eq!(deadline.remaining_secs(), 1);
+
+        // One more second exhausts the budget exactly; it never goes
+        // negative afterwards
+        clock.advance(Duration::from_secs(1));
+        assert!(deadline.expired());
+        clock.advance(Duration::from_secs(100));
+        assert_eq!(deadline.remaining_secs(), 0);
+    }
+}
@@ -0,0 +1,106 @@
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Directories already created (or confirmed) by this process.
+///
+/// Parallel copy workers all call `create_dir_all` for overlapping parents;
+/// on NFS this races into spurious EEXIST/ENOENT errors that used to
+/// surface as failed files. The cache makes each directory's creation
+/// happen effectively once, and [`ensure_dir_exists`] absorbs the races.
+static CREATED_DIRS: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Create a directory (and its parents) exactly once per process,
+/// tolerating concurrent creators: EEXIST-with-directory counts as
+/// success, and the transient ENOENT race seen on NFS is retried once.
+pub fn ensure_dir_exists(path: &Path) -> io::Result<()> {
+    if CREATED_DIRS.lock().contains(path) {
+        return Ok(());
+    }
+
+    match try_create(path) {
+        Ok(()) => {
+            CREATED_DIRS.lock().insert(path.to_path_buf());
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn try_create(path: &Path) -> io::Result<()> {
+    match std::fs::create_dir_all(path) {
+        Ok(()) => Ok(()),
+        Err(e) => match e.kind() {
+            // A concurrent worker won the race; a directory in place is
+            // exactly the outcome we wanted
+            io::ErrorKind::AlreadyExists if path.is_dir() => Ok(()),
+            // NFS can report ENOENT when a parent created by another
+            // worker is not yet visible; one retry resolves it
+            io::ErrorKind::NotFound => {
+                debug!("Retrying directory creation after ENOENT race: {}", path.display());
+                match std::fs::create_dir_all(path) {
+                    Ok(()) => Ok(()),
+                    Err(retry) if retry.kind() == io::ErrorKind::AlreadyExists && path.is_dir() => Ok(()),
+                    Err(retry) => Err(retry),
+                }
+            }
+            _ if path.is_dir() => Ok(()),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Forget cached entries under a prefix; used after deleting directories
+/// (e.g. trash purges) so later creations are not skipped incorrectly
+pub fn invalidate_prefix(prefix: &Path) {
+    CREATED_DIRS.lock().retain(|path| !path.starts_with(prefix));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_64_threads_creating_under_same_deep_directory() {
+        let temp = TempDir::new().unwrap();
+        let deep = temp.path().join("a/b/c/d/e/f");
+
+        let failures: Vec<String> = std::thread::scope(|scope| {
+            (0..64)
+                .map(|i| {
+                    let deep = deep.clone();
+                    scope.spawn(move || -> Result<(), String> {
+                        ensure_dir_exists(&deep).map_err(|e| format!("mkdir: {}", e))?;
+                        std::fs::write(deep.join(format!("file-{}", i)), b"contents")
+                            .map_err(|e| format!("write: {}", e))
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap().err())
+                .collect()
+        });
+
+        assert!(failures.is_empty(), "unexpected failures: {:?}", failures);
+        assert_eq!(std::fs::read_dir(&deep).unwrap().count(), 64);
+    }
+
+    #[test]
+    fn test_existing_directory_counts_as_success_and_invalidation_recreates() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("nested/dir");
+        ensure_dir_exists(&dir).unwrap();
+        // Idempotent: a second call is a cache hit
+        ensure_dir_exists(&dir).unwrap();
+
+        // After external deletion the cache must be invalidated to recreate
+        std::fs::remove_dir_all(temp.path().join("nested")).unwrap();
+        invalidate_prefix(temp.path());
+        ensure_dir_exists(&dir).unwrap();
+        assert!(dir.is_dir());
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::fs;
+use std::path::Path;
+
+/// Block alignment required by O_DIRECT for buffers, offsets and lengths
+const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+/// Copy buffer size for direct I/O; a multiple of the alignment
+const DIRECT_IO_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// Default minimum file size before direct I/O is used; small files gain
+/// nothing and the page cache they displace is negligible
+pub const DEFAULT_DIRECT_IO_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+/// When to bypass the page cache for file copies.
+///
+/// Copying huge checkpoints through the page cache evicts the working set
+/// of the still-running user process during pre-termination backups; files
+/// above `min_size` are copied with O_DIRECT instead, falling back to a
+/// normal copy when the filesystem refuses it.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectIoPolicy {
+    /// Minimum file size in bytes before O_DIRECT applies
+    pub min_size: u64,
+}
+
+impl Default for DirectIoPolicy {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_DIRECT_IO_MIN_SIZE,
+        }
+    }
+}
+
+/// Globally installed policy, set once at binary startup from `--direct-io`
+static DIRECT_IO_POLICY: Lazy<RwLock<Option<DirectIoPolicy>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install the process-wide direct I/O policy
+pub fn install(min_size: u64) {
+    *DIRECT_IO_POLICY.write() = Some(DirectIoPolicy { min_size });
+}
+
+/// The installed policy, if `--direct-io` was given
+pub fn installed_policy() -> Option<DirectIoPolicy> {
+    *DIRECT_IO_POLICY.read()
+}
+
+/// Copy a file honoring the policy: O_DIRECT for large files with an
+/// automatic fallback to a page-cached copy when the filesystem refuses
+/// O_DIRECT (tmpfs, some network filesystems). Returns bytes copied.
+pub fn copy_file_with_policy(src: &Path, dst: &Path, policy: &DirectIoPolicy) -> Result<u64> {
+    let size = fs::metadata(src)
+        .with_context(|| format!("Failed to stat source file: {}", src.display()))?
+        .len();
+
+    if size >= policy.min_size {
+        match copy_file_direct(src, dst) {
+            Ok(bytes) => {
+                debug!("Direct I/O copy: {} -> {} ({} bytes)", src.display(), dst.display(), bytes);
+                return Ok(bytes);
+            }
+            Err(e) if is_direct_io_unsupported(&e) => {
+                debug!(
+                    "Filesystem refused O_DIRECT for {} ({}); falling back to cached copy",
+                    src.display(),
+                    e
+                );
+            }
+            Err(e) => {
+                warn!("Direct I/O copy failed for {} ({}); falling back to cached copy", src.display(), e);
+            }
+        }
+    }
+
+    fs::copy(src, dst).with_context(|| {
+        format!("Failed to copy file from {} to {}", src.display(), dst.display())
+    })
+}
+
+/// Whether an error means the filesystem does not support O_DIRECT
+fn is_direct_io_unsupported(error: &std::io::Error) -> bool {
+    matches!(error.raw_os_error(), Some(libc::EINVAL) | Some(libc::EOPNOTSUPP))
+}
+
+/// A heap buffer aligned for O_DIRECT: over-allocate and slice at the
+/// first aligned offset
+struct AlignedBuffer {
+    storage: Vec<u8>,
+    offset: usize,
+}
+
+impl AlignedBuffer {
+    fn new(size: usize) -> Self {
+        let storage = vec![0u8; size + DIRECT_IO_ALIGNMENT];
+        let offset = storage.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+        Self { storage, offset }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        let end = self.offset + DIRECT_IO_BUFFER_SIZE;
+        &mut self.storage[self.offset..end]
+    }
+}
+
+/// Copy a file with O_DIRECT on both ends. The final partial block is
+/// written zero-padded to the alignment and the destination is truncated
+/// back to the true length afterwards, as O_DIRECT writes must be
+/// block-aligned.
+#[cfg(target_os = "linux")]
+pub fn copy_file_direct(src: &Path, dst: &Path) -> std::io::Result<u64> {
+    use std::io::{Read, Write};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut src_file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(src)?;
+    let mut dst_file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(dst)?;
+
+    let mut buffer = AlignedBuffer::new(DIRECT_IO_BUFFER_SIZE);
+    let mut total_copied = 0u64;
+
+    loop {
+        let block = buffer.as_mut_slice();
+        let mut filled = 0;
+        // Fill a whole block where possible; O_DIRECT reads may return
+        // short counts at block granularity
+        while filled < block.len() {
+            let n = src_file.read(&mut block[filled..])?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        if filled == 0 {
+            break;
+        }
+
+        // Zero-pad the tail up to the alignment so the write stays valid
+        let padded = filled.next_multiple_of(DIRECT_IO_ALIGNMENT);
+        block[filled..padded].fill(0);
+        dst_file.write_all(&block[..padded])?;
+        total_copied += filled as u64;
+
+        if filled < block.len() {
+            break;
+        }
+    }
+
+    // Trim any zero padding written for the final partial block
+    dst_file.set_len(total_copied)?;
+    dst_file.sync_all()?;
+    Ok(total_copied)
+}
+
+/// O_DIRECT is Linux-specific; elsewhere the caller's fallback path is used
+#[cfg(not(target_os = "linux"))]
+pub fn copy_file_direct(_src: &Path, _dst: &Path) -> std::io::Result<u64> {
+    Err(std::io::Error::from_raw_os_error(libc::EOPNOTSUPP))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn patterned_contents(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_direct_copy_matches_source_hash_with_unaligned_tail() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("checkpoint.bin");
+        let dst = temp.path().join("checkpoint.copy");
+
+        // Several full blocks plus an unaligned tail
+        let contents = patterned_contents(3 * DIRECT_IO_ALIGNMENT + 123);
+        fs::write(&src, &contents).unwrap();
+
+        let policy = DirectIoPolicy { min_size: 1 };
+        let bytes = copy_file_with_policy(&src, &dst, &policy).unwrap();
+        assert_eq!(bytes, contents.len() as u64);
+
+        // Correctness proven by hash comparison
+        assert_eq!(blake3::hash(&fs::read(&dst).unwrap()), blake3::hash(&contents));
+    }
+
+    #[test]
+    fn test_small_files_bypass_direct_io() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("small.txt");
+        let dst = temp.path().join("small.copy");
+        fs::write(&src, b"below threshold").unwrap();
+
+        let policy = DirectIoPolicy::default();
+        copy_file_with_policy(&src, &dst, &policy).unwrap();
+        assert_eq!(fs::read(&dst).unwrap(), b"below threshold");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_tmpfs_refusal_falls_back_to_cached_copy() {
+        // tmpfs rejects O_DIRECT with EINVAL at open time
+        let Ok(temp) = tempfile::Builder::new()
+            .prefix("direct-io-test-")
+            .tempdir_in("/dev/shm")
+        else {
+            eprintln!("skipping: /dev/shm not available");
+            return;
+        };
+        let src = temp.path().join("data.bin");
+        let dst = temp.path().join("data.copy");
+        let contents = patterned_contents(2 * DIRECT_IO_ALIGNMENT);
+        fs::write(&src, &contents).unwrap();
+
+        match copy_file_direct(&src, &dst) {
+            Err(e) => assert!(is_direct_io_unsupported(&e), "unexpected error: {}", e),
+            Ok(_) => {
+                eprintln!("skipping refusal assertion: filesystem accepted O_DIRECT");
+            }
+        }
+
+        // The policy path degrades transparently and stays correct
+        let policy = DirectIoPolicy { min_size: 1 };
+        let bytes = copy_file_with_policy(&src, &dst, &policy).unwrap();
+        assert_eq!(bytes, contents.len() as u64);
+        assert_eq!(blake3::hash(&fs::read(&dst).unwrap()), blake3::hash(&contents));
+    }
+}
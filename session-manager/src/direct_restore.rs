@@ -6,9 +6,20 @@ use std::path::{Path, PathBuf, Component};
 use std::io;
 use std::time::{Duration, SystemTime};
 use std::thread;
+use std::collections::{BTreeMap, HashMap};
+use parking_lot::Mutex;
 use rayon::prelude::*;
+use crate::adaptive_parallelism::AdaptiveConcurrency;
+use crate::deadline::Deadline;
+use crate::fs_capabilities::{self, CapabilitySet};
+use crate::manifest::{self, BackupManifest};
+use crate::prefetch::{self, Prefetcher};
+use crate::profiling::{self, RestoreProfiler};
 use crate::resource_manager::ResourceManager;
 
+/// Default cap on stored entries per detail vector (skipped, failed, cleaned)
+pub const DEFAULT_MAX_DETAIL_ENTRIES: usize = 10_000;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DirectRestoreResult {
     pub total_files: usize,
@@ -19,9 +30,106 @@ pub struct DirectRestoreResult {
     pub skipped_details: Vec<SkippedFile>,
     pub failed_details: Vec<FailedFile>,
     pub cleaned_details: Vec<PathBuf>,
+    /// Breakdown of skip reasons by normalized category
+    #[serde(default)]
+    pub skip_reason_counts: HashMap<String, usize>,
+    /// Per-top-level-target-directory breakdown, aggregated incrementally
+    /// during processing so it stays exact even when the capped detail
+    /// vectors are truncated
+    #[serde(default)]
+    pub directory_summaries: BTreeMap<String, DirectorySummary>,
+    /// Number of detail entries dropped across all categories once the
+    /// per-category cap was reached; the counters above stay exact
+    #[serde(default)]
+    pub truncated_details: usize,
+    /// Files whose prefetch advice landed before a copy worker reached them
+    #[serde(default)]
+    pub prefetch_hits: usize,
+    /// Total prefetch advisories issued
+    #[serde(default)]
+    pub prefetch_issued: usize,
+    /// Cleaned backup files renamed into the trash instead of deleted
+    #[serde(default)]
+    pub trashed_files: usize,
+    /// Cleaned backup files permanently removed
+    #[serde(default)]
+    pub permanently_deleted_files: usize,
+    #[serde(default)]
+    pub repaired_directories: usize,
+    /// Pod identity whose backup was restored; differs from the executing
+    /// identity only for explicit cross-pod restores
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_identity: Option<crate::backup_layout::BackupMeta>,
+    /// Pod identity of the container executing the restore, for audit
+    /// trails of cross-pod restores
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executing_identity: Option<crate::backup_layout::BackupMeta>,
+    /// Version of the tool that produced the backup, from the manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer_version: Option<String>,
+    /// Priority tier being restored when the deadline cut the run short;
+    /// absent when the run finished or `--order` was natural
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_tier_reached: Option<String>,
+    /// Restored files whose recorded setuid/setgid bits or file
+    /// capabilities were not re-applied, because the run lacked the
+    /// privilege or the target is outside the opt-in allow-list
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub privileged_attrs_not_applied: Vec<PathBuf>,
+    /// True when the run was cut short by the wall-clock deadline
+    #[serde(default)]
+    pub cancelled: bool,
     pub duration: Duration,
 }
 
+impl DirectRestoreResult {
+    /// Log the per-top-level-directory breakdown as a compact table so
+    /// "did /home make it?" is answerable without parsing the JSON result
+    pub fn log_directory_summaries(&self) {
+        if self.directory_summaries.is_empty() {
+            return;
+        }
+        info!("Per-directory results:");
+        info!("  {:<24} {:>9} {:>9} {:>9} {:>14}", "directory", "restored", "skipped", "failed", "bytes");
+        for (directory, summary) in &self.directory_summaries {
+            info!("  {:<24} {:>9} {:>9} {:>9} {:>14}",
+                  directory, summary.restored, summary.skipped, summary.failed, summary.bytes);
+        }
+    }
+}
+
+/// A hard traversal error carrying the progress made before it.
+///
+/// Per-file failures never abort a restore; only structural errors -- an
+/// unreadable directory, a path grown past the kernel limit, a failed
+/// cross-device probe -- do. When one strikes partway through, the files
+/// already restored are real work the caller still wants reported, so
+/// the partial [`DirectRestoreResult`] (with its duration finalized)
+/// rides the error instead of being dropped with it.
+#[derive(Debug)]
+pub struct RestoreInterrupted {
+    /// Progress accumulated up to the error
+    pub partial: DirectRestoreResult,
+    /// The traversal error that stopped the restore
+    pub source: anyhow::Error,
+}
+
+impl std::fmt::Display for RestoreInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Restore interrupted after {} of {} discovered files restored",
+            self.partial.successful_files, self.partial.total_files
+        )
+    }
+}
+
+impl std::error::Error for RestoreInterrupted {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SkippedFile {
     pub path: PathBuf,
@@ -34,6 +142,17 @@ pub struct FailedFile {
     pub error: String,
 }
 
+/// Restore outcome counts for one top-level target directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirectorySummary {
+    pub restored: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// Bytes present at the restored target paths
+    pub bytes: u64,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum CopyResult {
     Success,
@@ -91,12 +210,527 @@ pub struct CleanupDetail {
     pub message: String,
 }
 
+/// One problem found while verifying a backup without writing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyIssue {
+    /// The backup file the issue applies to
+    pub path: PathBuf,
+    /// Issue category: "mapping", "protected-path" or "unreadable"
+    pub kind: String,
+    pub detail: String,
+}
+
+/// Result of a `--dry-run-verify` pass over a backup directory
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyResult {
+    pub total_files: usize,
+    pub verified_files: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyResult {
+    pub fn is_restorable(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Confirm a backup entry's content can be read: regular files must open
+/// and yield their first block; symlinks must resolve to an existing target
+fn check_source_readable(path: &Path, metadata: &fs::Metadata) -> Result<()> {
+    use std::io::Read;
+
+    if metadata.file_type().is_symlink() {
+        fs::metadata(path)
+            .with_context(|| format!("Symlink target does not resolve: {}", path.display()))?;
+        return Ok(());
+    }
+
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Cannot open backup file: {}", path.display()))?;
+    let mut block = [0u8; 4096];
+    file.read(&mut block)
+        .with_context(|| format!("Cannot read backup file: {}", path.display()))?;
+    Ok(())
+}
+
+/// Cgroup markers that identify container runtimes in /proc/1/cgroup
+const CONTAINER_CGROUP_MARKERS: &[&str] = &["docker", "containerd", "kubepods", "libpod", "crio", "lxc"];
+
+/// Whether a /proc/1/cgroup dump names a container runtime
+pub fn cgroup_indicates_container(cgroup_contents: &str) -> bool {
+    cgroup_contents
+        .lines()
+        .any(|line| CONTAINER_CGROUP_MARKERS.iter().any(|marker| line.contains(marker)))
+}
+
+/// Best-effort detection of running inside a container, where restoring
+/// onto / is the intended deployment rather than a developer accident
+pub fn running_inside_container() -> bool {
+    if std::env::var_os("KUBERNETES_SERVICE_HOST").is_some() {
+        return true;
+    }
+    if Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists() {
+        return true;
+    }
+    fs::read_to_string("/proc/1/cgroup")
+        .map(|contents| cgroup_indicates_container(&contents))
+        .unwrap_or(false)
+}
+
+/// Restoring onto / outside a container has wiped real files on developer
+/// machines. Refuse unless the operator passed the explicit flag, set
+/// CONFIRM=1, or the process is detectably inside a container.
+pub fn check_root_restore_guard(explicit_flag: bool, inside_container: bool) -> Result<()> {
+    if explicit_flag {
+        return Ok(());
+    }
+    if std::env::var("CONFIRM").is_ok_and(|v| v == "1") {
+        return Ok(());
+    }
+    if inside_container {
+        return Ok(());
+    }
+    bail!(
+        "Refusing to restore onto / outside a container. \
+         Pass --i-understand-restore-to-root (or set CONFIRM=1) to proceed anyway."
+    )
+}
+
+/// Purge trash run directories under `<backup_root>/.trash` older than the
+/// retention period (by directory mtime). In dry-run the same report is
+/// produced — which runs the policy selects and how many bytes each would
+/// reclaim — without removing anything, so operators can preview a
+/// retention setting before enabling it. A missing trash directory is not
+/// an error.
+pub fn purge_trash(
+    backup_root: &Path,
+    retention: Duration,
+    dry_run: bool,
+) -> Result<crate::result_envelope::CleanupResult> {
+    purge_trash_with_clock(backup_root, retention, dry_run, &crate::clock::SYSTEM_CLOCK)
+}
+
+/// [`purge_trash`] against an injected clock, so tests can place "now"
+/// exactly at the retention boundary
+pub fn purge_trash_with_clock(
+    backup_root: &Path,
+    retention: Duration,
+    dry_run: bool,
+    clock: &dyn crate::clock::Clock,
+) -> Result<crate::result_envelope::CleanupResult> {
+    let mut report = crate::result_envelope::CleanupResult {
+        dry_run,
+        ..Default::default()
+    };
+    let trash_dir = backup_root.join(TRASH_DIR_NAME);
+    if !trash_dir.exists() {
+        return Ok(report);
+    }
+
+    for entry in fs::read_dir(&trash_dir)
+        .with_context(|| format!("Failed to read trash directory: {}", trash_dir.display()))?
+    {
+        let entry = entry?;
+        let run_dir = entry.path();
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let age = entry
+            .metadata()?
+            .modified()
+            .ok()
+            .and_then(|modified| clock.now().duration_since(modified).ok())
+            .unwrap_or(Duration::ZERO);
+        if age >= retention {
+            let bytes = directory_size(&run_dir);
+            if dry_run {
+                info!("DRY RUN: would purge trash run directory: {} (age {:?}, {} bytes)",
+                      run_dir.display(), age, bytes);
+            } else {
+                info!("Purging trash run directory: {} (age {:?}, {} bytes)",
+                      run_dir.display(), age, bytes);
+                fs::remove_dir_all(&run_dir)
+                    .with_context(|| format!("Failed to purge trash directory: {}", run_dir.display()))?;
+            }
+            report.purged_runs += 1;
+            report.reclaimable_bytes += bytes;
+            report.runs.push(crate::result_envelope::PurgedRun { path: run_dir, bytes });
+        }
+    }
+    if dry_run {
+        info!("DRY RUN: {} trash runs selected, {} bytes reclaimable",
+              report.purged_runs, report.reclaimable_bytes);
+    }
+    Ok(report)
+}
+
+/// Total size of the regular files under a directory; unreadable entries
+/// count as zero rather than failing the cleanup
+fn directory_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// How the copy path uses reflinks (`ioctl(FICLONE)`). On CoW
+/// filesystems (XFS with reflink, Btrfs) a clone is near-instant and
+/// shares extents instead of duplicating them; filesystems without
+/// clone support fall back to a normal copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReflinkMode {
+    /// Try a reflink first; fall back to a normal copy quietly
+    Auto,
+    /// Try a reflink first; fall back to a normal copy with a warning
+    Always,
+    /// Never attempt a reflink
+    #[default]
+    Never,
+}
+
 #[derive(Debug)]
 pub struct DirectRestoreEngine {
     pub dry_run: bool,
     pub timeout: u64,
     pub max_retries: u32,
     pub retry_delay: Duration,
+    /// Total retries allowed across the whole run; None means unlimited.
+    /// Per-file `max_retries` can multiply into enormous aggregate delays on
+    /// a degraded filesystem, so once this budget is spent remaining
+    /// transient errors fail immediately without further sleeping.
+    pub retry_budget: Option<u64>,
+    /// Retries consumed so far, shared atomically across parallel workers
+    retries_used: std::sync::atomic::AtomicU64,
+    /// Issue readahead advice for upcoming files while earlier ones are
+    /// being written; pays off on cold network filesystems
+    pub prefetch: bool,
+    /// How many files the prefetch pool keeps warmed ahead of the workers
+    pub prefetch_depth: usize,
+    /// Optional per-file timing collection for hotspot analysis
+    profiler: Option<RestoreProfiler>,
+    /// Rename cleaned backup files into `<backup_root>/.trash/<timestamp>/`
+    /// instead of deleting them, so a bad restore remains recoverable
+    pub trash_mode: bool,
+    /// Never consume the backup: files are copied instead of moved and no
+    /// cleanup runs afterwards, so the same backup stays restorable.
+    /// Checkpoint restores run in this mode.
+    pub preserve_backup: bool,
+    /// Trash destination for this run: (backup root, run trash directory)
+    trash_context: once_cell::sync::OnceCell<(PathBuf, PathBuf)>,
+    /// Cleaned files renamed into the trash this run
+    trashed_count: std::sync::atomic::AtomicUsize,
+    /// Cleaned files permanently deleted this run
+    deleted_count: std::sync::atomic::AtomicUsize,
+    /// Wall-clock budget for the whole run, created once from `timeout`
+    pub deadline: Deadline,
+    pub repair_parent_permissions: bool,
+    /// Treat skipped files (busy, read-only, permission denied) as failures
+    pub strict: bool,
+    /// When set, file batches are processed through a throughput-adaptive
+    /// concurrency controller instead of a flat par_iter over the whole batch
+    pub adaptive_parallelism: bool,
+    pub max_parallelism: usize,
+    /// Original permissions of parent directories that were temporarily made
+    /// writable so child file copies could proceed; restored at the end of a run
+    /// Per-category cap on stored detail entries; counters beyond the cap
+    /// stay exact while the entries themselves are dropped
+    pub max_detail_entries: usize,
+    /// Skip the per-file temp rollback copy during cleanup. Faster (halves
+    /// cleanup I/O) but a failed deletion can no longer be rolled back; only
+    /// the pre-deletion size/readability validation still applies.
+    pub fast_cleanup: bool,
+    /// Translate overlayfs whiteout nodes into deletions on the target
+    /// instead of skipping them as special files
+    pub overlayfs_whiteouts: bool,
+    /// Whether file copies try `ioctl(FICLONE)` before falling back to
+    /// a byte copy
+    pub reflink: ReflinkMode,
+    /// Cooperative cancellation handle for embedding daemons, checked on
+    /// the same boundaries as the deadline; a cancelled token finalizes
+    /// the run with the cancelled disposition
+    pub cancel: Option<tokio_util::sync::CancellationToken>,
+    /// Only create files absent from the container; never touch a target
+    /// that already exists (warm-start merge)
+    pub merge_missing_only: bool,
+    /// Skip symlinks whose target, resolved lexically, leaves the
+    /// top-level directory they are restored into (multi-tenant safety)
+    pub confine_symlinks: bool,
+    /// Paths under which recorded setuid/setgid bits and file capabilities
+    /// are re-applied after a successful restore. Empty (the default) means
+    /// never: privileged attributes are strictly opt-in.
+    pub privileged_attr_paths: Vec<PathBuf>,
+    /// Read-only subtree classification shared across worker threads
+    pub readonly_subtrees: ReadOnlySubtreeTracker,
+    /// Directories (from --no-restore-dir) that must never be restored into,
+    /// in addition to directories carrying the sentinel file
+    pub no_restore_dirs: Vec<PathBuf>,
+    /// File extensions admitted by `--restore-ext`, lowercased without
+    /// the leading dot; empty means no extension filtering
+    pub restore_extensions: Vec<String>,
+    /// Cache of per-directory sentinel lookups to avoid repeated stats
+    no_restore_cache: Mutex<HashMap<PathBuf, bool>>,
+    /// Destination filesystem capabilities, probed once on first use
+    capabilities: once_cell::sync::OnceCell<CapabilitySet>,
+    /// Backup manifest loaded lazily from the backup root, used for
+    /// transparent decompression and integrity checks
+    backup_manifest: once_cell::sync::OnceCell<Option<BackupManifest>>,
+    repaired_parents: Mutex<HashMap<PathBuf, std::fs::Permissions>>,
+    /// Bounds concurrent file opens so parallel workers respect the
+    /// process fd limit; sized from getrlimit unless overridden
+    fd_semaphore: crate::resource_manager::FdSemaphore,
+}
+
+/// Count a skipped file under its normalized reason category
+fn record_skip_reason(result: &mut DirectRestoreResult, reason: &str) {
+    *result
+        .skip_reason_counts
+        .entry(crate::categorize_skip_reason(reason).to_string())
+        .or_insert(0) += 1;
+}
+
+/// Feed one worker's outcome into the shared progress counters from
+/// inside the copy closure, so the heartbeat and periodic logger see
+/// movement before the outcome travels back for aggregation
+fn note_worker_progress(
+    progress: &crate::progress::ProgressCounters,
+    file_path: &Path,
+    outcome: &Result<FileProcessOutcome>,
+) {
+    match outcome {
+        Ok(FileProcessOutcome::Success) | Ok(FileProcessOutcome::Cleaned) => {
+            let bytes = fs::symlink_metadata(file_path).map(|m| m.len()).unwrap_or(0);
+            progress.record_file(bytes);
+        }
+        Ok(FileProcessOutcome::Skipped(_)) => progress.record_skip(),
+        Ok(FileProcessOutcome::Failed(_)) | Err(_) => progress.record_failure(),
+    }
+}
+
+/// Push a detail entry unless the per-category cap has been reached, in
+/// which case only the truncation counter is advanced
+fn push_detail_capped<T>(details: &mut Vec<T>, entry: T, cap: usize, truncated: &mut usize) {
+    if details.len() < cap {
+        details.push(entry);
+    } else {
+        *truncated += 1;
+    }
+}
+
+/// Summary key for a backup file: its first path component relative to
+/// the backup root (the top-level target directory, e.g. "/home"), or
+/// "/" for files restored directly into the target root
+fn summary_key(backup_file: &Path, backup_root: &Path) -> String {
+    let Ok(relative) = backup_file.strip_prefix(backup_root) else {
+        return "/".to_string();
+    };
+    let mut components = relative.components().filter_map(|component| match component {
+        Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+        _ => None,
+    });
+    match (components.next(), components.next()) {
+        (Some(first), Some(_)) => format!("/{}", first),
+        _ => "/".to_string(),
+    }
+}
+
+/// Resolve a symlink target lexically against the directory holding the
+/// link: `..` pops, `.` is dropped, an absolute target restarts at `/`.
+/// Lexical (rsync "safe links" style) rather than via canonicalize so a
+/// dangling target still resolves and intermediate links on the target
+/// machine cannot influence the verdict.
+fn lexical_resolve(link_dir: &Path, target: &Path) -> PathBuf {
+    let mut resolved = if target.is_absolute() {
+        PathBuf::from("/")
+    } else {
+        link_dir.to_path_buf()
+    };
+    for component in target.components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            // pop() at "/" is a no-op, so over-popping clamps at the root
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::RootDir | Component::Prefix(_) | Component::CurDir => {}
+        }
+    }
+    resolved
+}
+
+/// Sentinel file name marking a directory as runtime-managed: session data
+/// must never be restored into it
+pub const NO_RESTORE_SENTINEL: &str = ".session-no-restore";
+
+/// Directory under the backup root holding trashed (recoverable) cleanups
+pub const TRASH_DIR_NAME: &str = ".trash";
+
+/// Target prefixes a restore should never write into; flagged by
+/// `--dry-run-verify` so a bad backup layout is caught before any write
+pub const PROTECTED_TARGET_PREFIXES: &[&str] = &[
+    "/proc", "/sys", "/dev", "/boot", "/etc", "/bin", "/sbin", "/usr/bin", "/usr/sbin",
+];
+
+/// Whether a directory entry is an overlayfs whiteout: a character device
+/// with device number 0,0 recording a deletion in the upper layer
+#[cfg(unix)]
+pub fn is_overlayfs_whiteout(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::fs::MetadataExt;
+    metadata.file_type().is_char_device() && metadata.rdev() == 0
+}
+
+#[cfg(not(unix))]
+pub fn is_overlayfs_whiteout(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Whether a directory carries the overlayfs opaque marker
+/// (`trusted.overlay.opaque` = "y"), meaning it replaces rather than merges
+/// with the lower directory
+#[cfg(unix)]
+pub fn is_opaque_dir(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let attr = c"trusted.overlay.opaque";
+    let mut value = [0u8; 1];
+    let len = unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            attr.as_ptr(),
+            value.as_mut_ptr() as *mut libc::c_void,
+            value.len(),
+        )
+    };
+    len == 1 && value[0] == b'y'
+}
+
+/// Write the raw `security.capability` xattr onto a restored file; needs
+/// CAP_SETFCAP, so callers gate this behind the privilege check
+#[cfg(target_os = "linux")]
+fn set_capability_xattr(path: &Path, value: &[u8]) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains NUL"))?;
+    let attr = c"security.capability";
+    let rc = unsafe {
+        libc::setxattr(
+            c_path.as_ptr(),
+            attr.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_capability_xattr(_path: &Path, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "file capabilities are Linux-only"))
+}
+
+/// Whether this process has the privilege to set setuid bits and file
+/// capabilities regardless of ownership
+#[cfg(unix)]
+fn running_privileged() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn running_privileged() -> bool {
+    false
+}
+
+/// Log phrasing for the two early-stop triggers: the wall-clock deadline
+/// and an attached cancellation token
+fn stop_cause(deadline_expired: bool) -> &'static str {
+    if deadline_expired {
+        "Wall-clock deadline reached"
+    } else {
+        "Cancellation requested"
+    }
+}
+
+/// EROFS failures needed under one top-level directory before the whole
+/// subtree is treated as read-only
+const READONLY_SUBTREE_THRESHOLD: usize = 3;
+
+/// Tracks "Read-only filesystem" failures per top-level target directory.
+///
+/// On readOnlyRootFilesystem pods every copy to / fails with EROFS; without
+/// classification each file burns the full retry budget and a no-op restore
+/// crawls for minutes. After a few EROFS failures under the same top-level
+/// directory the subtree is marked read-only and remaining files under it
+/// are skipped immediately (EROFS is not transient, so no retries).
+#[derive(Debug, Default)]
+pub struct ReadOnlySubtreeTracker {
+    erofs_counts: Mutex<HashMap<PathBuf, usize>>,
+}
+
+impl ReadOnlySubtreeTracker {
+    /// The top-level directory of an absolute container path
+    /// (e.g. "/root/.bashrc" -> "/root")
+    fn top_level(target: &Path) -> PathBuf {
+        let mut top = PathBuf::from("/");
+        if let Some(component) = target
+            .components()
+            .find(|c| matches!(c, Component::Normal(_)))
+        {
+            top.push(component);
+        }
+        top
+    }
+
+    /// Record an EROFS failure for the target's top-level directory.
+    /// Returns true when this failure crossed the threshold.
+    pub fn record_erofs(&self, target: &Path) -> bool {
+        let top = Self::top_level(target);
+        let mut counts = self.erofs_counts.lock();
+        let count = counts.entry(top.clone()).or_insert(0);
+        *count += 1;
+        if *count == READONLY_SUBTREE_THRESHOLD {
+            warn!("Marking {} as read-only after {} EROFS failures; remaining files under it will be skipped",
+                  top.display(), READONLY_SUBTREE_THRESHOLD);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the target's subtree has been classified as read-only
+    pub fn is_readonly(&self, target: &Path) -> bool {
+        let top = Self::top_level(target);
+        self.erofs_counts
+            .lock()
+            .get(&top)
+            .is_some_and(|&count| count >= READONLY_SUBTREE_THRESHOLD)
+    }
+
+    /// Top-level directories classified as read-only, for the run summary
+    pub fn readonly_subtrees(&self) -> Vec<PathBuf> {
+        let mut subtrees: Vec<PathBuf> = self
+            .erofs_counts
+            .lock()
+            .iter()
+            .filter(|(_, &count)| count >= READONLY_SUBTREE_THRESHOLD)
+            .map(|(top, _)| top.clone())
+            .collect();
+        subtrees.sort();
+        subtrees
+    }
 }
 
 impl DirectRestoreEngine {
@@ -106,7 +740,166 @@ impl DirectRestoreEngine {
             timeout,
             max_retries: 3,
             retry_delay: Duration::from_millis(500),
+            retry_budget: None,
+            retries_used: std::sync::atomic::AtomicU64::new(0),
+            prefetch: false,
+            prefetch_depth: prefetch::DEFAULT_PREFETCH_DEPTH,
+            profiler: None,
+            trash_mode: false,
+            preserve_backup: false,
+            trash_context: once_cell::sync::OnceCell::new(),
+            trashed_count: std::sync::atomic::AtomicUsize::new(0),
+            deleted_count: std::sync::atomic::AtomicUsize::new(0),
+            deadline: Deadline::from_secs(timeout),
+            repair_parent_permissions: true,
+            strict: false,
+            adaptive_parallelism: false,
+            max_parallelism: 16,
+            max_detail_entries: DEFAULT_MAX_DETAIL_ENTRIES,
+            fast_cleanup: false,
+            overlayfs_whiteouts: false,
+            reflink: ReflinkMode::Never,
+            cancel: None,
+            merge_missing_only: false,
+            confine_symlinks: false,
+            privileged_attr_paths: Vec::new(),
+            readonly_subtrees: ReadOnlySubtreeTracker::default(),
+            no_restore_dirs: Vec::new(),
+            restore_extensions: Vec::new(),
+            no_restore_cache: Mutex::new(HashMap::new()),
+            capabilities: once_cell::sync::OnceCell::new(),
+            backup_manifest: once_cell::sync::OnceCell::new(),
+            repaired_parents: Mutex::new(HashMap::new()),
+            fd_semaphore: crate::resource_manager::FdSemaphore::from_fd_limit(
+                crate::resource_manager::ResourceMonitor::detect().max_open_files,
+            ),
+        }
+    }
+
+    /// Cap concurrent file opens below the given limit instead of the
+    /// detected rlimit
+    pub fn with_max_open_files(mut self, max_open_files: usize) -> Self {
+        self.fd_semaphore = crate::resource_manager::FdSemaphore::from_fd_limit(max_open_files);
+        self
+    }
+
+    pub fn with_merge_missing_only(mut self, enabled: bool) -> Self {
+        self.merge_missing_only = enabled;
+        self
+    }
+
+    /// Restrict the restore to files with one of the given extensions
+    /// (case-insensitive, leading dot optional); extensionless files
+    /// match nothing and are skipped when a filter is set
+    pub fn with_restore_extensions(mut self, extensions: Vec<String>) -> Self {
+        self.restore_extensions = extensions
+            .into_iter()
+            .map(|ext| ext.trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    pub fn with_no_restore_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.no_restore_dirs = dirs;
+        self
+    }
+
+    pub fn with_confine_symlinks(mut self, enabled: bool) -> Self {
+        self.confine_symlinks = enabled;
+        self
+    }
+
+    pub fn with_privileged_attr_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.privileged_attr_paths = paths;
+        self
+    }
+
+    pub fn with_overlayfs_whiteouts(mut self, enabled: bool) -> Self {
+        self.overlayfs_whiteouts = enabled;
+        self
+    }
+
+    /// Set how file copies use reflinks; see [`ReflinkMode`]
+    pub fn with_reflink(mut self, mode: ReflinkMode) -> Self {
+        self.reflink = mode;
+        self
+    }
+
+    /// Attach a cancellation token; cancelling it stops the restore on
+    /// the next directory or tar-entry boundary with `cancelled` set
+    pub fn with_cancellation(mut self, token: tokio_util::sync::CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// True when an attached cancellation token has been cancelled
+    fn cancellation_requested(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|token| token.is_cancelled())
+    }
+
+    pub fn with_fast_cleanup(mut self, fast_cleanup: bool) -> Self {
+        self.fast_cleanup = fast_cleanup;
+        self
+    }
+
+    pub fn with_max_detail_entries(mut self, max_detail_entries: usize) -> Self {
+        self.max_detail_entries = max_detail_entries.max(1);
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn with_adaptive_parallelism(mut self, enabled: bool, max_parallelism: usize) -> Self {
+        self.adaptive_parallelism = enabled;
+        self.max_parallelism = max_parallelism.max(1);
+        self
+    }
+
+    pub fn with_repair_parent_permissions(mut self, enabled: bool) -> Self {
+        self.repair_parent_permissions = enabled;
+        self
+    }
+
+    /// Rename cleaned backup files into the trash instead of deleting them
+    pub fn with_trash_mode(mut self, enabled: bool) -> Self {
+        self.trash_mode = enabled;
+        self
+    }
+
+    /// Never consume the backup: copy instead of move, skip all cleanup
+    pub fn with_preserve_backup(mut self, enabled: bool) -> Self {
+        self.preserve_backup = enabled;
+        self
+    }
+
+    /// Enable per-file timing collection, keeping the `top_n` slowest files
+    pub fn with_profiling(mut self, enabled: bool, top_n: usize) -> Self {
+        self.profiler = enabled.then(|| RestoreProfiler::new(top_n));
+        self
+    }
+
+    /// Expose the collected timing profile, when profiling is enabled
+    pub fn profile_report(&self) -> Option<profiling::ProfileReport> {
+        self.profiler.as_ref().map(|profiler| profiler.report())
+    }
+
+    /// Enable warm-cache prefetching with the given lookahead depth
+    pub fn with_prefetch(mut self, enabled: bool, depth: usize) -> Self {
+        self.prefetch = enabled;
+        if depth > 0 {
+            self.prefetch_depth = depth;
         }
+        self
+    }
+
+    /// Set the global retry budget: the total number of retries allowed
+    /// across the whole run, shared by all workers
+    pub fn with_retry_budget(mut self, retry_budget: Option<u64>) -> Self {
+        self.retry_budget = retry_budget;
+        self
     }
 
     pub fn with_retry_config(mut self, max_retries: u32, retry_delay: Duration) -> Self {
@@ -131,24 +924,73 @@ impl DirectRestoreEngine {
             skipped_details: Vec::new(),
             failed_details: Vec::new(),
             cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
             duration: Duration::from_secs(0),
         };
 
+        if self.trash_mode {
+            let run_dir = backup_path
+                .join(TRASH_DIR_NAME)
+                .join(chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string());
+            let _ = self.trash_context.set((backup_path.to_path_buf(), run_dir));
+        }
+
         if !backup_path.exists() {
             warn!("Backup path does not exist: {}", backup_path.display());
             result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
             return Ok(result);
         }
 
+        // A single-file backup path restores that one file, mapped
+        // relative to its parent directory
+        if backup_path.is_file() {
+            let backup_root = backup_path.parent().unwrap_or_else(|| Path::new("/"));
+            result.total_files = 1;
+            let outcome = self.process_single_file(backup_path, backup_root);
+            self.aggregate_file_outcome(backup_path.to_path_buf(), outcome, backup_root, &mut result);
+            result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+            return Ok(result);
+        }
+
         // Check if we're in a cross-device scenario and use bulk transfer if so
-        if self.is_cross_device_scenario(backup_path)? {
+        let cross_device = match self.is_cross_device_scenario(backup_path) {
+            Ok(cross_device) => cross_device,
+            Err(e) => return Err(self.interrupted(result, start_time, e)),
+        };
+        if cross_device {
             info!("Cross-device scenario detected, using bulk transfer optimization");
             return self.restore_with_bulk_transfer(backup_path, start_time);
         }
 
-        // Use parallel directory processing for same-device operations
-        self.process_directory_parallel(backup_path, backup_path, &mut result)?;
+        // Use parallel directory processing for same-device operations;
+        // a hard traversal error keeps the progress made so far on the
+        // returned error
+        if let Err(e) = self.process_directory_parallel(backup_path, backup_path, &mut result) {
+            return Err(self.interrupted(result, start_time, e));
+        }
+
+        // Small files packed at backup time are restored from the pack
+        // files, with the manifest as the index
+        self.restore_packed_entries(backup_path, &mut result);
 
+        // Restore the original modes of any parent directories we had to repair
+        result.repaired_directories = self.restore_repaired_parent_permissions();
+
+        result.trashed_files = self.trashed_count.swap(0, std::sync::atomic::Ordering::Relaxed);
+        result.permanently_deleted_files = self.deleted_count.swap(0, std::sync::atomic::Ordering::Relaxed);
         result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
         
         info!("Optimized direct restore completed:");
@@ -157,6 +999,34 @@ impl DirectRestoreEngine {
         info!("  Skipped: {}", result.skipped_files);
         info!("  Failed: {}", result.failed_files);
         info!("  Cleaned from backup: {}", result.cleaned_files);
+        if result.trashed_files > 0 || result.permanently_deleted_files > 0 {
+            info!("  Trashed: {} / permanently deleted: {}",
+                  result.trashed_files, result.permanently_deleted_files);
+        }
+        if result.repaired_directories > 0 {
+            info!("  Repaired parent directories: {}", result.repaired_directories);
+        }
+        if result.truncated_details > 0 {
+            warn!("  Truncated detail entries: {} (per-category cap: {}, counters remain exact)",
+                  result.truncated_details, self.max_detail_entries);
+        }
+        if !result.skip_reason_counts.is_empty() {
+            let mut breakdown: Vec<_> = result.skip_reason_counts.iter().collect();
+            breakdown.sort();
+            info!("  Skip reasons: {}",
+                  breakdown.iter().map(|(reason, count)| format!("{}={}", reason, count)).collect::<Vec<_>>().join(", "));
+        }
+        let readonly_subtrees = self.readonly_subtrees.readonly_subtrees();
+        if !readonly_subtrees.is_empty() {
+            warn!("  Top-level directories skipped as read-only: {}",
+                  readonly_subtrees.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", "));
+        }
+        if let Some(profiler) = &self.profiler {
+            profiler.log_summary();
+        }
+        if result.prefetch_issued > 0 {
+            info!("  Prefetch hits: {}/{} advisories", result.prefetch_hits, result.prefetch_issued);
+        }
         info!("  Duration: {:?}", result.duration);
 
         if !result.skipped_details.is_empty() {
@@ -188,45 +1058,31 @@ impl DirectRestoreEngine {
         Ok(result)
     }
 
-    /// Check if this is a cross-device scenario by testing a sample file move
-    fn is_cross_device_scenario(&self, backup_path: &Path) -> Result<bool> {
-        // Find a sample file to test
-        for entry in fs::read_dir(backup_path)? {
-            let entry = entry?;
-            let entry_path = entry.path();
-            
-            if entry_path.is_file() {
-                // Try to map to container path and test move
-                if let Ok(container_path) = self.map_backup_to_container_path(&entry_path, backup_path) {
-                    // Create parent directory for test
-                    if let Some(parent) = container_path.parent() {
-                        if let Err(_) = fs::create_dir_all(parent) {
-                            continue; // Skip this file, try another
-                        }
-                    }
-                    
-                    // Test rename (doesn't actually move, just checks if it would work)
-                    if let Err(e) = fs::hard_link(&entry_path, &container_path.with_extension("test_cross_device")) {
-                        if e.kind() == std::io::ErrorKind::CrossesDevices {
-                            debug!("Cross-device scenario detected via test file: {}", entry_path.display());
-                            return Ok(true);
-                        }
-                    } else {
-                        // Clean up test file
-                        let _ = fs::remove_file(&container_path.with_extension("test_cross_device"));
-                        return Ok(false); // Same device
-                    }
-                }
-            }
-        }
-        
-        Ok(false) // Default to same device if we can't test
+    /// Finalize a partially filled result and wrap the traversal error
+    /// in [`RestoreInterrupted`] so the caller can still report the
+    /// progress made before the failure
+    fn interrupted(&self, mut partial: DirectRestoreResult, start_time: SystemTime, source: anyhow::Error) -> anyhow::Error {
+        partial.trashed_files = self.trashed_count.swap(0, std::sync::atomic::Ordering::Relaxed);
+        partial.permanently_deleted_files = self.deleted_count.swap(0, std::sync::atomic::Ordering::Relaxed);
+        partial.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+        anyhow::Error::new(RestoreInterrupted { partial, source })
     }
 
-    /// Restore using bulk transfer for cross-device scenarios  
-    fn restore_with_bulk_transfer(&self, backup_path: &Path, start_time: SystemTime) -> Result<DirectRestoreResult> {
-        info!("Starting bulk transfer restoration for cross-device scenario");
-        
+    /// Restore directly from a tar stream, mapping each entry path to a
+    /// container path exactly as a loose backup file would be mapped
+    /// (entry `root/.bashrc` restores to `/root/.bashrc`).
+    ///
+    /// Every entry flows through the same validation and accounting as the
+    /// directory-based restore: path traversal rejection, merge mode,
+    /// no-restore directories, read-only subtree classification and symlink
+    /// confinement all apply. There is no backup directory to clean up, so
+    /// entries only ever count as restored, skipped or failed.
+    pub fn restore_from_tar<R: io::Read>(&self, reader: R) -> Result<DirectRestoreResult> {
+        let start_time = SystemTime::now();
+
+        info!("Starting streaming restore from tar archive");
+        info!("Dry run mode: {}", self.dry_run);
+
         let mut result = DirectRestoreResult {
             total_files: 0,
             successful_files: 0,
@@ -236,26 +1092,346 @@ impl DirectRestoreEngine {
             skipped_details: Vec::new(),
             failed_details: Vec::new(),
             cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
             duration: Duration::from_secs(0),
         };
 
-        // Count total files first
-        result.total_files = self.count_files_recursive(backup_path)?;
-        info!("Total files to transfer: {}", result.total_files);
+        // The mapping treats entry paths as relative to a synthetic backup
+        // root of "/", reusing the loose-file mapping and its validation
+        let backup_root = Path::new("/");
 
-        if self.dry_run {
-            info!("DRY RUN: Would perform bulk transfer of {} files", result.total_files);
-            result.successful_files = result.total_files;
-            result.cleaned_files = result.total_files;
-            result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
-            return Ok(result);
+        let mut archive = tar::Archive::new(reader);
+        archive.set_preserve_permissions(true);
+
+        for entry in archive.entries().context("Failed to read tar archive")? {
+            if self.deadline.expired() || self.cancellation_requested() {
+                warn!("{}, cancelling remaining tar entries", stop_cause(self.deadline.expired()));
+                result.cancelled = true;
+                break;
+            }
+
+            // A malformed header means the stream position is lost, and an
+            // unseekable stream cannot be resynchronized: stop hard rather
+            // than misattribute the remaining bytes
+            let mut entry = entry.context("Corrupt tar stream")?;
+            let relative = entry
+                .path()
+                .context("Tar entry has an unreadable path")?
+                .into_owned();
+
+            if entry.header().entry_type().is_dir() {
+                // Directories are not counted as files, matching the
+                // directory-based restore; just make sure they exist
+                if !self.dry_run {
+                    let target = backup_root.join(&relative);
+                    if self.validate_container_path(&target).is_ok() {
+                        let _ = crate::dir_cache::ensure_dir_exists(&target);
+                    }
+                }
+                continue;
+            }
+
+            result.total_files += 1;
+            let outcome = self.restore_tar_entry(&mut entry, &relative);
+            self.aggregate_file_outcome(backup_root.join(&relative), outcome, backup_root, &mut result);
         }
 
-        // Use rsync for efficient bulk transfer
-        match self.bulk_transfer_with_rsync(backup_path) {
-            Ok(transferred_count) => {
-                result.successful_files = transferred_count;
-                result.cleaned_files = transferred_count;
+        result.repaired_directories = self.restore_repaired_parent_permissions();
+        result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+
+        info!("Streaming tar restore completed:");
+        info!("  Total files: {}", result.total_files);
+        info!("  Successful: {}", result.successful_files);
+        info!("  Skipped: {}", result.skipped_files);
+        info!("  Failed: {}", result.failed_files);
+        info!("  Duration: {:?}", result.duration);
+
+        Ok(result)
+    }
+
+    /// Restore a single non-directory tar entry through the loose-file
+    /// validation pipeline, then unpack it in place
+    fn restore_tar_entry<R: io::Read>(
+        &self,
+        entry: &mut tar::Entry<R>,
+        relative: &Path,
+    ) -> Result<FileProcessOutcome> {
+        let backup_root = Path::new("/");
+        let target_path = match self.map_backup_to_container_path(&backup_root.join(relative), backup_root) {
+            Ok(path) => path,
+            Err(e) => {
+                error!("Failed to map tar entry to container path: {} - {}", relative.display(), e);
+                return Ok(FileProcessOutcome::Failed(format!("Path mapping failed: {}", e)));
+            }
+        };
+
+        debug!("Processing tar entry: {} -> {}", relative.display(), target_path.display());
+
+        // Merge mode only fills in gaps: an existing target (file, symlink
+        // or directory) is never touched
+        if self.merge_missing_only && fs::symlink_metadata(&target_path).is_ok() {
+            return Ok(FileProcessOutcome::Skipped("exists, merge mode".to_string()));
+        }
+
+        // Never restore into runtime-managed directories, whether flagged on
+        // the command line or marked with the sentinel file
+        if let Some(marker) = self.find_no_restore_ancestor(&target_path) {
+            return Ok(FileProcessOutcome::Skipped(format!(
+                "Destination under no-restore directory: {}", marker.display()
+            )));
+        }
+
+        if self.readonly_subtrees.is_readonly(&target_path) {
+            return Ok(FileProcessOutcome::Skipped(
+                "Read-only filesystem (subtree classified read-only)".to_string(),
+            ));
+        }
+
+        // The link target comes from the entry header rather than from disk
+        if self.confine_symlinks && entry.header().entry_type().is_symlink() {
+            if let Ok(Some(link_target)) = entry.link_name() {
+                if let Some(reason) = self.symlink_escape_reason(&link_target, &target_path) {
+                    return Ok(FileProcessOutcome::Skipped(reason));
+                }
+            }
+        }
+
+        if self.dry_run {
+            info!("DRY RUN: Would extract tar entry {} -> {}", relative.display(), target_path.display());
+            return Ok(FileProcessOutcome::Success);
+        }
+
+        // Create parent directories if needed (race-safe across parallel workers)
+        if let Some(parent) = target_path.parent() {
+            if let Err(e) = crate::dir_cache::ensure_dir_exists(parent) {
+                return Ok(FileProcessOutcome::Failed(format!("Failed to create parent directories: {}", e)));
+            }
+        }
+
+        match entry.unpack(&target_path) {
+            Ok(_) => {
+                info!("Successfully extracted: {}", target_path.display());
+                Ok(FileProcessOutcome::Success)
+            }
+            Err(e) => {
+                if self.is_file_busy(&e) {
+                    Ok(FileProcessOutcome::Skipped(format!("File busy: {}", e)))
+                } else if self.is_file_readonly(&e) {
+                    self.readonly_subtrees.record_erofs(&target_path);
+                    Ok(FileProcessOutcome::Skipped(format!("Read-only filesystem: {}", e)))
+                } else if self.is_permission_denied(&e) {
+                    Ok(FileProcessOutcome::Skipped(format!("Permission denied: {}", e)))
+                } else {
+                    error!("Failed to extract tar entry: {} - {}", target_path.display(), e);
+                    Ok(FileProcessOutcome::Failed(format!("Extract failed: {}", e)))
+                }
+            }
+        }
+    }
+
+    /// Restore a single container path from the backup without walking the
+    /// whole tree; a directory restores its subtree. See [`Self::restore_only`].
+    pub fn restore_single_path(&self, backup_path: &Path, container_path: &Path) -> Result<DirectRestoreResult> {
+        self.restore_only(backup_path, std::slice::from_ref(&container_path.to_path_buf()))
+    }
+
+    /// Restore only the given container paths (directories restore their
+    /// subtrees), applying the same validation, conflict policy and
+    /// attribute handling as a full restore. Loose and compressed copies
+    /// are resolved directly from their backup locations; packed small
+    /// files are resolved through the manifest index instead of scanning
+    /// every pack.
+    pub fn restore_only(&self, backup_path: &Path, container_paths: &[PathBuf]) -> Result<DirectRestoreResult> {
+        let start_time = SystemTime::now();
+
+        let mut result = DirectRestoreResult {
+            total_files: 0,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        if self.trash_mode {
+            let run_dir = backup_path
+                .join(TRASH_DIR_NAME)
+                .join(chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string());
+            let _ = self.trash_context.set((backup_path.to_path_buf(), run_dir));
+        }
+
+        for container_path in container_paths {
+            if self.deadline.expired() || self.cancellation_requested() {
+                warn!("{}, cancelling remaining selective restore work", stop_cause(self.deadline.expired()));
+                result.cancelled = true;
+                break;
+            }
+
+            let relative = container_path.strip_prefix("/").unwrap_or(container_path);
+            // Shared traversal validation before touching anything
+            self.validate_container_path(&Path::new("/").join(relative))?;
+
+            info!("Restoring selected path {} from {}", container_path.display(), backup_path.display());
+
+            let candidate = backup_path.join(relative);
+            // A file stored compressed sits next to where the loose copy
+            // would be, with the .zst suffix
+            let compressed = {
+                let mut name = candidate.clone().into_os_string();
+                name.push(".zst");
+                PathBuf::from(name)
+            };
+
+            if candidate.is_dir() {
+                self.process_directory_parallel(&candidate, backup_path, &mut result)?;
+                self.restore_packed_entries_filtered(backup_path, Some(relative), &mut result);
+            } else if fs::symlink_metadata(&candidate).is_ok() {
+                result.total_files += 1;
+                let outcome = self.process_single_file(&candidate, backup_path);
+                self.aggregate_file_outcome(candidate, outcome, backup_path, &mut result);
+            } else if compressed.is_file() {
+                result.total_files += 1;
+                let outcome = self.process_single_file(&compressed, backup_path);
+                self.aggregate_file_outcome(compressed, outcome, backup_path, &mut result);
+            } else {
+                // Not on disk as a loose or compressed file; the manifest
+                // index is the only place a packed entry appears
+                let before = result.total_files;
+                self.restore_packed_entries_filtered(backup_path, Some(relative), &mut result);
+                if result.total_files == before {
+                    result.total_files += 1;
+                    result.failed_files += 1;
+                    push_detail_capped(&mut result.failed_details, FailedFile {
+                        path: candidate,
+                        error: "Not found in backup (loose, compressed or packed)".to_string(),
+                    }, self.max_detail_entries, &mut result.truncated_details);
+                }
+            }
+        }
+
+        result.repaired_directories = self.restore_repaired_parent_permissions();
+        result.trashed_files = self.trashed_count.swap(0, std::sync::atomic::Ordering::Relaxed);
+        result.permanently_deleted_files = self.deleted_count.swap(0, std::sync::atomic::Ordering::Relaxed);
+        result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+
+        info!("Selective restore completed: {} total, {} successful, {} skipped, {} failed",
+              result.total_files, result.successful_files, result.skipped_files, result.failed_files);
+
+        Ok(result)
+    }
+
+    /// Check if this is a cross-device scenario by testing a sample file move
+    fn is_cross_device_scenario(&self, backup_path: &Path) -> Result<bool> {
+        // Find a sample file to test
+        for entry in fs::read_dir(backup_path)? {
+            let entry = entry?;
+            let entry_path = entry.path();
+            
+            if entry_path.is_file() {
+                // Try to map to container path and test move
+                if let Ok(container_path) = self.map_backup_to_container_path(&entry_path, backup_path) {
+                    // Create parent directory for test
+                    if let Some(parent) = container_path.parent() {
+                        if fs::create_dir_all(parent).is_err() {
+                            continue; // Skip this file, try another
+                        }
+                    }
+                    
+                    // Test rename (doesn't actually move, just checks if it would work)
+                    if let Err(e) = fs::hard_link(&entry_path, container_path.with_extension("test_cross_device")) {
+                        if e.kind() == std::io::ErrorKind::CrossesDevices {
+                            debug!("Cross-device scenario detected via test file: {}", entry_path.display());
+                            return Ok(true);
+                        }
+                    } else {
+                        // Clean up test file
+                        let _ = fs::remove_file(container_path.with_extension("test_cross_device"));
+                        return Ok(false); // Same device
+                    }
+                }
+            }
+        }
+        
+        Ok(false) // Default to same device if we can't test
+    }
+
+    /// Restore using bulk transfer for cross-device scenarios  
+    fn restore_with_bulk_transfer(&self, backup_path: &Path, start_time: SystemTime) -> Result<DirectRestoreResult> {
+        info!("Starting bulk transfer restoration for cross-device scenario");
+        
+        let mut result = DirectRestoreResult {
+            total_files: 0,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        // Count total files first
+        result.total_files = self.count_files_recursive(backup_path)?;
+        info!("Total files to transfer: {}", result.total_files);
+
+        if self.dry_run {
+            info!("DRY RUN: Would perform bulk transfer of {} files", result.total_files);
+            result.successful_files = result.total_files;
+            result.cleaned_files = result.total_files;
+            result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+            return Ok(result);
+        }
+
+        // Use rsync for efficient bulk transfer
+        match self.bulk_transfer_with_rsync(backup_path) {
+            Ok(transferred_count) => {
+                result.successful_files = transferred_count;
+                result.cleaned_files = transferred_count;
                 info!("Bulk transfer completed successfully: {} files", transferred_count);
                 
                 // Clean up backup directory after successful transfer
@@ -281,6 +1457,12 @@ impl DirectRestoreEngine {
         info!("Bulk transfer restoration completed:");
         info!("  Total files: {}", result.total_files);
         info!("  Successful: {}", result.successful_files);
+        if let Some(profiler) = &self.profiler {
+            profiler.log_summary();
+        }
+        if result.prefetch_issued > 0 {
+            info!("  Prefetch hits: {}/{} advisories", result.prefetch_hits, result.prefetch_issued);
+        }
         info!("  Duration: {:?}", result.duration);
 
         Ok(result)
@@ -735,108 +1917,1024 @@ impl DirectRestoreEngine {
         }
     }
 
-    /// Parallel directory processing for better performance
-    fn process_directory_parallel(&self, current_dir: &Path, backup_root: &Path, result: &mut DirectRestoreResult) -> Result<()> {
-        debug!("Processing directory with parallel operations: {}", current_dir.display());
+    /// Parallel directory processing for better performance.
+    /// Directories are walked with an explicit work queue rather than recursion
+    /// so pathologically deep trees cannot overflow the stack.
+    fn process_directory_parallel(&self, start_dir: &Path, backup_root: &Path, result: &mut DirectRestoreResult) -> Result<()> {
+        let mut queue: std::collections::VecDeque<(PathBuf, usize)> = std::collections::VecDeque::new();
+        queue.push_back((start_dir.to_path_buf(), 0));
+        // The tier of the last batch dispatched is recorded in the result
+        // when the deadline cuts a prioritized run short
+        let copy_order = crate::ordering::installed();
+        let mut last_tier: Option<&'static str> = None;
 
-        // Collect all file paths first
-        let mut file_paths = Vec::new();
-        let mut dir_paths = Vec::new();
-        
-        let entries = fs::read_dir(current_dir)
-            .with_context(|| format!("Failed to read directory: {}", current_dir.display()))?;
+        while let Some((current_dir, depth)) = queue.pop_front() {
+            crate::heartbeat::beat("restore", result.successful_files);
+            crate::progress::log_periodic("restore");
 
-        for entry in entries {
-            let entry = entry.with_context(|| format!("Failed to read directory entry in: {}", current_dir.display()))?;
-            let entry_path = entry.path();
+            // Finalize with the cancelled disposition once the budget is gone
+            if self.deadline.expired() || self.cancellation_requested() {
+                warn!("{}, cancelling remaining restore work", stop_cause(self.deadline.expired()));
+                result.cancelled = true;
+                result.priority_tier_reached = last_tier.map(str::to_string);
+                break;
+            }
+
+            debug!("Processing directory with parallel operations: {} (remaining budget: {:?})", 
+                   current_dir.display(), self.deadline.remaining());
+
+            // Collect all file paths first
+            let mut file_paths = Vec::new();
+
+            let entries = match fs::read_dir(&current_dir) {
+                Ok(entries) => entries,
+                // A directory deleted between listing and descent is
+                // benign; its contents are gone with it
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    debug!("Directory vanished mid-descend: {}", current_dir.display());
+                    record_skip_reason(result, "Directory vanished mid-descend");
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to read directory: {} (depth {}, path length {} bytes)",
+                                                          current_dir.display(), depth, current_dir.as_os_str().len()));
+                }
+            };
+
+            for entry in entries {
+                let entry = entry.with_context(|| format!("Failed to read directory entry in: {}", current_dir.display()))?;
+                let entry_path = entry.path();
+                
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    // Deleted between listing and stat: benign, never retried
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        debug!("Source vanished before stat: {}", entry_path.display());
+                        result.skipped_files += 1;
+                        record_skip_reason(result, "Source vanished before copy");
+                        continue;
+                    }
+                    Err(e) => {
+                        return Err(e).with_context(|| format!("Failed to get metadata for: {} (depth {}, path length {} bytes)",
+                                                              entry_path.display(), depth + 1, entry_path.as_os_str().len()));
+                    }
+                };
+
+                if metadata.is_dir() {
+                    if depth == 0 && entry_path.file_name().is_some_and(|n| n == TRASH_DIR_NAME) {
+                        // Trashed cleanups from prior runs are not session
+                        // data; restoring them again is opt-in by pointing
+                        // the backup path at a trash timestamp directory
+                        debug!("Skipping trash directory: {}", entry_path.display());
+                        continue;
+                    }
+                    if depth == 0 && entry_path.file_name().is_some_and(|n| n == crate::checkpoint::CHECKPOINTS_DIR_NAME) {
+                        // On-demand checkpoints are restored explicitly,
+                        // never as part of the lifecycle restore
+                        debug!("Skipping checkpoints directory: {}", entry_path.display());
+                        continue;
+                    }
+                    #[cfg(unix)]
+                    if self.overlayfs_whiteouts && is_opaque_dir(&entry_path) {
+                        // An opaque directory replaces the target directory
+                        // wholesale instead of merging into it
+                        if let Err(e) = self.apply_opaque_dir(&entry_path, backup_root) {
+                            warn!("Failed to apply opaque directory marker for {}: {}", entry_path.display(), e);
+                        }
+                    }
+                    queue.push_back((entry_path, depth + 1));
+                } else if metadata.is_file() {
+                    if depth == 0 && entry_path.file_name().is_some_and(|n| n == manifest::MANIFEST_FILE_NAME) {
+                        // The manifest describes the backup; it is not part
+                        // of the session data itself
+                        debug!("Skipping backup manifest: {}", entry_path.display());
+                        continue;
+                    }
+                    if depth == 0 && entry_path.file_name().is_some_and(|n| {
+                        n == crate::backup_layout::BACKUP_META_FILE_NAME
+                            || n == crate::checkpoint::CHECKPOINT_META_FILE_NAME
+                    }) {
+                        // Identity metadata describing the backup or
+                        // checkpoint, not session data
+                        debug!("Skipping identity metadata: {}", entry_path.display());
+                        continue;
+                    }
+                    if depth == 0 && entry_path.file_name().is_some_and(crate::packing::is_pack_file_name) {
+                        // Pack files are containers of many small files;
+                        // their contents are restored separately via the
+                        // manifest index
+                        debug!("Skipping pack file: {}", entry_path.display());
+                        continue;
+                    }
+                    file_paths.push(entry_path);
+                } else if metadata.file_type().is_symlink() {
+                    // Include symlinks for processing
+                    file_paths.push(entry_path);
+                } else if self.overlayfs_whiteouts && is_overlayfs_whiteout(&metadata) {
+                    // A whiteout records a deletion: remove the target path
+                    // rather than restoring the device node
+                    result.total_files += 1;
+                    let outcome = self.apply_whiteout(&entry_path, backup_root);
+                    self.aggregate_file_outcome(entry_path, outcome, backup_root, result);
+                } else {
+                    // Handle other special file types
+                    debug!("Skipping special file type: {}", entry_path.display());
+                    result.skipped_files += 1;
+                    record_skip_reason(result, "Special file type");
+                    result.directory_summaries
+                        .entry(summary_key(&entry_path, backup_root))
+                        .or_default()
+                        .skipped += 1;
+                    push_detail_capped(&mut result.skipped_details, SkippedFile {
+                        path: entry_path.clone(),
+                        reason: "Special file type (not regular file or symlink)".to_string(),
+                    }, self.max_detail_entries, &mut result.truncated_details);
+                }
+            }
             
-            let metadata = entry.metadata()
-                .with_context(|| format!("Failed to get metadata for: {}", entry_path.display()))?;
-
-            if metadata.is_dir() {
-                dir_paths.push(entry_path);
-            } else if metadata.is_file() {
-                file_paths.push(entry_path);
-            } else if metadata.file_type().is_symlink() {
-                // Include symlinks for processing
-                file_paths.push(entry_path);
-            } else {
-                // Handle other special file types
-                debug!("Skipping special file type: {}", entry_path.display());
-                result.skipped_files += 1;
-                result.skipped_details.push(SkippedFile {
-                    path: entry_path.clone(),
-                    reason: "Special file type (not regular file or symlink)".to_string(),
+            // Rearrange the batch under the configured copy order before
+            // any file is dispatched, and note the tier the queue tail
+            // belongs to for the deadline report
+            if copy_order != crate::ordering::CopyOrder::Natural {
+                let order_key = |path: &Path| {
+                    let relative = path.strip_prefix(backup_root).unwrap_or(path).to_path_buf();
+                    let size = fs::symlink_metadata(path).map(|m| m.len()).unwrap_or(0);
+                    (relative, size)
+                };
+                file_paths.sort_by_cached_key(|path| {
+                    let (relative, size) = order_key(path);
+                    copy_order.sort_key(&relative, size)
                 });
+                if let Some(last) = file_paths.last() {
+                    let (relative, size) = order_key(last);
+                    last_tier = Some(copy_order.tier(&relative, size));
+                }
+            }
+
+            result.total_files += file_paths.len();
+
+            // Keep the pipeline full on cold filesystems: warm upcoming
+            // files while earlier ones are being written
+            let prefetcher = self
+                .prefetch
+                .then(|| Prefetcher::spawn(file_paths.clone(), self.prefetch_depth));
+
+            // Process files in parallel using resource manager
+            let file_results = if self.adaptive_parallelism {
+                self.process_files_adaptive(&file_paths, backup_root, prefetcher.as_ref())
+            } else {
+                let progress = crate::progress::global();
+                let resource_manager = ResourceManager::global();
+                resource_manager.thread_pool.io_pool().install(|| {
+                    file_paths.par_iter().map(|file_path| {
+                        let outcome = self.process_single_file(file_path, backup_root);
+                        note_worker_progress(&progress, file_path, &outcome);
+                        if let Some(prefetcher) = prefetcher.as_ref() {
+                            prefetcher.note_consumed();
+                        }
+                        (file_path.clone(), outcome)
+                    }).collect()
+                })
+            };
+
+            if let Some(prefetcher) = prefetcher {
+                let stats = prefetcher.finish();
+                result.prefetch_hits += stats.hits;
+                result.prefetch_issued += stats.issued;
+            }
+
+            // Aggregate results
+            for (file_path, file_result) in file_results {
+                self.aggregate_file_outcome(file_path, file_result, backup_root, result);
             }
         }
-        
-        result.total_files += file_paths.len();
-        
-        // Process files in parallel using resource manager
+
+        Ok(())
+    }
+
+    /// Process a file batch in throughput-adaptive chunks: each chunk is run
+    /// at the controller's current concurrency and its measured throughput
+    /// feeds back into the next chunk's concurrency
+    fn process_files_adaptive(&self, file_paths: &[PathBuf], backup_root: &Path, prefetcher: Option<&Prefetcher>) -> Vec<(PathBuf, Result<FileProcessOutcome>)> {
+        let mut controller = AdaptiveConcurrency::new(2, self.max_parallelism);
+        let mut results = Vec::with_capacity(file_paths.len());
+        let progress = crate::progress::global();
         let resource_manager = ResourceManager::global();
-        let file_results: Vec<_> = resource_manager.thread_pool.io_pool().install(|| {
-            file_paths.par_iter().map(|file_path| {
-                self.process_single_file(file_path, backup_root)
-            }).collect()
-        });
-        
-        // Aggregate results
-        for file_result in file_results {
-            match file_result {
-                Ok(file_outcome) => {
-                    match file_outcome {
-                        FileProcessOutcome::Success => result.successful_files += 1,
-                        FileProcessOutcome::Skipped(_reason) => {
-                            result.skipped_files += 1;
-                            // Add to skipped details would need the path, which we'd need to track
-                        }
-                        FileProcessOutcome::Failed(_error) => {
-                            result.failed_files += 1;
-                            // Add to failed details would need the path
-                        }
-                        FileProcessOutcome::Cleaned => {
-                            result.successful_files += 1;
-                            result.cleaned_files += 1;
-                        }
+
+        let mut remaining = file_paths;
+        while !remaining.is_empty() {
+            let chunk_size = controller.current().min(remaining.len());
+            let (chunk, rest) = remaining.split_at(chunk_size);
+            remaining = rest;
+
+            let chunk_bytes: u64 = chunk.iter()
+                .filter_map(|path| fs::symlink_metadata(path).ok())
+                .map(|metadata| metadata.len())
+                .sum();
+
+            let window_start = std::time::Instant::now();
+            let chunk_results: Vec<_> = resource_manager.thread_pool.io_pool().install(|| {
+                chunk.par_iter().map(|file_path| {
+                    let outcome = self.process_single_file(file_path, backup_root);
+                    note_worker_progress(&progress, file_path, &outcome);
+                    if let Some(prefetcher) = prefetcher {
+                        prefetcher.note_consumed();
                     }
-                }
-                Err(e) => {
-                    result.failed_files += 1;
-                    result.failed_details.push(FailedFile {
-                        path: PathBuf::from("unknown"), // Would need better error tracking
-                        error: e.to_string(),
-                    });
-                }
+                    (file_path.clone(), outcome)
+                }).collect()
+            });
+            results.extend(chunk_results);
+
+            controller.record_window(chunk_bytes, window_start.elapsed());
+        }
+
+        results
+    }
+
+    /// The backup manifest for this run's backup root, loaded once
+    fn manifest_for(&self, backup_root: &Path) -> Option<&BackupManifest> {
+        self.backup_manifest
+            .get_or_init(|| manifest::load_manifest_lenient(backup_root))
+            .as_ref()
+    }
+
+    /// The manifest entry for a `.zst`-stored backup file, when the
+    /// manifest marks the original path as compressed
+    fn compressed_manifest_entry(&self, backup_file_path: &Path, backup_root: &Path) -> Option<manifest::ManifestEntry> {
+        let relative = backup_file_path.strip_prefix(backup_root).ok()?;
+        if relative.extension()? != "zst" {
+            return None;
+        }
+        let original_relative = relative.with_extension("");
+        let entry = self.manifest_for(backup_root)?.get(&original_relative)?;
+        if entry.compressed {
+            Some(entry.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Decompress a zstd-stored backup file to its target path, verifying
+    /// the decompressed content against the manifest's original hash
+    fn restore_compressed_file(
+        &self,
+        backup_file_path: &Path,
+        compressed_target: &Path,
+        entry: &manifest::ManifestEntry,
+    ) -> Result<FileProcessOutcome> {
+        // The mapped target still carries the .zst suffix; the real
+        // destination is the original name
+        let target_path = compressed_target.with_extension("");
+
+        if self.dry_run {
+            info!("DRY RUN: Would decompress {} -> {}", backup_file_path.display(), target_path.display());
+            return Ok(FileProcessOutcome::Success);
+        }
+
+        if let Some(parent) = target_path.parent() {
+            if let Err(e) = crate::dir_cache::ensure_dir_exists(parent) {
+                return Ok(FileProcessOutcome::Failed(format!(
+                    "Failed to create parent directories for {}: {}", target_path.display(), e
+                )));
             }
         }
-        
-        // Recursively process subdirectories
-        for dir_path in dir_paths {
-            self.process_directory_parallel(&dir_path, backup_root, result)?;
+
+        if let Err(e) = crate::compression::decompress_file(backup_file_path, &target_path) {
+            return Ok(FileProcessOutcome::Failed(format!(
+                "Failed to decompress {}: {}", backup_file_path.display(), e
+            )));
         }
 
-        Ok(())
+        // Verify against the original (uncompressed) content hash
+        match manifest::hash_file_contents(&target_path) {
+            Ok(hash) if hash == entry.original_hash => {
+                info!("Decompressed and verified: {}", target_path.display());
+            }
+            Ok(hash) if entry.unstable => {
+                // The source changed while being backed up; a mismatch
+                // against the pre-copy hash is expected, not corruption
+                warn!(
+                    "Hash mismatch on unstable entry {} (changed during backup); keeping restored content ({})",
+                    target_path.display(), hash
+                );
+            }
+            Ok(hash) => {
+                let _ = fs::remove_file(&target_path);
+                return Ok(FileProcessOutcome::Failed(format!(
+                    "Hash mismatch after decompressing {}: expected {}, got {}",
+                    backup_file_path.display(), entry.original_hash, hash
+                )));
+            }
+            Err(e) => {
+                warn!("Could not verify decompressed file {}: {}", target_path.display(), e);
+            }
+        }
+
+        Ok(self.consume_backup_file(backup_file_path))
     }
 
-    /// Process a single file with optimized operations
-    fn process_single_file(&self, backup_file_path: &Path, backup_root: &Path) -> Result<FileProcessOutcome> {
-        // Map backup file path to container target path
-        let target_path = match self.map_backup_to_container_path(backup_file_path, backup_root) {
-            Ok(path) => path,
+    /// Consume the backup copy of a successfully restored file, unless the
+    /// engine is preserving the backup (checkpoint restores). A failed
+    /// cleanup downgrades to plain success: the restore itself happened.
+    fn consume_backup_file(&self, backup_file_path: &Path) -> FileProcessOutcome {
+        if self.preserve_backup {
+            return FileProcessOutcome::Success;
+        }
+        match self.cleanup_backup_file(backup_file_path) {
+            Ok(()) => FileProcessOutcome::Cleaned,
             Err(e) => {
-                error!("Failed to map backup path to container path: {} - {}", backup_file_path.display(), e);
-                return Ok(FileProcessOutcome::Failed(format!("Path mapping failed: {}", e)));
+                warn!("Cleanup failed for {}: {}", backup_file_path.display(), e);
+                FileProcessOutcome::Success
             }
+        }
+    }
+
+    /// Restore files stored in shared pack files, using the manifest as the
+    /// index. Runs after the directory walk, which deliberately skips the
+    /// pack files themselves; a pack is cleaned up only once every one of
+    /// its entries restored successfully.
+    fn restore_packed_entries(&self, backup_root: &Path, result: &mut DirectRestoreResult) {
+        self.restore_packed_entries_filtered(backup_root, None, result)
+    }
+
+    /// Like [`Self::restore_packed_entries`], but restricted to entries at
+    /// or below `only` when given. Filtered runs never delete pack files:
+    /// the entries outside the filter still live in them.
+    fn restore_packed_entries_filtered(&self, backup_root: &Path, only: Option<&Path>, result: &mut DirectRestoreResult) {
+        let packed: Vec<(String, manifest::ManifestEntry)> = match self.manifest_for(backup_root) {
+            Some(manifest) => manifest
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.pack.is_some() && !entry.deleted)
+                .filter(|(relative, _)| only.is_none_or(|prefix| Path::new(relative).starts_with(prefix)))
+                .map(|(relative, entry)| (relative.clone(), entry.clone()))
+                .collect(),
+            None => return,
         };
+        if packed.is_empty() {
+            return;
+        }
+        info!("Restoring {} packed small files via the manifest index", packed.len());
 
-        debug!("Processing file: {} -> {}", backup_file_path.display(), target_path.display());
+        let mut incomplete_packs: std::collections::HashSet<String> = std::collections::HashSet::new();
+        let mut pack_files: std::collections::BTreeSet<String> = std::collections::BTreeSet::new();
+        for (relative, entry) in packed {
+            result.total_files += 1;
+            let location = entry.pack.clone().expect("filtered on pack presence");
+            pack_files.insert(location.pack_file.clone());
 
-        // Try move first (most efficient), then fallback to copy
-        let move_result = self.move_file_with_retry(backup_file_path, &target_path);
-        
-        match move_result {
-            CopyResult::Success => {
+            let outcome = self.restore_one_packed_entry(backup_root, Path::new(&relative), &entry, &location);
+            if !matches!(outcome, Ok(FileProcessOutcome::Success)) {
+                incomplete_packs.insert(location.pack_file.clone());
+            }
+            self.aggregate_file_outcome(backup_root.join(&relative), outcome, backup_root, result);
+        }
+
+        if self.dry_run || only.is_some() || self.preserve_backup {
+            return;
+        }
+        for pack_file in pack_files {
+            if incomplete_packs.contains(&pack_file) {
+                info!("Keeping pack {} (not all entries restored)", pack_file);
+                continue;
+            }
+            let pack_path = backup_root.join(&pack_file);
+            match self.cleanup_backup_file(&pack_path) {
+                Ok(()) => {
+                    result.cleaned_files += 1;
+                    push_detail_capped(&mut result.cleaned_details, pack_path, self.max_detail_entries, &mut result.truncated_details);
+                }
+                Err(e) => warn!("Cleanup failed for pack {}: {}", pack_path.display(), e),
+            }
+        }
+    }
+
+    /// Restore one packed file to its container path, verifying its
+    /// content against the manifest hash
+    fn restore_one_packed_entry(
+        &self,
+        backup_root: &Path,
+        relative: &Path,
+        entry: &manifest::ManifestEntry,
+        location: &manifest::PackLocation,
+    ) -> Result<FileProcessOutcome> {
+        // Reuse the path mapping (and its traversal validation) by
+        // treating the entry as if it sat loose under the backup root
+        let target_path = match self.map_backup_to_container_path(&backup_root.join(relative), backup_root) {
+            Ok(path) => path,
+            Err(e) => {
+                return Ok(FileProcessOutcome::Failed(format!("Path mapping failed: {}", e)));
+            }
+        };
+
+        // The extension filter applies to packed files too
+        if !self.extension_admitted(relative) {
+            return Ok(FileProcessOutcome::Skipped("Extension filtered".to_string()));
+        }
+
+        if self.dry_run {
+            info!("DRY RUN: Would unpack {} -> {}", relative.display(), target_path.display());
+            return Ok(FileProcessOutcome::Success);
+        }
+
+        if self.merge_missing_only && fs::symlink_metadata(&target_path).is_ok() {
+            return Ok(FileProcessOutcome::Skipped("exists, merge mode".to_string()));
+        }
+        if let Some(marker) = self.find_no_restore_ancestor(&target_path) {
+            return Ok(FileProcessOutcome::Skipped(format!(
+                "Destination under no-restore directory: {}", marker.display()
+            )));
+        }
+        if self.readonly_subtrees.is_readonly(&target_path) {
+            return Ok(FileProcessOutcome::Skipped(
+                "Read-only filesystem (subtree classified read-only)".to_string(),
+            ));
+        }
+
+        let contents = match crate::packing::read_packed_entry(backup_root, location) {
+            Ok(contents) => contents,
+            Err(e) => return Ok(FileProcessOutcome::Failed(format!("Failed to read pack entry: {}", e))),
+        };
+
+        let hash = blake3::hash(&contents).to_hex().to_string();
+        if hash != entry.original_hash {
+            if entry.unstable {
+                warn!(
+                    "Hash mismatch on unstable packed entry {} (changed during backup); keeping packed content",
+                    relative.display()
+                );
+            } else {
+                return Ok(FileProcessOutcome::Failed(format!(
+                    "Hash mismatch for packed entry {}: expected {}, got {}",
+                    relative.display(), entry.original_hash, hash
+                )));
+            }
+        }
+
+        if let Some(parent) = target_path.parent() {
+            if let Err(e) = crate::dir_cache::ensure_dir_exists(parent) {
+                return Ok(FileProcessOutcome::Failed(format!(
+                    "Failed to create parent directories for {}: {}", target_path.display(), e
+                )));
+            }
+        }
+        if let Err(e) = fs::write(&target_path, &contents) {
+            return Ok(FileProcessOutcome::Failed(format!(
+                "Failed to write {}: {}", target_path.display(), e
+            )));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if let Err(e) = fs::set_permissions(&target_path, fs::Permissions::from_mode(location.mode)) {
+                warn!("Failed to restore permissions on {}: {}", target_path.display(), e);
+            }
+        }
+        debug!("Unpacked {} -> {}", relative.display(), target_path.display());
+        Ok(FileProcessOutcome::Success)
+    }
+
+    /// Verify that a backup is restorable without writing anything: every
+    /// file must be readable and must map to a valid target that is neither
+    /// a traversal attempt nor under a protected system prefix. Distinct
+    /// from `--dry-run`, which lists planned actions; this is a pure
+    /// validation pass with a structured result.
+    pub fn verify_backup(&self, backup_path: &Path) -> Result<VerifyResult> {
+        let mut result = VerifyResult {
+            total_files: 0,
+            verified_files: 0,
+            issues: Vec::new(),
+        };
+
+        if !backup_path.exists() {
+            bail!("Backup path does not exist: {}", backup_path.display());
+        }
+
+        let mut queue: std::collections::VecDeque<PathBuf> = std::collections::VecDeque::new();
+        queue.push_back(backup_path.to_path_buf());
+
+        while let Some(current_dir) = queue.pop_front() {
+            let entries = fs::read_dir(&current_dir)
+                .with_context(|| format!("Failed to read directory: {}", current_dir.display()))?;
+            for entry in entries {
+                let entry = entry?;
+                let entry_path = entry.path();
+                let metadata = fs::symlink_metadata(&entry_path)
+                    .with_context(|| format!("Failed to stat: {}", entry_path.display()))?;
+
+                if metadata.is_dir() {
+                    let name = entry_path.file_name().unwrap_or_default();
+                    if current_dir == backup_path && name == TRASH_DIR_NAME {
+                        continue;
+                    }
+                    queue.push_back(entry_path);
+                    continue;
+                }
+                if current_dir == backup_path
+                    && entry_path.file_name().is_some_and(|n| n == manifest::MANIFEST_FILE_NAME)
+                {
+                    continue;
+                }
+
+                result.total_files += 1;
+
+                // Mapping and traversal validation
+                let target_path = match self.map_backup_to_container_path(&entry_path, backup_path) {
+                    Ok(target) => target,
+                    Err(e) => {
+                        result.issues.push(VerifyIssue {
+                            path: entry_path,
+                            kind: "mapping".to_string(),
+                            detail: e.to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+                // Protected system prefixes
+                if let Some(prefix) = PROTECTED_TARGET_PREFIXES
+                    .iter()
+                    .find(|prefix| target_path.starts_with(prefix))
+                {
+                    result.issues.push(VerifyIssue {
+                        path: entry_path,
+                        kind: "protected-path".to_string(),
+                        detail: format!("Target {} is under protected prefix {}", target_path.display(), prefix),
+                    });
+                    continue;
+                }
+
+                // Source readability: the content must actually be readable
+                // (a dangling symlink or an unopenable file cannot be
+                // restored with its content intact)
+                if let Err(e) = check_source_readable(&entry_path, &metadata) {
+                    result.issues.push(VerifyIssue {
+                        path: entry_path,
+                        kind: "unreadable".to_string(),
+                        detail: e.to_string(),
+                    });
+                    continue;
+                }
+
+                result.verified_files += 1;
+            }
+        }
+
+        info!(
+            "Backup verification of {}: {}/{} files restorable, {} issues",
+            backup_path.display(),
+            result.verified_files,
+            result.total_files,
+            result.issues.len()
+        );
+        Ok(result)
+    }
+
+    /// Rename a cleaned backup file into the run's trash directory,
+    /// preserving its path relative to the backup root. Returns Ok(false)
+    /// when trash is not applicable (no context, file outside the backup
+    /// root, or a cross-filesystem rename).
+    fn move_to_trash(&self, backup_file_path: &Path) -> Result<bool> {
+        let Some((backup_root, run_dir)) = self.trash_context.get() else {
+            return Ok(false);
+        };
+        let Ok(relative) = backup_file_path.strip_prefix(backup_root) else {
+            return Ok(false);
+        };
+
+        let trash_path = run_dir.join(relative);
+        if let Some(parent) = trash_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create trash directory: {}", parent.display()))?;
+        }
+        match fs::rename(backup_file_path, &trash_path) {
+            Ok(()) => {
+                debug!("Trashed backup file: {} -> {}", backup_file_path.display(), trash_path.display());
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => Ok(false),
+            Err(e) => Err(e).with_context(|| {
+                format!("Failed to rename {} into trash", backup_file_path.display())
+            }),
+        }
+    }
+
+    /// Find the no-restore directory (if any) covering a target path: either
+    /// an entry from `--no-restore-dir` or the nearest ancestor directory
+    /// containing the `.session-no-restore` sentinel. Sentinel lookups are
+    /// cached per directory so large trees do not repeat the same stats.
+    fn find_no_restore_ancestor(&self, target_path: &Path) -> Option<PathBuf> {
+        for no_restore_dir in &self.no_restore_dirs {
+            if target_path.starts_with(no_restore_dir) {
+                return Some(no_restore_dir.clone());
+            }
+        }
+
+        let mut ancestors: Vec<&Path> = target_path.ancestors().skip(1).collect();
+        // Check from the nearest parent upward
+        ancestors.retain(|dir| !dir.as_os_str().is_empty());
+        for dir in ancestors {
+            let mut cache = self.no_restore_cache.lock();
+            let marked = *cache
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| dir.join(NO_RESTORE_SENTINEL).is_file());
+            if marked {
+                return Some(dir.to_path_buf());
+            }
+        }
+        None
+    }
+
+    /// Translate an overlayfs whiteout node into a deletion of the
+    /// corresponding target path, then drop the whiteout from the backup
+    fn apply_whiteout(&self, whiteout_path: &Path, backup_root: &Path) -> Result<FileProcessOutcome> {
+        let target_path = self.map_backup_to_container_path(whiteout_path, backup_root)?;
+
+        if self.dry_run {
+            info!("DRY RUN: Would delete {} (overlayfs whiteout)", target_path.display());
+            return Ok(FileProcessOutcome::Success);
+        }
+
+        match fs::symlink_metadata(&target_path) {
+            Ok(metadata) => {
+                let removal = if metadata.is_dir() {
+                    fs::remove_dir_all(&target_path)
+                } else {
+                    fs::remove_file(&target_path)
+                };
+                match removal {
+                    Ok(()) => info!("Applied overlayfs whiteout: deleted {}", target_path.display()),
+                    Err(e) => {
+                        return Ok(FileProcessOutcome::Failed(format!(
+                            "Failed to apply whiteout for {}: {}", target_path.display(), e
+                        )));
+                    }
+                }
+            }
+            Err(_) => {
+                debug!("Whiteout target already absent: {}", target_path.display());
+            }
+        }
+
+        // The whiteout itself is consumed, mirroring regular file cleanup
+        if self.preserve_backup {
+            return Ok(FileProcessOutcome::Success);
+        }
+        if let Err(e) = fs::remove_file(whiteout_path) {
+            warn!("Failed to remove whiteout node {}: {}", whiteout_path.display(), e);
+            return Ok(FileProcessOutcome::Success);
+        }
+        Ok(FileProcessOutcome::Cleaned)
+    }
+
+    /// An opaque directory marker means the target directory is replaced
+    /// rather than merged: clear the existing target contents so only the
+    /// backup's files remain after restore
+    #[cfg(unix)]
+    fn apply_opaque_dir(&self, backup_dir: &Path, backup_root: &Path) -> Result<()> {
+        let target_path = self.map_backup_to_container_path(backup_dir, backup_root)?;
+
+        if self.dry_run {
+            info!("DRY RUN: Would replace directory {} (opaque marker)", target_path.display());
+            return Ok(());
+        }
+
+        if target_path.is_dir() {
+            info!("Applying opaque directory marker: replacing {}", target_path.display());
+            fs::remove_dir_all(&target_path)
+                .with_context(|| format!("Failed to clear opaque target directory: {}", target_path.display()))?;
+        }
+        fs::create_dir_all(&target_path)
+            .with_context(|| format!("Failed to recreate opaque target directory: {}", target_path.display()))?;
+        Ok(())
+    }
+
+    /// Re-apply recorded setuid/setgid/sticky bits and the file capability
+    /// xattr to a restored target, when the manifest recorded any for it.
+    /// Application needs both the privilege (euid 0) and the target to sit
+    /// under the opt-in allow-list; otherwise the file is recorded under
+    /// `privileged_attrs_not_applied` so the omission stays visible.
+    fn apply_privileged_attrs_if_any(&self, backup_file: &Path, backup_root: &Path, result: &mut DirectRestoreResult) {
+        let Ok(relative) = backup_file.strip_prefix(backup_root) else {
+            return;
+        };
+        // Compressed files are stored (and mapped) with a .zst suffix; the
+        // manifest is keyed by the original name
+        let original_relative = if relative.extension().is_some_and(|ext| ext == "zst") {
+            relative.with_extension("")
+        } else {
+            relative.to_path_buf()
+        };
+        let attrs = match self
+            .manifest_for(backup_root)
+            .and_then(|manifest| manifest.get(&original_relative))
+            .and_then(|entry| entry.privileged_attrs.clone())
+        {
+            Some(attrs) => attrs,
+            None => return,
+        };
+
+        let target_path = Path::new("/").join(&original_relative);
+        if self.dry_run {
+            info!("DRY RUN: Would apply privileged attributes (mode {:o}) to {}", attrs.mode, target_path.display());
+            return;
+        }
+
+        let allowed = self
+            .privileged_attr_paths
+            .iter()
+            .any(|prefix| target_path.starts_with(prefix));
+        if !allowed || !running_privileged() {
+            let why = if allowed {
+                "not running privileged"
+            } else {
+                "target not under --privileged-attr-path allow-list"
+            };
+            warn!("Privileged attributes not applied to {} ({})", target_path.display(), why);
+            push_detail_capped(
+                &mut result.privileged_attrs_not_applied,
+                target_path,
+                self.max_detail_entries,
+                &mut result.truncated_details,
+            );
+            return;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if let Err(e) = fs::set_permissions(&target_path, fs::Permissions::from_mode(attrs.mode & 0o7777)) {
+                warn!("Failed to re-apply mode {:o} on {}: {}", attrs.mode, target_path.display(), e);
+                push_detail_capped(
+                    &mut result.privileged_attrs_not_applied,
+                    target_path,
+                    self.max_detail_entries,
+                    &mut result.truncated_details,
+                );
+                return;
+            }
+        }
+        if let Some(capability) = attrs.capability_bytes() {
+            if let Err(e) = set_capability_xattr(&target_path, &capability) {
+                warn!("Failed to re-apply file capabilities on {}: {}", target_path.display(), e);
+                push_detail_capped(
+                    &mut result.privileged_attrs_not_applied,
+                    target_path,
+                    self.max_detail_entries,
+                    &mut result.truncated_details,
+                );
+                return;
+            }
+        }
+        info!("Re-applied privileged attributes (mode {:o}) to {}", attrs.mode, target_path.display());
+    }
+
+    /// Fold a single file outcome into the aggregated result
+    fn aggregate_file_outcome(&self, file_path: PathBuf, file_result: Result<FileProcessOutcome>, backup_root: &Path, result: &mut DirectRestoreResult) {
+        // Incremental per-top-level-directory aggregation; counting here
+        // rather than over the detail vectors keeps it exact when those
+        // are truncated by the per-category cap
+        let (restored, skipped, failed) = match &file_result {
+            Ok(FileProcessOutcome::Success) | Ok(FileProcessOutcome::Cleaned) => (1, 0, 0),
+            Ok(FileProcessOutcome::Skipped(_)) if !self.strict => (0, 1, 0),
+            _ => (0, 0, 1),
+        };
+        if restored == 1 {
+            self.apply_privileged_attrs_if_any(&file_path, backup_root, result);
+        }
+        let bytes = if restored == 1 {
+            self.map_backup_to_container_path(&file_path, backup_root)
+                .ok()
+                .and_then(|target| fs::symlink_metadata(target).ok())
+                .map(|metadata| metadata.len())
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let summary = result
+            .directory_summaries
+            .entry(summary_key(&file_path, backup_root))
+            .or_default();
+        summary.restored += restored;
+        summary.skipped += skipped;
+        summary.failed += failed;
+        summary.bytes += bytes;
+
+        match file_result {
+            Ok(file_outcome) => {
+                match file_outcome {
+                    FileProcessOutcome::Success => result.successful_files += 1,
+                    FileProcessOutcome::Skipped(reason) => {
+                        if self.strict {
+                            // Strict mode: partial restores are unacceptable,
+                            // so a skip is a failure
+                            result.failed_files += 1;
+                            push_detail_capped(&mut result.failed_details, FailedFile {
+                                path: file_path,
+                                error: format!("Skipped in strict mode: {}", reason),
+                            }, self.max_detail_entries, &mut result.truncated_details);
+                        } else {
+                            result.skipped_files += 1;
+                            record_skip_reason(result, &reason);
+                            push_detail_capped(&mut result.skipped_details, SkippedFile {
+                                path: file_path,
+                                reason,
+                            }, self.max_detail_entries, &mut result.truncated_details);
+                        }
+                    }
+                    FileProcessOutcome::Failed(error) => {
+                        result.failed_files += 1;
+                        push_detail_capped(&mut result.failed_details, FailedFile {
+                            path: file_path,
+                            error,
+                        }, self.max_detail_entries, &mut result.truncated_details);
+                    }
+                    FileProcessOutcome::Cleaned => {
+                        result.successful_files += 1;
+                        result.cleaned_files += 1;
+                        push_detail_capped(&mut result.cleaned_details, file_path, self.max_detail_entries, &mut result.truncated_details);
+                    }
+                }
+            }
+            Err(e) => {
+                result.failed_files += 1;
+                push_detail_capped(&mut result.failed_details, FailedFile {
+                    path: file_path,
+                    error: e.to_string(),
+                }, self.max_detail_entries, &mut result.truncated_details);
+            }
+        }
+    }
+
+    /// Retry only the failed files recorded in a prior restore report.
+    /// Each recorded path is validated to still lie under the backup root
+    /// before being attempted again, and a new report is produced.
+    pub fn retry_from_report(&self, report: &DirectRestoreResult, backup_root: &Path) -> Result<DirectRestoreResult> {
+        let start_time = SystemTime::now();
+
+        info!("Retrying {} failed files from prior report against backup root {}", 
+              report.failed_details.len(), backup_root.display());
+
+        let mut result = DirectRestoreResult {
+            total_files: 0,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        for failed in &report.failed_details {
+            // Validate the recorded path is still under the backup root
+            if !failed.path.starts_with(backup_root) {
+                warn!("Skipping report entry outside backup root: {}", failed.path.display());
+                result.failed_files += 1;
+                push_detail_capped(&mut result.failed_details, FailedFile {
+                    path: failed.path.clone(),
+                    error: format!("Recorded path is outside backup root {}", backup_root.display()),
+                }, self.max_detail_entries, &mut result.truncated_details);
+                continue;
+            }
+
+            if !failed.path.exists() {
+                result.skipped_files += 1;
+                record_skip_reason(&mut result, "No longer present in backup");
+                result.directory_summaries
+                    .entry(summary_key(&failed.path, backup_root))
+                    .or_default()
+                    .skipped += 1;
+                push_detail_capped(&mut result.skipped_details, SkippedFile {
+                    path: failed.path.clone(),
+                    reason: "No longer present in backup".to_string(),
+                }, self.max_detail_entries, &mut result.truncated_details);
+                continue;
+            }
+
+            result.total_files += 1;
+            let file_result = self.process_single_file(&failed.path, backup_root);
+            self.aggregate_file_outcome(failed.path.clone(), file_result, backup_root, &mut result);
+        }
+
+        result.repaired_directories = self.restore_repaired_parent_permissions();
+        result.duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+
+        info!("Retry from report completed: {} retried, {} successful, {} skipped, {} failed",
+              result.total_files, result.successful_files, result.skipped_files, result.failed_files);
+
+        Ok(result)
+    }
+
+    /// Process a single file with optimized operations
+    /// Whether the extension filter admits this backup file. Compressed
+    /// blobs are stored as `<name>.zst`, so the filter applies to the
+    /// original name; extensionless files match nothing when a filter
+    /// is set.
+    fn extension_admitted(&self, backup_file_path: &Path) -> bool {
+        if self.restore_extensions.is_empty() {
+            return true;
+        }
+        let name = backup_file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let original = name.strip_suffix(".zst").unwrap_or(&name);
+        match Path::new(original).extension() {
+            Some(ext) => {
+                let ext = ext.to_string_lossy().to_lowercase();
+                self.restore_extensions.contains(&ext)
+            }
+            None => false,
+        }
+    }
+
+    fn process_single_file(&self, backup_file_path: &Path, backup_root: &Path) -> Result<FileProcessOutcome> {
+        let Some(profiler) = &self.profiler else {
+            return self.process_single_file_inner(backup_file_path, backup_root);
+        };
+        let copy_start = std::time::Instant::now();
+        let outcome = self.process_single_file_inner(backup_file_path, backup_root);
+        profiler.record(backup_file_path, copy_start.elapsed());
+        outcome
+    }
+
+    fn process_single_file_inner(&self, backup_file_path: &Path, backup_root: &Path) -> Result<FileProcessOutcome> {
+        // Hold an fd permit for the whole copy so the parallel workers
+        // cannot exhaust the process file descriptor limit
+        let _fd_permit = self.fd_semaphore.acquire();
+
+        // Map backup file path to container target path
+        let target_path = match self.map_backup_to_container_path(backup_file_path, backup_root) {
+            Ok(path) => path,
+            Err(e) => {
+                error!("Failed to map backup path to container path: {} - {}", backup_file_path.display(), e);
+                return Ok(FileProcessOutcome::Failed(format!("Path mapping failed: {}", e)));
+            }
+        };
+
+        debug!("Processing file: {} -> {}", backup_file_path.display(), target_path.display());
+
+        // An extension filter restores only the matching files
+        if !self.extension_admitted(backup_file_path) {
+            return Ok(FileProcessOutcome::Skipped("Extension filtered".to_string()));
+        }
+
+        // Files stored compressed at backup time (see the backup manifest)
+        // are decompressed transparently instead of restored as .zst blobs
+        if let Some(entry) = self.compressed_manifest_entry(backup_file_path, backup_root) {
+            return self.restore_compressed_file(backup_file_path, &target_path, &entry);
+        }
+
+        // Merge mode only fills in gaps: an existing target (file, symlink
+        // or directory) is never touched
+        if self.merge_missing_only && fs::symlink_metadata(&target_path).is_ok() {
+            return Ok(FileProcessOutcome::Skipped("exists, merge mode".to_string()));
+        }
+
+        // Never restore into runtime-managed directories, whether flagged on
+        // the command line or marked with the sentinel file
+        if let Some(marker) = self.find_no_restore_ancestor(&target_path) {
+            return Ok(FileProcessOutcome::Skipped(format!(
+                "Destination under no-restore directory: {}", marker.display()
+            )));
+        }
+
+        // Skip immediately when the subtree is already classified read-only:
+        // EROFS is not transient, retrying each file only burns the budget
+        if self.readonly_subtrees.is_readonly(&target_path) {
+            return Ok(FileProcessOutcome::Skipped(
+                "Read-only filesystem (subtree classified read-only)".to_string(),
+            ));
+        }
+
+        // Try move first (most efficient), then fallback to copy. A move
+        // consumes the backup file, so trash mode forces the copy path to
+        // keep a recoverable copy for the trash, and preserve mode forces
+        // it to leave the backup untouched.
+        let move_result = if self.trash_mode || self.preserve_backup {
+            CopyResult::Failed("trash/preserve mode requires copy".to_string())
+        } else {
+            self.move_file_with_retry(backup_file_path, &target_path)
+        };
+        
+        match move_result {
+            CopyResult::Success => {
                 info!("Successfully moved: {}", target_path.display());
                 
                 // Validate that the moved file is accessible
@@ -848,6 +2946,9 @@ impl DirectRestoreEngine {
                 Ok(FileProcessOutcome::Cleaned)
             }
             CopyResult::Skipped(reason) => {
+                if reason.contains("Read-only filesystem") {
+                    self.readonly_subtrees.record_erofs(&target_path);
+                }
                 info!("Skipped file move: {} - {}", target_path.display(), reason);
                 Ok(FileProcessOutcome::Skipped(reason))
             }
@@ -865,17 +2966,9 @@ impl DirectRestoreEngine {
                         }
                         
                         // Clean up backup file after successful copy
-                        if !self.dry_run {
+                        if !self.dry_run && !self.preserve_backup {
                             match self.validate_file_before_cleanup(backup_file_path, &target_path) {
-                                Ok(()) => {
-                                    match self.cleanup_backup_file(backup_file_path) {
-                                        Ok(()) => Ok(FileProcessOutcome::Cleaned),
-                                        Err(e) => {
-                                            warn!("Cleanup failed for {}: {}", backup_file_path.display(), e);
-                                            Ok(FileProcessOutcome::Success)
-                                        }
-                                    }
-                                }
+                                Ok(()) => Ok(self.consume_backup_file(backup_file_path)),
                                 Err(e) => {
                                     warn!("File validation failed before cleanup for {}: {}", backup_file_path.display(), e);
                                     Ok(FileProcessOutcome::Success)
@@ -886,6 +2979,9 @@ impl DirectRestoreEngine {
                         }
                     }
                     CopyResult::Skipped(reason) => {
+                        if reason.contains("Read-only filesystem") {
+                            self.readonly_subtrees.record_erofs(&target_path);
+                        }
                         info!("Skipped file copy: {} - {}", target_path.display(), reason);
                         Ok(FileProcessOutcome::Skipped(reason))
                     }
@@ -950,6 +3046,11 @@ impl DirectRestoreEngine {
             match &result {
                 CopyResult::Skipped(reason) if self.is_transient_error(reason) => {
                     if attempt < self.max_retries {
+                        if !self.try_consume_retry() {
+                            debug!("Global retry budget exhausted; not retrying move of {}: {}",
+                                   dst.display(), reason);
+                            return result;
+                        }
                         debug!("Transient error on move attempt {} for {}: {}. Retrying in {:?}...", 
                                attempt + 1, dst.display(), reason, self.retry_delay);
                         thread::sleep(self.retry_delay);
@@ -974,9 +3075,9 @@ impl DirectRestoreEngine {
             return CopyResult::Success;
         }
 
-        // Create parent directories if needed
+        // Create parent directories if needed (race-safe across parallel workers)
         if let Some(parent) = dst.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
+            if let Err(e) = crate::dir_cache::ensure_dir_exists(parent) {
                 return CopyResult::Failed(format!("Failed to create parent directories: {}", e));
             }
         }
@@ -985,6 +3086,9 @@ impl DirectRestoreEngine {
         match fs::symlink_metadata(src) {
             Ok(metadata) => {
                 if metadata.file_type().is_symlink() {
+                    if let Some(reason) = self.confined_symlink_rejection(src, dst) {
+                        return CopyResult::Skipped(reason);
+                    }
                     // Handle symlinks specially - copy symlink, then remove original
                     match self.copy_symlink(src, dst) {
                         Ok(()) => {
@@ -1004,6 +3108,24 @@ impl DirectRestoreEngine {
                         Err(e) => CopyResult::Failed(format!("Failed to move symlink: {}", e)),
                     }
                 } else {
+                    // Idempotency for re-runs after an interrupted move: if a
+                    // prior run already renamed this file into place but died
+                    // before accounting for it, the target exists with the
+                    // source's size. Treat it as moved and drop the leftover
+                    // source instead of clobbering the restored copy.
+                    if let Ok(dst_metadata) = fs::metadata(dst) {
+                        if dst_metadata.is_file() && dst_metadata.len() == metadata.len() {
+                            debug!("Target already present with matching size, treating as moved: {}", dst.display());
+                            return match fs::remove_file(src) {
+                                Ok(()) => CopyResult::Success,
+                                Err(e) => {
+                                    warn!("Failed to remove leftover source {}: {}", src.display(), e);
+                                    CopyResult::Success
+                                }
+                            };
+                        }
+                    }
+
                     // Regular file - try atomic move
                     match fs::rename(src, dst) {
                         Ok(()) => {
@@ -1017,7 +3139,14 @@ impl DirectRestoreEngine {
                             } else if self.is_file_readonly(&e) {
                                 CopyResult::Skipped(format!("Read-only filesystem: {}", e))
                             } else if self.is_permission_denied(&e) {
-                                CopyResult::Skipped(format!("Permission denied: {}", e))
+                                if self.try_repair_parent_permissions(dst) {
+                                    match fs::rename(src, dst) {
+                                        Ok(()) => CopyResult::Success,
+                                        Err(e) => CopyResult::Skipped(format!("Permission denied: {}", e)),
+                                    }
+                                } else {
+                                    CopyResult::Skipped(format!("Permission denied: {}", e))
+                                }
                             } else if e.kind() == std::io::ErrorKind::CrossesDevices {
                                 // Cross-device move - will need copy+delete fallback
                                 CopyResult::Failed(format!("Cross-device move (fallback needed): {}", e))
@@ -1040,6 +3169,11 @@ impl DirectRestoreEngine {
             match &result {
                 CopyResult::Skipped(reason) if self.is_transient_error(reason) => {
                     if attempt < self.max_retries {
+                        if !self.try_consume_retry() {
+                            debug!("Global retry budget exhausted; not retrying copy of {}: {}",
+                                   dst.display(), reason);
+                            return result;
+                        }
                         debug!("Transient error on attempt {} for {}: {}. Retrying in {:?}...", 
                                attempt + 1, dst.display(), reason, self.retry_delay);
                         thread::sleep(self.retry_delay);
@@ -1058,6 +3192,32 @@ impl DirectRestoreEngine {
         CopyResult::Failed("Unexpected retry loop exit".to_string())
     }
 
+    /// Atomically consume one retry from the global budget. Returns false
+    /// once the budget is spent; a compare-and-swap loop keeps the count
+    /// exact under parallel workers.
+    fn try_consume_retry(&self) -> bool {
+        use std::sync::atomic::Ordering;
+
+        let Some(budget) = self.retry_budget else {
+            return true;
+        };
+        let mut used = self.retries_used.load(Ordering::Relaxed);
+        loop {
+            if used >= budget {
+                return false;
+            }
+            match self.retries_used.compare_exchange_weak(
+                used,
+                used + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => used = current,
+            }
+        }
+    }
+
     /// Check if an error reason indicates a transient condition that might be retried
     fn is_transient_error(&self, reason: &str) -> bool {
         reason.contains("File busy") || reason.contains("Resource busy")
@@ -1070,9 +3230,11 @@ impl DirectRestoreEngine {
             return CopyResult::Success;
         }
 
-        // Create parent directories if needed
+        crate::fault_injection::fire_before_file(src);
+
+        // Create parent directories if needed (race-safe across parallel workers)
         if let Some(parent) = dst.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
+            if let Err(e) = crate::dir_cache::ensure_dir_exists(parent) {
                 return CopyResult::Failed(format!("Failed to create parent directories: {}", e));
             }
         }
@@ -1081,6 +3243,9 @@ impl DirectRestoreEngine {
         match fs::symlink_metadata(src) {
             Ok(metadata) => {
                 if metadata.file_type().is_symlink() {
+                    if let Some(reason) = self.confined_symlink_rejection(src, dst) {
+                        return CopyResult::Skipped(reason);
+                    }
                     // Handle symlinks
                     match self.copy_symlink(src, dst) {
                         Ok(()) => {
@@ -1088,7 +3253,7 @@ impl DirectRestoreEngine {
                             CopyResult::Success
                         }
                         Err(e) => {
-                            if self.is_permission_denied(&e.downcast_ref::<std::io::Error>().unwrap_or(&std::io::Error::new(std::io::ErrorKind::Other, ""))) {
+                            if self.is_permission_denied(e.downcast_ref::<std::io::Error>().unwrap_or(&std::io::Error::other(""))) {
                                 CopyResult::Skipped(format!("Permission denied for symlink: {}", e))
                             } else {
                                 CopyResult::Failed(format!("Failed to copy symlink: {}", e))
@@ -1096,7 +3261,28 @@ impl DirectRestoreEngine {
                         }
                     }
                 } else {
-                    // Regular file - attempt to copy
+                    // Regular file: a reflink first when enabled, then the
+                    // byte copy. Clones share extents on CoW filesystems;
+                    // anything that cannot clone falls through
+                    #[cfg(target_os = "linux")]
+                    if self.reflink != ReflinkMode::Never {
+                        match crate::reflink::try_reflink(src, dst) {
+                            Ok(()) => {
+                                debug!("Reflinked: {} -> {}", src.display(), dst.display());
+                                if let Err(e) = self.preserve_file_attributes(src, dst) {
+                                    warn!("Failed to preserve file attributes for {}: {}", dst.display(), e);
+                                }
+                                return CopyResult::Success;
+                            }
+                            Err(e) if self.reflink == ReflinkMode::Always => {
+                                warn!("Reflink failed for {} ({}), falling back to copy", dst.display(), e);
+                            }
+                            Err(e) => {
+                                debug!("Reflink unavailable for {} ({}), falling back to copy", dst.display(), e);
+                            }
+                        }
+                    }
+                    // Attempt the byte copy
                     match fs::copy(src, dst) {
                         Ok(_) => {
                             // Try to preserve permissions and timestamps
@@ -1108,12 +3294,29 @@ impl DirectRestoreEngine {
                         }
                         Err(e) => {
                             // Classify the error and decide whether to skip or fail
-                            if self.is_file_busy(&e) {
+                            if e.kind() == io::ErrorKind::NotFound && !src.exists() {
+                                // Deleted between the stat above and the copy
+                                CopyResult::Skipped(format!("Source vanished before copy: {}", src.display()))
+                            } else if self.is_file_busy(&e) {
                                 CopyResult::Skipped(format!("File busy: {}", e))
                             } else if self.is_file_readonly(&e) {
                                 CopyResult::Skipped(format!("Read-only filesystem: {}", e))
                             } else if self.is_permission_denied(&e) {
-                                CopyResult::Skipped(format!("Permission denied: {}", e))
+                                // A restrictive parent directory mode (e.g. captured as
+                                // r-x) is a common cause; try repairing it and retrying
+                                if self.try_repair_parent_permissions(dst) {
+                                    match fs::copy(src, dst) {
+                                        Ok(_) => {
+                                            if let Err(e) = self.preserve_file_attributes(src, dst) {
+                                                warn!("Failed to preserve file attributes for {}: {}", dst.display(), e);
+                                            }
+                                            CopyResult::Success
+                                        }
+                                        Err(e) => CopyResult::Skipped(format!("Permission denied: {}", e)),
+                                    }
+                                } else {
+                                    CopyResult::Skipped(format!("Permission denied: {}", e))
+                                }
                             } else {
                                 CopyResult::Failed(format!("Copy failed: {}", e))
                             }
@@ -1121,12 +3324,25 @@ impl DirectRestoreEngine {
                     }
                 }
             }
+            // Listed moments ago, gone now: the still-running user
+            // process deleted it, which is benign and never retried
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                CopyResult::Skipped(format!("Source vanished before copy: {}", src.display()))
+            }
             Err(e) => {
                 CopyResult::Failed(format!("Failed to get file metadata: {}", e))
             }
         }
     }
 
+    /// Capabilities of the restore destination (the container root),
+    /// probed once per run; the probe emits a single summary warning for
+    /// any missing capabilities instead of one warning per file
+    fn destination_capabilities(&self) -> &CapabilitySet {
+        self.capabilities
+            .get_or_init(|| fs_capabilities::probe_destination(Path::new("/tmp")))
+    }
+
     /// Preserve file attributes (permissions, timestamps)
     fn preserve_file_attributes(&self, src: &Path, dst: &Path) -> Result<()> {
         let src_metadata = fs::metadata(src)
@@ -1137,10 +3353,20 @@ impl DirectRestoreEngine {
         fs::set_permissions(dst, permissions)
             .with_context(|| format!("Failed to set permissions for: {}", dst.display()))?;
 
-        // Preserve timestamps (modified time)
-        if let Ok(modified) = src_metadata.modified() {
-            if let Err(e) = filetime::set_file_mtime(dst, filetime::FileTime::from_system_time(modified)) {
-                warn!("Failed to set modified time for {}: {}", dst.display(), e);
+        // Preserve timestamps (access and modified time), unless the
+        // destination filesystem was probed as not supporting timestamp
+        // updates. Birth time cannot be set portably; it is recorded in
+        // the manifest for informational use only.
+        if self.destination_capabilities().timestamps {
+            if let Ok(modified) = src_metadata.modified() {
+                let mtime = filetime::FileTime::from_system_time(modified);
+                let atime = src_metadata
+                    .accessed()
+                    .map(filetime::FileTime::from_system_time)
+                    .unwrap_or(mtime);
+                if let Err(e) = filetime::set_file_times(dst, atime, mtime) {
+                    warn!("Failed to set timestamps for {}: {}", dst.display(), e);
+                }
             }
         }
 
@@ -1219,8 +3445,35 @@ impl DirectRestoreEngine {
             return Ok(());
         }
 
-        // Create backup of the file before deletion for potential rollback
-        let backup_copy_path = self.create_cleanup_backup(backup_file_path)?;
+        // Trash mode: rename into the run's trash directory (cheap,
+        // same-filesystem) instead of deleting; cross-device renames fall
+        // through to the regular delete path below
+        if self.trash_mode {
+            match self.move_to_trash(backup_file_path) {
+                Ok(true) => {
+                    self.trashed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    if let Some(parent) = backup_file_path.parent() {
+                        let _ = self.cleanup_empty_directories(parent);
+                    }
+                    return Ok(());
+                }
+                Ok(false) => {
+                    debug!("Trash rename not possible for {}; falling back to deletion", backup_file_path.display());
+                }
+                Err(e) => {
+                    warn!("Failed to trash {} ({}); falling back to deletion", backup_file_path.display(), e);
+                }
+            }
+        }
+
+        // Create backup of the file before deletion for potential rollback,
+        // unless fast cleanup was requested (no rollback copy, half the I/O)
+        let backup_copy_path = if self.fast_cleanup {
+            debug!("Fast cleanup: skipping temp rollback copy for {}", backup_file_path.display());
+            None
+        } else {
+            Some(self.create_cleanup_backup(backup_file_path)?)
+        };
         
         // Log file size before removal for audit purposes
         debug!("Removing backup file: {} ({} bytes)", backup_file_path.display(), metadata.len());
@@ -1229,11 +3482,14 @@ impl DirectRestoreEngine {
         match fs::remove_file(backup_file_path) {
             Ok(()) => {
                 info!("Successfully cleaned backup file: {}", backup_file_path.display());
+                self.deleted_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 
                 // Cleanup was successful, remove the temporary backup copy
-                if let Err(e) = fs::remove_file(&backup_copy_path) {
-                    warn!("Failed to remove temporary backup copy {}: {}", backup_copy_path.display(), e);
-                    // Don't fail the operation for this
+                if let Some(ref backup_copy_path) = backup_copy_path {
+                    if let Err(e) = fs::remove_file(backup_copy_path) {
+                        warn!("Failed to remove temporary backup copy {}: {}", backup_copy_path.display(), e);
+                        // Don't fail the operation for this
+                    }
                 }
                 
                 // Try to remove empty parent directories (but don't fail if we can't)
@@ -1250,13 +3506,15 @@ impl DirectRestoreEngine {
                 let error_msg = format!("Failed to remove backup file {}: {}", backup_file_path.display(), e);
                 error!("{}", error_msg);
                 
-                // Attempt to restore from backup copy
-                if let Err(restore_err) = self.restore_from_cleanup_backup(&backup_copy_path, backup_file_path) {
-                    error!("Failed to restore backup file after cleanup failure: {}", restore_err);
-                    // Keep the temporary backup for manual recovery
-                    warn!("Temporary backup preserved for manual recovery: {}", backup_copy_path.display());
-                } else {
-                    info!("Successfully restored backup file after cleanup failure");
+                // Attempt to restore from backup copy (not available in fast cleanup mode)
+                if let Some(ref backup_copy_path) = backup_copy_path {
+                    if let Err(restore_err) = self.restore_from_cleanup_backup(backup_copy_path, backup_file_path) {
+                        error!("Failed to restore backup file after cleanup failure: {}", restore_err);
+                        // Keep the temporary backup for manual recovery
+                        warn!("Temporary backup preserved for manual recovery: {}", backup_copy_path.display());
+                    } else {
+                        info!("Successfully restored backup file after cleanup failure");
+                    }
                 }
                 
                 Err(anyhow::anyhow!(error_msg))
@@ -1339,8 +3597,122 @@ impl DirectRestoreEngine {
         }
     }
 
+    /// Attempt to make a non-writable parent directory writable so a child
+    /// copy/move can proceed. Only repairs directories owned by the current
+    /// user; the original mode is recorded and restored at the end of the run.
+    #[cfg(unix)]
+    pub fn try_repair_parent_permissions(&self, dst: &Path) -> bool {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        if !self.repair_parent_permissions || self.dry_run {
+            return false;
+        }
+
+        let parent = match dst.parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+
+        let metadata = match fs::metadata(parent) {
+            Ok(metadata) if metadata.is_dir() => metadata,
+            _ => return false,
+        };
+
+        // Only repair directories we own
+        if metadata.uid() != unsafe { libc::geteuid() } {
+            return false;
+        }
+
+        let mode = metadata.permissions().mode();
+        if mode & 0o200 != 0 {
+            // Already writable by owner; the failure has another cause
+            return false;
+        }
+
+        {
+            let mut repaired = self.repaired_parents.lock();
+            repaired.entry(parent.to_path_buf()).or_insert_with(|| metadata.permissions());
+        }
+
+        let writable = fs::Permissions::from_mode(mode | 0o200);
+        match fs::set_permissions(parent, writable) {
+            Ok(()) => {
+                info!("Temporarily made parent directory writable for restore: {} (mode {:o})", 
+                      parent.display(), mode);
+                true
+            }
+            Err(e) => {
+                warn!("Failed to repair parent directory permissions for {}: {}", parent.display(), e);
+                self.repaired_parents.lock().remove(parent);
+                false
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn try_repair_parent_permissions(&self, _dst: &Path) -> bool {
+        false
+    }
+
+    /// Restore the original modes of all parent directories repaired during
+    /// this run, returning the number of directories that were repaired
+    pub fn restore_repaired_parent_permissions(&self) -> usize {
+        let repaired: Vec<_> = self.repaired_parents.lock().drain().collect();
+        let count = repaired.len();
+
+        for (parent, original) in repaired {
+            if let Err(e) = fs::set_permissions(&parent, original) {
+                warn!("Failed to restore original permissions for {}: {}", parent.display(), e);
+            } else {
+                debug!("Restored original permissions for repaired directory: {}", parent.display());
+            }
+        }
+
+        count
+    }
+
+    /// With --confine-symlinks, the reason this link must not be restored:
+    /// its target, resolved lexically against the link's own directory,
+    /// leaves the top-level directory the link is being restored into
+    /// (e.g. a link under /root must stay under /root)
+    fn confined_symlink_rejection(&self, src: &Path, dst: &Path) -> Option<String> {
+        if !self.confine_symlinks {
+            return None;
+        }
+        let link_target = fs::read_link(src).ok()?;
+        self.symlink_escape_reason(&link_target, dst)
+    }
+
+    /// The confinement check itself, on an already-known link target so the
+    /// tar streaming path (where the target comes from the entry header, not
+    /// from disk) can share it with [`Self::confined_symlink_rejection`]
+    fn symlink_escape_reason(&self, link_target: &Path, dst: &Path) -> Option<String> {
+        // First normal component of the destination, e.g. /tmp for
+        // /tmp/xyz/link; a link directly under / is confined to itself
+        let root = dst.components().find_map(|component| match component {
+            Component::Normal(name) => Some(Path::new("/").join(name)),
+            _ => None,
+        })?;
+        let resolved = lexical_resolve(dst.parent().unwrap_or(Path::new("/")), link_target);
+        if resolved.starts_with(&root) {
+            None
+        } else {
+            Some(format!(
+                "Symlink target {} escapes restore root {}",
+                link_target.display(),
+                root.display()
+            ))
+        }
+    }
+
     /// Copy symlink preserving its target
     fn copy_symlink(&self, src: &Path, dst: &Path) -> Result<()> {
+        if !self.destination_capabilities().symlinks {
+            // Already covered by the probe's one-line summary warning
+            debug!("Destination does not support symlinks, skipping: {}", dst.display());
+            return Ok(());
+        }
+
         let link_target = fs::read_link(src)
             .with_context(|| format!("Failed to read symlink: {}", src.display()))?;
         
@@ -1417,6 +3789,7 @@ impl DirectRestoreEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[test]
     fn test_map_backup_to_container_path() {
@@ -1490,7 +3863,6 @@ mod tests {
     fn test_file_restoration_safety_validation() {
         use std::fs::File;
         use std::io::Write;
-        use tempfile::TempDir;
         
         let temp_dir = TempDir::new().unwrap();
         let engine = DirectRestoreEngine::new(true, 300);
@@ -1518,7 +3890,6 @@ mod tests {
     fn test_content_sample_validation() {
         use std::fs::File;
         use std::io::Write;
-        use tempfile::TempDir;
         
         let temp_dir = TempDir::new().unwrap();
         let engine = DirectRestoreEngine::new(true, 300);
@@ -1585,6 +3956,1125 @@ mod tests {
         assert!(!engine.is_transient_error("Read-only filesystem"));
     }
 
+    #[test]
+    fn test_detail_vectors_are_capped_with_exact_counters() {
+        let engine = DirectRestoreEngine::new(false, 300);
+        let mut result = DirectRestoreResult {
+            total_files: 0,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        // Failure storm: 100k synthetic failures through the aggregation
+        for i in 0..100_000 {
+            engine.aggregate_file_outcome(
+                PathBuf::from(format!("/backup/file-{}", i)),
+                Ok(FileProcessOutcome::Failed("Input/output error".to_string())),
+                Path::new("/backup"),
+                &mut result,
+            );
+        }
+
+        // Counters are exact, the stored details are bounded by the cap
+        assert_eq!(result.failed_files, 100_000);
+        assert_eq!(result.failed_details.len(), DEFAULT_MAX_DETAIL_ENTRIES);
+        assert_eq!(result.truncated_details, 100_000 - DEFAULT_MAX_DETAIL_ENTRIES);
+
+        // The truncation is visible in the serialized report
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains("\"truncated_details\":90000"));
+    }
+
+    #[test]
+    fn test_fast_cleanup_skips_temp_rollback_copy() {
+        let temp = TempDir::new().unwrap();
+        let backup_file = temp.path().join("restored.bin");
+        fs::write(&backup_file, b"restored contents").unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300).with_fast_cleanup(true);
+        engine.cleanup_backup_file(&backup_file).unwrap();
+
+        // The file is removed without any cleanup_backup_* temp copy appearing
+        assert!(!backup_file.exists());
+        if temp.path().exists() {
+            for entry in fs::read_dir(temp.path()).unwrap() {
+                let name = entry.unwrap().file_name();
+                assert!(
+                    !name.to_string_lossy().contains("cleanup_backup_"),
+                    "unexpected rollback copy: {:?}",
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_readonly_subtree_classification_from_injected_erofs() {
+        let tracker = ReadOnlySubtreeTracker::default();
+
+        // Inject EROFS failures for files under the same top-level directory
+        assert!(!tracker.record_erofs(Path::new("/root/.bashrc")));
+        assert!(!tracker.record_erofs(Path::new("/root/.profile")));
+        assert!(!tracker.is_readonly(Path::new("/root/.vimrc")));
+        assert!(tracker.record_erofs(Path::new("/root/nested/file")));
+
+        // The whole subtree is now classified read-only
+        assert!(tracker.is_readonly(Path::new("/root/anything/else")));
+        // Other top-level directories are unaffected
+        assert!(!tracker.is_readonly(Path::new("/home/user/file")));
+
+        assert_eq!(tracker.readonly_subtrees(), vec![PathBuf::from("/root")]);
+    }
+
+    #[test]
+    fn test_readonly_subtree_short_circuits_processing() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+        fs::create_dir_all(backup_root.join("root")).unwrap();
+        let backup_file = backup_root.join("root/.bashrc");
+        fs::write(&backup_file, b"aliases").unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300);
+        for _ in 0..READONLY_SUBTREE_THRESHOLD {
+            engine.readonly_subtrees.record_erofs(Path::new("/root/.bashrc"));
+        }
+
+        // The file is skipped without touching the filesystem
+        let outcome = engine.process_single_file(&backup_file, &backup_root).unwrap();
+        match outcome {
+            FileProcessOutcome::Skipped(reason) => assert!(reason.contains("Read-only filesystem")),
+            other => panic!("expected skip, got {:?}", other),
+        }
+        assert!(backup_file.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_whiteout_deletes_target_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+
+        // The container path for the test lives under /tmp so the deletion
+        // is confined to a unique scratch directory
+        let scratch = tempfile::Builder::new()
+            .prefix("whiteout-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let target_file = scratch.path().join("deleted-by-whiteout");
+        fs::write(&target_file, b"stale").unwrap();
+
+        // Mirror the scratch layout inside the backup and place a synthetic
+        // whiteout (char dev 0,0) where the deleted file was
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let whiteout_dir = backup_root.join(relative);
+        fs::create_dir_all(&whiteout_dir).unwrap();
+        let whiteout_path = whiteout_dir.join("deleted-by-whiteout");
+        let c_path = std::ffi::CString::new(whiteout_path.as_os_str().as_bytes()).unwrap();
+        let rc = unsafe { libc::mknod(c_path.as_ptr(), libc::S_IFCHR | 0o600, 0) };
+        if rc != 0 {
+            // mknod needs CAP_MKNOD; skip quietly where unavailable
+            eprintln!("skipping whiteout test: mknod not permitted");
+            return;
+        }
+
+        let metadata = fs::symlink_metadata(&whiteout_path).unwrap();
+        assert!(is_overlayfs_whiteout(&metadata));
+
+        let engine = DirectRestoreEngine::new(false, 300).with_overlayfs_whiteouts(true);
+        let outcome = engine.apply_whiteout(&whiteout_path, &backup_root).unwrap();
+        assert!(matches!(outcome, FileProcessOutcome::Cleaned));
+
+        // The target was deleted and the whiteout node consumed
+        assert!(!target_file.exists());
+        assert!(!whiteout_path.exists());
+    }
+
+    #[test]
+    fn test_no_restore_sentinel_covers_nested_destinations() {
+        let temp = TempDir::new().unwrap();
+        let managed = temp.path().join("opt/conda/pkgs");
+        fs::create_dir_all(managed.join("cache/deep")).unwrap();
+        fs::write(managed.join(NO_RESTORE_SENTINEL), b"").unwrap();
+
+        let engine = DirectRestoreEngine::new(true, 300);
+
+        // Files below the marker are covered, however deep
+        assert_eq!(
+            engine.find_no_restore_ancestor(&managed.join("pkg.tar.bz2")),
+            Some(managed.clone())
+        );
+        assert_eq!(
+            engine.find_no_restore_ancestor(&managed.join("cache/deep/index.json")),
+            Some(managed.clone())
+        );
+
+        // Files above or beside the marker are not
+        assert_eq!(engine.find_no_restore_ancestor(&temp.path().join("opt/conda/bin/python")), None);
+        assert_eq!(engine.find_no_restore_ancestor(&temp.path().join("unrelated.txt")), None);
+    }
+
+    #[test]
+    fn test_no_restore_nested_sentinels_and_global_dirs() {
+        let temp = TempDir::new().unwrap();
+        let outer = temp.path().join("outer");
+        let inner = outer.join("inner");
+        fs::create_dir_all(&inner).unwrap();
+        fs::write(outer.join(NO_RESTORE_SENTINEL), b"").unwrap();
+        fs::write(inner.join(NO_RESTORE_SENTINEL), b"").unwrap();
+
+        let engine = DirectRestoreEngine::new(true, 300);
+        // With nested sentinels the nearest marked ancestor is reported
+        assert_eq!(
+            engine.find_no_restore_ancestor(&inner.join("file")),
+            Some(inner.clone())
+        );
+
+        // A global --no-restore-dir needs no sentinel at all
+        let global = temp.path().join("global-managed");
+        let engine = DirectRestoreEngine::new(true, 300).with_no_restore_dirs(vec![global.clone()]);
+        assert_eq!(
+            engine.find_no_restore_ancestor(&global.join("sub/file")),
+            Some(global)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_confine_symlinks_keeps_in_tree_links_and_skips_escapes() {
+        let temp = TempDir::new().unwrap();
+        let backup = temp.path().join("backup");
+        let restored = temp.path().join("restored");
+        fs::create_dir_all(&backup).unwrap();
+        fs::create_dir_all(&restored).unwrap();
+
+        // In-tree relative link, absolute escaping link, ..-escaping link
+        std::os::unix::fs::symlink("sibling.txt", backup.join("rel_link")).unwrap();
+        std::os::unix::fs::symlink("/etc/shadow", backup.join("abs_link")).unwrap();
+        std::os::unix::fs::symlink(
+            "../../../../../../../../etc/passwd",
+            backup.join("dotdot_link"),
+        )
+        .unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300).with_confine_symlinks(true);
+
+        // The relative link resolves beside itself and is restored intact
+        assert!(matches!(
+            engine.copy_file_with_fallback(&backup.join("rel_link"), &restored.join("rel_link")),
+            CopyResult::Success
+        ));
+        assert_eq!(
+            fs::read_link(restored.join("rel_link")).unwrap(),
+            PathBuf::from("sibling.txt")
+        );
+
+        // Both escaping links are skipped with the target in the reason
+        for name in ["abs_link", "dotdot_link"] {
+            match engine.copy_file_with_fallback(&backup.join(name), &restored.join(name)) {
+                CopyResult::Skipped(reason) => {
+                    assert!(reason.contains("escapes restore root"), "{}", reason)
+                }
+                other => panic!("{} must be skipped, got {:?}", name, other),
+            }
+            assert!(fs::symlink_metadata(restored.join(name)).is_err());
+        }
+
+        // Without the flag the same links restore verbatim
+        let permissive = DirectRestoreEngine::new(false, 300);
+        assert!(matches!(
+            permissive.copy_file_with_fallback(&backup.join("abs_link"), &restored.join("abs_link")),
+            CopyResult::Success
+        ));
+        assert_eq!(
+            fs::read_link(restored.join("abs_link")).unwrap(),
+            PathBuf::from("/etc/shadow")
+        );
+    }
+
+    #[test]
+    fn test_skip_reasons_are_categorized_in_aggregation() {
+        let engine = DirectRestoreEngine::new(false, 300);
+        let mut result = DirectRestoreResult {
+            total_files: 0,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        let reasons = [
+            "File busy: Device or resource busy",
+            "File busy: Device or resource busy",
+            "Read-only filesystem (subtree classified read-only)",
+            "Permission denied: operation not permitted",
+            "Destination under no-restore directory: /opt/conda/pkgs",
+            "Something unclassified",
+        ];
+        for (i, reason) in reasons.iter().enumerate() {
+            engine.aggregate_file_outcome(
+                PathBuf::from(format!("/backup/file-{}", i)),
+                Ok(FileProcessOutcome::Skipped(reason.to_string())),
+                Path::new("/backup"),
+                &mut result,
+            );
+        }
+
+        assert_eq!(result.skip_reason_counts["busy"], 2);
+        assert_eq!(result.skip_reason_counts["read-only"], 1);
+        assert_eq!(result.skip_reason_counts["permission"], 1);
+        assert_eq!(result.skip_reason_counts["excluded"], 1);
+        assert_eq!(result.skip_reason_counts["other"], 1);
+
+        // The breakdown appears in the JSON report
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains("skip_reason_counts"));
+    }
+
+    #[test]
+    fn test_merge_missing_only_creates_absent_files_only() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+
+        // Container paths live under a unique /tmp scratch directory
+        let scratch = tempfile::Builder::new()
+            .prefix("merge-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let existing_target = scratch.path().join("existing.txt");
+        fs::write(&existing_target, b"container contents").unwrap();
+
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let backup_dir = backup_root.join(relative);
+        fs::create_dir_all(&backup_dir).unwrap();
+        fs::write(backup_dir.join("existing.txt"), b"stale session contents").unwrap();
+        fs::write(backup_dir.join("missing.txt"), b"new session contents").unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300).with_merge_missing_only(true);
+
+        // The existing target is skipped untouched
+        let outcome = engine
+            .process_single_file(&backup_dir.join("existing.txt"), &backup_root)
+            .unwrap();
+        match outcome {
+            FileProcessOutcome::Skipped(reason) => assert_eq!(reason, "exists, merge mode"),
+            other => panic!("expected skip, got {:?}", other),
+        }
+        assert_eq!(fs::read(&existing_target).unwrap(), b"container contents");
+
+        // The absent target is created
+        let outcome = engine
+            .process_single_file(&backup_dir.join("missing.txt"), &backup_root)
+            .unwrap();
+        assert!(matches!(outcome, FileProcessOutcome::Cleaned | FileProcessOutcome::Success));
+        assert_eq!(fs::read(scratch.path().join("missing.txt")).unwrap(), b"new session contents");
+    }
+
+    #[test]
+    fn test_compressed_backup_file_is_decompressed_and_verified() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+
+        // Container paths live under a unique /tmp scratch directory
+        let scratch = tempfile::Builder::new()
+            .prefix("decompress-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let backup_dir = backup_root.join(relative);
+        fs::create_dir_all(&backup_dir).unwrap();
+
+        let contents = "checkpoint line\n".repeat(10_000);
+        let original = backup_dir.join("metrics.csv");
+        fs::write(&original, &contents).unwrap();
+        let compressed = backup_dir.join("metrics.csv.zst");
+        crate::compression::compress_file(&original, &compressed).unwrap();
+        fs::remove_file(&original).unwrap();
+
+        let mut backup_manifest = manifest::BackupManifest::default();
+        backup_manifest.record(
+            &Path::new(relative).join("metrics.csv"),
+            manifest::ManifestEntry {
+                original_size: contents.len() as u64,
+                stored_size: fs::metadata(&compressed).unwrap().len(),
+                compressed: true,
+                original_hash: blake3::hash(contents.as_bytes()).to_hex().to_string(),
+                unstable: false,
+                pack: None,
+                db_unit: None,
+                deleted: false,
+                birth_time: None,
+                privileged_attrs: None,
+            },
+        );
+        backup_manifest.save(&backup_root).unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300);
+        let outcome = engine.process_single_file(&compressed, &backup_root).unwrap();
+        assert!(matches!(outcome, FileProcessOutcome::Cleaned));
+
+        // The file lands decompressed under its original name, and the
+        // consumed backup copy is cleaned up
+        assert_eq!(fs::read_to_string(scratch.path().join("metrics.csv")).unwrap(), contents);
+        assert!(!scratch.path().join("metrics.csv.zst").exists());
+        assert!(!compressed.exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_privileged_attrs_are_applied_only_for_allow_listed_paths() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+
+        let scratch = tempfile::Builder::new()
+            .prefix("privattr-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let backup_dir = backup_root.join(relative);
+
+        // The stored copy deliberately lacks the setuid bit (a plain
+        // permission copy loses it); only the manifest remembers it
+        let stage_backup = || {
+            fs::create_dir_all(&backup_dir).unwrap();
+            let stored = backup_dir.join("ping.bin");
+            fs::write(&stored, b"#!/bin/true\n").unwrap();
+            fs::set_permissions(&stored, fs::Permissions::from_mode(0o755)).unwrap();
+            let mut backup_manifest = manifest::BackupManifest::default();
+            backup_manifest.record(
+                &Path::new(relative).join("ping.bin"),
+                manifest::ManifestEntry {
+                    original_size: 12,
+                    stored_size: 12,
+                    compressed: false,
+                    original_hash: blake3::hash(b"#!/bin/true\n").to_hex().to_string(),
+                    unstable: false,
+                    pack: None,
+                    db_unit: None,
+                    deleted: false,
+                    birth_time: None,
+                    privileged_attrs: Some(manifest::PrivilegedAttrs {
+                        mode: 0o4755,
+                        capabilities: None,
+                    }),
+                },
+            );
+            backup_manifest.save(&backup_root).unwrap();
+        };
+
+        // Without opt-in the file restores, but the setuid bit is not
+        // re-applied and the omission is recorded
+        stage_backup();
+        let engine = DirectRestoreEngine::new(false, 300);
+        let result = engine.restore_to_container_root(&backup_root).unwrap();
+        let target = scratch.path().join("ping.bin");
+        assert_eq!(result.successful_files, 1);
+        assert_eq!(result.privileged_attrs_not_applied, vec![target.clone()]);
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o7000, 0);
+
+        // With the target under the allow-list (and the test running as
+        // root) the recorded mode comes back, setuid bit included
+        stage_backup();
+        let engine = DirectRestoreEngine::new(false, 300)
+            .with_privileged_attr_paths(vec![scratch.path().to_path_buf()]);
+        let result = engine.restore_to_container_root(&backup_root).unwrap();
+        assert!(result.privileged_attrs_not_applied.is_empty());
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o7777, 0o4755);
+    }
+
+    #[test]
+    fn test_restore_from_tar_maps_entries_and_validates_paths() {
+        fn append_file<W: io::Write>(builder: &mut tar::Builder<W>, path: &Path, contents: &[u8]) {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents).unwrap();
+        }
+
+        let scratch = tempfile::Builder::new()
+            .prefix("tar-restore-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+
+        // Entry paths are backup-root-relative, exactly like loose files:
+        // "tmp/<scratch>/greeting.txt" restores to /tmp/<scratch>/greeting.txt
+        let mut builder = tar::Builder::new(Vec::new());
+        append_file(&mut builder, &relative.join("greeting.txt"), b"hello");
+        append_file(&mut builder, &relative.join("nested/dir/data.bin"), b"payload");
+        // A traversal entry must be rejected by the shared path validation;
+        // tar::Builder refuses to write ".." itself, so forge the header
+        // bytes the way a hostile archive would
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        let hostile = b"../escape.txt";
+        header.as_gnu_mut().unwrap().name[..hostile.len()].copy_from_slice(hostile);
+        header.set_cksum();
+        builder.append(&header, &b"nope"[..]).unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300);
+        let result = engine.restore_from_tar(archive.as_slice()).unwrap();
+
+        assert_eq!(result.total_files, 3);
+        assert_eq!(result.successful_files, 2);
+        assert_eq!(result.failed_files, 1);
+        assert_eq!(fs::read_to_string(scratch.path().join("greeting.txt")).unwrap(), "hello");
+        assert_eq!(fs::read(scratch.path().join("nested/dir/data.bin")).unwrap(), b"payload");
+        assert!(result.failed_details[0].error.contains("Path mapping failed"));
+        assert!(!Path::new("/escape.txt").exists());
+    }
+
+    #[test]
+    fn test_restore_single_path_restores_only_the_requested_entries() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+
+        let scratch = tempfile::Builder::new()
+            .prefix("only-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let backup_dir = backup_root.join(relative);
+        fs::create_dir_all(backup_dir.join("docs")).unwrap();
+        fs::write(backup_dir.join("docs").join("notebook.ipynb"), b"cells").unwrap();
+        fs::write(backup_dir.join("docs").join("extra.txt"), b"extra").unwrap();
+        fs::write(backup_dir.join("other.txt"), b"other").unwrap();
+
+        // One file: nothing else is touched
+        let engine = DirectRestoreEngine::new(false, 300);
+        let result = engine
+            .restore_single_path(&backup_root, &scratch.path().join("docs").join("notebook.ipynb"))
+            .unwrap();
+        assert_eq!(result.total_files, 1);
+        assert_eq!(result.successful_files, 1);
+        assert_eq!(fs::read_to_string(scratch.path().join("docs").join("notebook.ipynb")).unwrap(), "cells");
+        assert!(!scratch.path().join("docs").join("extra.txt").exists());
+        assert!(!scratch.path().join("other.txt").exists());
+
+        // A directory restores its subtree
+        let result = engine
+            .restore_single_path(&backup_root, &scratch.path().join("docs"))
+            .unwrap();
+        assert_eq!(result.successful_files, 1);
+        assert_eq!(fs::read_to_string(scratch.path().join("docs").join("extra.txt")).unwrap(), "extra");
+        assert!(!scratch.path().join("other.txt").exists());
+
+        // A path in neither the backup tree nor the manifest is a failure
+        let result = engine
+            .restore_single_path(&backup_root, &scratch.path().join("missing.txt"))
+            .unwrap();
+        assert_eq!(result.failed_files, 1);
+        assert!(result.failed_details[0].error.contains("Not found in backup"));
+    }
+
+    #[test]
+    fn test_corrupt_compressed_backup_fails_hash_verification() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+
+        let scratch = tempfile::Builder::new()
+            .prefix("corrupt-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let backup_dir = backup_root.join(relative);
+        fs::create_dir_all(&backup_dir).unwrap();
+
+        let contents = "weights\n".repeat(5_000);
+        let original = backup_dir.join("model.bin");
+        fs::write(&original, &contents).unwrap();
+        let compressed = backup_dir.join("model.bin.zst");
+        crate::compression::compress_file(&original, &compressed).unwrap();
+        fs::remove_file(&original).unwrap();
+
+        let mut backup_manifest = manifest::BackupManifest::default();
+        backup_manifest.record(
+            &Path::new(relative).join("model.bin"),
+            manifest::ManifestEntry {
+                original_size: contents.len() as u64,
+                stored_size: fs::metadata(&compressed).unwrap().len(),
+                compressed: true,
+                // A hash that cannot match the decompressed content
+                original_hash: "0".repeat(64),
+                unstable: false,
+                pack: None,
+                db_unit: None,
+                deleted: false,
+                birth_time: None,
+                privileged_attrs: None,
+            },
+        );
+        backup_manifest.save(&backup_root).unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300);
+        let outcome = engine.process_single_file(&compressed, &backup_root).unwrap();
+        match outcome {
+            FileProcessOutcome::Failed(reason) => assert!(reason.contains("Hash mismatch")),
+            other => panic!("expected failure, got {:?}", other),
+        }
+
+        // The mismatching decompressed file is removed; the backup remains
+        assert!(!scratch.path().join("model.bin").exists());
+        assert!(compressed.exists());
+    }
+
+    #[test]
+    fn test_global_retry_budget_caps_total_retries_across_workers() {
+        use rayon::prelude::*;
+
+        let engine = DirectRestoreEngine::new(true, 300)
+            .with_retry_config(10, Duration::from_millis(1))
+            .with_retry_budget(Some(3));
+
+        // Many parallel workers each hitting transient errors: only the
+        // budgeted number of retries is granted in total
+        let granted: usize = (0..64)
+            .into_par_iter()
+            .map(|_| usize::from(engine.try_consume_retry()))
+            .sum();
+        assert_eq!(granted, 3);
+
+        // Once spent, every further request is refused immediately
+        assert!(!engine.try_consume_retry());
+    }
+
+    #[test]
+    fn test_unlimited_retry_budget_always_grants() {
+        let engine = DirectRestoreEngine::new(true, 300);
+        for _ in 0..1000 {
+            assert!(engine.try_consume_retry());
+        }
+    }
+
+    #[test]
+    fn test_rerun_after_interrupted_move_completes_and_cleans_sources() {
+        let temp = TempDir::new().unwrap();
+        let source_dir = temp.path().join("backup");
+        let target_dir = temp.path().join("restored");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300);
+
+        // Simulated prior partial run: some files were already renamed into
+        // place (target exists, source left behind by the interrupted run)
+        fs::write(source_dir.join("moved.txt"), b"already moved").unwrap();
+        fs::write(target_dir.join("moved.txt"), b"already moved").unwrap();
+        // And some files were never reached
+        fs::write(source_dir.join("pending.txt"), b"not yet moved").unwrap();
+
+        let outcome = engine.move_file_with_fallback(
+            &source_dir.join("moved.txt"),
+            &target_dir.join("moved.txt"),
+        );
+        assert!(matches!(outcome, CopyResult::Success));
+        // The leftover source is cleaned and the restored copy untouched
+        assert!(!source_dir.join("moved.txt").exists());
+        assert_eq!(fs::read(target_dir.join("moved.txt")).unwrap(), b"already moved");
+
+        let outcome = engine.move_file_with_fallback(
+            &source_dir.join("pending.txt"),
+            &target_dir.join("pending.txt"),
+        );
+        assert!(matches!(outcome, CopyResult::Success));
+        assert!(!source_dir.join("pending.txt").exists());
+        assert_eq!(fs::read(target_dir.join("pending.txt")).unwrap(), b"not yet moved");
+    }
+
+    #[test]
+    fn test_move_still_replaces_target_with_different_size() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("session.log");
+        let dst = temp.path().join("restored.log");
+        fs::write(&src, b"fresh session contents").unwrap();
+        // A stale target of different size is not mistaken for a completed move
+        fs::write(&dst, b"stale").unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300);
+        let outcome = engine.move_file_with_fallback(&src, &dst);
+        assert!(matches!(outcome, CopyResult::Success));
+        assert!(!src.exists());
+        assert_eq!(fs::read(&dst).unwrap(), b"fresh session contents");
+    }
+
+    #[test]
+    fn test_trash_mode_renames_cleanups_preserving_relative_paths() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+
+        // Container paths live under a unique /tmp scratch directory
+        let scratch = tempfile::Builder::new()
+            .prefix("trash-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let backup_dir = backup_root.join(relative).join("workspace");
+        fs::create_dir_all(&backup_dir).unwrap();
+        fs::write(backup_dir.join("notebook.ipynb"), b"cells").unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300).with_trash_mode(true);
+        let result = engine.restore_to_container_root(&backup_root).unwrap();
+        assert_eq!(result.cleaned_files, 1);
+        assert_eq!(result.trashed_files, 1);
+        assert_eq!(result.permanently_deleted_files, 0);
+
+        // The file was restored and its backup copy moved into the trash,
+        // keeping the backup-root-relative path
+        assert_eq!(fs::read(scratch.path().join("workspace/notebook.ipynb")).unwrap(), b"cells");
+        assert!(!backup_dir.join("notebook.ipynb").exists());
+        let trash_dir = backup_root.join(TRASH_DIR_NAME);
+        let run_dir = fs::read_dir(&trash_dir).unwrap().next().unwrap().unwrap().path();
+        let trashed = run_dir.join(relative).join("workspace/notebook.ipynb");
+        assert_eq!(fs::read(&trashed).unwrap(), b"cells");
+
+        // A later restore over the same backup root ignores the trash
+        let rerun = DirectRestoreEngine::new(false, 300)
+            .restore_to_container_root(&backup_root)
+            .unwrap();
+        assert_eq!(rerun.total_files, 0);
+        assert!(trashed.exists());
+    }
+
+    #[test]
+    fn test_purge_trash_honors_retention() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path();
+        let run_dir = backup_root.join(TRASH_DIR_NAME).join("20260101-000000");
+        fs::create_dir_all(&run_dir).unwrap();
+        fs::write(run_dir.join("old.txt"), b"stale").unwrap();
+
+        // Fresh runs survive a long retention window
+        assert_eq!(purge_trash(backup_root, Duration::from_secs(3600), false).unwrap().purged_runs, 0);
+        assert!(run_dir.exists());
+
+        // A dry run reports the same selection and the reclaimable bytes
+        // without removing anything
+        let preview = purge_trash(backup_root, Duration::ZERO, true).unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(preview.purged_runs, 1);
+        assert_eq!(preview.reclaimable_bytes, 5);
+        assert_eq!(preview.runs[0].path, run_dir);
+        assert_eq!(preview.runs[0].bytes, 5);
+        assert!(run_dir.exists());
+
+        // Zero retention empties the trash immediately; same report shape
+        let purged = purge_trash(backup_root, Duration::ZERO, false).unwrap();
+        assert!(!purged.dry_run);
+        assert_eq!(purged.purged_runs, 1);
+        assert_eq!(purged.reclaimable_bytes, 5);
+        assert!(!run_dir.exists());
+
+        // A missing trash directory is not an error
+        assert_eq!(purge_trash(&backup_root.join("elsewhere"), Duration::ZERO, false).unwrap().purged_runs, 0);
+    }
+
+    #[test]
+    fn test_purge_trash_retention_boundary_under_a_mock_clock() {
+        let temp = TempDir::new().unwrap();
+        let run_dir = temp.path().join(TRASH_DIR_NAME).join("20260101-000000");
+        fs::create_dir_all(&run_dir).unwrap();
+        fs::write(run_dir.join("old.txt"), b"stale").unwrap();
+        // Pin the run directory's mtime and measure its age against a
+        // mock clock instead of sleeping through the window
+        let trashed_at = 1_700_000_000;
+        filetime::set_file_mtime(&run_dir, filetime::FileTime::from_unix_time(trashed_at, 0))
+            .unwrap();
+
+        let retention = Duration::from_secs(24 * 3600);
+        let just_inside =
+            crate::clock::MockClock::at_unix_seconds(trashed_at as u64 + 24 * 3600 - 1);
+        assert_eq!(
+            purge_trash_with_clock(temp.path(), retention, false, &just_inside)
+                .unwrap()
+                .purged_runs,
+            0
+        );
+        assert!(run_dir.exists());
+
+        let at_boundary = crate::clock::MockClock::at_unix_seconds(trashed_at as u64 + 24 * 3600);
+        assert_eq!(
+            purge_trash_with_clock(temp.path(), retention, false, &at_boundary)
+                .unwrap()
+                .purged_runs,
+            1
+        );
+        assert!(!run_dir.exists());
+    }
+
+    #[test]
+    fn test_verify_backup_reports_unreadable_and_traversal_issues() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+        fs::create_dir_all(backup_root.join("home/user")).unwrap();
+
+        // A healthy file, a traversal-triggering name, an unreadable source
+        // (dangling symlink) and a protected-prefix target
+        fs::write(backup_root.join("home/user/notes.txt"), b"fine").unwrap();
+        fs::write(backup_root.join("home/user/..evil"), b"traversal").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("/nonexistent/target", backup_root.join("home/user/dangling")).unwrap();
+        fs::create_dir_all(backup_root.join("etc")).unwrap();
+        fs::write(backup_root.join("etc/passwd"), b"root:x:0:0").unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 300);
+        let verification = engine.verify_backup(&backup_root).unwrap();
+
+        assert_eq!(verification.verified_files, 1);
+        assert!(!verification.is_restorable());
+
+        let kind_for = |name: &str| {
+            verification
+                .issues
+                .iter()
+                .find(|issue| issue.path.file_name().is_some_and(|n| n == name))
+                .map(|issue| issue.kind.as_str())
+        };
+        assert_eq!(kind_for("..evil"), Some("mapping"));
+        #[cfg(unix)]
+        assert_eq!(kind_for("dangling"), Some("unreadable"));
+        assert_eq!(kind_for("passwd"), Some("protected-path"));
+
+        // Nothing was written or consumed: verification is read-only
+        assert!(backup_root.join("home/user/notes.txt").exists());
+        assert!(backup_root.join("home/user/..evil").exists());
+    }
+
+    #[test]
+    fn test_strict_mode_counts_skips_as_failures() {
+        let lenient = DirectRestoreEngine::new(true, 300);
+        let strict = DirectRestoreEngine::new(true, 300).with_strict(true);
+
+        let mut result = DirectRestoreResult {
+            total_files: 1,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        lenient.aggregate_file_outcome(
+            PathBuf::from("/backup/busy.txt"),
+            Ok(FileProcessOutcome::Skipped("File busy".to_string())),
+            Path::new("/backup"),
+            &mut result,
+        );
+        assert_eq!(result.skipped_files, 1);
+        assert_eq!(result.failed_files, 0);
+
+        strict.aggregate_file_outcome(
+            PathBuf::from("/backup/busy.txt"),
+            Ok(FileProcessOutcome::Skipped("File busy".to_string())),
+            Path::new("/backup"),
+            &mut result,
+        );
+        assert_eq!(result.skipped_files, 1);
+        assert_eq!(result.failed_files, 1);
+        assert!(result.failed_details[0].error.contains("strict mode"));
+    }
+
+    #[test]
+    fn test_directory_summaries_aggregate_by_first_component() {
+        let engine = DirectRestoreEngine::new(true, 300);
+        let backup_root = Path::new("/backup");
+        let mut result = DirectRestoreResult {
+            total_files: 4,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 0,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        engine.aggregate_file_outcome(
+            PathBuf::from("/backup/home/user/kept.txt"),
+            Ok(FileProcessOutcome::Success),
+            backup_root,
+            &mut result,
+        );
+        engine.aggregate_file_outcome(
+            PathBuf::from("/backup/home/user/busy.txt"),
+            Ok(FileProcessOutcome::Skipped("File busy".to_string())),
+            backup_root,
+            &mut result,
+        );
+        engine.aggregate_file_outcome(
+            PathBuf::from("/backup/opt/conda/lib.so"),
+            Ok(FileProcessOutcome::Failed("Input/output error".to_string())),
+            backup_root,
+            &mut result,
+        );
+        // A file directly in the backup root lands under "/"
+        engine.aggregate_file_outcome(
+            PathBuf::from("/backup/toplevel.txt"),
+            Ok(FileProcessOutcome::Success),
+            backup_root,
+            &mut result,
+        );
+
+        let home = &result.directory_summaries["/home"];
+        assert_eq!((home.restored, home.skipped, home.failed), (1, 1, 0));
+        let opt = &result.directory_summaries["/opt"];
+        assert_eq!((opt.restored, opt.skipped, opt.failed), (0, 0, 1));
+        let root = &result.directory_summaries["/"];
+        assert_eq!((root.restored, root.skipped, root.failed), (1, 0, 0));
+
+        // The breakdown travels with the serialized result
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains("\"directory_summaries\""));
+        assert!(json.contains("\"/home\""));
+    }
+
+    #[test]
+    fn test_tiny_open_file_limit_serializes_copies() {
+        let temp = TempDir::new().unwrap();
+        let backup_root = temp.path().join("backup");
+        let scratch = tempfile::Builder::new()
+            .prefix("fd-limit-test-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let relative = scratch.path().strip_prefix("/").unwrap();
+        let backup_dir = backup_root.join(relative);
+        fs::create_dir_all(&backup_dir).unwrap();
+
+        let mut files = Vec::new();
+        for i in 0..8 {
+            let path = backup_dir.join(format!("file-{}.txt", i));
+            fs::write(&path, format!("contents {}", i)).unwrap();
+            files.push(path);
+        }
+
+        // A limit below the fd headroom leaves a single permit, so the
+        // parallel workers serialize instead of failing with "Too many
+        // open files"
+        let engine = DirectRestoreEngine::new(false, 300).with_max_open_files(2);
+        let outcomes: Vec<_> = files
+            .par_iter()
+            .map(|file| engine.process_single_file(file, &backup_root).unwrap())
+            .collect();
+
+        assert!(outcomes
+            .iter()
+            .all(|outcome| matches!(outcome, FileProcessOutcome::Cleaned | FileProcessOutcome::Success)));
+        for i in 0..8 {
+            assert_eq!(
+                fs::read_to_string(scratch.path().join(format!("file-{}.txt", i))).unwrap(),
+                format!("contents {}", i)
+            );
+        }
+    }
+
+    #[test]
+    fn test_retry_from_report_only_retries_failures() {
+
+        let temp_dir = TempDir::new().unwrap();
+        let backup_root = temp_dir.path().join("backup");
+        fs::create_dir_all(backup_root.join("home")).unwrap();
+
+        // Two previously failed files plus one untouched file in the backup
+        let failed_a = backup_root.join("home/a.txt");
+        let failed_b = backup_root.join("home/b.txt");
+        fs::write(&failed_a, b"a").unwrap();
+        fs::write(&failed_b, b"b").unwrap();
+        fs::write(backup_root.join("home/untouched.txt"), b"u").unwrap();
+
+        let prior = DirectRestoreResult {
+            total_files: 3,
+            successful_files: 1,
+            skipped_files: 0,
+            failed_files: 2,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: vec![
+                FailedFile { path: failed_a, error: "Copy failed".to_string() },
+                FailedFile { path: failed_b, error: "Copy failed".to_string() },
+            ],
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        // Dry run so nothing is written to the container root
+        let engine = DirectRestoreEngine::new(true, 300);
+        let result = engine.retry_from_report(&prior, &backup_root).unwrap();
+
+        // Only the two recorded failures are retried
+        assert_eq!(result.total_files, 2);
+        assert_eq!(result.successful_files, 2);
+        assert_eq!(result.failed_files, 0);
+    }
+
+    #[test]
+    fn test_retry_from_report_rejects_paths_outside_root() {
+
+        let temp_dir = TempDir::new().unwrap();
+        let backup_root = temp_dir.path().join("backup");
+        fs::create_dir_all(&backup_root).unwrap();
+
+        let prior = DirectRestoreResult {
+            total_files: 1,
+            successful_files: 0,
+            skipped_files: 0,
+            failed_files: 1,
+            cleaned_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: vec![
+                FailedFile { path: PathBuf::from("/etc/passwd"), error: "Copy failed".to_string() },
+            ],
+            cleaned_details: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(0),
+        };
+
+        let engine = DirectRestoreEngine::new(true, 300);
+        let result = engine.retry_from_report(&prior, &backup_root).unwrap();
+
+        assert_eq!(result.total_files, 0);
+        assert_eq!(result.failed_files, 1);
+        assert!(result.failed_details[0].error.contains("outside backup root"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_parent_permission_repair() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = DirectRestoreEngine::new(false, 300);
+
+        // Source file to copy into a read-only parent
+        let src = temp_dir.path().join("source.txt");
+        fs::write(&src, b"repair me").unwrap();
+
+        // Fixture directory captured with a restrictive r-x mode
+        let restricted = temp_dir.path().join("restricted");
+        fs::create_dir(&restricted).unwrap();
+        fs::set_permissions(&restricted, fs::Permissions::from_mode(0o500)).unwrap();
+
+        let dst = restricted.join("child.txt");
+
+        // The repair path must make the parent writable and record it
+        assert!(engine.try_repair_parent_permissions(&dst));
+        let mode = fs::metadata(&restricted).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o700);
+
+        // The copy now succeeds into the repaired directory
+        let result = engine.copy_file_with_fallback(&src, &dst);
+        assert_eq!(result, CopyResult::Success);
+
+        // Restoring puts the original mode back and reports the repair count
+        assert_eq!(engine.restore_repaired_parent_permissions(), 1);
+        let mode = fs::metadata(&restricted).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o500);
+
+        // Reset so TempDir can clean up
+        fs::set_permissions(&restricted, fs::Permissions::from_mode(0o700)).unwrap();
+    }
+
     #[test]
     fn test_retry_configuration() {
         let engine = DirectRestoreEngine::new(true, 300)
@@ -1593,4 +5083,340 @@ mod tests {
         assert_eq!(engine.max_retries, 5);
         assert_eq!(engine.retry_delay, Duration::from_millis(100));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_packed_backup_restores_identically_to_loose_layout() {
+        let temp = TempDir::new().unwrap();
+        let scratch = tempfile::Builder::new()
+            .prefix("packed-restore-")
+            .tempdir_in("/tmp")
+            .unwrap();
+        let scratch_relative = scratch.path().strip_prefix("/").unwrap();
+
+        // Source mirrors container-absolute layout: many small files plus
+        // one large file that must stay loose
+        let source = temp.path().join("source");
+        let session = source.join(scratch_relative);
+        fs::create_dir_all(session.join("nested/deeper")).unwrap();
+        let mut expected = Vec::new();
+        for i in 0..120 {
+            let relative = match i % 3 {
+                0 => format!("small-{:03}.txt", i),
+                1 => format!("nested/small-{:03}.txt", i),
+                _ => format!("nested/deeper/small-{:03}.txt", i),
+            };
+            let contents = format!("tiny file number {} with its own contents", i);
+            fs::write(session.join(&relative), &contents).unwrap();
+            expected.push((relative, contents.into_bytes()));
+        }
+        let large_contents = vec![0x5au8; 8 * 1024];
+        fs::write(session.join("model.bin"), &large_contents).unwrap();
+        expected.push(("model.bin".to_string(), large_contents));
+
+        let backup = temp.path().join("backup");
+        let policy = crate::compression::CompressionPolicy { min_size: u64::MAX };
+        let transfer = crate::transfer_data_with_compression(
+            &source,
+            &backup,
+            crate::Deadline::from_secs(120),
+            &std::collections::HashSet::new(),
+            &policy,
+            false,
+            Some(4096),
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(transfer.error_count, 0);
+        assert_eq!(transfer.success_count, 121);
+
+        // Small files live only in the pack; the large file stays loose
+        let packs: Vec<_> = fs::read_dir(&backup)
+            .unwrap()
+            .flatten()
+            .filter(|entry| crate::packing::is_pack_file_name(&entry.file_name()))
+            .collect();
+        assert_eq!(packs.len(), 1);
+        assert!(!backup.join(scratch_relative).join("small-000.txt").exists());
+        assert!(backup.join(scratch_relative).join("model.bin").exists());
+
+        let engine = DirectRestoreEngine::new(false, 60);
+        let result = engine.restore_to_container_root(&backup).unwrap();
+        assert_eq!(result.failed_files, 0, "failures: {:?}", result.failed_details);
+        assert_eq!(result.successful_files, 121);
+
+        // The restored tree is byte-identical to the loose original
+        for (relative, contents) in &expected {
+            assert_eq!(&fs::read(scratch.path().join(relative)).unwrap(), contents, "{} differs", relative);
+        }
+
+        // Fully restored packs are cleaned from the backup
+        assert!(!backup.join(packs[0].file_name()).exists());
+    }
+
+    #[test]
+    fn test_cgroup_container_detection_heuristics() {
+        let kubepods = "12:memory:/kubepods/burstable/pod1234/abcd\n1:name=systemd:/kubepods/pod1234\n";
+        assert!(cgroup_indicates_container(kubepods));
+        assert!(cgroup_indicates_container("0::/system.slice/docker-abcdef.scope\n"));
+
+        let bare_metal = "12:memory:/user.slice\n1:name=systemd:/init.scope\n0::/init.scope\n";
+        assert!(!cgroup_indicates_container(bare_metal));
+    }
+
+    #[test]
+    fn test_root_restore_guard_confirmation_paths() {
+        // One test covers all CONFIRM states: parallel tests must not
+        // race on the process-wide environment variable
+        std::env::remove_var("CONFIRM");
+        let err = check_root_restore_guard(false, false).unwrap_err();
+        assert!(err.to_string().contains("--i-understand-restore-to-root"));
+
+        assert!(check_root_restore_guard(true, false).is_ok());
+        assert!(check_root_restore_guard(false, true).is_ok());
+
+        std::env::set_var("CONFIRM", "1");
+        assert!(check_root_restore_guard(false, false).is_ok());
+        std::env::remove_var("CONFIRM");
+    }
+
+    #[test]
+    fn test_preserve_file_attributes_keeps_atime_and_mtime() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("source.txt");
+        let dst = temp.path().join("restored.txt");
+        std::fs::write(&src, b"timestamped contents").unwrap();
+        std::fs::write(&dst, b"timestamped contents").unwrap();
+
+        // Distinct, well-known timestamps on the source
+        let atime = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+        let mtime = filetime::FileTime::from_unix_time(1_500_000_000, 0);
+        filetime::set_file_times(&src, atime, mtime).unwrap();
+
+        let engine = DirectRestoreEngine::new(false, 60);
+        engine.preserve_file_attributes(&src, &dst).unwrap();
+
+        let dst_metadata = std::fs::metadata(&dst).unwrap();
+        assert_eq!(filetime::FileTime::from_last_modification_time(&dst_metadata), mtime);
+        assert_eq!(filetime::FileTime::from_last_access_time(&dst_metadata), atime);
+    }
+
+    #[test]
+    fn test_result_identity_fields_round_trip_and_default() {
+        let mut result = DirectRestoreResult {
+            total_files: 1,
+            successful_files: 1,
+            skipped_files: 0,
+            failed_files: 0,
+            skipped_details: Vec::new(),
+            failed_details: Vec::new(),
+            skip_reason_counts: std::collections::HashMap::new(),
+            directory_summaries: BTreeMap::new(),
+            cleaned_files: 0,
+            cleaned_details: Vec::new(),
+            truncated_details: 0,
+            prefetch_hits: 0,
+            prefetch_issued: 0,
+            trashed_files: 0,
+            permanently_deleted_files: 0,
+            repaired_directories: 0,
+            source_identity: None,
+            executing_identity: None,
+            producer_version: None,
+            priority_tier_reached: None,
+            privileged_attrs_not_applied: Vec::new(),
+            cancelled: false,
+            duration: Duration::from_secs(1),
+        };
+        result.source_identity = Some(crate::backup_layout::BackupMeta {
+            namespace: "teco".to_string(),
+            pod_name: "nb-old-0".to_string(),
+            container_name: "inference".to_string(),
+        });
+        result.executing_identity = Some(crate::backup_layout::BackupMeta {
+            namespace: "teco".to_string(),
+            pod_name: "nb-new-0".to_string(),
+            container_name: "inference".to_string(),
+        });
+
+        // Both identities survive the report round trip for audits
+        let json = serde_json::to_string(&result).unwrap();
+        let parsed: DirectRestoreResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.source_identity.unwrap().pod_name, "nb-old-0");
+        assert_eq!(parsed.executing_identity.unwrap().pod_name, "nb-new-0");
+
+        // Reports written before the fields existed still parse
+        let legacy: DirectRestoreResult = serde_json::from_str(
+            r#"{"total_files":0,"successful_files":0,"skipped_files":0,"failed_files":0,
+                "skipped_details":[],"failed_details":[],"cleaned_files":0,"cleaned_details":[],
+                "duration":{"secs":0,"nanos":0}}"#,
+        )
+        .unwrap();
+        assert!(legacy.source_identity.is_none());
+        assert!(legacy.executing_identity.is_none());
+    }
+
+    #[test]
+    fn test_hard_traversal_error_returns_partial_result() {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = temp_dir.path().join("backup");
+        std::fs::create_dir_all(&backup_dir).unwrap();
+
+        // Build a directory chain whose absolute path exceeds PATH_MAX:
+        // the walk enqueues each level via the parent's dirfd, but
+        // read_dir on the over-long absolute path fails hard
+        // (ENAMETOOLONG), which is exactly the mid-run error class the
+        // partial result must survive. Built with relative mkdir+chdir in
+        // a child process (a shell cannot: its cd resolves the logical
+        // absolute path and trips PATH_MAX itself)
+        let status = std::process::Command::new("python3")
+            .arg("-c")
+            .arg(
+                "import os, sys\n\
+                 os.chdir(sys.argv[1])\n\
+                 for _ in range(30):\n\
+                 \tos.mkdir('d' * 200)\n\
+                 \tos.chdir('d' * 200)\n",
+            )
+            .arg(&backup_dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // Two restorable files one level down; kept off the top level so
+        // the cross-device probe has nothing to hard-link at real
+        // container paths
+        let first_level = std::fs::read_dir(&backup_dir).unwrap().next().unwrap().unwrap().path();
+        std::fs::write(first_level.join("notes.txt"), b"restored before the error").unwrap();
+        std::fs::write(first_level.join("data.bin"), b"also restored").unwrap();
+
+        let engine = DirectRestoreEngine::new(true, 300);
+        let err = engine.restore_to_container_root(&backup_dir).unwrap_err();
+
+        // The partial result rides the error with its counters and
+        // duration intact, so the binary can still emit a report
+        let interrupted = err
+            .downcast_ref::<RestoreInterrupted>()
+            .expect("hard traversal errors carry the partial result");
+        assert_eq!(interrupted.partial.total_files, 2);
+        assert_eq!(interrupted.partial.successful_files, 2);
+        assert!(!interrupted.partial.cancelled);
+        assert!(interrupted.partial.duration > Duration::from_secs(0));
+        assert!(format!("{:#}", interrupted.source).contains("Failed to read directory"));
+
+        // The partial result still serializes for the report envelope
+        let json = serde_json::to_string(&interrupted.partial).unwrap();
+        assert!(json.contains("\"successful_files\":2"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_reflink_clones_when_supported_and_falls_back_otherwise() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("src.bin");
+        fs::write(&src, b"reflink payload").unwrap();
+
+        // Direct probe: on a reflink-capable filesystem the clone succeeds
+        // and the destination matches; otherwise the failed clone must not
+        // leave a truncated stub behind for the byte-copy fallback
+        let probe_dst = temp.path().join("probe.bin");
+        match crate::reflink::try_reflink(&src, &probe_dst) {
+            Ok(()) => assert_eq!(fs::read(&probe_dst).unwrap(), b"reflink payload"),
+            Err(_) => assert!(!probe_dst.exists()),
+        }
+
+        // Regardless of filesystem support, Always mode still produces a
+        // correct copy through the fallback path
+        let engine = DirectRestoreEngine::new(false, 300).with_reflink(ReflinkMode::Always);
+        let copy_dst = temp.path().join("copy.bin");
+        assert!(matches!(
+            engine.copy_file_with_fallback(&src, &copy_dst),
+            CopyResult::Success
+        ));
+        assert_eq!(fs::read(&copy_dst).unwrap(), b"reflink payload");
+    }
+
+    #[test]
+    fn test_cancelled_token_finalizes_restore_without_processing() {
+        let temp = TempDir::new().unwrap();
+        let backup_dir = temp.path().join("backup");
+        fs::create_dir_all(backup_dir.join("etc")).unwrap();
+        fs::write(backup_dir.join("etc").join("app.conf"), b"config").unwrap();
+
+        let token = tokio_util::sync::CancellationToken::new();
+        token.cancel();
+        let engine = DirectRestoreEngine::new(true, 300).with_cancellation(token);
+
+        let result = engine.restore_to_container_root(&backup_dir).unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.successful_files, 0);
+        assert_eq!(result.total_files, 0);
+    }
+
+    #[test]
+    fn test_source_vanishing_between_listing_and_copy_is_a_benign_skip() {
+        let temp = TempDir::new().unwrap();
+        // A name no other concurrently running test copies: the hook is
+        // process-wide, so it must only react to this test's file
+        let src = temp.path().join("vanishing-fault-test.txt");
+        let dst = temp.path().join("out").join("vanishing-fault-test.txt");
+        fs::write(&src, b"about to vanish").unwrap();
+
+        // The fault-injection hook synchronizes with a deleter thread:
+        // it hands over the path and blocks until the deletion is done,
+        // reproducing a user process deleting the file in the window
+        // between listing and copy
+        let (path_tx, path_rx) = std::sync::mpsc::channel::<PathBuf>();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let deleter = std::thread::spawn(move || {
+            let path = path_rx.recv().unwrap();
+            fs::remove_file(path).unwrap();
+            done_tx.send(()).unwrap();
+        });
+        let done_rx = Mutex::new(done_rx);
+        crate::fault_injection::install_before_file_hook(std::sync::Arc::new(move |path: &Path| {
+            if path.file_name().is_some_and(|n| n == "vanishing-fault-test.txt") {
+                path_tx.send(path.to_path_buf()).unwrap();
+                done_rx.lock().recv().unwrap();
+            }
+        }));
+
+        let engine = DirectRestoreEngine::new(false, 300);
+        let outcome = engine.copy_file_with_fallback(&src, &dst);
+        crate::fault_injection::clear_before_file_hook();
+        deleter.join().unwrap();
+
+        match outcome {
+            CopyResult::Skipped(reason) => {
+                assert!(reason.contains("vanished"), "unexpected reason: {}", reason);
+                assert_eq!(crate::categorize_skip_reason(&reason), "vanished");
+            }
+            other => panic!("vanished source must be a benign skip, got {:?}", other),
+        }
+        assert!(!dst.exists());
+    }
+
+    #[test]
+    fn test_extension_filter_restores_only_matching_files() {
+        let temp = TempDir::new().unwrap();
+        let backup_dir = temp.path().join("backup");
+        let work = backup_dir.join("work");
+        fs::create_dir_all(&work).unwrap();
+        fs::write(work.join("notes.txt"), b"keep").unwrap();
+        fs::write(work.join("REPORT.TXT"), b"keep, case-insensitive").unwrap();
+        fs::write(work.join("model.ipynb"), b"filtered").unwrap();
+        fs::write(work.join("Makefile"), b"extensionless, filtered").unwrap();
+
+        let engine = DirectRestoreEngine::new(true, 300)
+            .with_restore_extensions(vec![".txt".to_string()]);
+        let result = engine.restore_to_container_root(&backup_dir).unwrap();
+
+        assert_eq!(result.total_files, 4);
+        assert_eq!(result.successful_files, 2);
+        assert_eq!(result.skipped_files, 2);
+        assert!(result
+            .skipped_details
+            .iter()
+            .all(|skip| skip.reason == "Extension filtered"));
+    }
+}
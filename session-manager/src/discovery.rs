@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+use log::{debug, info};
+use std::path::{Path, PathBuf};
+
+/// Default containerd state root probed for the snapshotter layout
+pub const DEFAULT_CONTAINERD_ROOT: &str = "/var/lib/containerd";
+
+/// Default snapshotter whose layout is probed
+pub const DEFAULT_SNAPSHOTTER: &str = "overlayfs";
+
+/// Candidate sessions directories under the containerd root, in probe
+/// order: the current plugin-directory layout first, then the older
+/// layout that kept sessions next to the snapshots directory
+pub fn candidate_sessions_paths(containerd_root: &Path, snapshotter: &str) -> Vec<PathBuf> {
+    let plugin_dir = containerd_root.join(format!("io.containerd.snapshotter.v1.{}", snapshotter));
+    vec![
+        plugin_dir.join("sessions"),
+        plugin_dir.join("snapshots").join("sessions"),
+    ]
+}
+
+/// Locate the sessions directory by probing known snapshotter layouts,
+/// validating each candidate against the current mapping: the directory
+/// is only accepted when `<pod_hash>/<snapshot_hash>/fs` exists under it.
+///
+/// An explicit `--sessions-path` should always bypass this probe; it is a
+/// fallback for environments where the snapshotter root moves between
+/// containerd versions.
+pub fn discover_sessions_path(
+    containerd_root: &Path,
+    snapshotter: &str,
+    pod_hash: &str,
+    snapshot_hash: &str,
+) -> Result<PathBuf> {
+    let candidates = candidate_sessions_paths(containerd_root, snapshotter);
+    for candidate in &candidates {
+        let probe = candidate.join(pod_hash).join(snapshot_hash).join("fs");
+        info!("Probing sessions layout: {}", candidate.display());
+        if probe.is_dir() {
+            info!("Discovered sessions path: {}", candidate.display());
+            return Ok(candidate.clone());
+        }
+        debug!("No session at {}", probe.display());
+    }
+    bail!(
+        "Could not discover a sessions directory for {}/{} under {} (probed {} layout(s)); \
+         pass --sessions-path explicitly",
+        pod_hash,
+        snapshot_hash,
+        containerd_root.display(),
+        candidates.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_discovery_probes_both_snapshotter_layouts() {
+        // Current layout: sessions directly under the plugin directory
+        let current = TempDir::new().unwrap();
+        let current_sessions = current
+            .path()
+            .join("io.containerd.snapshotter.v1.overlayfs/sessions");
+        fs::create_dir_all(current_sessions.join("a1b2c3d4/e5f6a7b8/fs")).unwrap();
+        assert_eq!(
+            discover_sessions_path(current.path(), "overlayfs", "a1b2c3d4", "e5f6a7b8").unwrap(),
+            current_sessions
+        );
+
+        // Older layout: sessions next to the snapshots directory
+        let legacy = TempDir::new().unwrap();
+        let legacy_sessions = legacy
+            .path()
+            .join("io.containerd.snapshotter.v1.overlayfs/snapshots/sessions");
+        fs::create_dir_all(legacy_sessions.join("a1b2c3d4/e5f6a7b8/fs")).unwrap();
+        assert_eq!(
+            discover_sessions_path(legacy.path(), "overlayfs", "a1b2c3d4", "e5f6a7b8").unwrap(),
+            legacy_sessions
+        );
+    }
+
+    #[test]
+    fn test_discovery_rejects_layouts_without_the_current_mapping() {
+        let temp = TempDir::new().unwrap();
+        // The layout exists but holds a different pod's session
+        fs::create_dir_all(
+            temp.path()
+                .join("io.containerd.snapshotter.v1.overlayfs/sessions/other/session/fs"),
+        )
+        .unwrap();
+
+        let err = discover_sessions_path(temp.path(), "overlayfs", "a1b2c3d4", "e5f6a7b8")
+            .unwrap_err();
+        assert!(err.to_string().contains("--sessions-path"));
+    }
+}
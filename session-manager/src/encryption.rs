@@ -0,0 +1,351 @@
+use anyhow::{bail, Context, Result};
+use log::{debug, info};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Magic bytes identifying an encrypted backup file
+const MAGIC: &[u8; 6] = b"SMENC1";
+/// Hex-encoded key identifier length in the file header
+const KEY_ID_LEN: usize = 16;
+/// Per-file nonce length in the file header
+const NONCE_LEN: usize = 24;
+/// Streaming chunk size for encryption/decryption
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Domain separation string for deriving file keys from key file contents
+const KEY_DERIVATION_CONTEXT: &str = "session-manager backup encryption v1";
+
+/// A single encryption key loaded from a key file.
+///
+/// The key id is derived from the key material itself, so the same key file
+/// always produces the same id regardless of its path or name.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    pub id: String,
+    key: [u8; 32],
+}
+
+impl EncryptionKey {
+    fn from_key_material(material: &[u8]) -> Self {
+        let key = blake3::derive_key(KEY_DERIVATION_CONTEXT, material);
+        let id_hash = blake3::hash(&key);
+        let id = id_hash.as_bytes()[..KEY_ID_LEN / 2]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        Self { id, key }
+    }
+}
+
+/// An ordered set of encryption keys supporting key rotation.
+///
+/// The first key is the current key and is used for all new backups; the
+/// remaining keys are older rotated-out keys kept so that backups written
+/// before the rotation can still be decrypted. On decryption the key is
+/// selected by the key id stored in each file's header.
+pub struct Keyring {
+    keys: Vec<EncryptionKey>,
+}
+
+impl Keyring {
+    /// Load a keyring from key files, in the order given. The first file
+    /// holds the current key.
+    pub fn load(key_files: &[PathBuf]) -> Result<Self> {
+        if key_files.is_empty() {
+            bail!("At least one encryption key file is required");
+        }
+
+        let mut keys = Vec::with_capacity(key_files.len());
+        for key_file in key_files {
+            let material = fs::read(key_file)
+                .with_context(|| format!("Failed to read encryption key file: {}", key_file.display()))?;
+            if material.is_empty() {
+                bail!("Encryption key file is empty: {}", key_file.display());
+            }
+            let key = EncryptionKey::from_key_material(&material);
+            debug!("Loaded encryption key {} from {}", key.id, key_file.display());
+            keys.push(key);
+        }
+
+        Ok(Self { keys })
+    }
+
+    /// The current key used for new backups
+    pub fn current(&self) -> &EncryptionKey {
+        &self.keys[0]
+    }
+
+    /// Find a key by the id recorded in a file header
+    pub fn find(&self, id: &str) -> Option<&EncryptionKey> {
+        self.keys.iter().find(|k| k.id == id)
+    }
+
+    /// All key ids in the keyring, for error messages
+    fn known_ids(&self) -> Vec<&str> {
+        self.keys.iter().map(|k| k.id.as_str()).collect()
+    }
+
+    /// Encrypt `source` into `target` using the current key. The target file
+    /// starts with a header (magic, key id, nonce) followed by ciphertext.
+    pub fn encrypt_file(&self, source: &Path, target: &Path) -> Result<()> {
+        let key = self.current();
+        let nonce = generate_nonce()?;
+
+        let mut input = fs::File::open(source)
+            .with_context(|| format!("Failed to open file for encryption: {}", source.display()))?;
+        let mut output = fs::File::create(target)
+            .with_context(|| format!("Failed to create encrypted file: {}", target.display()))?;
+
+        output.write_all(MAGIC)?;
+        output.write_all(key.id.as_bytes())?;
+        output.write_all(&nonce)?;
+
+        apply_keystream(&key.key, &nonce, &mut input, &mut output)
+            .with_context(|| format!("Failed to encrypt {}", source.display()))?;
+
+        Ok(())
+    }
+
+    /// Decrypt `source` into `target`, selecting the key by the id stored in
+    /// the file header.
+    pub fn decrypt_file(&self, source: &Path, target: &Path) -> Result<()> {
+        let mut input = fs::File::open(source)
+            .with_context(|| format!("Failed to open encrypted file: {}", source.display()))?;
+
+        let mut header = [0u8; 6 + KEY_ID_LEN + NONCE_LEN];
+        input
+            .read_exact(&mut header)
+            .with_context(|| format!("Encrypted file too short: {}", source.display()))?;
+
+        if &header[..6] != MAGIC {
+            bail!("Not an encrypted backup file (bad magic): {}", source.display());
+        }
+
+        let key_id = std::str::from_utf8(&header[6..6 + KEY_ID_LEN])
+            .with_context(|| format!("Corrupt key id in header: {}", source.display()))?;
+        let key = self.find(key_id).with_context(|| {
+            format!(
+                "No key in keyring for key id {} (known ids: {:?}) while decrypting {}",
+                key_id,
+                self.known_ids(),
+                source.display()
+            )
+        })?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&header[6 + KEY_ID_LEN..]);
+
+        let mut output = fs::File::create(target)
+            .with_context(|| format!("Failed to create decrypted file: {}", target.display()))?;
+
+        apply_keystream(&key.key, &nonce, &mut input, &mut output)
+            .with_context(|| format!("Failed to decrypt {}", source.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Generate a random per-file nonce
+fn generate_nonce() -> Result<[u8; NONCE_LEN]> {
+    let mut nonce = [0u8; NONCE_LEN];
+    let mut urandom = fs::File::open("/dev/urandom")
+        .context("Failed to open /dev/urandom for nonce generation")?;
+    urandom
+        .read_exact(&mut nonce)
+        .context("Failed to read nonce from /dev/urandom")?;
+    Ok(nonce)
+}
+
+/// XOR the input stream with a Blake3-derived keystream (keyed XOF over the
+/// nonce). Encryption and decryption are the same operation.
+fn apply_keystream<R: Read, W: Write>(
+    key: &[u8; 32],
+    nonce: &[u8],
+    input: &mut R,
+    output: &mut W,
+) -> Result<()> {
+    let mut hasher = blake3::Hasher::new_keyed(key);
+    hasher.update(nonce);
+    let mut keystream = hasher.finalize_xof();
+
+    let mut data = vec![0u8; CHUNK_SIZE];
+    let mut stream = vec![0u8; CHUNK_SIZE];
+
+    loop {
+        let n = input.read(&mut data)?;
+        if n == 0 {
+            break;
+        }
+        keystream.fill(&mut stream[..n]);
+        for i in 0..n {
+            data[i] ^= stream[i];
+        }
+        output.write_all(&data[..n])?;
+    }
+
+    Ok(())
+}
+
+/// Encrypt every regular file under `backup_dir` in place, replacing each
+/// file with a `<name>.enc` counterpart. Already-encrypted files are skipped
+/// so the operation is idempotent.
+pub fn encrypt_backup_dir(keyring: &Keyring, backup_dir: &Path) -> Result<usize> {
+    let mut encrypted = 0;
+    for entry in WalkDir::new(backup_dir) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "enc") {
+            continue;
+        }
+
+        let mut target = path.as_os_str().to_os_string();
+        target.push(".enc");
+        let target = PathBuf::from(target);
+
+        keyring.encrypt_file(path, &target)?;
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove plaintext after encryption: {}", path.display()))?;
+        encrypted += 1;
+    }
+
+    if encrypted > 0 {
+        info!("Encrypted {} backup files with key {}", encrypted, keyring.current().id);
+    }
+    Ok(encrypted)
+}
+
+/// Decrypt every `.enc` file under `backup_dir` in place, restoring the
+/// original file names. Files encrypted with any key in the keyring are
+/// handled; an unknown key id is an error.
+pub fn decrypt_backup_dir(keyring: &Keyring, backup_dir: &Path) -> Result<usize> {
+    let mut decrypted = 0;
+    for entry in WalkDir::new(backup_dir) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "enc") {
+            continue;
+        }
+
+        let target = path.with_extension("");
+        keyring.decrypt_file(path, &target)?;
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove encrypted file after decryption: {}", path.display()))?;
+        decrypted += 1;
+    }
+
+    if decrypted > 0 {
+        info!("Decrypted {} backup files", decrypted);
+    }
+    Ok(decrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_key_file(dir: &Path, name: &str, material: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, material).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let key_file = write_key_file(temp.path(), "key-a", b"key material a");
+        let keyring = Keyring::load(&[key_file]).unwrap();
+
+        let plain = temp.path().join("data.txt");
+        fs::write(&plain, b"session contents").unwrap();
+        let enc = temp.path().join("data.txt.enc");
+        let restored = temp.path().join("restored.txt");
+
+        keyring.encrypt_file(&plain, &enc).unwrap();
+        assert_ne!(fs::read(&enc).unwrap(), b"session contents");
+        keyring.decrypt_file(&enc, &restored).unwrap();
+        assert_eq!(fs::read(&restored).unwrap(), b"session contents");
+    }
+
+    #[test]
+    fn test_key_rotation_restores_old_and_new_backups() {
+        let temp = TempDir::new().unwrap();
+        let key_a = write_key_file(temp.path(), "key-a", b"key material a");
+        let key_b = write_key_file(temp.path(), "key-b", b"key material b");
+
+        let backup = temp.path().join("backup");
+        fs::create_dir_all(&backup).unwrap();
+
+        // Encrypt one file while key A was current
+        fs::write(backup.join("old.txt"), b"written under key A").unwrap();
+        let keyring_a = Keyring::load(std::slice::from_ref(&key_a)).unwrap();
+        keyring_a
+            .encrypt_file(&backup.join("old.txt"), &backup.join("old.txt.enc"))
+            .unwrap();
+        fs::remove_file(backup.join("old.txt")).unwrap();
+
+        // Rotate: key B is now current, key A retained in the keyring
+        let keyring_rotated = Keyring::load(&[key_b.clone(), key_a.clone()]).unwrap();
+        fs::write(backup.join("new.txt"), b"written under key B").unwrap();
+        keyring_rotated
+            .encrypt_file(&backup.join("new.txt"), &backup.join("new.txt.enc"))
+            .unwrap();
+        fs::remove_file(backup.join("new.txt")).unwrap();
+
+        // New backups use the current (first) key
+        let keyring_b_only = Keyring::load(&[key_b]).unwrap();
+        assert_eq!(keyring_rotated.current().id, keyring_b_only.current().id);
+
+        // Restore a mix of A- and B-encrypted files with the rotated keyring
+        let count = decrypt_backup_dir(&keyring_rotated, &backup).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(fs::read(backup.join("old.txt")).unwrap(), b"written under key A");
+        assert_eq!(fs::read(backup.join("new.txt")).unwrap(), b"written under key B");
+    }
+
+    #[test]
+    fn test_unknown_key_id_is_rejected() {
+        let temp = TempDir::new().unwrap();
+        let key_a = write_key_file(temp.path(), "key-a", b"key material a");
+        let key_b = write_key_file(temp.path(), "key-b", b"key material b");
+
+        let plain = temp.path().join("data.txt");
+        fs::write(&plain, b"secret").unwrap();
+        let enc = temp.path().join("data.txt.enc");
+
+        Keyring::load(&[key_a]).unwrap().encrypt_file(&plain, &enc).unwrap();
+
+        let err = Keyring::load(&[key_b])
+            .unwrap()
+            .decrypt_file(&enc, &temp.path().join("out.txt"))
+            .unwrap_err();
+        assert!(err.to_string().contains("No key in keyring"));
+    }
+
+    #[test]
+    fn test_encrypt_backup_dir_is_idempotent() {
+        let temp = TempDir::new().unwrap();
+        let key_file = write_key_file(temp.path(), "key-a", b"key material a");
+        let keyring = Keyring::load(&[key_file]).unwrap();
+
+        let backup = temp.path().join("backup");
+        fs::create_dir_all(backup.join("nested")).unwrap();
+        fs::write(backup.join("a.txt"), b"a").unwrap();
+        fs::write(backup.join("nested/b.txt"), b"b").unwrap();
+
+        assert_eq!(encrypt_backup_dir(&keyring, &backup).unwrap(), 2);
+        // Second pass finds only .enc files and does nothing
+        assert_eq!(encrypt_backup_dir(&keyring, &backup).unwrap(), 0);
+
+        assert_eq!(decrypt_backup_dir(&keyring, &backup).unwrap(), 2);
+        assert_eq!(fs::read(backup.join("a.txt")).unwrap(), b"a");
+        assert_eq!(fs::read(backup.join("nested/b.txt")).unwrap(), b"b");
+    }
+}
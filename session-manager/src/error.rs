@@ -0,0 +1,206 @@
+//! Typed errors for embedding callers.
+//!
+//! The binaries log `TransferResult` details and derive their exit codes
+//! directly from the counts, but integrators embedding the library want a
+//! single typed error they can propagate with `?`. [`SessionError`]
+//! collapses a result's error tally into one value while keeping the
+//! first few distinct messages for context, and [`SessionManagerError`]
+//! categorizes the library surface so a controller can branch on "no
+//! session found" versus "mapping parse error" versus "fatal I/O"
+//! programmatically. The binaries keep anyhow at the edge; typed errors
+//! travel through anyhow chains and are recovered by downcast, with
+//! [`exit_code_for`] as the single place mapping categories to process
+//! exit codes.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// How many distinct error messages an aggregated error carries; the
+/// full deduplicated list stays on the `TransferResult`
+pub const MAX_SAMPLE_ERRORS: usize = 3;
+
+/// An operation-level error aggregated from per-file failures
+#[derive(Debug)]
+pub enum SessionError {
+    /// A transfer finished with hard errors
+    Transfer {
+        /// Number of files that failed
+        error_count: usize,
+        /// Number of files transferred successfully
+        success_count: usize,
+        /// Up to [`MAX_SAMPLE_ERRORS`] distinct error messages, each with
+        /// its occurrence count
+        sample: Vec<String>,
+        /// Distinct messages beyond the sample
+        omitted_messages: usize,
+    },
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Transfer {
+                error_count,
+                success_count,
+                sample,
+                omitted_messages,
+            } => {
+                write!(
+                    f,
+                    "Transfer finished with {} errors ({} files transferred)",
+                    error_count, success_count
+                )?;
+                if !sample.is_empty() {
+                    write!(f, ": {}", sample.join("; "))?;
+                }
+                if *omitted_messages > 0 {
+                    write!(f, " (+{} more distinct errors)", omitted_messages)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+/// Categorized error for the library surface. Variants are constructed
+/// at the site that knows the category; [`SessionManagerError::classify`]
+/// recovers them from an anyhow chain at the edge
+#[derive(Debug, thiserror::Error)]
+pub enum SessionManagerError {
+    /// The mappings file has no entry matching the pod identity
+    #[error("No session found for {namespace}/{pod_name}/{container_name}")]
+    NoSessionFound {
+        namespace: String,
+        pod_name: String,
+        container_name: String,
+    },
+
+    /// The mappings file exists but its JSON cannot be parsed
+    #[error("Failed to parse mappings file {path}")]
+    MappingParse {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// A transfer finished but some files failed
+    #[error(transparent)]
+    TransferPartial(#[from] SessionError),
+
+    /// An unrecoverable filesystem error
+    #[error("Fatal I/O error during {operation}")]
+    FatalIo {
+        operation: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Anything not yet categorized; the original chain rides along
+    #[error("{0:#}")]
+    Other(anyhow::Error),
+}
+
+impl From<std::io::Error> for SessionManagerError {
+    fn from(source: std::io::Error) -> Self {
+        SessionManagerError::FatalIo {
+            operation: "filesystem access".to_string(),
+            source,
+        }
+    }
+}
+
+impl SessionManagerError {
+    /// Recover the typed category from an anyhow chain: a typed variant
+    /// (or an aggregated [`SessionError`]) anywhere in the chain wins,
+    /// everything else is [`SessionManagerError::Other`]
+    pub fn classify(err: anyhow::Error) -> Self {
+        match err.downcast::<SessionManagerError>() {
+            Ok(typed) => typed,
+            Err(err) => match err.downcast::<SessionError>() {
+                Ok(transfer) => SessionManagerError::TransferPartial(transfer),
+                Err(err) => SessionManagerError::Other(err),
+            },
+        }
+    }
+
+    /// The process exit code for this category; kept here so the
+    /// binaries and any wrapper scripts agree on the numbers
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SessionManagerError::NoSessionFound { .. } => 3,
+            SessionManagerError::MappingParse { .. } => 4,
+            SessionManagerError::TransferPartial(_) => 5,
+            SessionManagerError::FatalIo { .. } => 6,
+            SessionManagerError::Other(_) => 1,
+        }
+    }
+}
+
+/// Exit code for an error leaving a binary: typed categories anywhere in
+/// the chain map through [`SessionManagerError::exit_code`], everything
+/// else exits 1
+pub fn exit_code_for(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if let Some(typed) = cause.downcast_ref::<SessionManagerError>() {
+            return typed.exit_code();
+        }
+        if cause.is::<SessionError>() {
+            return 5;
+        }
+    }
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_survive_anyhow_context_wrapping() {
+        let no_session: anyhow::Error = SessionManagerError::NoSessionFound {
+            namespace: "default".to_string(),
+            pod_name: "nb-test-0".to_string(),
+            container_name: "inference".to_string(),
+        }
+        .into();
+        let wrapped = no_session.context("while resolving the session");
+        assert_eq!(exit_code_for(&wrapped), 3);
+
+        let transfer: anyhow::Error = SessionError::Transfer {
+            error_count: 2,
+            success_count: 10,
+            sample: vec!["Input/output error (x2)".to_string()],
+            omitted_messages: 0,
+        }
+        .into();
+        assert_eq!(exit_code_for(&transfer.context("backing up")), 5);
+
+        assert_eq!(exit_code_for(&anyhow::anyhow!("untyped failure")), 1);
+    }
+
+    #[test]
+    fn test_classify_recovers_typed_variants_and_source_chains() {
+        let parse_err = serde_json::from_str::<serde_json::Value>("{broken").unwrap_err();
+        let typed: anyhow::Error = SessionManagerError::MappingParse {
+            path: PathBuf::from("/etc/path-mappings.json"),
+            source: parse_err,
+        }
+        .into();
+        match SessionManagerError::classify(typed.context("loading mappings")) {
+            SessionManagerError::MappingParse { path, source } => {
+                assert_eq!(path, PathBuf::from("/etc/path-mappings.json"));
+                assert!(source.to_string().contains("key must be a string"));
+            }
+            other => panic!("expected MappingParse, got {:?}", other),
+        }
+
+        match SessionManagerError::classify(anyhow::anyhow!("untyped failure")) {
+            SessionManagerError::Other(inner) => {
+                assert_eq!(inner.to_string(), "untyped failure")
+            }
+            other => panic!("expected Other, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Test seam for injecting faults between listing and copy.
+//!
+//! The pipelines list directory entries first and touch the files later,
+//! so a still-running user process can mutate or delete a source in the
+//! gap. Reproducing that window in a test needs a synchronization point
+//! at exactly the moment a file is about to be copied; this module
+//! provides one, the same way [`crate::clock`] substitutes time.
+//! Production binaries never install a hook, and the per-file cost of
+//! the disabled seam is a single relaxed atomic load.
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Hook fired with each source path right before it is copied
+pub type BeforeFileHook = Arc<dyn Fn(&Path) + Send + Sync>;
+
+static BEFORE_FILE_HOOK: Lazy<RwLock<Option<BeforeFileHook>>> = Lazy::new(|| RwLock::new(None));
+static HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Install the process-wide before-file hook. Hooks fire for every file
+/// the process copies, so a test hook must filter for its own paths.
+pub fn install_before_file_hook(hook: BeforeFileHook) {
+    *BEFORE_FILE_HOOK.write() = Some(hook);
+    HOOK_INSTALLED.store(true, Ordering::Release);
+}
+
+/// Remove the installed hook, restoring the no-op seam
+pub fn clear_before_file_hook() {
+    HOOK_INSTALLED.store(false, Ordering::Release);
+    *BEFORE_FILE_HOOK.write() = None;
+}
+
+/// Fire the before-file hook for `path`; a no-op when none is installed
+pub fn fire_before_file(path: &Path) {
+    if !HOOK_INSTALLED.load(Ordering::Acquire) {
+        return;
+    }
+    let hook = BEFORE_FILE_HOOK.read().clone();
+    if let Some(hook) = hook {
+        hook(path);
+    }
+}
@@ -0,0 +1,186 @@
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::path::Path;
+
+/// User-configured exclude/include globs, matched against paths relative
+/// to the session root. Includes are evaluated after excludes: a file an
+/// exclude would skip is re-admitted when an include matches it, so
+/// `--exclude '**/cache' --include '**/cache/keepme'` keeps just that
+/// one file. Patterns support `*` and `?` within a path segment and `**`
+/// for any number of segments.
+#[derive(Debug, Clone, Default)]
+pub struct TransferFilter {
+    excludes: Vec<String>,
+    includes: Vec<String>,
+}
+
+impl TransferFilter {
+    pub fn new(excludes: &[String], includes: &[String]) -> Self {
+        Self {
+            excludes: excludes.to_vec(),
+            includes: includes.to_vec(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.excludes.is_empty() && self.includes.is_empty()
+    }
+
+    /// Raw exclude patterns, for transfer backends with native filtering
+    pub fn exclude_patterns(&self) -> &[String] {
+        &self.excludes
+    }
+
+    /// Raw include patterns, for transfer backends with native filtering
+    pub fn include_patterns(&self) -> &[String] {
+        &self.includes
+    }
+
+    /// Whether the file at `relative` should be transferred: excluded when
+    /// any exclude matches it or an ancestor, unless an include re-admits it
+    pub fn admits(&self, relative: &Path) -> bool {
+        let segments = path_segments(relative);
+        let excluded = self
+            .excludes
+            .iter()
+            .any(|pattern| matches_path_or_ancestor(pattern, &segments));
+        if !excluded {
+            return true;
+        }
+        self.includes
+            .iter()
+            .any(|pattern| matches_path_or_ancestor(pattern, &segments))
+    }
+
+    /// Whether the walk should descend into the directory at `relative`.
+    /// An excluded directory is still entered when an include pattern
+    /// could match something beneath it, so includes can rescue files
+    /// inside an excluded tree.
+    pub fn should_descend(&self, relative: &Path) -> bool {
+        let segments = path_segments(relative);
+        let excluded = self
+            .excludes
+            .iter()
+            .any(|pattern| matches_path_or_ancestor(pattern, &segments));
+        if !excluded {
+            return true;
+        }
+        self.includes.iter().any(|pattern| {
+            matches_path_or_ancestor(pattern, &segments)
+                || could_match_within(pattern, &segments)
+        })
+    }
+}
+
+/// Globally installed filter, set once at binary startup from
+/// `--exclude`/`--include`
+static TRANSFER_FILTER: Lazy<RwLock<Option<TransferFilter>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install the process-wide transfer filter
+pub fn install(filter: TransferFilter) {
+    debug!(
+        "Installing transfer filter: {} excludes, {} includes",
+        filter.excludes.len(),
+        filter.includes.len()
+    );
+    *TRANSFER_FILTER.write() = Some(filter);
+}
+
+/// The installed filter, if any patterns were given
+pub fn installed_filter() -> Option<TransferFilter> {
+    TRANSFER_FILTER.read().clone()
+}
+
+fn path_segments(relative: &Path) -> Vec<String> {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect()
+}
+
+/// A pattern matching a directory also matches everything beneath it, so
+/// test the path and each of its ancestors
+fn matches_path_or_ancestor(pattern: &str, segments: &[String]) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    (1..=segments.len()).any(|len| glob_match(&pattern, &segments[..len], false))
+}
+
+/// Whether the pattern could match some path strictly below this directory
+fn could_match_within(pattern: &str, segments: &[String]) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    glob_match(&pattern, segments, true)
+}
+
+/// Segment-wise glob match. In prefix mode, a fully consumed path with
+/// pattern remaining counts as a match (the pattern could still match
+/// deeper in the tree).
+fn glob_match(pattern: &[&str], path: &[String], prefix: bool) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => !prefix,
+        (None, Some(_)) => false,
+        (Some(_), None) => prefix || pattern.iter().all(|s| *s == "**"),
+        (Some(&"**"), Some(_)) => {
+            glob_match(&pattern[1..], path, prefix) || glob_match(pattern, &path[1..], prefix)
+        }
+        (Some(segment), Some(name)) => {
+            segment_match(segment.as_bytes(), name.as_bytes())
+                && glob_match(&pattern[1..], &path[1..], prefix)
+        }
+    }
+}
+
+/// Single-segment match with `*` and `?` wildcards
+pub(crate) fn segment_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (None, Some(_)) => false,
+        (Some(b'*'), None) => segment_match(&pattern[1..], name),
+        (Some(_), None) => false,
+        (Some(b'*'), Some(_)) => {
+            segment_match(&pattern[1..], name) || segment_match(pattern, &name[1..])
+        }
+        (Some(b'?'), Some(_)) => segment_match(&pattern[1..], &name[1..]),
+        (Some(c), Some(n)) => c == n && segment_match(&pattern[1..], &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_include_rescues_file_inside_excluded_directory() {
+        let filter = TransferFilter::new(
+            &["**/cache".to_string()],
+            &["**/cache/keepme".to_string()],
+        );
+
+        // The exclude matches the directory and everything beneath it
+        assert!(!filter.admits(&PathBuf::from("work/cache/dropme")));
+        assert!(!filter.admits(&PathBuf::from("work/cache/sub/dropme")));
+        // The include re-admits exactly the rescued file
+        assert!(filter.admits(&PathBuf::from("work/cache/keepme")));
+        // Unrelated files are unaffected
+        assert!(filter.admits(&PathBuf::from("work/data.txt")));
+
+        // The walk must still enter the excluded directory to reach the
+        // rescued file, but not excluded trees nothing can rescue
+        assert!(filter.should_descend(&PathBuf::from("work/cache")));
+        let no_rescue = TransferFilter::new(&["**/cache".to_string()], &[]);
+        assert!(!no_rescue.should_descend(&PathBuf::from("work/cache")));
+    }
+
+    #[test]
+    fn test_glob_segments_and_wildcards() {
+        let filter = TransferFilter::new(&["logs/*.log".to_string(), "**/?mp".to_string()], &[]);
+        assert!(!filter.admits(&PathBuf::from("logs/app.log")));
+        assert!(filter.admits(&PathBuf::from("logs/app.txt")));
+        // `*` does not cross segment boundaries
+        assert!(filter.admits(&PathBuf::from("logs/sub/app.log")));
+        // `?` matches a single character, `**` any depth
+        assert!(!filter.admits(&PathBuf::from("a/b/tmp")));
+        assert!(filter.admits(&PathBuf::from("a/b/temp")));
+    }
+}
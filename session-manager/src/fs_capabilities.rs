@@ -0,0 +1,185 @@
+use log::{debug, warn};
+use std::fs;
+use std::path::Path;
+
+/// Attribute operations supported by a destination filesystem.
+///
+/// Network filesystems (CIFS, some NFS exports) silently lack chown, xattrs,
+/// symlinks or timestamp updates; without a single up-front probe every file
+/// copy rediscovers this and emits its own warning, flooding the logs with
+/// thousands of identical lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapabilitySet {
+    pub chown: bool,
+    pub xattrs: bool,
+    pub timestamps: bool,
+    pub symlinks: bool,
+}
+
+impl CapabilitySet {
+    /// A filesystem assumed to support everything; used when the probe
+    /// itself cannot run, so no operation is ever skipped by mistake
+    pub fn full() -> Self {
+        Self {
+            chown: true,
+            xattrs: true,
+            timestamps: true,
+            symlinks: true,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        *self == Self::full()
+    }
+
+    /// Names of the missing capabilities, for the one-line summary warning
+    pub fn missing(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if !self.chown {
+            missing.push("chown");
+        }
+        if !self.xattrs {
+            missing.push("xattrs");
+        }
+        if !self.timestamps {
+            missing.push("timestamps");
+        }
+        if !self.symlinks {
+            missing.push("symlinks");
+        }
+        missing
+    }
+}
+
+/// Probe the destination filesystem once by exercising each attribute
+/// operation against a temporary file. Probe artifacts are always cleaned
+/// up; if the probe cannot even create its temp file, full capability is
+/// assumed so nothing is skipped incorrectly.
+pub fn probe_destination(dir: &Path) -> CapabilitySet {
+    let probe_path = dir.join(format!(".session-manager-probe-{}", std::process::id()));
+    let link_path = dir.join(format!(".session-manager-probe-link-{}", std::process::id()));
+
+    if fs::write(&probe_path, b"probe").is_err() {
+        debug!(
+            "Capability probe could not create {}; assuming full capability",
+            probe_path.display()
+        );
+        return CapabilitySet::full();
+    }
+
+    let capabilities = CapabilitySet {
+        chown: probe_chown(&probe_path),
+        xattrs: probe_xattr(&probe_path),
+        timestamps: filetime::set_file_mtime(&probe_path, filetime::FileTime::now()).is_ok(),
+        symlinks: probe_symlink(&link_path),
+    };
+
+    let _ = fs::remove_file(&probe_path);
+    let _ = fs::remove_file(&link_path);
+
+    if capabilities.is_full() {
+        debug!("Destination {} supports all attribute operations", dir.display());
+    } else {
+        // The single summary warning replacing the per-file noise
+        warn!(
+            "Destination {} does not support: {}; these attribute operations will be skipped silently",
+            dir.display(),
+            capabilities.missing().join(", ")
+        );
+    }
+
+    capabilities
+}
+
+#[cfg(unix)]
+fn probe_chown(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return true;
+    };
+    // Chown to our own uid/gid: a no-op everywhere it is supported
+    unsafe { libc::chown(c_path.as_ptr(), libc::geteuid(), libc::getegid()) == 0 }
+}
+
+#[cfg(not(unix))]
+fn probe_chown(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn probe_xattr(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return true;
+    };
+    let attr = c"user.session-manager-probe";
+    let value = b"1";
+    unsafe {
+        libc::setxattr(
+            c_path.as_ptr(),
+            attr.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+        ) == 0
+    }
+}
+
+#[cfg(not(unix))]
+fn probe_xattr(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn probe_symlink(link_path: &Path) -> bool {
+    std::os::unix::fs::symlink("session-manager-probe-target", link_path).is_ok()
+}
+
+#[cfg(not(unix))]
+fn probe_symlink(_link_path: &Path) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_probe_cleans_up_and_reports_local_fs_capabilities() {
+        let temp = TempDir::new().unwrap();
+        let capabilities = probe_destination(temp.path());
+
+        // Local filesystems support symlinks and timestamps; chown to our
+        // own uid/gid is always permitted
+        assert!(capabilities.chown);
+        assert!(capabilities.timestamps);
+        assert!(capabilities.symlinks);
+
+        // No probe artifacts are left behind
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_unprobeable_destination_assumes_full_capability() {
+        let capabilities = probe_destination(Path::new("/nonexistent/probe/dir"));
+        assert!(capabilities.is_full());
+        assert!(capabilities.missing().is_empty());
+    }
+
+    #[test]
+    fn test_missing_lists_capability_names() {
+        let capabilities = CapabilitySet {
+            chown: false,
+            xattrs: false,
+            timestamps: true,
+            symlinks: false,
+        };
+        assert_eq!(capabilities.missing(), vec!["chown", "xattrs", "symlinks"]);
+        assert!(!capabilities.is_full());
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Periodically refreshed liveness file for Kubernetes probes.
+///
+/// Long backups and restores can look identical to a hung process from the
+/// outside. The copy loops call [`beat`] on every iteration; the file is
+/// only rewritten once per interval, so the probe can check the file's
+/// freshness (mtime) and the short status line inside it.
+pub struct Heartbeat {
+    path: PathBuf,
+    interval: Duration,
+    last_write: Mutex<Option<Instant>>,
+}
+
+impl Heartbeat {
+    pub fn new(path: PathBuf, interval: Duration) -> Self {
+        Self {
+            path,
+            interval,
+            last_write: Mutex::new(None),
+        }
+    }
+
+    /// Refresh the heartbeat file if the interval has elapsed (the first
+    /// call always writes). Write failures are logged, never fatal: a
+    /// broken probe file must not abort a working backup.
+    pub fn beat(&self, phase: &str, files_done: usize) {
+        let mut last_write = self.last_write.lock();
+        let due = match *last_write {
+            Some(last) => last.elapsed() >= self.interval,
+            None => true,
+        };
+        if !due {
+            return;
+        }
+
+        // The shared counters add what the per-operation count cannot:
+        // bytes moved and the failure/skip tallies across all engines
+        let progress = crate::progress::global().snapshot();
+        let status = format!(
+            "{} {} files_done={} bytes_done={} failures={} skips={}\n",
+            chrono::Utc::now().to_rfc3339(), phase, files_done,
+            progress.bytes_done, progress.failures, progress.skips
+        );
+        match fs::write(&self.path, status) {
+            Ok(()) => {
+                *last_write = Some(Instant::now());
+                debug!("Heartbeat updated: {} ({} files done)", phase, files_done);
+            }
+            Err(e) => {
+                warn!("Failed to update heartbeat file {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// Globally installed heartbeat, set once at binary startup
+static HEARTBEAT: Lazy<RwLock<Option<Arc<Heartbeat>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install the process-wide heartbeat. Called from binary startup when
+/// `--heartbeat-file` is given; the copy loops then update it via [`beat`].
+pub fn install(path: PathBuf, interval_secs: u64) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create heartbeat directory: {}", parent.display()))?;
+        }
+    }
+    let heartbeat = Arc::new(Heartbeat::new(path, Duration::from_secs(interval_secs)));
+    heartbeat.beat("starting", 0);
+    *HEARTBEAT.write() = Some(heartbeat);
+    Ok(())
+}
+
+/// Update the installed heartbeat; a no-op when none is installed. The
+/// status socket shares the beat so the two probes never disagree.
+pub fn beat(phase: &str, files_done: usize) {
+    crate::status_server::note_progress(phase, files_done);
+    if let Some(heartbeat) = HEARTBEAT.read().as_ref() {
+        heartbeat.beat(phase, files_done);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_heartbeat_mtime_advances_during_long_copy() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("heartbeat");
+        let heartbeat = Heartbeat::new(path.clone(), Duration::from_millis(10));
+
+        // Simulated long copy: many iterations spanning several intervals
+        heartbeat.beat("transfer", 0);
+        let first_mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        let first_contents = fs::read_to_string(&path).unwrap();
+
+        for files_done in 1..=5 {
+            std::thread::sleep(Duration::from_millis(25));
+            heartbeat.beat("transfer", files_done * 100);
+        }
+
+        let last_mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        assert!(last_mtime > first_mtime, "heartbeat mtime should advance");
+        let last_contents = fs::read_to_string(&path).unwrap();
+        assert_ne!(first_contents, last_contents);
+        assert!(last_contents.contains("files_done=500"));
+    }
+
+    #[test]
+    fn test_heartbeat_throttles_within_interval() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("heartbeat");
+        let heartbeat = Heartbeat::new(path.clone(), Duration::from_secs(3600));
+
+        heartbeat.beat("transfer", 1);
+        let contents = fs::read_to_string(&path).unwrap();
+
+        // Within the interval the file is not rewritten
+        heartbeat.beat("transfer", 2);
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+    }
+}
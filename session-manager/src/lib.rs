@@ -13,15 +13,85 @@ use once_cell::sync::Lazy;
 use std::num::NonZeroUsize;
 use std::collections::HashSet;
 
+pub mod adaptive_parallelism;
+pub mod age;
+pub mod analyze;
+pub mod backup_layout;
+pub mod checkpoint;
+pub mod clock;
+pub mod compression;
+#[cfg(feature = "cri")]
+pub mod cri;
+pub mod deadline;
+pub mod encryption;
+pub mod error;
+pub mod fault_injection;
+pub mod filter;
+pub mod fs_capabilities;
+pub mod heartbeat;
+pub mod logging;
+pub mod manifest;
+pub mod mount_index;
+pub mod db_aware;
+pub mod dir_cache;
+pub mod direct_io;
+pub mod discovery;
+pub mod prefetch;
+pub mod profiling;
+pub mod progress;
 pub mod direct_restore;
 pub mod lockless_backup;
+pub mod open_files;
+pub mod ordering;
+pub mod packing;
+pub mod quiesce;
+pub mod quota;
+pub mod reflink;
+pub mod result_envelope;
+pub mod retention;
+pub mod rsync_itemize;
+pub mod selftest;
+pub mod snapshot_farm;
+pub mod status_server;
+pub mod strategy;
 mod optimized_io;
+pub use optimized_io::{
+    copy_file_async_hashed, copy_file_hashed, install_parallel_hash_threshold,
+    DEFAULT_PARALLEL_HASH_THRESHOLD,
+};
 mod resource_manager;
 mod async_operations;
 
+pub use deadline::Deadline;
+
+/// Default capacity of the global path mapping cache
+pub const DEFAULT_PATH_MAPPING_CACHE_CAPACITY: usize = 1000;
+
 // Global LRU cache for path mappings
-static PATH_MAPPING_CACHE: Lazy<Arc<RwLock<LruCache<String, PathMapping>>>> = 
-    Lazy::new(|| Arc::new(RwLock::new(LruCache::new(NonZeroUsize::new(1000).unwrap()))));
+static PATH_MAPPING_CACHE: Lazy<Arc<RwLock<LruCache<String, PathMapping>>>> =
+    Lazy::new(|| Arc::new(RwLock::new(LruCache::new(clamp_cache_capacity(DEFAULT_PATH_MAPPING_CACHE_CAPACITY)))));
+
+/// Clamp a requested cache capacity to at least 1 entry instead of panicking on 0
+fn clamp_cache_capacity(capacity: usize) -> NonZeroUsize {
+    match NonZeroUsize::new(capacity) {
+        Some(cap) => cap,
+        None => {
+            warn!("Requested path mapping cache capacity of 0 is invalid, clamping to 1");
+            NonZeroUsize::MIN
+        }
+    }
+}
+
+/// Initialize (resize) the global path mapping cache to the given capacity.
+/// A capacity of 0 is clamped to 1 with a warning rather than panicking.
+/// Returns the effective capacity that was applied.
+pub fn init_path_mapping_cache(capacity: usize) -> Result<usize> {
+    let effective = clamp_cache_capacity(capacity);
+    let mut cache = PATH_MAPPING_CACHE.write();
+    cache.resize(effective);
+    info!("Path mapping cache initialized with capacity {}", effective);
+    Ok(effective.get())
+}
 
 
 
@@ -49,22 +119,317 @@ fn default_namespace() -> String {
     "default".to_string()
 }
 
+/// Fail on duplicate keys in the mappings object instead of warning;
+/// set once at binary startup from `--strict-mappings`
+static STRICT_MAPPINGS: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+pub fn install_strict_mappings(enabled: bool) {
+    STRICT_MAPPINGS.store(enabled, std::sync::atomic::Ordering::Relaxed);
+}
+
+fn strict_mappings_enabled() -> bool {
+    STRICT_MAPPINGS.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Keys appearing more than once in the `mappings` object of the raw
+/// JSON, in first-occurrence order. `serde_json` silently keeps the last
+/// value for a duplicate key, which has masked config generator bugs
+/// where two entries collided; the loader warns about duplicates (or
+/// fails, with `--strict-mappings`) instead of dropping one silently
+pub fn duplicate_mapping_keys(content: &str) -> std::result::Result<Vec<String>, serde_json::Error> {
+    use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+
+    // Visits the `mappings` value without building it: keys go through a
+    // seen-set, values are ignored, so duplicates survive where a
+    // HashMap-based deserialization would overwrite them
+    struct MappingKeys;
+    impl<'de> DeserializeSeed<'de> for MappingKeys {
+        type Value = Vec<String>;
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error> {
+            struct KeysVisitor;
+            impl<'de> Visitor<'de> for KeysVisitor {
+                type Value = Vec<String>;
+                fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str("a mappings object")
+                }
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+                    let mut seen = HashSet::new();
+                    let mut duplicates = Vec::new();
+                    while let Some(key) = map.next_key::<String>()? {
+                        map.next_value::<IgnoredAny>()?;
+                        if !seen.insert(key.clone()) && !duplicates.contains(&key) {
+                            duplicates.push(key);
+                        }
+                    }
+                    Ok(duplicates)
+                }
+            }
+            deserializer.deserialize_map(KeysVisitor)
+        }
+    }
+
+    struct TopLevelVisitor;
+    impl<'de> Visitor<'de> for TopLevelVisitor {
+        type Value = Vec<String>;
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("a path mappings document")
+        }
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+            let mut duplicates = Vec::new();
+            while let Some(key) = map.next_key::<String>()? {
+                if key == "mappings" {
+                    duplicates.extend(map.next_value_seed(MappingKeys)?);
+                } else {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+            Ok(duplicates)
+        }
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(content);
+    deserializer.deserialize_map(TopLevelVisitor)
+}
+
 #[derive(Debug)]
 pub struct SessionInfo {
     pub pod_hash: String,
     pub snapshot_hash: String,
+    /// Numeric snapshot id recorded by newer snapshotter builds, which
+    /// nest the content one level deeper (see [`resolve_session_fs_dir`])
+    pub snapshot_id: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// How the container name was matched when an alias transformation was
+    /// needed (None for an exact match)
+    pub matched_alias: Option<String>,
 }
 
-#[derive(Debug)]
+/// Transformations tried, in order, when the reported container name does not
+/// exactly match a mapping entry. Older manifests set CURRENT_CONTAINER_NAME
+/// to the full "<pod>-<container>" string, and StatefulSet sidecars report
+/// names with a trailing ordinal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerNameTransform {
+    /// Strip a leading "<pod_name>-" prefix from the reported name
+    StripPodNamePrefix,
+    /// Strip a trailing "-<ordinal>" numeric suffix from the reported name
+    StripOrdinalSuffix,
+}
+
+/// Default transformation order for container name matching
+pub const DEFAULT_CONTAINER_NAME_TRANSFORMS: &[ContainerNameTransform] = &[
+    ContainerNameTransform::StripPodNamePrefix,
+    ContainerNameTransform::StripOrdinalSuffix,
+];
+
+/// Outcome of matching a reported container name against a mapping entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerNameMatch {
+    /// The name that actually matched the mapping entry
+    pub matched_name: String,
+    /// The transform that produced the match, None for an exact match
+    pub applied_transform: Option<ContainerNameTransform>,
+}
+
+impl ContainerNameMatch {
+    /// Human-readable description of how the match was made, for logging
+    pub fn alias_description(&self) -> Option<String> {
+        self.applied_transform.map(|transform| {
+            format!("{:?} -> {}", transform, self.matched_name)
+        })
+    }
+}
+
+/// Apply a single container name transformation, returning the transformed
+/// name when the transform is applicable to the reported name
+pub fn apply_container_name_transform(
+    reported: &str,
+    pod_name: &str,
+    transform: ContainerNameTransform,
+) -> Option<String> {
+    match transform {
+        ContainerNameTransform::StripPodNamePrefix => reported
+            .strip_prefix(pod_name)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .filter(|rest| !rest.is_empty())
+            .map(str::to_string),
+        ContainerNameTransform::StripOrdinalSuffix => {
+            let (base, suffix) = reported.rsplit_once('-')?;
+            if !base.is_empty() && !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()) {
+                Some(base.to_string())
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Pure container name matching layer: an exact match is tried first, then
+/// each transform in order. Returns how the match was made so callers can
+/// record and log the applied alias.
+pub fn match_container_name(
+    mapping_name: &str,
+    reported: &str,
+    pod_name: &str,
+    transforms: &[ContainerNameTransform],
+) -> Option<ContainerNameMatch> {
+    if mapping_name == reported {
+        return Some(ContainerNameMatch {
+            matched_name: reported.to_string(),
+            applied_transform: None,
+        });
+    }
+
+    for &transform in transforms {
+        if let Some(transformed) = apply_container_name_transform(reported, pod_name, transform) {
+            if mapping_name == transformed {
+                info!("Container name '{}' matched mapping entry '{}' via {:?}",
+                      reported, mapping_name, transform);
+                return Some(ContainerNameMatch {
+                    matched_name: transformed,
+                    applied_transform: Some(transform),
+                });
+            }
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TransferResult {
     pub success_count: usize,
     pub error_count: usize,
     pub skipped_count: usize,
-    pub errors: Vec<String>,
+    /// Distinct error messages with occurrence counts. A failure storm
+    /// repeats the same few messages millions of times; storing each
+    /// occurrence individually has OOM-killed the process before.
+    pub errors: Vec<TransferError>,
+    /// Breakdown of skip reasons by category (see [`categorize_skip_reason`])
+    pub skip_reason_counts: HashMap<String, usize>,
+    /// Sources that changed (size or mtime) while being copied; their
+    /// backup copies may be torn
+    pub unstable_files: Vec<PathBuf>,
+    /// Files found open for writing before the transfer started; filled
+    /// in by the open-file scan when enabled
+    pub at_risk_files: Vec<open_files::OpenFileInfo>,
+    /// How the source was captured when the two-phase snapshot is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture_mode: Option<snapshot_farm::CaptureMode>,
+    /// Time spent building the hardlink farm before the copy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_phase_duration: Option<std::time::Duration>,
+    /// Directories whose mode, ownership or mtime could not be mirrored
+    /// onto the target
+    #[serde(default)]
+    pub dir_metadata_errors: usize,
+    /// Entries omitted by --over-quota=trim to fit --max-backup-bytes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trimmed_for_quota: Vec<quota::TrimmedEntry>,
+    /// Per-file change records parsed from rsync's itemized output;
+    /// filled only by the rsync transfers under --rsync-itemize
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub itemized_changes: Vec<rsync_itemize::ItemizedChange>,
+    /// How the transfer backend was chosen when --transfer-strategy is in
+    /// effect; absent under the legacy static selection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy_decision: Option<strategy::StrategyDecision>,
+    /// Priority tier being copied when the deadline cut the run short;
+    /// absent when the run finished or `--order` was natural
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_tier_reached: Option<String>,
+    /// True when the run was stopped early by a cancellation token
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Files stored as reflink clones instead of byte copies; non-zero
+    /// only under --cow on a reflink-capable backup filesystem
+    #[serde(default)]
+    pub cloned_files: usize,
 }
 
-#[derive(Debug)]
+/// A deduplicated transfer error message with its occurrence count
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferError {
+    pub message: String,
+    pub count: usize,
+}
+
+/// Normalize a free-form skip reason into a small set of categories so the
+/// final report can show a breakdown (busy vs read-only vs permission vs
+/// excluded) instead of thousands of distinct strings
+pub fn categorize_skip_reason(reason: &str) -> &'static str {
+    let lower = reason.to_lowercase();
+    if lower.contains("busy") {
+        "busy"
+    } else if lower.contains("read-only") {
+        "read-only"
+    } else if lower.contains("permission") {
+        "permission"
+    } else if lower.contains("excluded") || lower.contains("mounted") || lower.contains("no-restore") {
+        "excluded"
+    } else if lower.contains("vanished") {
+        "vanished"
+    } else if lower.contains("unchanged") {
+        "unchanged"
+    } else {
+        "other"
+    }
+}
+
+impl TransferResult {
+    /// Record an error message, deduplicating identical messages into a
+    /// single entry with a count. Does not touch `error_count`; call sites
+    /// decide whether an error message also counts as a hard error.
+    pub fn record_error(&mut self, message: String) {
+        progress::global().record_failure();
+        if let Some(existing) = self.errors.iter_mut().find(|e| e.message == message) {
+            existing.count += 1;
+        } else {
+            self.errors.push(TransferError { message, count: 1 });
+        }
+    }
+
+    /// Record a skipped entry under its normalized reason category
+    pub fn record_skip(&mut self, reason: &str) {
+        progress::global().record_skip();
+        self.skipped_count += 1;
+        *self
+            .skip_reason_counts
+            .entry(categorize_skip_reason(reason).to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// Collapse the result into a single typed error so embedding callers
+    /// can use `?`: Ok when the transfer finished without hard errors,
+    /// otherwise an [`error::SessionError::Transfer`] summarizing the
+    /// counts and the first few distinct messages
+    pub fn into_result(self) -> std::result::Result<(), error::SessionError> {
+        if self.error_count == 0 {
+            return Ok(());
+        }
+        let sample: Vec<String> = self
+            .errors
+            .iter()
+            .take(error::MAX_SAMPLE_ERRORS)
+            .map(|e| {
+                if e.count > 1 {
+                    format!("{} (x{})", e.message, e.count)
+                } else {
+                    e.message.clone()
+                }
+            })
+            .collect();
+        Err(error::SessionError::Transfer {
+            error_count: self.error_count,
+            success_count: self.success_count,
+            sample,
+            omitted_messages: self.errors.len().saturating_sub(error::MAX_SAMPLE_ERRORS),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PodInfo {
     pub namespace: String,
     pub pod_name: String,
@@ -97,12 +462,39 @@ impl PodInfo {
     }
 }
 
+/// Explain a canonicalization failure in terms a field operator can act
+/// on: a missing path, a broken symlink, denied access and symlink loops
+/// all surface as the same generic io::Error otherwise
+fn canonicalize_failure_detail(path: &Path, error: &std::io::Error) -> String {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => {
+            // The path itself existing while resolution fails means a
+            // symlink along the way points at nothing
+            if fs::symlink_metadata(path).is_ok() {
+                format!("{} is a broken symlink (target does not exist)", path.display())
+            } else {
+                format!("{} does not exist", path.display())
+            }
+        }
+        std::io::ErrorKind::PermissionDenied => {
+            format!("permission denied while resolving {}", path.display())
+        }
+        // ErrorKind::FilesystemLoop is not stable yet; match ELOOP directly
+        _ if error.raw_os_error() == Some(libc::ELOOP) => {
+            format!("symlink loop while resolving {}", path.display())
+        }
+        _ => format!("{}: {}", path.display(), error),
+    }
+}
+
 pub fn validate_path_security(path: &Path, allowed_base: &Path) -> Result<()> {
-    let canonical_path = path.canonicalize()
-        .with_context(|| format!("Failed to canonicalize path: {}", path.display()))?;
-    
-    let canonical_base = allowed_base.canonicalize()
-        .with_context(|| format!("Failed to canonicalize base path: {}", allowed_base.display()))?;
+    let canonical_path = path.canonicalize().map_err(|e| {
+        anyhow::anyhow!("Failed to canonicalize path: {}", canonicalize_failure_detail(path, &e))
+    })?;
+
+    let canonical_base = allowed_base.canonicalize().map_err(|e| {
+        anyhow::anyhow!("Failed to canonicalize base path: {}", canonicalize_failure_detail(allowed_base, &e))
+    })?;
     
     if !canonical_path.starts_with(&canonical_base) {
         bail!("Path traversal detected: {} is outside allowed base {}", 
@@ -135,6 +527,134 @@ pub async fn find_current_session_async(
     find_current_session_cached(mappings_file, pod_info).await
 }
 
+/// How many times a mid-rewrite mappings file is re-read before giving up
+const MAPPINGS_READ_MAX_ATTEMPTS: usize = 5;
+
+/// Pause between mappings read attempts, long enough for the
+/// snapshotter's atomic rename to land
+const MAPPINGS_READ_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Read and parse the mappings file, tolerating the snapshotter rewriting
+/// it concurrently: a `.tmp` sibling (the write-in-progress artifact) and
+/// an unstable file size both trigger a short backoff, and parse errors
+/// are retried before propagating. Returns None for a stably empty file.
+fn load_mappings_resilient(mappings_file: &Path) -> Result<Option<PathMappings>> {
+    let mut tmp_name = mappings_file.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_sibling = mappings_file.with_file_name(tmp_name);
+
+    let mut last_failure: Option<(serde_json::Error, String)> = None;
+    for attempt in 1..=MAPPINGS_READ_MAX_ATTEMPTS {
+        if tmp_sibling.exists() {
+            info!(
+                "Mappings rewrite in progress ({} exists), waiting (attempt {}/{})",
+                tmp_sibling.display(), attempt, MAPPINGS_READ_MAX_ATTEMPTS
+            );
+            std::thread::sleep(MAPPINGS_READ_RETRY_DELAY);
+            continue;
+        }
+
+        let size_before = fs::metadata(mappings_file).map(|m| m.len()).ok();
+        let content = match optimized_io::read_file_optimized(mappings_file) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to read mappings file (attempt {}/{}): {}", attempt, MAPPINGS_READ_MAX_ATTEMPTS, e);
+                std::thread::sleep(MAPPINGS_READ_RETRY_DELAY);
+                continue;
+            }
+        };
+        let size_after = fs::metadata(mappings_file).map(|m| m.len()).ok();
+        if size_before.is_none() || size_before != size_after {
+            warn!(
+                "Mappings file size changed during read ({:?} -> {:?}), retrying (attempt {}/{})",
+                size_before, size_after, attempt, MAPPINGS_READ_MAX_ATTEMPTS
+            );
+            std::thread::sleep(MAPPINGS_READ_RETRY_DELAY);
+            continue;
+        }
+
+        if content.trim().is_empty() {
+            return Ok(None);
+        }
+        match serde_json::from_str(&content) {
+            Ok(path_mappings) => {
+                // The parse above keeps the last value for a duplicate
+                // key; surface the collision instead of hiding it
+                if let Ok(duplicates) = duplicate_mapping_keys(&content) {
+                    if !duplicates.is_empty() {
+                        if strict_mappings_enabled() {
+                            return Err(anyhow::Error::new(error::SessionManagerError::MappingParse {
+                                path: mappings_file.to_path_buf(),
+                                source: serde::de::Error::custom(format!(
+                                    "duplicate mapping key(s): {}",
+                                    duplicates.join(", ")
+                                )),
+                            })
+                            .context("Strict mappings mode rejects duplicate keys (--strict-mappings)"));
+                        }
+                        warn!(
+                            "Mappings file {} has duplicate key(s), keeping the last value for each: {}",
+                            mappings_file.display(), duplicates.join(", ")
+                        );
+                    }
+                }
+                return Ok(Some(path_mappings));
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to parse mappings JSON (attempt {}/{}): {}",
+                    attempt, MAPPINGS_READ_MAX_ATTEMPTS, e
+                );
+                last_failure = Some((e, content));
+                std::thread::sleep(MAPPINGS_READ_RETRY_DELAY);
+            }
+        }
+    }
+
+    match last_failure {
+        Some((e, content)) => {
+            let snippet = String::from_utf8_lossy(&content.as_bytes()[..content.len().min(200)]).into_owned();
+            // Typed so embedding callers can branch on the parse category
+            Err(anyhow::Error::new(error::SessionManagerError::MappingParse {
+                path: mappings_file.to_path_buf(),
+                source: e,
+            })
+            .context(format!(
+                "Failed to parse path mappings JSON after {} attempts; content starts with: {:?}",
+                MAPPINGS_READ_MAX_ATTEMPTS, snippet
+            )))
+        }
+        None => Err(anyhow::anyhow!(
+            "Mappings file {} never became readable and stable within {} attempts",
+            mappings_file.display(), MAPPINGS_READ_MAX_ATTEMPTS
+        )),
+    }
+}
+
+/// Distinct container names with a mapping entry for this namespace+pod,
+/// sorted so multi-container runs are deterministic
+pub fn list_pod_containers(mappings_file: &Path, namespace: &str, pod_name: &str) -> Result<Vec<String>> {
+    if !mappings_file.exists() {
+        warn!("Path mappings file not found: {}", mappings_file.display());
+        return Ok(Vec::new());
+    }
+
+    let path_mappings = match load_mappings_resilient(mappings_file)? {
+        Some(path_mappings) => path_mappings,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut names: Vec<String> = path_mappings
+        .mappings
+        .into_values()
+        .filter(|mapping| mapping.namespace == namespace && mapping.pod_name == pod_name)
+        .map(|mapping| mapping.container_name)
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
 pub fn find_current_session(
     mappings_file: &Path,
     pod_info: &PodInfo,
@@ -144,50 +664,58 @@ pub fn find_current_session(
         return Ok(None);
     }
 
-    let content = optimized_io::read_file_optimized(mappings_file)
-        .with_context(|| format!("Failed to read mappings file: {}", mappings_file.display()))?;
-
-    if content.trim().is_empty() {
-        warn!("Path mappings file is empty: {}", mappings_file.display());
-        return Ok(None);
-    }
-
-    let path_mappings: PathMappings = serde_json::from_str(&content)
-        .with_context(|| format!("Failed to parse path mappings JSON from {}", mappings_file.display()))?;
+    let path_mappings = match load_mappings_resilient(mappings_file)? {
+        Some(path_mappings) => path_mappings,
+        None => {
+            warn!("Path mappings file is empty: {}", mappings_file.display());
+            return Ok(None);
+        }
+    };
 
     info!("Loaded {} path mappings", path_mappings.mappings.len());
 
     // Find the most recent matching entry
-    let mut best_match: Option<(String, PathMapping)> = None;
+    let mut best_match: Option<(String, PathMapping, ContainerNameMatch)> = None;
     let mut latest_time: Option<chrono::DateTime<chrono::Utc>> = None;
 
     for (path_key, mapping) in path_mappings.mappings {
-        if mapping.namespace == pod_info.namespace
-            && mapping.pod_name == pod_info.pod_name
-            && mapping.container_name == pod_info.container_name
-        {
+        if mapping.namespace != pod_info.namespace || mapping.pod_name != pod_info.pod_name {
+            continue;
+        }
+
+        if let Some(name_match) = match_container_name(
+            &mapping.container_name,
+            &pod_info.container_name,
+            &pod_info.pod_name,
+            DEFAULT_CONTAINER_NAME_TRANSFORMS,
+        ) {
             let created_at = chrono::DateTime::parse_from_rfc3339(&mapping.created_at)
                 .with_context(|| format!("Invalid created_at timestamp: {} for mapping {}", mapping.created_at, path_key))?
                 .with_timezone(&chrono::Utc);
 
-            if latest_time.map_or(true, |t| created_at > t) {
+            if latest_time.is_none_or(|t| created_at > t) {
                 latest_time = Some(created_at);
-                best_match = Some((path_key, mapping));
+                best_match = Some((path_key, mapping, name_match));
             }
         }
     }
 
     match best_match {
-        Some((path_key, mapping)) => {
+        Some((path_key, mapping, name_match)) => {
             let created_at = chrono::DateTime::parse_from_rfc3339(&mapping.created_at)?
                 .with_timezone(&chrono::Utc);
             
             info!("Found matching session mapping: {}", path_key);
+            if let Some(alias) = name_match.alias_description() {
+                info!("Container name matched via alias: {}", alias);
+            }
             
             Ok(Some(SessionInfo {
                 pod_hash: mapping.pod_hash,
                 snapshot_hash: mapping.snapshot_hash,
+                snapshot_id: mapping.snapshot_id,
                 created_at,
+                matched_alias: name_match.alias_description(),
             }))
         }
         None => {
@@ -198,6 +726,184 @@ pub fn find_current_session(
     }
 }
 
+/// Resolve the session content directory beneath
+/// `<sessions>/<pod_hash>/<snapshot_hash>`. Newer snapshotter builds nest
+/// the content under the numeric snapshot id (`<snapshot_id>/fs`); the
+/// legacy layout is a plain `fs`. When the mapping carries a snapshot_id
+/// the new layout is tried first, with the legacy path as fallback; if
+/// neither exists the error lists what is actually there so layout
+/// mismatches are diagnosable from the log alone.
+pub fn resolve_session_fs_dir(
+    sessions_path: &Path,
+    pod_hash: &str,
+    snapshot_hash: &str,
+    snapshot_id: Option<&str>,
+) -> Result<PathBuf> {
+    let base = sessions_path.join(pod_hash).join(snapshot_hash);
+
+    let mut candidates = Vec::new();
+    if let Some(id) = snapshot_id {
+        candidates.push(base.join(id).join("fs"));
+    }
+    candidates.push(base.join("fs"));
+
+    for candidate in &candidates {
+        if candidate.is_dir() {
+            debug!("Resolved session content directory: {}", candidate.display());
+            return Ok(candidate.clone());
+        }
+    }
+
+    let listing = match fs::read_dir(&base) {
+        Ok(entries) => {
+            let mut names: Vec<String> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            if names.is_empty() {
+                "<empty>".to_string()
+            } else {
+                names.join(", ")
+            }
+        }
+        Err(e) => format!("<unreadable: {}>", e),
+    };
+    let tried: Vec<String> = candidates
+        .iter()
+        .map(|candidate| {
+            candidate
+                .strip_prefix(&base)
+                .unwrap_or(candidate)
+                .display()
+                .to_string()
+        })
+        .collect();
+    bail!(
+        "No session content found under {} (tried: {}); directory contains: {}",
+        base.display(),
+        tried.join(", "),
+        listing
+    )
+}
+
+/// File name of the identity stamp the snapshotter records inside each
+/// session directory
+pub const SESSION_META_FILE_NAME: &str = "session.meta.json";
+
+/// Pod identity recorded in a session directory's `session.meta.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMeta {
+    pub namespace: String,
+    pub pod_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+}
+
+impl SessionMeta {
+    /// True when the stamp names the same pod as `pod_info`
+    fn matches(&self, pod_info: &PodInfo) -> bool {
+        self.namespace == pod_info.namespace && self.pod_name == pod_info.pod_name
+    }
+}
+
+/// Outcome of [`cleanup_old_sessions`]
+#[derive(Debug, Default)]
+pub struct OldSessionCleanup {
+    /// Sibling sessions deleted after their identity was verified
+    pub removed: Vec<PathBuf>,
+    /// Sessions whose recorded identity names a different pod: a
+    /// pod_hash collision, never deleted
+    pub foreign: Vec<PathBuf>,
+    /// Sessions without (or with unreadable) metadata, kept because
+    /// `delete_unverified` was off
+    pub unverified_kept: Vec<PathBuf>,
+}
+
+/// Delete old sibling session directories under `<sessions>/<pod_hash>`,
+/// keeping the current snapshot.
+///
+/// The pod_hash is a truncated hash and the snapshotter has produced
+/// collisions before, so trusting it alone would let one pod's cleanup
+/// remove another pod's sessions. Each candidate's recorded identity
+/// ([`SESSION_META_FILE_NAME`]) is cross-checked against the current pod
+/// first: mismatches are never deleted and are reported loudly. Sessions
+/// without metadata are only deleted when `delete_unverified` is set
+/// (the legacy trust-the-hash behavior); by default they are kept.
+pub fn cleanup_old_sessions(
+    sessions_path: &Path,
+    pod_hash: &str,
+    current_snapshot_hash: &str,
+    pod_info: &PodInfo,
+    delete_unverified: bool,
+) -> Result<OldSessionCleanup> {
+    let pod_dir = sessions_path.join(pod_hash);
+    let mut outcome = OldSessionCleanup::default();
+
+    let entries = match fs::read_dir(&pod_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(outcome),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read pod session directory: {}", pod_dir.display()))
+        }
+    };
+
+    for entry in entries {
+        let entry = entry
+            .with_context(|| format!("Failed to read directory entry in: {}", pod_dir.display()))?;
+        let path = entry.path();
+        if !path.is_dir() || entry.file_name() == current_snapshot_hash {
+            continue;
+        }
+
+        let meta_path = path.join(SESSION_META_FILE_NAME);
+        let meta = match fs::read_to_string(&meta_path) {
+            Ok(content) => match serde_json::from_str::<SessionMeta>(&content) {
+                Ok(meta) => Some(meta),
+                Err(e) => {
+                    warn!("Unparseable session metadata in {}: {}", meta_path.display(), e);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
+        match meta {
+            Some(meta) if meta.matches(pod_info) => {
+                info!("Removing old session with verified identity: {}", path.display());
+                fs::remove_dir_all(&path)
+                    .with_context(|| format!("Failed to remove old session: {}", path.display()))?;
+                outcome.removed.push(path);
+            }
+            Some(meta) => {
+                log::error!(
+                    "REFUSING to delete session {}: recorded identity {}/{} does not match \
+                     current pod {}/{} - pod_hash collision under {}",
+                    path.display(), meta.namespace, meta.pod_name,
+                    pod_info.namespace, pod_info.pod_name, pod_hash
+                );
+                outcome.foreign.push(path);
+            }
+            None if delete_unverified => {
+                warn!("Removing old session without identity metadata (unverified): {}", path.display());
+                fs::remove_dir_all(&path)
+                    .with_context(|| format!("Failed to remove old session: {}", path.display()))?;
+                outcome.removed.push(path);
+            }
+            None => {
+                warn!(
+                    "Keeping old session without identity metadata: {} (pass delete_unverified to remove)",
+                    path.display()
+                );
+                outcome.unverified_kept.push(path);
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
 pub fn is_directory_empty(path: &Path) -> Result<bool> {
     if !path.exists() {
         return Ok(true);
@@ -233,29 +939,210 @@ pub fn show_directory_contents(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Age past which a directory-creation lock is considered abandoned even
+/// when its recorded pid is alive again (pid reuse after a crash)
+pub const STALE_LOCK_MAX_AGE_SECS: u64 = 300;
+
+/// What the holder writes into `<dir>.lock`, so another process (or the
+/// sweeper) can tell whether the lock is still backed by a live owner
+#[derive(Debug, Serialize, Deserialize)]
+struct DirLockRecord {
+    pid: u32,
+    /// Unix seconds when the lock was taken
+    created_at: u64,
+}
+
+/// RAII guard for a directory-creation lock; the lock file is removed
+/// when the guard drops, so the happy path never leaves `<dir>.lock`
+/// behind for the next run to trip over
+pub struct DirLockGuard {
+    lock_path: PathBuf,
+}
+
+impl Drop for DirLockGuard {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.lock_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove lock file {}: {}", self.lock_path.display(), e);
+            }
+        }
+    }
+}
+
+/// The lock file guarding `path`: a `.lock` sibling next to the directory
+fn dir_lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_os_string();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+/// Whether a lock file was abandoned: its recorded pid is dead, its
+/// record is older than `max_age_secs`, or an unreadable record's file
+/// itself is older than `max_age_secs`
+fn dir_lock_is_stale(lock_path: &Path, max_age_secs: u64) -> bool {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    match fs::read_to_string(lock_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<DirLockRecord>(&content).ok())
+    {
+        Some(record) => {
+            !dir_lock_holder_alive(record.pid) || now.saturating_sub(record.created_at) > max_age_secs
+        }
+        // Legacy or torn lock files carry no record; age the file itself
+        None => fs::metadata(lock_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age.as_secs() > max_age_secs),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn dir_lock_holder_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn dir_lock_holder_alive(_pid: u32) -> bool {
+    // No /proc to consult; only the age threshold can declare staleness
+    true
+}
+
+/// Take the `.lock` sibling of `path`, failing fast when another live
+/// process holds it. A lock left behind by a crashed or long-gone holder
+/// is taken over instead of waited on
+pub fn acquire_dir_lock(path: &Path) -> Result<DirLockGuard> {
+    use std::io::Write;
+
+    let lock_path = dir_lock_path(path);
+    for attempt in 0..2 {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+            Ok(mut file) => {
+                let record = DirLockRecord {
+                    pid: std::process::id(),
+                    created_at: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0),
+                };
+                let json = serde_json::to_string(&record)
+                    .context("Failed to serialize lock record")?;
+                file.write_all(json.as_bytes())
+                    .with_context(|| format!("Failed to write lock file: {}", lock_path.display()))?;
+                return Ok(DirLockGuard { lock_path });
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                if attempt == 0 && dir_lock_is_stale(&lock_path, STALE_LOCK_MAX_AGE_SECS) {
+                    warn!("Taking over stale lock file: {}", lock_path.display());
+                    let _ = fs::remove_file(&lock_path);
+                    continue;
+                }
+                bail!(
+                    "Directory {} is locked by another process ({} exists)",
+                    path.display(),
+                    lock_path.display()
+                );
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to create lock file: {}", lock_path.display()));
+            }
+        }
+    }
+    unreachable!("lock acquisition attempts exhausted");
+}
+
+/// Create a directory under a `.lock` sibling held for the duration, for
+/// the few layouts multiple processes create concurrently; the lock is
+/// released (and its file removed) when creation finishes
 pub fn create_directory_with_lock(path: &Path) -> Result<()> {
-    // Use lockless mechanism since session backup is single-process per pod
+    let _guard = acquire_dir_lock(path)?;
     crate::lockless_backup::create_directory_simple(path)
 }
 
+/// Remove abandoned `.lock` files directly under `directory`: those whose
+/// recorded pid is dead or whose record (or file) is older than
+/// `max_age_secs`. Returns how many were swept
+pub fn sweep_stale_locks(directory: &Path, max_age_secs: u64) -> Result<usize> {
+    let mut swept = 0;
+    for entry in fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory: {}", directory.display()))?
+        .flatten()
+    {
+        let lock_path = entry.path();
+        if lock_path.extension().and_then(|e| e.to_str()) != Some("lock")
+            || !entry.file_type().map(|t| t.is_file()).unwrap_or(false)
+        {
+            continue;
+        }
+        if dir_lock_is_stale(&lock_path, max_age_secs) {
+            match fs::remove_file(&lock_path) {
+                Ok(()) => {
+                    info!("Swept stale lock file: {}", lock_path.display());
+                    swept += 1;
+                }
+                Err(e) => warn!("Failed to sweep lock file {}: {}", lock_path.display(), e),
+            }
+        }
+    }
+    Ok(swept)
+}
+
 pub fn transfer_data_rsync(source: &Path, target: &Path, timeout: u64) -> Result<TransferResult> {
+    transfer_data_rsync_deadline(source, target, Deadline::from_secs(timeout))
+}
+
+/// The rsync binary the transfers invoke; overridable through the
+/// SESSION_MANAGER_RSYNC environment variable so tests can substitute a
+/// stub without a real rsync on PATH
+fn rsync_program() -> std::ffi::OsString {
+    std::env::var_os("SESSION_MANAGER_RSYNC").unwrap_or_else(|| "rsync".into())
+}
+
+fn transfer_data_rsync_deadline(source: &Path, target: &Path, deadline: Deadline) -> Result<TransferResult> {
     let mut result = TransferResult {
         success_count: 0,
         error_count: 0,
         skipped_count: 0,
         errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
     };
 
-    info!("Using rsync for data transfer from {} to {}", source.display(), target.display());
+    info!("Using rsync for data transfer from {} to {} (remaining budget: {:?})", 
+          source.display(), target.display(), deadline.remaining());
+
+    if deadline.expired() {
+        result.record_error("Operation timed out".to_string());
+        result.error_count += 1;
+        return Ok(result);
+    }
     
-    let output = Command::new("timeout")
-        .arg(timeout.to_string())
-        .arg("rsync")
+    let mut cmd = Command::new("timeout");
+    cmd.arg(deadline.remaining_secs().max(1).to_string())
+        .arg(rsync_program())
         .arg("-av")
         .arg("--delete")
         .arg("--ignore-errors")
         .arg("--force")
-        .arg("--stats")
+        .arg("--stats");
+    if rsync_itemize::enabled() {
+        cmd.arg("-i").arg(format!("--out-format={}", rsync_itemize::OUT_FORMAT));
+    }
+    let output = cmd
         .arg(format!("{}/", source.display()))
         .arg(format!("{}/", target.display()))
         .output()
@@ -263,9 +1150,12 @@ pub fn transfer_data_rsync(source: &Path, target: &Path, timeout: u64) -> Result
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
-    
+
     debug!("Rsync stdout: {}", stdout);
-    
+    if rsync_itemize::enabled() {
+        result.itemized_changes = rsync_itemize::parse_itemized(&stdout);
+    }
+
     if output.status.success() {
         info!("Rsync transfer completed successfully");
         // Parse rsync stats for file count (simplified)
@@ -273,12 +1163,12 @@ pub fn transfer_data_rsync(source: &Path, target: &Path, timeout: u64) -> Result
     } else {
         match output.status.code() {
             Some(124) => {
-                result.errors.push("Operation timed out".to_string());
+                result.record_error("Operation timed out".to_string());
                 result.error_count += 1;
             }
             Some(code) => {
                 warn!("Rsync transfer completed with exit code {}: {}", code, stderr);
-                result.errors.push(format!("Rsync exit code {}: {}", code, stderr));
+                result.record_error(format!("Rsync exit code {}: {}", code, stderr));
                 // Don't count as error if it's just warnings
                 if code < 12 { // rsync exit codes < 12 are usually warnings
                     result.success_count = 1;
@@ -287,7 +1177,7 @@ pub fn transfer_data_rsync(source: &Path, target: &Path, timeout: u64) -> Result
                 }
             }
             None => {
-                result.errors.push("Rsync was terminated by signal".to_string());
+                result.record_error("Rsync was terminated by signal".to_string());
                 result.error_count += 1;
             }
         }
@@ -297,18 +1187,50 @@ pub fn transfer_data_rsync(source: &Path, target: &Path, timeout: u64) -> Result
 }
 
 pub fn transfer_data_tar(source: &Path, target: &Path, timeout: u64) -> Result<TransferResult> {
+    transfer_data_tar_deadline(source, target, Deadline::from_secs(timeout), false)
+}
+
+/// Like [`transfer_data_tar`] but never clobbers destination files that
+/// are newer than the archived copy (tar's `--keep-newer-files`),
+/// mirroring the native path's only-newer merge behavior
+pub fn transfer_data_tar_no_clobber(source: &Path, target: &Path, timeout: u64) -> Result<TransferResult> {
+    transfer_data_tar_deadline(source, target, Deadline::from_secs(timeout), true)
+}
+
+fn transfer_data_tar_deadline(source: &Path, target: &Path, deadline: Deadline, no_clobber: bool) -> Result<TransferResult> {
     let mut result = TransferResult {
         success_count: 0,
         error_count: 0,
         skipped_count: 0,
         errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
     };
 
-    info!("Using tar for data transfer from {} to {}", source.display(), target.display());
+    info!("Using tar for data transfer from {} to {} (remaining budget: {:?})", 
+          source.display(), target.display(), deadline.remaining());
+
+    if deadline.expired() {
+        result.record_error("Operation timed out".to_string());
+        result.error_count += 1;
+        return Ok(result);
+    }
+
+    let tar_timeout = deadline.remaining_secs().max(1);
     
     // Create tar source process
     let mut source_cmd = Command::new("timeout")
-        .arg(timeout.to_string())
+        .arg(tar_timeout.to_string())
         .arg("tar")
         .arg("-cf")
         .arg("-")
@@ -328,11 +1250,11 @@ pub fn transfer_data_tar(source: &Path, target: &Path, timeout: u64) -> Result<T
 
     // Create tar target process
     let target_cmd = Command::new("timeout")
-        .arg(timeout.to_string())
+        .arg(tar_timeout.to_string())
         .arg("tar")
         .arg("-xf")
         .arg("-")
-        .arg("--overwrite")
+        .arg(if no_clobber { "--keep-newer-files" } else { "--overwrite" })
         .arg("-C")
         .arg(target)
         .stdin(source_stdout)
@@ -355,18 +1277,18 @@ pub fn transfer_data_tar(source: &Path, target: &Path, timeout: u64) -> Result<T
         let target_stderr = String::from_utf8_lossy(&target_output.stderr);
         
         if !source_result.success() {
-            result.errors.push(format!("Tar source failed with exit code: {:?}", source_result.code()));
+            result.record_error(format!("Tar source failed with exit code: {:?}", source_result.code()));
             result.error_count += 1;
         }
         
         if !target_output.status.success() {
             if target_stderr.contains("Exiting with failure status due to previous errors") {
                 warn!("Tar transfer completed with some skipped files (this is normal for busy files)");
-                result.skipped_count += 1;
+                result.record_skip("File busy");
                 result.success_count = 1; // Still consider it successful
             } else {
                 warn!("Tar target failed: {}", target_stderr);
-                result.errors.push(format!("Tar target error: {}", target_stderr));
+                result.record_error(format!("Tar target error: {}", target_stderr));
                 result.error_count += 1;
             }
         }
@@ -376,26 +1298,108 @@ pub fn transfer_data_tar(source: &Path, target: &Path, timeout: u64) -> Result<T
 }
 
 pub fn transfer_data(source: &Path, target: &Path, timeout: u64) -> Result<TransferResult> {
+    transfer_data_with_deadline(source, target, Deadline::from_secs(timeout))
+}
+
+/// Deadline-aware variant of [`transfer_data`]: the caller creates one
+/// [`Deadline`] at startup and every phase draws from the same budget
+pub fn transfer_data_with_deadline(source: &Path, target: &Path, deadline: Deadline) -> Result<TransferResult> {
     // Validate paths for security
     validate_path_security(source, &PathBuf::from("/"))?;
+
+    // A single-file source copies that one file; the directory-oriented
+    // rsync/tar machinery is skipped entirely
+    if source.is_file() {
+        return transfer_single_file(source, target, deadline);
+    }
+
     validate_path_security(target, &PathBuf::from("/"))?;
-    
+
     // Use resource manager for optimized operations
     let resource_manager = resource_manager::ResourceManager::global();
-    
+
     resource_manager.thread_pool.execute_io(|| {
         // Try optimized rsync first if available
         if which::which("rsync").is_ok() {
-            transfer_data_rsync(source, target, timeout)
+            transfer_data_rsync_deadline(source, target, deadline)
         } else {
-            transfer_data_tar(source, target, timeout)
+            transfer_data_tar_deadline(source, target, deadline, false)
         }
     })
 }
 
-/// Cached version of find_current_session with async support
-async fn find_current_session_cached(
-    mappings_file: &Path,
+/// Copy a single-file source to the target. An existing directory target
+/// receives the file under its own name; otherwise a target with an
+/// extension is treated as the destination file, and one without as a
+/// directory to create and copy into.
+fn transfer_single_file(source: &Path, target: &Path, deadline: Deadline) -> Result<TransferResult> {
+    let mut result = TransferResult {
+        success_count: 0,
+        error_count: 0,
+        skipped_count: 0,
+        errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
+    };
+
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Source file has no file name: {}", source.display()))?;
+    let target_path = if target.is_dir() || target.extension().is_none() {
+        target.join(file_name)
+    } else {
+        target.to_path_buf()
+    };
+
+    if target.exists() {
+        validate_path_security(target, &PathBuf::from("/"))?;
+    }
+
+    info!("Source is a single file; copying {} to {}", source.display(), target_path.display());
+
+    if deadline.expired() {
+        result.record_error("Operation timed out".to_string());
+        result.error_count += 1;
+        return Err(anyhow::anyhow!("Transfer operation timed out"));
+    }
+    if let Some(parent) = target_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create target directory: {}", parent.display()))?;
+    }
+
+    match copy_file_with_permissions(source, &target_path) {
+        Ok(_) => {
+            result.success_count = 1;
+            progress::global().record_file(fs::metadata(source).map(|m| m.len()).unwrap_or(0));
+            info!("Single-file transfer completed: {}", target_path.display());
+        }
+        Err(e) => {
+            let error_msg = format!(
+                "Failed to copy file {} to {}: {}",
+                source.display(), target_path.display(), e
+            );
+            warn!("{}", error_msg);
+            result.record_error(error_msg);
+            result.error_count = 1;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Cached version of find_current_session with async support
+async fn find_current_session_cached(
+    mappings_file: &Path,
     pod_info: &PodInfo,
 ) -> Result<Option<SessionInfo>> {
     crate::async_operations::find_current_session_cached(mappings_file, pod_info).await
@@ -403,39 +1407,113 @@ async fn find_current_session_cached(
 
 /// Transfer data with optimized parallel operations
 pub async fn transfer_data_parallel(source: &Path, target: &Path, timeout: u64) -> Result<TransferResult> {
+    transfer_data_parallel_cancellable(
+        source,
+        target,
+        timeout,
+        &tokio_util::sync::CancellationToken::new(),
+    )
+    .await
+}
+
+/// Cancellable variant of [`transfer_data_parallel`] for embedding
+/// daemons: the token is checked on every file boundary, so cancelling
+/// stops the transfer before the next file begins and marks the result
+/// as cancelled; the in-flight file is never torn and stays bounded by
+/// the per-file timeout. A directory source is walked file by file; a
+/// file source is a single copy.
+pub async fn transfer_data_parallel_cancellable(
+    source: &Path,
+    target: &Path,
+    timeout: u64,
+    cancel: &tokio_util::sync::CancellationToken,
+) -> Result<TransferResult> {
     // Validate paths for security
     validate_path_security(source, &PathBuf::from("/"))?;
     validate_path_security(target, &PathBuf::from("/"))?;
-    
+
     let mut result = TransferResult {
         success_count: 0,
         error_count: 0,
         skipped_count: 0,
         errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
     };
     
     info!("Using optimized parallel transfer from {} to {}", source.display(), target.display());
-    
-    // Use async file operations with timeout
-    let transfer_future = optimized_io::copy_file_async(source, target);
-    let timeout_duration = std::time::Duration::from_secs(timeout);
-    
-    match tokio::time::timeout(timeout_duration, transfer_future).await {
-        Ok(Ok(bytes_copied)) => {
-            info!("Parallel transfer completed successfully: {} bytes", bytes_copied);
-            result.success_count = 1;
+
+    // One (source, target) pair per regular file: a file source is a
+    // single pair, a directory source is walked in deterministic order
+    let mut pairs: Vec<(PathBuf, PathBuf)> = Vec::new();
+    if source.is_dir() {
+        for entry in walkdir::WalkDir::new(source).sort_by_file_name() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    result.record_error(format!("Failed to walk source tree: {}", e));
+                    result.error_count += 1;
+                    continue;
+                }
+            };
+            if entry.file_type().is_file() {
+                let relative = entry.path().strip_prefix(source).unwrap_or(entry.path());
+                pairs.push((entry.path().to_path_buf(), target.join(relative)));
+            }
         }
-        Ok(Err(e)) => {
-            warn!("Parallel transfer failed: {}", e);
-            result.errors.push(format!("Transfer error: {}", e));
-            result.error_count = 1;
+    } else {
+        pairs.push((source.to_path_buf(), target.to_path_buf()));
+    }
+
+    let timeout_duration = std::time::Duration::from_secs(timeout);
+    for (src, dst) in pairs {
+        // File boundaries are the cancellation points: a token cancelled
+        // here stops the transfer without touching files already copied
+        if cancel.is_cancelled() {
+            warn!("Cancellation requested, stopping remaining transfer work");
+            result.cancelled = true;
+            break;
         }
-        Err(_) => {
-            result.errors.push("Operation timed out".to_string());
-            result.error_count = 1;
+
+        let copied = tokio::time::timeout(timeout_duration, optimized_io::copy_file_async(&src, &dst)).await;
+        match copied {
+            Ok(Ok(bytes_copied)) => {
+                debug!("Parallel transfer copied {} ({} bytes)", src.display(), bytes_copied);
+                result.success_count += 1;
+                progress::global().record_file(bytes_copied);
+            }
+            Ok(Err(e)) => {
+                warn!("Parallel transfer failed for {}: {}", src.display(), e);
+                result.record_error(format!("Transfer error: {}", e));
+                result.error_count += 1;
+            }
+            Err(_) => {
+                result.record_error("Operation timed out".to_string());
+                result.error_count += 1;
+            }
         }
+
+        // Give a cancelling caller a scheduling point on every file
+        // boundary even when the copy completes without yielding
+        tokio::task::yield_now().await;
     }
-    
+
+    if result.cancelled {
+        info!("Parallel transfer cancelled after {} file(s)", result.success_count);
+    } else {
+        info!("Parallel transfer completed: {} copied, {} errors", result.success_count, result.error_count);
+    }
+
     Ok(result)
 }
 
@@ -474,6 +1552,21 @@ pub fn get_mounted_paths() -> Result<HashSet<PathBuf>> {
     Ok(mounted_paths)
 }
 
+/// File-name patterns for this tool's own artifacts (locks, backup
+/// metadata, cleanup staging, rsync partials); these must never be
+/// captured into a backup and restored into the container. Extend the
+/// list as new artifacts appear.
+pub const INTERNAL_ARTIFACT_PATTERNS: &[&str] =
+    &["*.backup_meta", "*.lock", "*.cleanup_backup_*", ".rsync-partial"];
+
+/// True when the file name matches one of [`INTERNAL_ARTIFACT_PATTERNS`]
+pub fn is_internal_artifact(file_name: &std::ffi::OsStr) -> bool {
+    let name = file_name.to_string_lossy();
+    INTERNAL_ARTIFACT_PATTERNS
+        .iter()
+        .any(|pattern| filter::segment_match(pattern.as_bytes(), name.as_bytes()))
+}
+
 /// Check if a path or any of its parents are mounted
 pub fn is_path_mounted(path: &Path, mounted_paths: &HashSet<PathBuf>) -> bool {
     // Check if the exact path is mounted
@@ -493,291 +1586,1606 @@ pub fn is_path_mounted(path: &Path, mounted_paths: &HashSet<PathBuf>) -> bool {
 
 /// Transfer data with mount bypassing capability
 pub fn transfer_data_with_mount_bypass(source: &Path, target: &Path, timeout: u64, bypass_mounts: bool) -> Result<TransferResult> {
+    transfer_data_with_mount_bypass_deadline(source, target, Deadline::from_secs(timeout), bypass_mounts)
+}
+
+/// Deadline-aware variant of [`transfer_data_with_mount_bypass`]: rsync and
+/// any native fallback share one wall-clock budget instead of each getting
+/// a fresh timeout
+pub fn transfer_data_with_mount_bypass_deadline(source: &Path, target: &Path, deadline: Deadline, bypass_mounts: bool) -> Result<TransferResult> {
+    transfer_data_with_mount_bypass_exclusions(source, target, deadline, bypass_mounts, &HashSet::new())
+}
+
+/// Like [`transfer_data_with_mount_bypass_deadline`] but with additional
+/// caller-supplied exclusion paths (e.g. the backup target itself) that are
+/// skipped alongside any detected mount points
+pub fn transfer_data_with_mount_bypass_exclusions(
+    source: &Path,
+    target: &Path,
+    deadline: Deadline,
+    bypass_mounts: bool,
+    extra_exclusions: &HashSet<PathBuf>,
+) -> Result<TransferResult> {
     // Validate paths for security
     validate_path_security(source, &PathBuf::from("/"))?;
     validate_path_security(target, &PathBuf::from("/"))?;
-    
+
+    let mut excluded_paths = extra_exclusions.clone();
     if bypass_mounts {
         info!("Mount bypass enabled - detecting mounted paths");
-        let mounted_paths = get_mounted_paths()?;
-        transfer_data_with_exclusions_robust(source, target, timeout, &mounted_paths)
+        excluded_paths.extend(get_mounted_paths()?);
+    }
+
+    if excluded_paths.is_empty() {
+        transfer_data_with_deadline(source, target, deadline)
     } else {
-        transfer_data(source, target, timeout)
+        transfer_data_with_exclusions_robust(source, target, deadline, &excluded_paths)
     }
 }
 
-/// Robust transfer with multiple fallback strategies
-fn transfer_data_with_exclusions_robust(source: &Path, target: &Path, timeout: u64, mounted_paths: &HashSet<PathBuf>) -> Result<TransferResult> {
-    // Try rsync first if available
-    if which::which("rsync").is_ok() {
-        info!("Using rsync for transfer with mount exclusions");
-        match transfer_data_with_exclusions_rsync(source, target, timeout, mounted_paths) {
-            Ok(result) if result.error_count == 0 => return Ok(result),
-            Ok(result) => {
-                warn!("Rsync completed with errors, trying native fallback");
-                debug!("Rsync errors: {:?}", result.errors);
-            }
-            Err(e) => {
-                warn!("Rsync failed: {}, trying native fallback", e);
-            }
-        }
-    } else {
-        info!("rsync not available, using native file operations");
+/// Refuse to consume a source some process is still working in: a live
+/// container filesystem shows up as processes whose current working
+/// directory lies under the source, and renaming files out from under
+/// them would corrupt the running session
+pub fn ensure_source_not_in_use(source: &Path) -> Result<()> {
+    let holders = open_files::scan_cwd_under(source)?;
+    if let Some(first) = holders.first() {
+        bail!(
+            "Refusing to consume {}: {} process(es) still have their working directory \
+             inside it (first: pid {} {}); the source looks like a live container filesystem",
+            source.display(),
+            holders.len(),
+            first.pid,
+            first.process_name
+        );
     }
-    
-    // Fall back to native Rust file operations
-    transfer_data_with_exclusions_native(source, target, timeout, mounted_paths)
+    Ok(())
 }
 
-/// Native Rust file copying with mount exclusions
-fn transfer_data_with_exclusions_native(source: &Path, target: &Path, timeout: u64, mounted_paths: &HashSet<PathBuf>) -> Result<TransferResult> {
+/// Native transfer that consumes its source: each regular file is renamed
+/// into the backup when source and target sit on the same device, with a
+/// copy-plus-unlink fallback across devices. Intended for the terminal
+/// backup of a session that is about to be destroyed anyway; refused via
+/// [`ensure_source_not_in_use`] while the source still looks live
+pub fn transfer_data_consuming_source(
+    source: &Path,
+    target: &Path,
+    deadline: Deadline,
+    bypass_mounts: bool,
+    extra_exclusions: &HashSet<PathBuf>,
+) -> Result<TransferResult> {
+    validate_path_security(source, &PathBuf::from("/"))?;
+    ensure_source_not_in_use(source)?;
+
+    let mut excluded_paths = extra_exclusions.clone();
+    if bypass_mounts {
+        excluded_paths.extend(get_mounted_paths()?);
+    }
+
     let mut result = TransferResult {
         success_count: 0,
         error_count: 0,
         skipped_count: 0,
         errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
     };
 
-    info!("Using native file operations with mount exclusions from {} to {}", source.display(), target.display());
-    
-    let start_time = std::time::Instant::now();
-    let timeout_duration = std::time::Duration::from_secs(timeout);
-    
-    // Create target directory if it doesn't exist
+    info!("Using consuming native transfer from {} to {} (remaining budget: {:?})",
+          source.display(), target.display(), deadline.remaining());
+
     if !target.exists() {
         fs::create_dir_all(target)
             .with_context(|| format!("Failed to create target directory: {}", target.display()))?;
     }
-    
-    // Recursively copy files with mount exclusions
-    copy_directory_recursive(source, target, source, mounted_paths, &mut result, start_time, timeout_duration)?;
-    
-    if result.success_count > 0 || (result.success_count == 0 && result.error_count == 0) {
-        info!("Native transfer completed successfully: {} files copied, {} skipped, {} errors", 
-              result.success_count, result.skipped_count, result.error_count);
-    }
-    
+    let capabilities = fs_capabilities::probe_destination(target);
+    copy_directory_iterative(source, target, source, &excluded_paths, &capabilities,
+                             None, None, None, false, true, false, None, &mut result, deadline)?;
+
+    info!("Consuming transfer completed: {} files moved, {} skipped, {} errors",
+          result.success_count, result.skipped_count, result.error_count);
     Ok(result)
 }
 
-/// Recursively copy directory contents with exclusions
-fn copy_directory_recursive(
-    current_source: &Path,
-    current_target: &Path, 
-    source_root: &Path,
-    mounted_paths: &HashSet<PathBuf>,
-    result: &mut TransferResult,
-    start_time: std::time::Instant,
-    timeout: std::time::Duration,
-) -> Result<()> {
-    // Check timeout
-    if start_time.elapsed() > timeout {
-        result.errors.push("Operation timed out".to_string());
-        result.error_count += 1;
-        return Err(anyhow::anyhow!("Transfer operation timed out"));
+/// Transfer with an explicitly chosen or auto-measured backend instead of
+/// the static rsync-if-present selection. The decision (and, for auto
+/// mode, the per-strategy sample timings) is recorded on the result.
+pub fn transfer_data_with_strategy(
+    source: &Path,
+    target: &Path,
+    deadline: Deadline,
+    bypass_mounts: bool,
+    extra_exclusions: &HashSet<PathBuf>,
+    choice: strategy::StrategyChoice,
+) -> Result<TransferResult> {
+    // Validate paths for security
+    validate_path_security(source, &PathBuf::from("/"))?;
+    validate_path_security(target, &PathBuf::from("/"))?;
+
+    let mut excluded_paths = extra_exclusions.clone();
+    if bypass_mounts {
+        info!("Mount bypass enabled - detecting mounted paths");
+        excluded_paths.extend(get_mounted_paths()?);
     }
-    
-    let entries = match fs::read_dir(current_source) {
-        Ok(entries) => entries,
-        Err(e) => {
-            let error_msg = format!("Failed to read directory {}: {}", current_source.display(), e);
-            warn!("{}", error_msg);
-            result.errors.push(error_msg);
-            result.error_count += 1;
-            return Ok(()); // Continue with other directories
+
+    let decision = match choice {
+        strategy::StrategyChoice::Pinned(pinned) => {
+            // The tar pipeline has no way to honor the exclusion set; a
+            // silent downgrade would hide that the pin was not respected
+            if pinned == strategy::TransferStrategy::Tar && !excluded_paths.is_empty() {
+                bail!(
+                    "--transfer-strategy tar cannot honor mount/exclusion bypass; \
+                     pin rsync or native, or disable --bypass-mounts"
+                );
+            }
+            if !pinned.available() {
+                bail!("Pinned transfer strategy {} is not available on this host", pinned);
+            }
+            strategy::StrategyDecision::pinned(pinned)
         }
+        strategy::StrategyChoice::Auto => strategy::auto_select(source, &excluded_paths)?,
     };
-    
-    for entry in entries {
-        let entry = match entry {
-            Ok(entry) => entry,
+    info!(
+        "Transfer strategy: {} ({})",
+        decision.strategy,
+        if decision.pinned { "pinned" } else { "measured" }
+    );
+
+    let mut result = match decision.strategy {
+        // The rsync path keeps its native fallback: a measured winner that
+        // then hits errors must not cost the whole backup
+        strategy::TransferStrategy::Rsync => {
+            transfer_data_with_exclusions_robust(source, target, deadline, &excluded_paths)?
+        }
+        strategy::TransferStrategy::Tar => {
+            transfer_data_tar_deadline(source, target, deadline, false)?
+        }
+        strategy::TransferStrategy::Native => {
+            transfer_data_with_exclusions_native(source, target, deadline, &excluded_paths)?
+        }
+    };
+    result.strategy_decision = Some(decision);
+    Ok(result)
+}
+
+/// Stream the session tree as a tar archive into `writer` (typically
+/// stdout), honoring the same exclusion logic as the native transfer:
+/// internal artifacts, excluded/mounted paths and the installed transfer
+/// filter are skipped, symlinks are archived as links. Nothing is written
+/// to the backup directory.
+pub fn backup_to_tar<W: std::io::Write>(
+    source: &Path,
+    writer: W,
+    deadline: Deadline,
+    excluded_paths: &HashSet<PathBuf>,
+) -> Result<TransferResult> {
+    let mut result = TransferResult::default();
+
+    // Fetched once: the filter is installed before the transfer starts
+    let transfer_filter = filter::installed_filter();
+    let mount_index = mount_index::MountIndex::new(excluded_paths);
+
+    let mut builder = tar::Builder::new(writer);
+    // Symlinks become link entries instead of duplicated content
+    builder.follow_symlinks(false);
+
+    // Iterative walk, mirroring copy_directory_iterative's queue so
+    // pathologically deep trees cannot overflow the stack
+    let mut queue: std::collections::VecDeque<PathBuf> = std::collections::VecDeque::new();
+    queue.push_back(source.to_path_buf());
+
+    while let Some(current_source) = queue.pop_front() {
+        heartbeat::beat("tar-stream", result.success_count);
+
+        if deadline.expired() {
+            result.record_error("Operation timed out".to_string());
+            result.error_count += 1;
+            return Err(anyhow::anyhow!("Tar stream operation timed out"));
+        }
+
+        let entries = match fs::read_dir(&current_source) {
+            Ok(entries) => entries,
             Err(e) => {
-                let error_msg = format!("Failed to read directory entry in {}: {}", current_source.display(), e);
+                let error_msg = format!("Failed to read directory {}: {}", current_source.display(), e);
                 warn!("{}", error_msg);
-                result.errors.push(error_msg);
+                result.record_error(error_msg);
                 result.error_count += 1;
                 continue;
             }
         };
-        
-        let source_path = entry.path();
-        let file_name = entry.file_name();
-        let target_path = current_target.join(&file_name);
-        
-        // Check if this path should be excluded (mounted path)
-        if is_path_excluded(&source_path, source_root, mounted_paths) {
-            debug!("Skipping mounted path: {}", source_path.display());
-            result.skipped_count += 1;
-            continue;
-        }
-        
-        let metadata = match entry.metadata() {
-            Ok(metadata) => metadata,
-            Err(e) => {
-                let error_msg = format!("Failed to get metadata for {}: {}", source_path.display(), e);
-                warn!("{}", error_msg);
-                result.errors.push(error_msg);
-                result.error_count += 1;
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let error_msg = format!("Failed to read directory entry in {}: {}", current_source.display(), e);
+                    warn!("{}", error_msg);
+                    result.record_error(error_msg);
+                    result.error_count += 1;
+                    continue;
+                }
+            };
+
+            let source_path = entry.path();
+            let file_name = entry.file_name();
+
+            // Never capture this tool's own lock/metadata artifacts
+            if is_internal_artifact(&file_name) {
+                debug!("Skipping internal artifact: {}", source_path.display());
+                result.record_skip("Internal artifact");
                 continue;
             }
-        };
-        
-        if metadata.is_dir() {
-            // Create target directory
-            if let Err(e) = fs::create_dir_all(&target_path) {
-                let error_msg = format!("Failed to create directory {}: {}", target_path.display(), e);
-                warn!("{}", error_msg);
-                result.errors.push(error_msg);
-                result.error_count += 1;
+
+            // Check if this path should be excluded (mounted path)
+            if is_path_excluded(&source_path, source, &mount_index) {
+                debug!("Skipping mounted path: {}", source_path.display());
+                result.record_skip("Excluded mounted path");
                 continue;
             }
-            
-            // Recursively copy directory contents
-            copy_directory_recursive(&source_path, &target_path, source_root, mounted_paths, result, start_time, timeout)?;
-        } else if metadata.is_file() {
-            // Copy file
-            match copy_file_with_permissions(&source_path, &target_path) {
-                Ok(_) => {
-                    result.success_count += 1;
-                    debug!("Copied file: {} -> {}", source_path.display(), target_path.display());
-                }
+
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
                 Err(e) => {
-                    let error_msg = format!("Failed to copy file {} to {}: {}", source_path.display(), target_path.display(), e);
+                    let error_msg = format!("Failed to get metadata for {}: {}", source_path.display(), e);
                     warn!("{}", error_msg);
-                    result.errors.push(error_msg);
+                    result.record_error(error_msg);
                     result.error_count += 1;
+                    continue;
                 }
-            }
-        } else if metadata.file_type().is_symlink() {
-            // Handle symlinks
-            match copy_symlink(&source_path, &target_path) {
-                Ok(_) => {
-                    result.success_count += 1;
-                    debug!("Copied symlink: {} -> {}", source_path.display(), target_path.display());
+            };
+
+            let Ok(relative) = source_path.strip_prefix(source) else { continue };
+
+            // User-configured globs, with the native path's semantics:
+            // excluded directories are still entered when an include could
+            // match something beneath them
+            if let Some(filter) = &transfer_filter {
+                let keep = if metadata.is_dir() {
+                    filter.should_descend(relative)
+                } else {
+                    filter.admits(relative)
+                };
+                if !keep {
+                    debug!("Skipping path excluded by pattern: {}", source_path.display());
+                    result.record_skip("Excluded by pattern");
+                    continue;
                 }
-                Err(e) => {
-                    let error_msg = format!("Failed to copy symlink {} to {}: {}", source_path.display(), target_path.display(), e);
+            }
+
+            if metadata.is_dir() {
+                // The directory entry itself carries mode and mtime, and
+                // keeps empty directories restorable
+                if let Err(e) = builder.append_path_with_name(&source_path, relative) {
+                    let error_msg = format!("Failed to archive directory {}: {}", source_path.display(), e);
                     warn!("{}", error_msg);
-                    result.errors.push(error_msg);
+                    result.record_error(error_msg);
                     result.error_count += 1;
+                    continue;
                 }
+                queue.push_back(source_path);
+            } else if metadata.is_file() || metadata.file_type().is_symlink() {
+                match builder.append_path_with_name(&source_path, relative) {
+                    Ok(()) => result.success_count += 1,
+                    Err(e) => {
+                        let error_msg = format!("Failed to archive {}: {}", source_path.display(), e);
+                        warn!("{}", error_msg);
+                        result.record_error(error_msg);
+                        result.error_count += 1;
+                    }
+                }
+            } else {
+                debug!("Skipping special file: {}", source_path.display());
+                result.record_skip("Special file");
             }
-        } else {
-            // Skip special files (devices, pipes, etc.)
-            debug!("Skipping special file: {}", source_path.display());
-            result.skipped_count += 1;
-        }
-        
-        // Check timeout periodically
-        if start_time.elapsed() > timeout {
-            result.errors.push("Operation timed out".to_string());
-            result.error_count += 1;
-            return Err(anyhow::anyhow!("Transfer operation timed out"));
         }
     }
-    
-    Ok(())
+
+    let mut writer = builder.into_inner().context("Failed to finish tar archive")?;
+    writer.flush().context("Failed to flush tar archive")?;
+
+    info!("Tar stream completed: {} entries written, {} skipped, {} errors",
+          result.success_count, result.skipped_count, result.error_count);
+
+    Ok(result)
 }
 
-/// Check if a path should be excluded based on mount points
-fn is_path_excluded(file_path: &Path, source_root: &Path, mounted_paths: &HashSet<PathBuf>) -> bool {
-    // Get the path relative to source root to check against mounted paths
-    if let Ok(relative_path) = file_path.strip_prefix(source_root) {
-        let absolute_path = PathBuf::from("/").join(relative_path);
-        
-        // Check if this absolute path or any of its parents is mounted
-        if is_path_mounted(&absolute_path, mounted_paths) {
-            return true;
-        }
+/// Canonicalize a path that may not fully exist yet by canonicalizing its
+/// nearest existing ancestor and re-appending the remaining components
+pub fn canonicalize_lenient(path: &Path) -> Result<PathBuf> {
+    if let Ok(canonical) = path.canonicalize() {
+        return Ok(canonical);
     }
-    
-    false
-}
 
-/// Copy a file preserving permissions and metadata
-fn copy_file_with_permissions(source: &Path, target: &Path) -> Result<()> {
-    // Create parent directory if needed
-    if let Some(parent) = target.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create parent directory for: {}", target.display()))?;
+    let mut existing = path;
+    let mut remainder = Vec::new();
+    loop {
+        match existing.parent() {
+            Some(parent) => {
+                remainder.push(existing.file_name().with_context(|| {
+                    format!("Cannot canonicalize relative traversal: {}", path.display())
+                })?);
+                existing = parent;
+                if existing.exists() {
+                    break;
+                }
+            }
+            None => bail!("No existing ancestor for path: {}", path.display()),
+        }
     }
-    
-    // Copy the file
-    fs::copy(source, target)
-        .with_context(|| format!("Failed to copy file from {} to {}", source.display(), target.display()))?;
-    
-    // Copy permissions
-    #[cfg(unix)]
-    {
-        let metadata = source.metadata()
-            .with_context(|| format!("Failed to get metadata for: {}", source.display()))?;
-        let permissions = metadata.permissions();
-        fs::set_permissions(target, permissions)
-            .with_context(|| format!("Failed to set permissions for: {}", target.display()))?;
+
+    let mut canonical = existing
+        .canonicalize()
+        .with_context(|| format!("Failed to canonicalize {}", existing.display()))?;
+    for component in remainder.iter().rev() {
+        canonical.push(component);
     }
-    
-    Ok(())
+    Ok(canonical)
 }
 
-/// Copy a symlink
-fn copy_symlink(source: &Path, target: &Path) -> Result<()> {
-    let link_target = fs::read_link(source)
-        .with_context(|| format!("Failed to read symlink: {}", source.display()))?;
-    
-    // Remove target if it exists
-    if target.exists() {
-        fs::remove_file(target)
-            .with_context(|| format!("Failed to remove existing target: {}", target.display()))?;
-    }
-    
-    #[cfg(unix)]
-    std::os::unix::fs::symlink(&link_target, target)
-        .with_context(|| format!("Failed to create symlink from {} to {}", link_target.display(), target.display()))?;
-    
-    #[cfg(windows)]
-    {
-        if link_target.is_dir() {
-            std::os::windows::fs::symlink_dir(&link_target, target)
-                .with_context(|| format!("Failed to create directory symlink from {} to {}", link_target.display(), target.display()))?;
-        } else {
-            std::os::windows::fs::symlink_file(&link_target, target)
-                .with_context(|| format!("Failed to create file symlink from {} to {}", link_target.display(), target.display()))?;
-        }
+/// Refuse backup configurations where the backup target lies inside the
+/// session directory (or vice versa). Such a layout makes the transfer copy
+/// the growing backup into itself; we hit this with relative backup paths.
+pub fn check_backup_nesting(session_dir: &Path, backup_dir: &Path) -> Result<()> {
+    let canonical_session = canonicalize_lenient(session_dir)
+        .with_context(|| format!("Failed to canonicalize session directory: {}", session_dir.display()))?;
+    let canonical_backup = canonicalize_lenient(backup_dir)
+        .with_context(|| format!("Failed to canonicalize backup directory: {}", backup_dir.display()))?;
+
+    if canonical_backup.starts_with(&canonical_session) || canonical_session.starts_with(&canonical_backup) {
+        bail!(
+            "Backup path {} and session directory {} are nested inside each other; \
+             the backup would copy itself recursively. Point --backup-path at an \
+             absolute directory outside the session filesystem.",
+            canonical_backup.display(),
+            canonical_session.display()
+        );
     }
-    
+
     Ok(())
 }
 
-/// Transfer data excluding mounted paths using rsync (fallback)
-fn transfer_data_with_exclusions_rsync(source: &Path, target: &Path, timeout: u64, mounted_paths: &HashSet<PathBuf>) -> Result<TransferResult> {
-    let mut result = TransferResult {
-        success_count: 0,
+/// Robust transfer with multiple fallback strategies
+fn transfer_data_with_exclusions_robust(source: &Path, target: &Path, deadline: Deadline, mounted_paths: &HashSet<PathBuf>) -> Result<TransferResult> {
+    // Try rsync first if available
+    if which::which("rsync").is_ok() {
+        info!("Using rsync for transfer with mount exclusions");
+        match transfer_data_with_exclusions_rsync(source, target, deadline, mounted_paths) {
+            Ok(result) if result.error_count == 0 => return Ok(result),
+            Ok(result) => {
+                warn!("Rsync completed with errors, trying native fallback");
+                debug!("Rsync errors: {:?}", result.errors);
+            }
+            Err(e) => {
+                warn!("Rsync failed: {}, trying native fallback", e);
+            }
+        }
+    } else {
+        info!("rsync not available, using native file operations");
+    }
+    
+    // Fall back to native Rust file operations with whatever budget remains
+    transfer_data_with_exclusions_native(source, target, deadline, mounted_paths)
+}
+
+/// Native Rust file copying with mount exclusions
+fn transfer_data_with_exclusions_native(source: &Path, target: &Path, deadline: Deadline, mounted_paths: &HashSet<PathBuf>) -> Result<TransferResult> {
+    let mut result = TransferResult {
+        success_count: 0,
+        error_count: 0,
+        skipped_count: 0,
+        errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
+    };
+
+    info!("Using native file operations with mount exclusions from {} to {} (remaining budget: {:?})", 
+          source.display(), target.display(), deadline.remaining());
+    
+    // Create target directory if it doesn't exist
+    if !target.exists() {
+        fs::create_dir_all(target)
+            .with_context(|| format!("Failed to create target directory: {}", target.display()))?;
+    }
+    
+    // Probe the destination once so unsupported attribute operations are
+    // skipped silently instead of warning per file
+    let capabilities = fs_capabilities::probe_destination(target);
+
+    // Copy files with mount exclusions using an iterative work queue
+    copy_directory_iterative(source, target, source, mounted_paths, &capabilities, None, None, None, false, false, false, None, &mut result, deadline)?;
+    
+    if result.success_count > 0 || (result.success_count == 0 && result.error_count == 0) {
+        info!("Native transfer completed successfully: {} files copied, {} skipped, {} errors", 
+              result.success_count, result.skipped_count, result.error_count);
+    }
+    
+    Ok(result)
+}
+
+/// Describe a path's depth and byte length for diagnostics on pathological trees
+fn path_diagnostics(path: &Path, depth: usize) -> String {
+    format!("depth {}, path length {} bytes", depth, path.as_os_str().len())
+}
+
+/// Copy directory contents with exclusions using an iterative work queue.
+/// The explicit queue avoids stack overflows on pathologically deep trees
+/// (e.g. nested conda environment caches) that recursion cannot handle.
+/// Native transfer with per-file zstd compression: files selected by the
+/// policy are stored as `<name>.zst` and every transferred file is recorded
+/// in a manifest written at the target root, so restore can decompress
+/// transparently and verify against the original content hash
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_data_with_compression(
+    source: &Path,
+    target: &Path,
+    deadline: Deadline,
+    mounted_paths: &HashSet<PathBuf>,
+    policy: &compression::CompressionPolicy,
+    recopy_unstable: bool,
+    pack_threshold: Option<u64>,
+    db_aware: bool,
+    previous_manifest: Option<&manifest::BackupManifest>,
+) -> Result<TransferResult> {
+    let mut result = TransferResult {
+        success_count: 0,
+        error_count: 0,
+        skipped_count: 0,
+        errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
+    };
+
+    info!("Using native transfer with per-file compression from {} to {} (min size {} bytes)",
+          source.display(), target.display(), policy.min_size);
+
+    if !target.exists() {
+        fs::create_dir_all(target)
+            .with_context(|| format!("Failed to create target directory: {}", target.display()))?;
+    }
+
+    let capabilities = fs_capabilities::probe_destination(target);
+    let mut backup_manifest = manifest::BackupManifest::default();
+    let mut packer = pack_threshold.map(|threshold| packing::PackWriter::new(target, threshold));
+
+    copy_directory_iterative(source, target, source, mounted_paths, &capabilities,
+                             Some(policy), Some(&mut backup_manifest), packer.as_mut(),
+                             recopy_unstable, false, db_aware, previous_manifest, &mut result, deadline)?;
+
+    if let Some(packer) = packer {
+        packer.finish()?;
+    }
+
+    // Incremental mode: files present in the previous manifest but gone
+    // from the source were deleted; drop their stale stored copies and
+    // keep a tombstone entry so the deletion is recorded
+    if let Some(previous) = previous_manifest {
+        let mut deletions = 0;
+        for (relative, prev_entry) in &previous.entries {
+            if prev_entry.deleted || backup_manifest.entries.contains_key(relative) {
+                continue;
+            }
+            if prev_entry.pack.is_none() {
+                let stored = if prev_entry.compressed {
+                    target.join(format!("{}.zst", relative))
+                } else {
+                    target.join(relative)
+                };
+                if let Err(e) = fs::remove_file(&stored) {
+                    debug!("Could not remove stale backup file {}: {}", stored.display(), e);
+                }
+            }
+            let mut tombstone = prev_entry.clone();
+            tombstone.deleted = true;
+            backup_manifest.record(Path::new(relative), tombstone);
+            deletions += 1;
+        }
+        if deletions > 0 {
+            info!("Incremental backup recorded {} deletion(s) since the previous manifest", deletions);
+        }
+    }
+
+    backup_manifest.save(target)?;
+    Ok(result)
+}
+
+/// Options for [`backup_and_verify`]: a small, embedding-friendly subset
+/// of the CLI surface
+#[derive(Debug, Clone)]
+pub struct BackupVerifyOptions {
+    /// Wall-clock budget for the transfer, in seconds
+    pub timeout_secs: u64,
+    /// Exclude mounted paths under the source
+    pub bypass_mounts: bool,
+    /// Compress large compressible files; disabled when None
+    pub compression: Option<compression::CompressionPolicy>,
+    /// Re-copy once any file whose source changed while being copied
+    pub recopy_unstable: bool,
+}
+
+impl Default for BackupVerifyOptions {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 900,
+            bypass_mounts: false,
+            compression: None,
+            recopy_unstable: false,
+        }
+    }
+}
+
+/// One stored file whose re-read content does not hash to what the
+/// manifest recorded
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HashMismatch {
+    /// Path relative to the backup root
+    pub path: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Combined result of [`backup_and_verify`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupReport {
+    pub transfer: TransferResult,
+    /// Manifest entries whose stored content re-read to the recorded hash
+    pub verified_files: usize,
+    pub mismatches: Vec<HashMismatch>,
+}
+
+/// Single entry point for embedders: run the transfer, generate the
+/// manifest, then re-read every stored file (decompressing and unpacking
+/// as needed) and compare against the recorded content hashes. Mismatches
+/// are reported, not fatal: a file changing between copy and verify is a
+/// finding, not an error in the backup machinery.
+pub fn backup_and_verify(
+    source: &Path,
+    target: &Path,
+    opts: &BackupVerifyOptions,
+) -> Result<BackupReport> {
+    let deadline = Deadline::from_secs(opts.timeout_secs);
+    // The compressing transfer is used even without a policy because it is
+    // the path that maintains the manifest the verification reads back
+    let no_compression = compression::CompressionPolicy { min_size: u64::MAX };
+    let policy = opts.compression.as_ref().unwrap_or(&no_compression);
+    let mut excluded_paths = HashSet::new();
+    if opts.bypass_mounts {
+        excluded_paths.extend(get_mounted_paths()?);
+    }
+
+    let transfer = transfer_data_with_compression(
+        source, target, deadline, &excluded_paths, policy, opts.recopy_unstable, None, false, None,
+    )?;
+
+    let backup_manifest = manifest::BackupManifest::load(target)?
+        .ok_or_else(|| anyhow::anyhow!("No manifest found after transfer: {}", target.display()))?;
+    let (verified_files, mismatches) = verify_stored_hashes(target, &backup_manifest)?;
+
+    if mismatches.is_empty() {
+        info!("Backup verified: {} stored files match their recorded hashes", verified_files);
+    } else {
+        warn!("Backup verification found {} hash mismatch(es) out of {} files",
+              mismatches.len(), verified_files + mismatches.len());
+    }
+    Ok(BackupReport { transfer, verified_files, mismatches })
+}
+
+/// Re-read every stored file named by the manifest — decompressing and
+/// unpacking as needed — and compare against the recorded content hashes
+fn verify_stored_hashes(
+    target: &Path,
+    backup_manifest: &manifest::BackupManifest,
+) -> Result<(usize, Vec<HashMismatch>)> {
+    let mut verified_files = 0;
+    let mut mismatches = Vec::new();
+    for (relative, entry) in &backup_manifest.entries {
+        if entry.deleted {
+            continue;
+        }
+        let actual = if let Some(location) = &entry.pack {
+            let bytes = packing::read_packed_entry(target, location)?;
+            blake3::hash(&bytes).to_hex().to_string()
+        } else if entry.compressed {
+            let stored = target.join(format!("{}.zst", relative));
+            let decompressed = tempfile::NamedTempFile::new()
+                .context("Failed to create temp file for verification")?;
+            compression::decompress_file(&stored, decompressed.path())?;
+            manifest::hash_file_contents(decompressed.path())?
+        } else {
+            manifest::hash_file_contents(&target.join(relative))?
+        };
+        if actual == entry.original_hash {
+            verified_files += 1;
+        } else {
+            mismatches.push(HashMismatch {
+                path: relative.clone(),
+                expected: entry.original_hash.clone(),
+                actual,
+            });
+        }
+    }
+    Ok((verified_files, mismatches))
+}
+
+/// Compress one file into the backup and record its manifest entry
+fn store_file_compressed(
+    source_path: &Path,
+    compressed_target: &Path,
+    source_root: &Path,
+    original_size: u64,
+    backup_manifest: Option<&mut manifest::BackupManifest>,
+) -> Result<()> {
+    let stored_size = compression::compress_file(source_path, compressed_target)?;
+    if let Some(manifest) = backup_manifest {
+        let relative = source_path.strip_prefix(source_root).unwrap_or(source_path);
+        manifest.record(relative, manifest::ManifestEntry {
+            original_size,
+            stored_size,
+            compressed: true,
+            original_hash: manifest::hash_file_contents(source_path)?,
+            unstable: false,
+            pack: None,
+            db_unit: None,
+            deleted: false,
+            birth_time: manifest::birth_time_rfc3339(source_path),
+            privileged_attrs: manifest::privileged_attrs_for(source_path),
+        });
+    }
+    Ok(())
+}
+
+/// Record a manifest entry for a file stored raw
+/// Whether a source file's size or mtime differs from the metadata
+/// captured just before its copy started
+fn source_changed_during_copy(source_path: &Path, before: &fs::Metadata) -> bool {
+    match fs::symlink_metadata(source_path) {
+        Ok(after) => {
+            after.len() != before.len() || after.modified().ok() != before.modified().ok()
+        }
+        // A source deleted mid-backup definitely changed
+        Err(_) => true,
+    }
+}
+
+fn record_raw_manifest_entry(
+    source_path: &Path,
+    source_root: &Path,
+    original_size: u64,
+    original_hash: String,
+    backup_manifest: &mut manifest::BackupManifest,
+) {
+    let relative = source_path.strip_prefix(source_root).unwrap_or(source_path);
+    backup_manifest.record(relative, manifest::ManifestEntry {
+        original_size,
+        stored_size: original_size,
+        compressed: false,
+        original_hash,
+        unstable: false,
+        pack: None,
+        db_unit: None,
+        deleted: false,
+        birth_time: manifest::birth_time_rfc3339(source_path),
+        privileged_attrs: manifest::privileged_attrs_for(source_path),
+    });
+}
+
+/// Record a manifest entry for a file captured as part of a database unit
+fn record_db_unit_entry(
+    source_path: &Path,
+    source_root: &Path,
+    unit: &str,
+    stable: bool,
+    backup_manifest: Option<&mut manifest::BackupManifest>,
+) {
+    if let Some(manifest) = backup_manifest {
+        let size = fs::metadata(source_path).map(|m| m.len()).unwrap_or(0);
+        let relative = source_path.strip_prefix(source_root).unwrap_or(source_path);
+        manifest.record(relative, manifest::ManifestEntry {
+            original_size: size,
+            stored_size: size,
+            compressed: false,
+            original_hash: manifest::hash_file_contents(source_path).unwrap_or_default(),
+            unstable: !stable,
+            pack: None,
+            db_unit: Some(unit.to_string()),
+            deleted: false,
+            birth_time: manifest::birth_time_rfc3339(source_path),
+            privileged_attrs: manifest::privileged_attrs_for(source_path),
+        });
+    }
+}
+
+/// Capture an SQLite database together with its -wal/-shm sidecars as one
+/// unit, retrying until the trio holds still across two passes
+fn backup_sqlite_unit(
+    db_path: &Path,
+    current_target: &Path,
+    source_root: &Path,
+    mut backup_manifest: Option<&mut manifest::BackupManifest>,
+    result: &mut TransferResult,
+    db_handled: &mut HashSet<PathBuf>,
+) {
+    let members = db_aware::sqlite_unit_members(db_path);
+    let unit = format!(
+        "sqlite:{}",
+        db_path.strip_prefix(source_root).unwrap_or(db_path).display()
+    );
+    debug!("Capturing SQLite unit {} ({} members)", unit, members.len());
+
+    let copy_outcome = db_aware::copy_unit_stable(&members, |member| {
+        let target = current_target.join(member.file_name().unwrap_or_default());
+        copy_file_with_permissions(member, &target)
+    });
+
+    match copy_outcome {
+        Ok(stable) => {
+            if !stable {
+                warn!("SQLite unit never stabilized, captured copy may be inconsistent: {}", unit);
+            }
+            for member in &members {
+                record_db_unit_entry(member, source_root, &unit, stable, backup_manifest.as_deref_mut());
+                if !stable {
+                    result.unstable_files.push(member.clone());
+                }
+                db_handled.insert(member.clone());
+                result.success_count += 1;
+            }
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to capture SQLite unit {}: {}", unit, e);
+            warn!("{}", error_msg);
+            result.record_error(error_msg);
+            result.error_count += 1;
+        }
+    }
+}
+
+/// Capture a LevelDB/RocksDB directory as one unit under a stability check
+fn backup_leveldb_unit(
+    store_dir: &Path,
+    target_dir: &Path,
+    source_root: &Path,
+    mut backup_manifest: Option<&mut manifest::BackupManifest>,
+    result: &mut TransferResult,
+) {
+    let members: Vec<PathBuf> = walkdir::WalkDir::new(store_dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect();
+    let unit = format!(
+        "leveldb:{}",
+        store_dir.strip_prefix(source_root).unwrap_or(store_dir).display()
+    );
+    debug!("Capturing LevelDB unit {} ({} members)", unit, members.len());
+
+    let copy_outcome = db_aware::copy_unit_stable(&members, |member| {
+        let relative = member.strip_prefix(store_dir).unwrap_or(member);
+        let target = target_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            dir_cache::ensure_dir_exists(parent)?;
+        }
+        copy_file_with_permissions(member, &target)
+    });
+
+    match copy_outcome {
+        Ok(stable) => {
+            if !stable {
+                warn!("LevelDB unit never stabilized, captured copy may be inconsistent: {}", unit);
+            }
+            for member in &members {
+                record_db_unit_entry(member, source_root, &unit, stable, backup_manifest.as_deref_mut());
+                if !stable {
+                    result.unstable_files.push(member.clone());
+                }
+                result.success_count += 1;
+            }
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to capture LevelDB unit {}: {}", unit, e);
+            warn!("{}", error_msg);
+            result.record_error(error_msg);
+            result.error_count += 1;
+        }
+    }
+}
+
+/// Pack one small file and record it in the manifest index
+fn pack_file_entry(
+    source_path: &Path,
+    source_root: &Path,
+    packer: &mut packing::PackWriter,
+    backup_manifest: &mut manifest::BackupManifest,
+) -> Result<()> {
+    let location = packer.append(source_path)?;
+    let relative = source_path.strip_prefix(source_root).unwrap_or(source_path);
+    backup_manifest.record(relative, manifest::ManifestEntry {
+        original_size: location.length,
+        stored_size: location.length,
+        compressed: false,
+        original_hash: manifest::hash_file_contents(source_path)?,
+        unstable: false,
+        pack: Some(location),
+        db_unit: None,
+        deleted: false,
+        birth_time: manifest::birth_time_rfc3339(source_path),
+        privileged_attrs: manifest::privileged_attrs_for(source_path),
+    });
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn copy_directory_iterative(
+    source: &Path,
+    target: &Path,
+    source_root: &Path,
+    mounted_paths: &HashSet<PathBuf>,
+    capabilities: &fs_capabilities::CapabilitySet,
+    compression: Option<&compression::CompressionPolicy>,
+    mut backup_manifest: Option<&mut manifest::BackupManifest>,
+    mut packer: Option<&mut packing::PackWriter>,
+    recopy_unstable: bool,
+    consume_source: bool,
+    db_aware: bool,
+    previous_manifest: Option<&manifest::BackupManifest>,
+    result: &mut TransferResult,
+    deadline: Deadline,
+) -> Result<()> {
+    // Members of database units already captured with their unit;
+    // the walk must not copy them a second time
+    let mut db_handled: HashSet<PathBuf> = HashSet::new();
+    // Fetched once: the filter is installed before the transfer starts
+    let transfer_filter = filter::installed_filter();
+    // Fetched once: the cutoff is fixed at install time so the whole
+    // walk applies one consistent age threshold
+    let age_cutoff = age::installed_cutoff();
+    // Fetched once: the tier of the last file copied is recorded in the
+    // result when the deadline cuts a prioritized run short
+    let copy_order = ordering::installed();
+    let mut last_tier: Option<&'static str> = None;
+    // Clone counter snapshot: the delta over this walk is the run's
+    // cloned-vs-copied report under --cow
+    let cloned_before = reflink::files_cloned();
+    // Indexed once per transfer: the mount check runs for every entry and
+    // must not pay one hash lookup per ancestor
+    let mount_index = mount_index::MountIndex::new(mounted_paths);
+    // Cloned once so the hot loop feeds the shared progress tally without
+    // touching the Lazy cell per entry
+    let progress_counters = progress::global();
+    // Work queue of (source directory, target directory, depth)
+    let mut queue: std::collections::VecDeque<(PathBuf, PathBuf, usize)> = std::collections::VecDeque::new();
+    queue.push_back((source.to_path_buf(), target.to_path_buf(), 0));
+    // Directory pairs in BFS order; their metadata is mirrored once the
+    // walk is done so writes into children cannot clobber restored mtimes
+    let mut completed_dirs: Vec<(PathBuf, PathBuf)> = vec![(source.to_path_buf(), target.to_path_buf())];
+
+    while let Some((current_source, current_target, depth)) = queue.pop_front() {
+        heartbeat::beat("transfer", result.success_count);
+        progress::log_periodic("transfer");
+
+        // Check the shared wall-clock budget
+        if deadline.expired() {
+            result.record_error("Operation timed out".to_string());
+            result.error_count += 1;
+            result.priority_tier_reached = last_tier.map(str::to_string);
+            return Err(anyhow::anyhow!("Transfer operation timed out"));
+        }
+
+        let entries = match fs::read_dir(&current_source) {
+            Ok(entries) => entries,
+            // A directory deleted by the still-running user process
+            // between listing and descent is benign, not a hard failure
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("Directory vanished mid-descend: {}", current_source.display());
+                result.record_skip("Directory vanished mid-descend");
+                continue;
+            }
+            Err(e) => {
+                let error_msg = format!("Failed to read directory {} ({}): {}",
+                                        current_source.display(), path_diagnostics(&current_source, depth), e);
+                warn!("{}", error_msg);
+                result.record_error(error_msg);
+                result.error_count += 1;
+                continue; // Continue with other directories
+            }
+        };
+
+        // Materialize the listing so the configured copy order can
+        // rearrange it before any bytes move
+        let mut entry_list: Vec<fs::DirEntry> = Vec::new();
+        for entry in entries {
+            match entry {
+                Ok(entry) => entry_list.push(entry),
+                Err(e) => {
+                    let error_msg = format!("Failed to read directory entry in {}: {}", current_source.display(), e);
+                    warn!("{}", error_msg);
+                    result.record_error(error_msg);
+                    result.error_count += 1;
+                }
+            }
+        }
+        if copy_order != ordering::CopyOrder::Natural {
+            entry_list.sort_by_cached_key(|entry| {
+                let path = entry.path();
+                let relative = path.strip_prefix(source_root).unwrap_or(&path).to_path_buf();
+                // Directories carry no size of their own; they sort as
+                // empty files and are only enqueued, never copied here
+                let size = entry
+                    .metadata()
+                    .map(|m| if m.is_file() { m.len() } else { 0 })
+                    .unwrap_or(0);
+                copy_order.sort_key(&relative, size)
+            });
+        }
+
+        for entry in entry_list {
+            let source_path = entry.path();
+            let file_name = entry.file_name();
+            let target_path = current_target.join(&file_name);
+            let entry_depth = depth + 1;
+
+            // Never capture this tool's own lock/metadata artifacts
+            if is_internal_artifact(&file_name) {
+                debug!("Skipping internal artifact: {}", source_path.display());
+                result.record_skip("Internal artifact");
+                continue;
+            }
+
+            // Check if this path should be excluded (mounted path)
+            if is_path_excluded(&source_path, source_root, &mount_index) {
+                debug!("Skipping mounted path: {}", source_path.display());
+                result.record_skip("Excluded mounted path");
+                continue;
+            }
+
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                // Deleted between listing and stat: benign, never retried
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    debug!("Source vanished before stat: {}", source_path.display());
+                    result.record_skip("Source vanished before copy");
+                    continue;
+                }
+                Err(e) => {
+                    let error_msg = format!("Failed to get metadata for {} ({}): {}",
+                                            source_path.display(), path_diagnostics(&source_path, entry_depth), e);
+                    warn!("{}", error_msg);
+                    result.record_error(error_msg);
+                    result.error_count += 1;
+                    continue;
+                }
+            };
+
+            // User-configured globs: includes are evaluated after excludes
+            // and re-admit matching files; excluded directories are still
+            // entered when an include could match something beneath them
+            if let Some(filter) = &transfer_filter {
+                if let Ok(relative) = source_path.strip_prefix(source_root) {
+                    let keep = if metadata.is_dir() {
+                        filter.should_descend(relative)
+                    } else {
+                        filter.admits(relative)
+                    };
+                    if !keep {
+                        debug!("Skipping path excluded by pattern: {}", source_path.display());
+                        result.record_skip("Excluded by pattern");
+                        continue;
+                    }
+                }
+            }
+
+            // Age cutoff from --exclude-older-than; directories still
+            // descend, as their children can be newer than they are
+            if !metadata.is_dir() {
+                if let Some(cutoff) = age_cutoff {
+                    if age::is_stale(&metadata, cutoff) {
+                        debug!("Skipping file older than the age cutoff: {}", source_path.display());
+                        result.record_skip("Excluded by age");
+                        continue;
+                    }
+                }
+            }
+
+            if metadata.is_dir() {
+                // LevelDB/RocksDB stores are only consistent as a whole:
+                // capture the directory as one unit instead of queueing it
+                if db_aware && db_aware::is_leveldb_dir(&source_path) {
+                    backup_leveldb_unit(&source_path, &target_path, source_root,
+                                        backup_manifest.as_deref_mut(), result);
+                    continue;
+                }
+
+                // Create target directory and queue its contents instead of recursing
+                if let Err(e) = fs::create_dir_all(&target_path) {
+                    let error_msg = format!("Failed to create directory {} ({}): {}", 
+                                            target_path.display(), path_diagnostics(&target_path, entry_depth), e);
+                    warn!("{}", error_msg);
+                    result.record_error(error_msg);
+                    result.error_count += 1;
+                    continue;
+                }
+
+                completed_dirs.push((source_path.clone(), target_path.clone()));
+                queue.push_back((source_path, target_path, entry_depth));
+            } else if metadata.is_file() {
+                if copy_order != ordering::CopyOrder::Natural {
+                    let relative = source_path.strip_prefix(source_root).unwrap_or(&source_path);
+                    last_tier = Some(copy_order.tier(relative, metadata.len()));
+                }
+                fault_injection::fire_before_file(&source_path);
+                if db_aware {
+                    // Already captured as part of a database unit
+                    if db_handled.contains(&source_path) {
+                        continue;
+                    }
+                    // Sidecars are captured together with their database
+                    // when the walk reaches the main file
+                    if db_aware::sqlite_sidecar_main(&source_path).is_some() {
+                        continue;
+                    }
+                    if db_aware::is_sqlite_file(&source_path) {
+                        backup_sqlite_unit(&source_path, &current_target, source_root,
+                                           backup_manifest.as_deref_mut(), result, &mut db_handled);
+                        continue;
+                    }
+                }
+
+                // Incremental mode: content matching the previous backup's
+                // manifest is carried over instead of re-transferred
+                if let Some(previous) = previous_manifest {
+                    let relative = source_path.strip_prefix(source_root).unwrap_or(&source_path);
+                    if let Some(prev_entry) = previous.get(relative) {
+                        if !prev_entry.deleted
+                            && prev_entry.original_size == metadata.len()
+                            && manifest::hash_file_contents(&source_path)
+                                .is_ok_and(|hash| hash == prev_entry.original_hash)
+                        {
+                            if let Some(manifest) = backup_manifest.as_deref_mut() {
+                                manifest.record(relative, prev_entry.clone());
+                            }
+                            result.record_skip("Unchanged since previous backup");
+                            debug!("Unchanged since previous backup: {}", source_path.display());
+                            continue;
+                        }
+                    }
+                }
+
+                // Small files go into shared pack files (the manifest is
+                // the index) so restore is not syscall-bound on millions
+                // of tiny files; everything else is stored loose
+                if let (Some(pack_writer), Some(manifest)) = (packer.as_deref_mut(), backup_manifest.as_deref_mut()) {
+                    if pack_writer.should_pack(metadata.len()) {
+                        match pack_file_entry(&source_path, source_root, pack_writer, manifest) {
+                            Ok(()) => {
+                                result.success_count += 1;
+                                progress_counters.record_file(metadata.len());
+                                debug!("Packed file: {}", source_path.display());
+                            }
+                            Err(e) => {
+                                let error_msg = format!("Failed to pack file {}: {}", source_path.display(), e);
+                                warn!("{}", error_msg);
+                                result.record_error(error_msg);
+                                result.error_count += 1;
+                            }
+                        }
+                        continue;
+                    }
+                }
+
+                // Large compressible files are stored as <name>.zst and
+                // recorded in the manifest with their original size/hash
+                let compress = compression
+                    .is_some_and(|policy| policy.should_compress(&source_path, metadata.len()));
+                // Only the plain copy path can rename the source away; the
+                // compressing and hashing paths transform the content in
+                // flight. A consumed source is gone, so the torn-copy
+                // re-stat below must not run for it
+                let consumed = consume_source && !compress && backup_manifest.is_none();
+                let copy_outcome = if compress {
+                    let mut compressed_target = target_path.as_os_str().to_os_string();
+                    compressed_target.push(".zst");
+                    let compressed_target = PathBuf::from(compressed_target);
+                    store_file_compressed(
+                        &source_path,
+                        &compressed_target,
+                        source_root,
+                        metadata.len(),
+                        backup_manifest.as_deref_mut(),
+                    )
+                } else if let Some(manifest) = backup_manifest.as_deref_mut() {
+                    // Hash from the same buffers the copy writes, so the
+                    // manifest costs no second read of the source
+                    match copy_file_with_permissions_hashed(&source_path, &target_path) {
+                        Ok(hash) => {
+                            record_raw_manifest_entry(&source_path, source_root, metadata.len(), hash, manifest);
+                            Ok(())
+                        }
+                        Err(e) => Err(e),
+                    }
+                } else if consumed {
+                    move_file_with_permissions(&source_path, &target_path)
+                } else {
+                    copy_file_with_permissions(&source_path, &target_path)
+                };
+
+                match copy_outcome {
+                    Ok(_) => {
+                        result.success_count += 1;
+                        progress_counters.record_file(metadata.len());
+                        debug!("Copied file: {} -> {}", source_path.display(), target_path.display());
+
+                        // The user process is still running: re-stat the
+                        // source and flag copies whose source changed
+                        // mid-copy, as the backup may hold a torn copy
+                        if !consumed && source_changed_during_copy(&source_path, &metadata) {
+                            warn!("Source changed during backup: {}", source_path.display());
+                            if recopy_unstable {
+                                info!("Re-copying unstable file once: {}", source_path.display());
+                                let recopy = if compress {
+                                    let mut compressed_target = target_path.as_os_str().to_os_string();
+                                    compressed_target.push(".zst");
+                                    store_file_compressed(
+                                        &source_path,
+                                        &PathBuf::from(compressed_target),
+                                        source_root,
+                                        fs::metadata(&source_path).map(|m| m.len()).unwrap_or(metadata.len()),
+                                        backup_manifest.as_deref_mut(),
+                                    )
+                                } else if let Some(manifest) = backup_manifest.as_deref_mut() {
+                                    match copy_file_with_permissions_hashed(&source_path, &target_path) {
+                                        Ok(hash) => {
+                                            let size = fs::metadata(&source_path).map(|m| m.len()).unwrap_or(metadata.len());
+                                            record_raw_manifest_entry(&source_path, source_root, size, hash, manifest);
+                                            Ok(())
+                                        }
+                                        Err(e) => Err(e),
+                                    }
+                                } else {
+                                    copy_file_with_permissions(&source_path, &target_path)
+                                };
+                                if let Err(e) = recopy {
+                                    warn!("Re-copy of unstable file {} failed: {}", source_path.display(), e);
+                                }
+                            }
+                            if let Some(manifest) = backup_manifest.as_deref_mut() {
+                                let relative = source_path.strip_prefix(source_root).unwrap_or(&source_path);
+                                manifest.mark_unstable(relative);
+                            }
+                            result.unstable_files.push(source_path.clone());
+                        }
+                    }
+                    // Existed at listing time, gone by copy time: the
+                    // user process deleted it, which is benign
+                    Err(e) if e.downcast_ref::<std::io::Error>()
+                        .is_some_and(|io| io.kind() == std::io::ErrorKind::NotFound)
+                        && !source_path.exists() =>
+                    {
+                        debug!("Source vanished before copy: {}", source_path.display());
+                        result.record_skip("Source vanished before copy");
+                    }
+                    Err(e) => {
+                        let error_msg = format!("Failed to copy file {} to {} ({}): {}",
+                                                source_path.display(), target_path.display(),
+                                                path_diagnostics(&target_path, entry_depth), e);
+                        warn!("{}", error_msg);
+                        result.record_error(error_msg);
+                        result.error_count += 1;
+                    }
+                }
+            } else if metadata.file_type().is_symlink() {
+                if !capabilities.symlinks {
+                    // The probe already warned once for the whole run
+                    debug!("Destination does not support symlinks, skipping: {}", source_path.display());
+                    result.record_skip("Symlinks unsupported by destination");
+                    continue;
+                }
+                // Handle symlinks
+                match copy_symlink(&source_path, &target_path) {
+                    Ok(_) => {
+                        result.success_count += 1;
+                        progress_counters.record_file(0);
+                        debug!("Copied symlink: {} -> {}", source_path.display(), target_path.display());
+                    }
+                    Err(e) => {
+                        let error_msg = format!("Failed to copy symlink {} to {}: {}", source_path.display(), target_path.display(), e);
+                        warn!("{}", error_msg);
+                        result.record_error(error_msg);
+                        result.error_count += 1;
+                    }
+                }
+            } else {
+                // Skip special files (devices, pipes, etc.)
+                debug!("Skipping special file: {}", source_path.display());
+                result.record_skip("Special file");
+            }
+
+            // Check the budget periodically
+            if deadline.expired() {
+                result.record_error("Operation timed out".to_string());
+                result.error_count += 1;
+                return Err(anyhow::anyhow!("Transfer operation timed out"));
+            }
+        }
+    }
+
+    // Mirror directory metadata deepest-first (reverse BFS order), as
+    // rsync -a does once it has finished a directory's children
+    for (source_dir, target_dir) in completed_dirs.iter().rev() {
+        if let Err(e) = apply_directory_metadata(source_dir, target_dir) {
+            debug!("Failed to apply directory metadata to {}: {}", target_dir.display(), e);
+            result.dir_metadata_errors += 1;
+        }
+    }
+
+    result.cloned_files = (reflink::files_cloned() - cloned_before) as usize;
+    if result.cloned_files > 0 {
+        info!("CoW clones: {} cloned, {} byte-copied",
+              result.cloned_files,
+              result.success_count.saturating_sub(result.cloned_files));
+    }
+
+    Ok(())
+}
+
+/// Mirror a source directory's mode, ownership and mtime onto its target
+fn apply_directory_metadata(source: &Path, target: &Path) -> Result<()> {
+    let metadata = fs::metadata(source)?;
+    fs::set_permissions(target, metadata.permissions())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::os::unix::fs::chown(target, Some(metadata.uid()), Some(metadata.gid()))?;
+    }
+    let mtime = filetime::FileTime::from_last_modification_time(&metadata);
+    filetime::set_file_mtime(target, mtime)?;
+    Ok(())
+}
+
+/// Check if a path should be excluded based on mount points
+fn is_path_excluded(file_path: &Path, source_root: &Path, mounts: &mount_index::MountIndex) -> bool {
+    // Get the path relative to source root to check against mounted paths
+    if let Ok(relative_path) = file_path.strip_prefix(source_root) {
+        let absolute_path = PathBuf::from("/").join(relative_path);
+
+        // One trie walk covers the path and all of its ancestors
+        if mounts.covers(&absolute_path) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Copy a file preserving permissions and metadata
+fn copy_file_with_permissions(source: &Path, target: &Path) -> Result<()> {
+    // Create parent directory if needed (race-safe across parallel workers)
+    if let Some(parent) = target.parent() {
+        dir_cache::ensure_dir_exists(parent)
+            .with_context(|| format!("Failed to create parent directory for: {}", target.display()))?;
+    }
+    
+    // CoW mode: clone extents instead of copying bytes; anything the
+    // kernel refuses (EOPNOTSUPP, EXDEV) falls through to the byte copy
+    let cloned = reflink::cow_enabled()
+        && match reflink::try_reflink(source, target) {
+            Ok(()) => {
+                reflink::note_clone();
+                true
+            }
+            Err(e) => {
+                debug!("Reflink unavailable for {} ({}); copying bytes", target.display(), e);
+                false
+            }
+        };
+
+    // Copy the file, bypassing the page cache for large files when the
+    // direct I/O policy is installed
+    if !cloned {
+        if let Some(policy) = direct_io::installed_policy() {
+            direct_io::copy_file_with_policy(source, target, &policy)?;
+        } else {
+            fs::copy(source, target)
+                .with_context(|| format!("Failed to copy file from {} to {}", source.display(), target.display()))?;
+        }
+    }
+    
+    // Copy permissions
+    #[cfg(unix)]
+    {
+        let metadata = source.metadata()
+            .with_context(|| format!("Failed to get metadata for: {}", source.display()))?;
+        let permissions = metadata.permissions();
+        fs::set_permissions(target, permissions)
+            .with_context(|| format!("Failed to set permissions for: {}", target.display()))?;
+    }
+    
+    Ok(())
+}
+
+/// Move a file into the backup preserving permissions, preferring a
+/// rename when source and target parent share a device; falls back to
+/// copy-plus-unlink across devices or when the rename is refused (a bind
+/// mount boundary reports the same st_dev but still fails with EXDEV)
+fn move_file_with_permissions(source: &Path, target: &Path) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        dir_cache::ensure_dir_exists(parent)
+            .with_context(|| format!("Failed to create parent directory for: {}", target.display()))?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let same_device = source
+            .symlink_metadata()
+            .ok()
+            .zip(target.parent().and_then(|p| p.metadata().ok()))
+            .is_some_and(|(s, t)| s.dev() == t.dev());
+        if same_device {
+            match fs::rename(source, target) {
+                Ok(()) => return Ok(()),
+                Err(e) => debug!("Rename {} -> {} refused ({}); copying instead",
+                                 source.display(), target.display(), e),
+            }
+        }
+    }
+
+    copy_file_with_permissions(source, target)?;
+    fs::remove_file(source)
+        .with_context(|| format!("Failed to remove consumed source file: {}", source.display()))?;
+    Ok(())
+}
+
+/// Copy a file preserving permissions while hashing the content from the
+/// same buffers used for writing; returns the blake3 hex digest so the
+/// manifest build costs no second read. Direct I/O copies use their own
+/// aligned buffers, so that path falls back to hashing the stored copy.
+fn copy_file_with_permissions_hashed(source: &Path, target: &Path) -> Result<String> {
+    if let Some(parent) = target.parent() {
+        dir_cache::ensure_dir_exists(parent)
+            .with_context(|| format!("Failed to create parent directory for: {}", target.display()))?;
+    }
+
+    let hash = if reflink::cow_enabled() && reflink::try_reflink(source, target).is_ok() {
+        // The clone writes no bytes to hash in flight, but reading the
+        // stored clone back costs the same single read the copy saved
+        reflink::note_clone();
+        manifest::hash_file_contents(target)?
+    } else if let Some(policy) = direct_io::installed_policy() {
+        direct_io::copy_file_with_policy(source, target, &policy)?;
+        manifest::hash_file_contents(target)?
+    } else {
+        let (_, hash) = optimized_io::copy_file_hashed(source, target)
+            .with_context(|| format!("Failed to copy file from {} to {}", source.display(), target.display()))?;
+        hash
+    };
+
+    // Copy permissions
+    #[cfg(unix)]
+    {
+        let metadata = source.metadata()
+            .with_context(|| format!("Failed to get metadata for: {}", source.display()))?;
+        fs::set_permissions(target, metadata.permissions())
+            .with_context(|| format!("Failed to set permissions for: {}", target.display()))?;
+    }
+
+    Ok(hash)
+}
+
+/// Copy a symlink
+fn copy_symlink(source: &Path, target: &Path) -> Result<()> {
+    let link_target = fs::read_link(source)
+        .with_context(|| format!("Failed to read symlink: {}", source.display()))?;
+    
+    // Remove target if it exists
+    if target.exists() {
+        fs::remove_file(target)
+            .with_context(|| format!("Failed to remove existing target: {}", target.display()))?;
+    }
+    
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&link_target, target)
+        .with_context(|| format!("Failed to create symlink from {} to {}", link_target.display(), target.display()))?;
+    
+    #[cfg(windows)]
+    {
+        if link_target.is_dir() {
+            std::os::windows::fs::symlink_dir(&link_target, target)
+                .with_context(|| format!("Failed to create directory symlink from {} to {}", link_target.display(), target.display()))?;
+        } else {
+            std::os::windows::fs::symlink_file(&link_target, target)
+                .with_context(|| format!("Failed to create file symlink from {} to {}", link_target.display(), target.display()))?;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Relative rsync exclude patterns for mounts inside `source`, with child
+/// mounts collapsed into their already-excluded parents and glob
+/// metacharacters escaped so the paths match literally
+fn mount_exclusion_patterns(source: &Path, mounted_paths: &HashSet<PathBuf>) -> Vec<String> {
+    let mut inside: Vec<&PathBuf> = mounted_paths
+        .iter()
+        .filter(|mount| mount.strip_prefix(source).is_ok())
+        .collect();
+    inside.sort();
+
+    let mut kept: Vec<&PathBuf> = Vec::new();
+    let mut patterns = Vec::new();
+    for mount in inside {
+        let relative = mount.strip_prefix(source).unwrap();
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        // Sorted order guarantees a parent mount is seen before its children
+        if kept.iter().any(|parent| mount.starts_with(parent)) {
+            continue;
+        }
+        kept.push(mount);
+
+        // Build the pattern component by component so each segment is
+        // escaped and anchored, regardless of how the caller spelled the
+        // source path (trailing slash or not)
+        let mut pattern = String::new();
+        for component in relative.components() {
+            pattern.push('/');
+            pattern.push_str(&escape_rsync_pattern(&component.as_os_str().to_string_lossy()));
+        }
+        // Mount points are directories; the trailing slash keeps the
+        // pattern from swallowing a plain file of the same name
+        pattern.push('/');
+        patterns.push(pattern);
+    }
+    patterns
+}
+
+/// Backslash-escape rsync wildcard characters so a mount path containing
+/// `*`, `?` or brackets is excluded as a literal name
+pub(crate) fn escape_rsync_pattern(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Write one exclude pattern per line to a temp file for rsync's
+/// `--exclude-from`; the file is removed when the returned handle drops
+fn write_exclude_file(patterns: &[String]) -> Result<tempfile::NamedTempFile> {
+    use std::io::Write;
+    let mut file = tempfile::NamedTempFile::new()
+        .context("Failed to create rsync exclude file")?;
+    for pattern in patterns {
+        writeln!(file, "{}", pattern)
+            .context("Failed to write rsync exclude file")?;
+    }
+    file.flush().context("Failed to flush rsync exclude file")?;
+    Ok(file)
+}
+
+/// Transfer data excluding mounted paths using rsync (fallback)
+fn transfer_data_with_exclusions_rsync(source: &Path, target: &Path, deadline: Deadline, mounted_paths: &HashSet<PathBuf>) -> Result<TransferResult> {
+    let mut result = TransferResult {
+        success_count: 0,
         error_count: 0,
         skipped_count: 0,
         errors: Vec::new(),
+        skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
     };
 
-    info!("Using rsync with mount exclusions from {} to {}", source.display(), target.display());
+    info!("Using rsync with mount exclusions from {} to {} (remaining budget: {:?})", 
+          source.display(), target.display(), deadline.remaining());
+
+    if deadline.expired() {
+        result.record_error("Operation timed out".to_string());
+        result.error_count += 1;
+        return Ok(result);
+    }
     
     let mut cmd = Command::new("timeout");
-    cmd.arg(timeout.to_string())
-       .arg("rsync")
+    cmd.arg(deadline.remaining_secs().max(1).to_string())
+       .arg(rsync_program())
        .arg("-av")
        .arg("--delete")
        .arg("--ignore-errors")
        .arg("--force")
        .arg("--stats");
-    
-    // Add exclusions for mounted paths that are within the source directory
-    for mount_path in mounted_paths {
-        // Only exclude if mount is within source directory
-        if let Ok(relative_path) = mount_path.strip_prefix(source) {
-            let exclude_pattern = format!("/{}", relative_path.display());
-            cmd.arg("--exclude").arg(&exclude_pattern);
-            info!("Excluding mounted path: {}", exclude_pattern);
+    if rsync_itemize::enabled() {
+        cmd.arg("-i").arg(format!("--out-format={}", rsync_itemize::OUT_FORMAT));
+    }
+
+    // User-configured globs; rsync gives includes precedence only when
+    // they come before the excludes on the command line
+    if let Some(filter) = filter::installed_filter() {
+        for pattern in filter.include_patterns() {
+            cmd.arg("--include").arg(pattern);
+        }
+        for pattern in filter.exclude_patterns() {
+            cmd.arg("--exclude").arg(pattern);
         }
     }
-    
+
+    // This tool's own artifacts are never transferred
+    for pattern in INTERNAL_ARTIFACT_PATTERNS {
+        cmd.arg("--exclude").arg(pattern);
+    }
+
+    // Mount exclusions go through --exclude-from: nodes with hundreds of
+    // CSI/projected-volume mounts would otherwise push one --exclude per
+    // mount past ARG_MAX and the spawn would fail
+    let exclude_patterns = mount_exclusion_patterns(source, mounted_paths);
+    let _exclude_file = if exclude_patterns.is_empty() {
+        None
+    } else {
+        let file = write_exclude_file(&exclude_patterns)?;
+        info!("Excluding {} mounted paths via {}", exclude_patterns.len(), file.path().display());
+        cmd.arg(format!("--exclude-from={}", file.path().display()));
+        Some(file)
+    };
+
+    // rsync has no age filter of its own, so --exclude-older-than is
+    // honored by enumerating the stale files up front into a second
+    // exclude file
+    let _age_exclude_file = match age::installed_cutoff() {
+        Some(cutoff) => {
+            let stale_patterns = age::stale_relative_paths(source, cutoff);
+            if stale_patterns.is_empty() {
+                None
+            } else {
+                let file = write_exclude_file(&stale_patterns)?;
+                info!("Excluding {} files older than the age cutoff via {}",
+                      stale_patterns.len(), file.path().display());
+                cmd.arg(format!("--exclude-from={}", file.path().display()));
+                Some(file)
+            }
+        }
+        None => None,
+    };
+
     cmd.arg(format!("{}/", source.display()))
        .arg(format!("{}/", target.display()));
 
@@ -788,19 +3196,22 @@ fn transfer_data_with_exclusions_rsync(source: &Path, target: &Path, timeout: u6
     let stderr = String::from_utf8_lossy(&output.stderr);
     
     debug!("Rsync stdout: {}", stdout);
-    
+    if rsync_itemize::enabled() {
+        result.itemized_changes = rsync_itemize::parse_itemized(&stdout);
+    }
+
     if output.status.success() {
         info!("Rsync transfer with mount exclusions completed successfully");
         result.success_count = 1;
     } else {
         match output.status.code() {
             Some(124) => {
-                result.errors.push("Operation timed out".to_string());
+                result.record_error("Operation timed out".to_string());
                 result.error_count += 1;
             }
             Some(code) => {
                 warn!("Rsync transfer completed with exit code {}: {}", code, stderr);
-                result.errors.push(format!("Rsync exit code {}: {}", code, stderr));
+                result.record_error(format!("Rsync exit code {}: {}", code, stderr));
                 if code < 12 { // rsync exit codes < 12 are usually warnings
                     result.success_count = 1;
                 } else {
@@ -808,11 +3219,1069 @@ fn transfer_data_with_exclusions_rsync(source: &Path, target: &Path, timeout: u6
                 }
             }
             None => {
-                result.errors.push("Rsync was terminated by signal".to_string());
+                result.record_error("Rsync was terminated by signal".to_string());
                 result.error_count += 1;
             }
         }
     }
 
     Ok(result)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_deep_tree_native_transfer_round_trip() {
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+
+        // Build a tree nested well beyond any recursion comfort zone
+        let mut deep = source.clone();
+        for i in 0..80 {
+            deep = deep.join(format!("d{}", i));
+        }
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("leaf.txt"), b"deep content").unwrap();
+
+        let mounted = HashSet::new();
+        let result = transfer_data_with_exclusions_native(&source, &target, Deadline::from_secs(300), &mounted).unwrap();
+
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert_eq!(result.success_count, 1);
+
+        let mut restored = target;
+        for i in 0..80 {
+            restored = restored.join(format!("d{}", i));
+        }
+        assert_eq!(fs::read(restored.join("leaf.txt")).unwrap(), b"deep content");
+    }
+
+    #[test]
+    fn test_cow_transfer_clones_on_reflink_filesystems() {
+        let temp_dir = TempDir::new().unwrap();
+        if !reflink::probe_reflink_support(temp_dir.path()) {
+            eprintln!("Skipping CoW transfer test: filesystem has no reflink support");
+            return;
+        }
+
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(source.join("work")).unwrap();
+        fs::write(source.join("work/a.bin"), b"cow payload a").unwrap();
+        fs::write(source.join("work/b.bin"), b"cow payload b").unwrap();
+
+        let mounted = HashSet::new();
+        reflink::install_cow(true);
+        let result = transfer_data_with_exclusions_native(&source, &target, Deadline::from_secs(300), &mounted);
+        reflink::install_cow(false);
+        let result = result.unwrap();
+
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert_eq!(result.success_count, 2);
+        // Both files sit on the probed filesystem, so both must have
+        // been stored as clones rather than byte copies
+        assert!(result.cloned_files >= 2, "cloned_files: {}", result.cloned_files);
+        assert_eq!(fs::read(target.join("work/a.bin")).unwrap(), b"cow payload a");
+        assert_eq!(fs::read(target.join("work/b.bin")).unwrap(), b"cow payload b");
+    }
+
+    #[test]
+    fn test_age_cutoff_skips_only_stale_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(source.join("cache")).unwrap();
+        fs::write(source.join("cache/stale.bin"), b"decades old").unwrap();
+        fs::write(source.join("cache/fresh.bin"), b"still warm").unwrap();
+        use clock::Clock as _;
+        let forty_days_ago = clock::SYSTEM_CLOCK.unix_seconds() as i64 - 40 * 86400;
+        filetime::set_file_mtime(
+            source.join("cache/stale.bin"),
+            filetime::FileTime::from_unix_time(forty_days_ago, 0),
+        ).unwrap();
+
+        age::install_max_age(std::time::Duration::from_secs(30 * 86400));
+        let result = transfer_data_with_exclusions_native(
+            &source, &target, Deadline::from_secs(300), &HashSet::new());
+        age::clear_max_age();
+        let result = result.unwrap();
+
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.skip_reason_counts.get("excluded"), Some(&1));
+        assert_eq!(fs::read(target.join("cache/fresh.bin")).unwrap(), b"still warm");
+        assert!(!target.join("cache/stale.bin").exists());
+    }
+
+    #[test]
+    fn test_dir_lock_removed_on_success_and_stale_lock_taken_over() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("shared");
+        let lock_path = temp_dir.path().join("shared.lock");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        // Happy path: the lock file is gone once creation returns
+        create_directory_with_lock(&dir).unwrap();
+        assert!(dir.exists());
+        assert!(!lock_path.exists());
+
+        // A crashed holder leaves a lock with a dead pid; the next run
+        // takes it over instead of failing
+        fs::write(&lock_path, format!(r#"{{"pid":{},"created_at":{}}}"#, u32::MAX, now)).unwrap();
+        create_directory_with_lock(&dir).unwrap();
+        assert!(!lock_path.exists());
+
+        // A lock backed by a live process is respected
+        fs::write(&lock_path, format!(r#"{{"pid":{},"created_at":{}}}"#, std::process::id(), now)).unwrap();
+        let err = create_directory_with_lock(&dir).unwrap_err();
+        assert!(err.to_string().contains("locked by another process"), "unexpected error: {}", err);
+        assert!(lock_path.exists());
+    }
+
+    #[test]
+    fn test_sweeper_removes_dead_and_aged_locks_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        fs::write(temp_dir.path().join("dead.lock"),
+                  format!(r#"{{"pid":{},"created_at":{}}}"#, u32::MAX, now)).unwrap();
+        fs::write(temp_dir.path().join("aged.lock"),
+                  format!(r#"{{"pid":{},"created_at":0}}"#, std::process::id())).unwrap();
+        fs::write(temp_dir.path().join("live.lock"),
+                  format!(r#"{{"pid":{},"created_at":{}}}"#, std::process::id(), now)).unwrap();
+        fs::write(temp_dir.path().join("data.txt"), b"not a lock").unwrap();
+
+        let swept = sweep_stale_locks(temp_dir.path(), STALE_LOCK_MAX_AGE_SECS).unwrap();
+        assert_eq!(swept, 2);
+        assert!(!temp_dir.path().join("dead.lock").exists());
+        assert!(!temp_dir.path().join("aged.lock").exists());
+        assert!(temp_dir.path().join("live.lock").exists());
+        assert!(temp_dir.path().join("data.txt").exists());
+    }
+
+    #[test]
+    fn test_rsync_itemize_records_changes_from_stub_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&target).unwrap();
+
+        // Stub rsync printing the itemized listing a real transfer would;
+        // the override keeps it off PATH so concurrent tests never see it
+        let stub = temp_dir.path().join("rsync-stub");
+        fs::write(&stub, "#!/bin/sh\n\
+                          echo 'sending incremental file list'\n\
+                          echo '>f+++++++++ notes.txt'\n\
+                          echo '.f..t...... stale.bin'\n\
+                          echo 'sent 123 bytes  received 46 bytes'\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        std::env::set_var("SESSION_MANAGER_RSYNC", &stub);
+        rsync_itemize::install(true);
+        let result = transfer_data_rsync(&source, &target, 60);
+        rsync_itemize::install(false);
+        std::env::remove_var("SESSION_MANAGER_RSYNC");
+
+        let result = result.unwrap();
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        let pairs: Vec<(&str, &str)> = result.itemized_changes
+            .iter()
+            .map(|c| (c.flags.as_str(), c.path.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![
+            (">f+++++++++", "notes.txt"),
+            (".f..t......", "stale.bin"),
+        ]);
+    }
+
+    #[test]
+    fn test_consuming_transfer_moves_files_and_refuses_live_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("session");
+        let target = temp_dir.path().join("backup");
+        fs::create_dir_all(source.join("work")).unwrap();
+        fs::write(source.join("notes.txt"), b"move me").unwrap();
+        fs::write(source.join("work").join("model.bin"), b"weights").unwrap();
+
+        // A process still working inside the source marks it as live
+        let mut resident = std::process::Command::new("sleep")
+            .arg("30")
+            .current_dir(&source)
+            .spawn()
+            .unwrap();
+        let err = transfer_data_consuming_source(
+            &source, &target, Deadline::from_secs(60), false, &HashSet::new(),
+        ).unwrap_err();
+        assert!(err.to_string().contains("working directory"), "unexpected error: {}", err);
+        resident.kill().unwrap();
+        resident.wait().unwrap();
+
+        // With the source idle the transfer renames files away: the backup
+        // holds the content and the session files are gone
+        let result = transfer_data_consuming_source(
+            &source, &target, Deadline::from_secs(60), false, &HashSet::new(),
+        ).unwrap();
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert_eq!(result.success_count, 2);
+        assert_eq!(fs::read(target.join("notes.txt")).unwrap(), b"move me");
+        assert_eq!(fs::read(target.join("work").join("model.bin")).unwrap(), b"weights");
+        assert!(!source.join("notes.txt").exists());
+        assert!(!source.join("work").join("model.bin").exists());
+    }
+
+    #[test]
+    fn test_single_file_source_copies_to_file_or_directory_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("report.log");
+        fs::write(&source, b"single file payload").unwrap();
+
+        // file -> file: a target with an extension is the destination file
+        let file_target = temp_dir.path().join("out").join("copy.log");
+        let result = transfer_data_with_deadline(&source, &file_target, Deadline::from_secs(60)).unwrap();
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert_eq!(fs::read(&file_target).unwrap(), b"single file payload");
+
+        // file -> existing directory: copied under the source's own name
+        let dir_target = temp_dir.path().join("existing");
+        fs::create_dir_all(&dir_target).unwrap();
+        let result = transfer_data_with_deadline(&source, &dir_target, Deadline::from_secs(60)).unwrap();
+        assert_eq!(result.success_count, 1);
+        assert_eq!(fs::read(dir_target.join("report.log")).unwrap(), b"single file payload");
+    }
+
+    #[test]
+    fn test_into_result_aggregates_errors_for_propagation() {
+        let clean = TransferResult {
+            success_count: 10,
+            ..Default::default()
+        };
+        assert!(clean.into_result().is_ok());
+
+        let mut failed = TransferResult {
+            success_count: 2,
+            ..Default::default()
+        };
+        failed.record_error("Failed to copy /root/a: device busy".to_string());
+        failed.record_error("Failed to copy /root/a: device busy".to_string());
+        for i in 0..4 {
+            failed.record_error(format!("Failed to copy /root/b{}: permission denied", i));
+        }
+        failed.error_count = 6;
+
+        let err = failed.into_result().unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("6 errors"), "{}", message);
+        assert!(message.contains("2 files transferred"), "{}", message);
+        assert!(message.contains("device busy (x2)"), "{}", message);
+        // Only the first few distinct messages are inlined; the rest are
+        // summarized as a count
+        assert!(message.contains("+2 more distinct errors"), "{}", message);
+    }
+
+    #[test]
+    fn test_pinned_native_strategy_transfers_and_records_decision() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("notes.txt"), b"keep").unwrap();
+
+        // The backup dir exists before the transfer starts, as in the binary
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&target).unwrap();
+        let result = transfer_data_with_strategy(
+            &source, &target, Deadline::from_secs(300), false, &HashSet::new(),
+            strategy::StrategyChoice::Pinned(strategy::TransferStrategy::Native)).unwrap();
+
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert!(target.join("notes.txt").exists());
+        let decision = result.strategy_decision.expect("decision recorded");
+        assert!(decision.pinned);
+        assert_eq!(decision.strategy, strategy::TransferStrategy::Native);
+        assert!(decision.sample_timings.is_empty());
+    }
+
+    #[test]
+    fn test_backup_to_tar_streams_only_non_excluded_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(source.join("work")).unwrap();
+        fs::create_dir_all(source.join("mnt")).unwrap();
+        fs::write(source.join("notes.txt"), b"keep").unwrap();
+        fs::write(source.join("work").join("data.bin"), b"keep too").unwrap();
+        fs::write(source.join("work").join("env.lock"), b"drop").unwrap();
+        fs::write(source.join("mnt").join("remote.txt"), b"drop").unwrap();
+
+        // Exclusions are container-absolute, as produced by mount detection
+        let mut excluded = HashSet::new();
+        excluded.insert(PathBuf::from("/mnt"));
+
+        let mut archive_bytes = Vec::new();
+        let result = backup_to_tar(&source, &mut archive_bytes, Deadline::from_secs(300), &excluded).unwrap();
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert_eq!(result.success_count, 2);
+        assert_eq!(result.skipped_count, 2);
+
+        let mut files: Vec<String> = tar::Archive::new(archive_bytes.as_slice())
+            .entries().unwrap()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.header().entry_type().is_file())
+            .map(|entry| entry.path().unwrap().display().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["notes.txt", "work/data.bin"]);
+    }
+
+    #[test]
+    fn test_internal_artifacts_are_never_transferred() {
+        for name in ["state.backup_meta", "poetry.lock", "session.cleanup_backup_17", ".rsync-partial"] {
+            assert!(is_internal_artifact(std::ffi::OsStr::new(name)), "{}", name);
+        }
+        assert!(!is_internal_artifact(std::ffi::OsStr::new("notes.txt")));
+        assert!(!is_internal_artifact(std::ffi::OsStr::new("lockfile")));
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(source.join("work")).unwrap();
+        fs::write(source.join("notes.txt"), b"keep").unwrap();
+        fs::write(source.join("state.backup_meta"), b"drop").unwrap();
+        fs::write(source.join("work").join("env.lock"), b"drop").unwrap();
+        fs::write(source.join("work").join(".rsync-partial"), b"drop").unwrap();
+
+        let target = temp_dir.path().join("dst");
+        let result = transfer_data_with_exclusions_native(
+            &source, &target, Deadline::from_secs(300), &HashSet::new()).unwrap();
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+
+        assert!(target.join("notes.txt").exists());
+        assert!(!target.join("state.backup_meta").exists());
+        assert!(!target.join("work").join("env.lock").exists());
+        assert!(!target.join("work").join(".rsync-partial").exists());
+        assert_eq!(result.skipped_count, 3);
+    }
+
+    #[test]
+    fn test_resolve_session_fs_dir_handles_both_layouts() {
+        let temp = TempDir::new().unwrap();
+        let sessions = temp.path();
+
+        // Newer layout nests the content under the snapshot id
+        let new_layout = sessions.join("a1b2").join("c3d4").join("143").join("fs");
+        fs::create_dir_all(&new_layout).unwrap();
+        let resolved = resolve_session_fs_dir(sessions, "a1b2", "c3d4", Some("143")).unwrap();
+        assert_eq!(resolved, new_layout);
+
+        // A stale snapshot_id falls back to the legacy fs path
+        let legacy = sessions.join("a1b2").join("ffff").join("fs");
+        fs::create_dir_all(&legacy).unwrap();
+        let resolved = resolve_session_fs_dir(sessions, "a1b2", "ffff", Some("999")).unwrap();
+        assert_eq!(resolved, legacy);
+
+        // Mappings without a snapshot_id only know the legacy layout
+        let resolved = resolve_session_fs_dir(sessions, "a1b2", "ffff", None).unwrap();
+        assert_eq!(resolved, legacy);
+    }
+
+    #[test]
+    fn test_resolve_session_fs_dir_lists_contents_on_mismatch() {
+        let temp = TempDir::new().unwrap();
+        let base = temp.path().join("a1b2").join("c3d4");
+        // Neither candidate layout exists, only an unexpected id subdir
+        fs::create_dir_all(base.join("251")).unwrap();
+
+        let err = resolve_session_fs_dir(temp.path(), "a1b2", "c3d4", Some("143"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("143/fs"), "{}", err);
+        assert!(err.contains("directory contains: 251"), "{}", err);
+    }
+
+    #[test]
+    fn test_native_transfer_preserves_directory_modes_and_mtimes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let nested = source.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("f.txt"), b"payload").unwrap();
+
+        fs::set_permissions(&nested, fs::Permissions::from_mode(0o750)).unwrap();
+        fs::set_permissions(source.join("a"), fs::Permissions::from_mode(0o711)).unwrap();
+        // Set mtimes last: creating children bumps the parent's mtime
+        let stamp = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_mtime(&nested, stamp).unwrap();
+        filetime::set_file_mtime(source.join("a"), stamp).unwrap();
+
+        let target = temp_dir.path().join("dst");
+        let result = transfer_data_with_exclusions_native(
+            &source, &target, Deadline::from_secs(300), &HashSet::new()).unwrap();
+        assert_eq!(result.error_count, 0, "errors: {:?}", result.errors);
+        assert_eq!(result.dir_metadata_errors, 0);
+
+        let restored_b = fs::metadata(target.join("a").join("b")).unwrap();
+        assert_eq!(restored_b.permissions().mode() & 0o777, 0o750);
+        assert_eq!(filetime::FileTime::from_last_modification_time(&restored_b), stamp);
+
+        let restored_a = fs::metadata(target.join("a")).unwrap();
+        assert_eq!(restored_a.permissions().mode() & 0o777, 0o711);
+        assert_eq!(filetime::FileTime::from_last_modification_time(&restored_a), stamp);
+    }
+
+    #[test]
+    fn test_mount_exclusions_dedup_and_stay_off_the_command_line() {
+        let source = Path::new("/data/session");
+        let mut mounted = HashSet::new();
+        for i in 0..1000 {
+            mounted.insert(source.join(format!("volumes/vol-{:04}", i)));
+            // Child mounts are covered by the excluded parent
+            mounted.insert(source.join(format!("volumes/vol-{:04}/nested", i)));
+        }
+        // Mounts outside the source tree are not rsync's problem
+        mounted.insert(PathBuf::from("/outside/elsewhere"));
+
+        let patterns = mount_exclusion_patterns(source, &mounted);
+        assert_eq!(patterns.len(), 1000);
+        assert!(patterns.iter().all(|p| !p.contains("nested")));
+        assert!(patterns.contains(&"/volumes/vol-0042/".to_string()));
+
+        let file = write_exclude_file(&patterns).unwrap();
+        let contents = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(contents.lines().count(), 1000);
+
+        // The command line carries one short --exclude-from argument
+        // instead of a thousand --exclude pairs
+        let arg = format!("--exclude-from={}", file.path().display());
+        assert!(arg.len() < 256);
+
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists(), "exclude file should be cleaned up on drop");
+    }
+
+    #[test]
+    fn test_rsync_exclude_patterns_escape_glob_metacharacters() {
+        let source = Path::new("/mnt");
+        let mut mounted = HashSet::new();
+        mounted.insert(PathBuf::from("/mnt/data[1]"));
+        mounted.insert(PathBuf::from("/mnt/my volume"));
+        mounted.insert(PathBuf::from("/mnt/cache[1]/tmp*?"));
+
+        let patterns = mount_exclusion_patterns(source, &mounted);
+        assert_eq!(
+            patterns,
+            vec![
+                r"/cache\[1\]/tmp\*\?/".to_string(),
+                r"/data\[1\]/".to_string(),
+                "/my volume/".to_string(),
+            ]
+        );
+
+        // A trailing slash on the source must not change the anchoring
+        let slashed = mount_exclusion_patterns(Path::new("/mnt/"), &mounted);
+        assert_eq!(slashed, patterns);
+    }
+
+    #[test]
+    fn test_expired_deadline_cancels_native_transfer() {
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("file.txt"), b"content").unwrap();
+
+        let mounted = HashSet::new();
+        // Zero budget: the transfer must finalize as timed out without copying
+        let err = transfer_data_with_exclusions_native(&source, &target, Deadline::from_secs(0), &mounted);
+        assert!(err.is_err());
+        assert!(!target.join("file.txt").exists());
+    }
+
+    #[test]
+    fn test_container_name_exact_match() {
+        let matched = match_container_name("inference", "inference", "nb-test-0", DEFAULT_CONTAINER_NAME_TRANSFORMS).unwrap();
+        assert_eq!(matched.matched_name, "inference");
+        assert!(matched.applied_transform.is_none());
+        assert!(matched.alias_description().is_none());
+    }
+
+    #[test]
+    fn test_container_name_strip_pod_prefix() {
+        // Older manifests report "<pod>-<container>"
+        let matched = match_container_name("inference", "nb-test-0-inference", "nb-test-0", DEFAULT_CONTAINER_NAME_TRANSFORMS).unwrap();
+        assert_eq!(matched.matched_name, "inference");
+        assert_eq!(matched.applied_transform, Some(ContainerNameTransform::StripPodNamePrefix));
+        assert!(matched.alias_description().is_some());
+    }
+
+    #[test]
+    fn test_container_name_strip_ordinal_suffix() {
+        let matched = match_container_name("inference", "inference-0", "nb-test-0", DEFAULT_CONTAINER_NAME_TRANSFORMS).unwrap();
+        assert_eq!(matched.matched_name, "inference");
+        assert_eq!(matched.applied_transform, Some(ContainerNameTransform::StripOrdinalSuffix));
+    }
+
+    #[test]
+    fn test_container_name_transform_order_and_misses() {
+        // No transform makes these match
+        assert!(match_container_name("inference", "training", "nb-test-0", DEFAULT_CONTAINER_NAME_TRANSFORMS).is_none());
+        // A non-numeric suffix is not an ordinal
+        assert!(match_container_name("inference", "inference-main", "nb-test-0", DEFAULT_CONTAINER_NAME_TRANSFORMS).is_none());
+        // An empty transform list only allows exact matches
+        assert!(match_container_name("inference", "inference-0", "nb-test-0", &[]).is_none());
+    }
+
+    #[test]
+    fn test_apply_container_name_transform_edge_cases() {
+        // Prefix strip requires the "-" separator and a non-empty remainder
+        assert_eq!(apply_container_name_transform("pod-c", "pod", ContainerNameTransform::StripPodNamePrefix), Some("c".to_string()));
+        assert_eq!(apply_container_name_transform("pod-", "pod", ContainerNameTransform::StripPodNamePrefix), None);
+        assert_eq!(apply_container_name_transform("podc", "pod", ContainerNameTransform::StripPodNamePrefix), None);
+        // Ordinal strip requires an all-digit suffix
+        assert_eq!(apply_container_name_transform("c-12", "pod", ContainerNameTransform::StripOrdinalSuffix), Some("c".to_string()));
+        assert_eq!(apply_container_name_transform("c-1a", "pod", ContainerNameTransform::StripOrdinalSuffix), None);
+        assert_eq!(apply_container_name_transform("c", "pod", ContainerNameTransform::StripOrdinalSuffix), None);
+    }
+
+    #[test]
+    fn test_backup_nesting_rejects_backup_inside_session() {
+        let temp = TempDir::new().unwrap();
+        let session = temp.path().join("session");
+        let backup = session.join("fs").join("backup");
+        fs::create_dir_all(&backup).unwrap();
+
+        let err = check_backup_nesting(&session, &backup).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("nested inside each other"));
+        assert!(message.contains(session.canonicalize().unwrap().to_str().unwrap()));
+    }
+
+    #[test]
+    fn test_backup_nesting_rejects_session_inside_backup() {
+        let temp = TempDir::new().unwrap();
+        let backup = temp.path().join("backup");
+        let session = backup.join("session");
+        fs::create_dir_all(&session).unwrap();
+
+        assert!(check_backup_nesting(&session, &backup).is_err());
+    }
+
+    #[test]
+    fn test_backup_nesting_allows_siblings_and_outside() {
+        let temp = TempDir::new().unwrap();
+        let session = temp.path().join("session");
+        let sibling = temp.path().join("backup");
+        fs::create_dir_all(&session).unwrap();
+        fs::create_dir_all(&sibling).unwrap();
+        check_backup_nesting(&session, &sibling).unwrap();
+
+        // A backup directory that does not exist yet is still checked via
+        // its nearest existing ancestor
+        let outside = temp.path().join("not-created-yet").join("backup");
+        check_backup_nesting(&session, &outside).unwrap();
+    }
+
+    #[test]
+    fn test_transfer_errors_deduplicate_with_counts() {
+        let mut result = TransferResult {
+            success_count: 0,
+            error_count: 0,
+            skipped_count: 0,
+            errors: Vec::new(),
+            skip_reason_counts: HashMap::new(),
+        unstable_files: Vec::new(),
+        at_risk_files: Vec::new(),
+        capture_mode: None,
+        link_phase_duration: None,
+        dir_metadata_errors: 0,
+        trimmed_for_quota: Vec::new(),
+        itemized_changes: Vec::new(),
+        strategy_decision: None,
+        priority_tier_reached: None,
+        cancelled: false,
+        cloned_files: 0,
+        };
+
+        for _ in 0..50_000 {
+            result.record_error("Input/output error".to_string());
+        }
+        for _ in 0..50_000 {
+            result.record_error("Stale file handle".to_string());
+        }
+
+        assert_eq!(result.errors.len(), 2);
+        assert_eq!(result.errors[0].message, "Input/output error");
+        assert_eq!(result.errors[0].count, 50_000);
+        assert_eq!(result.errors[1].count, 50_000);
+    }
+
+    #[test]
+    fn test_cache_capacity_clamps_zero() {
+        // A capacity of 0 must clamp to 1 instead of panicking
+        let effective = init_path_mapping_cache(0).unwrap();
+        assert_eq!(effective, 1);
+
+        // Restore the default capacity for other tests
+        let effective = init_path_mapping_cache(DEFAULT_PATH_MAPPING_CACHE_CAPACITY).unwrap();
+        assert_eq!(effective, DEFAULT_PATH_MAPPING_CACHE_CAPACITY);
+    }
+
+    #[test]
+    fn test_compression_transfer_round_trip_with_manifest() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let target = temp.path().join("backup");
+        fs::create_dir_all(source.join("logs")).unwrap();
+
+        // Large compressible file, small file, and large incompressible file
+        let log_contents = "step=1 loss=0.25\n".repeat(20_000);
+        fs::write(source.join("logs/train.log"), &log_contents).unwrap();
+        fs::write(source.join("notes.txt"), b"small").unwrap();
+        let archive_contents = vec![0xa5u8; 512 * 1024];
+        fs::write(source.join("model.tar.gz"), &archive_contents).unwrap();
+
+        let policy = compression::CompressionPolicy { min_size: 1024 };
+        let result = transfer_data_with_compression(
+            &source,
+            &target,
+            Deadline::from_secs(60),
+            &HashSet::new(),
+            &policy,
+            false,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.success_count, 3);
+        assert_eq!(result.error_count, 0);
+
+        // The compressible file is stored as .zst and shrinks
+        assert!(target.join("logs/train.log.zst").exists());
+        assert!(!target.join("logs/train.log").exists());
+        let stored_size = fs::metadata(target.join("logs/train.log.zst")).unwrap().len();
+        assert!(stored_size < log_contents.len() as u64 / 2);
+
+        // Small and incompressible files are stored raw
+        assert_eq!(fs::read(target.join("notes.txt")).unwrap(), b"small");
+        assert_eq!(fs::read(target.join("model.tar.gz")).unwrap(), archive_contents);
+
+        // The manifest records compression state and original hashes
+        let backup_manifest = manifest::BackupManifest::load(&target).unwrap().unwrap();
+        let log_entry = backup_manifest.get(Path::new("logs/train.log")).unwrap();
+        assert!(log_entry.compressed);
+        assert_eq!(log_entry.original_size, log_contents.len() as u64);
+        assert_eq!(
+            log_entry.original_hash,
+            blake3::hash(log_contents.as_bytes()).to_hex().to_string()
+        );
+        let archive_entry = backup_manifest.get(Path::new("model.tar.gz")).unwrap();
+        assert!(!archive_entry.compressed);
+        assert_eq!(archive_entry.stored_size, archive_entry.original_size);
+    }
+
+    #[test]
+    fn test_source_changed_during_copy_compares_size_and_mtime() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("weights.bin");
+        fs::write(&file, b"initial contents").unwrap();
+
+        let before = fs::symlink_metadata(&file).unwrap();
+        assert!(!source_changed_during_copy(&file, &before));
+
+        // A size change is detected regardless of timestamps
+        fs::write(&file, b"rewritten with a different length").unwrap();
+        assert!(source_changed_during_copy(&file, &before));
+
+        // A deleted source counts as changed
+        fs::remove_file(&file).unwrap();
+        assert!(source_changed_during_copy(&file, &before));
+    }
+
+    #[test]
+    fn test_mutating_source_during_backup_is_flagged_unstable() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let target = temp.path().join("backup");
+        fs::create_dir_all(&source).unwrap();
+
+        // Large enough that the copy takes measurable time while the
+        // "user process" keeps appending to it
+        let live_file = source.join("training.log");
+        fs::write(&live_file, vec![0x42u8; 32 * 1024 * 1024]).unwrap();
+        fs::write(source.join("quiet.txt"), b"never touched").unwrap();
+
+        let stop = AtomicBool::new(false);
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                use std::io::Write;
+                let mut writer = fs::OpenOptions::new().append(true).open(&live_file).unwrap();
+                while !stop.load(Ordering::Relaxed) {
+                    writer.write_all(b"still training\n").unwrap();
+                }
+            });
+
+            // Compression threshold above the file sizes: raw copies only
+            let policy = compression::CompressionPolicy { min_size: u64::MAX };
+            let result = transfer_data_with_compression(
+                &source,
+                &target,
+                Deadline::from_secs(120),
+                &HashSet::new(),
+                &policy,
+                true,
+                None,
+                false,
+                None,
+            )
+            .unwrap();
+            stop.store(true, Ordering::Relaxed);
+            result
+        });
+
+        assert_eq!(result.error_count, 0);
+        assert_eq!(result.unstable_files, vec![live_file.clone()]);
+
+        // The manifest flags the entry so restore treats hash mismatches
+        // as expected rather than corruption
+        let backup_manifest = manifest::BackupManifest::load(&target).unwrap().unwrap();
+        assert!(backup_manifest.get(Path::new("training.log")).unwrap().unstable);
+        assert!(!backup_manifest.get(Path::new("quiet.txt")).unwrap().unstable);
+    }
+
+    #[test]
+    fn test_canonicalize_failures_name_the_specific_cause() {
+        let temp = TempDir::new().unwrap();
+
+        // A missing path says so instead of a generic canonicalize error
+        let missing = temp.path().join("does-not-exist");
+        let err = validate_path_security(&missing, temp.path()).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "got: {}", err);
+
+        // A broken symlink is called out as such
+        let dangling = temp.path().join("dangling");
+        std::os::unix::fs::symlink(temp.path().join("gone"), &dangling).unwrap();
+        let err = validate_path_security(&dangling, temp.path()).unwrap_err();
+        assert!(err.to_string().contains("broken symlink"), "got: {}", err);
+
+        // Permission problems map to their own message (synthesized, as
+        // the test may run as root where EACCES cannot be provoked)
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(canonicalize_failure_detail(&missing, &denied).contains("permission denied"));
+        let looped = std::io::Error::from_raw_os_error(libc::ELOOP);
+        assert!(canonicalize_failure_detail(&missing, &looped).contains("symlink loop"));
+    }
+
+    #[test]
+    fn test_tar_no_clobber_selects_keep_newer_files_flag() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let bin = temp.path().join("bin");
+        let source = temp.path().join("source");
+        let target = temp.path().join("target");
+        fs::create_dir_all(&bin).unwrap();
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&target).unwrap();
+
+        // Stub tar that records its arguments instead of archiving
+        let args_log = temp.path().join("tar-args.log");
+        let stub = bin.join("tar");
+        fs::write(&stub, format!("#!/bin/sh\necho \"$@\" >> {}\n", args_log.display())).unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let original_path = std::env::var("PATH").unwrap();
+        std::env::set_var("PATH", format!("{}:{}", bin.display(), original_path));
+        let no_clobber = transfer_data_tar_no_clobber(&source, &target, 30);
+        let overwrite = transfer_data_tar(&source, &target, 30);
+        std::env::set_var("PATH", original_path);
+        no_clobber.unwrap();
+        overwrite.unwrap();
+
+        let recorded = fs::read_to_string(&args_log).unwrap();
+        let extracts: Vec<&str> = recorded.lines().filter(|line| line.contains("-xf")).collect();
+        assert_eq!(extracts.len(), 2);
+        assert!(extracts[0].contains("--keep-newer-files") && !extracts[0].contains("--overwrite"));
+        assert!(extracts[1].contains("--overwrite") && !extracts[1].contains("--keep-newer-files"));
+    }
+
+    #[test]
+    fn test_mappings_read_retries_until_rewrite_completes() {
+        let temp = TempDir::new().unwrap();
+        let mappings_file = temp.path().join("path-mappings.json");
+        let complete = r#"{"mappings":{"a1b2/c3d4":{"namespace":"teco","pod_name":"nb-test-0","container_name":"inference","created_at":"2026-01-01T00:00:00Z","pod_hash":"a1b2","snapshot_hash":"c3d4"}}}"#;
+
+        // Caught mid-rewrite: only a prefix of the JSON has been flushed
+        fs::write(&mappings_file, &complete[..complete.len() / 2]).unwrap();
+
+        let writer = {
+            let mappings_file = mappings_file.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(150));
+                fs::write(&mappings_file, complete).unwrap();
+            })
+        };
+
+        // The first parse fails on the truncated prefix; the retry after
+        // the rewrite lands succeeds
+        let loaded = load_mappings_resilient(&mappings_file).unwrap().unwrap();
+        writer.join().unwrap();
+        assert_eq!(loaded.mappings.len(), 1);
+        assert_eq!(loaded.mappings["a1b2/c3d4"].pod_hash, "a1b2");
+    }
+
+    #[test]
+    fn test_duplicate_mapping_keys_detected_and_rejected_in_strict_mode() {
+        let temp = TempDir::new().unwrap();
+        let mappings_file = temp.path().join("path-mappings.json");
+        let duplicated = r#"{"mappings":{
+            "a1b2/c3d4":{"namespace":"teco","pod_name":"nb-test-0","container_name":"inference","created_at":"2026-01-01T00:00:00Z","pod_hash":"a1b2","snapshot_hash":"c3d4"},
+            "ffff/2233":{"namespace":"teco","pod_name":"other-pod","container_name":"web","created_at":"2026-01-01T00:00:00Z","pod_hash":"ffff","snapshot_hash":"2233"},
+            "a1b2/c3d4":{"namespace":"teco","pod_name":"nb-test-0","container_name":"inference","created_at":"2026-02-01T00:00:00Z","pod_hash":"a1b2","snapshot_hash":"9999"}
+        }}"#;
+
+        // serde_json keeps the last value silently; the scanner sees both
+        assert_eq!(duplicate_mapping_keys(duplicated).unwrap(), vec!["a1b2/c3d4".to_string()]);
+        assert!(duplicate_mapping_keys(r#"{"mappings":{"a":{"x":1},"b":{"x":2}}}"#).unwrap().is_empty());
+
+        // Default mode warns and keeps the last value for the key
+        fs::write(&mappings_file, duplicated).unwrap();
+        let loaded = load_mappings_resilient(&mappings_file).unwrap().unwrap();
+        assert_eq!(loaded.mappings.len(), 2);
+        assert_eq!(loaded.mappings["a1b2/c3d4"].snapshot_hash, "9999");
+
+        // Strict mode rejects the file, naming the offending key, and
+        // classifies as a mapping parse failure
+        install_strict_mappings(true);
+        let err = load_mappings_resilient(&mappings_file).unwrap_err();
+        install_strict_mappings(false);
+        assert!(format!("{:#}", err).contains("duplicate mapping key(s): a1b2/c3d4"));
+        assert_eq!(error::exit_code_for(&err), 4);
+    }
+
+    #[test]
+    fn test_list_pod_containers_enumerates_distinct_names() {
+        let temp = TempDir::new().unwrap();
+        let mappings_file = temp.path().join("path-mappings.json");
+        fs::write(&mappings_file, r#"{"mappings":{
+            "a1b2/c3d4":{"namespace":"teco","pod_name":"nb-test-0","container_name":"inference","created_at":"2026-01-01T00:00:00Z","pod_hash":"a1b2","snapshot_hash":"c3d4"},
+            "a1b2/e5f6":{"namespace":"teco","pod_name":"nb-test-0","container_name":"inference","created_at":"2026-02-01T00:00:00Z","pod_hash":"a1b2","snapshot_hash":"e5f6"},
+            "a1b2/0011":{"namespace":"teco","pod_name":"nb-test-0","container_name":"sidecar","created_at":"2026-01-01T00:00:00Z","pod_hash":"a1b2","snapshot_hash":"0011"},
+            "ffff/2233":{"namespace":"teco","pod_name":"other-pod","container_name":"web","created_at":"2026-01-01T00:00:00Z","pod_hash":"ffff","snapshot_hash":"2233"}
+        }}"#).unwrap();
+
+        // Two entries for "inference" collapse to one name; other pods
+        // are not included
+        let names = list_pod_containers(&mappings_file, "teco", "nb-test-0").unwrap();
+        assert_eq!(names, vec!["inference".to_string(), "sidecar".to_string()]);
+
+        assert!(list_pod_containers(&mappings_file, "teco", "unknown").unwrap().is_empty());
+        assert!(list_pod_containers(&temp.path().join("missing.json"), "teco", "nb-test-0").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_incremental_backup_transfers_only_changed_files() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let target = temp.path().join("target");
+        fs::create_dir_all(source.join("work")).unwrap();
+        fs::write(source.join("notes.txt"), b"original notes").unwrap();
+        fs::write(source.join("work/model.py"), b"print('v1')").unwrap();
+        fs::write(source.join("work/scratch.tmp"), b"to be deleted").unwrap();
+
+        let policy = compression::CompressionPolicy { min_size: u64::MAX };
+        let full = transfer_data_with_compression(
+            &source,
+            &target,
+            Deadline::from_secs(60),
+            &HashSet::new(),
+            &policy,
+            false,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(full.success_count, 3);
+
+        // One file changed, one deleted, one untouched
+        fs::write(source.join("work/model.py"), b"print('v2, now longer')").unwrap();
+        fs::remove_file(source.join("work/scratch.tmp")).unwrap();
+
+        let previous = manifest::BackupManifest::load(&target).unwrap().unwrap();
+        let incremental = transfer_data_with_compression(
+            &source,
+            &target,
+            Deadline::from_secs(60),
+            &HashSet::new(),
+            &policy,
+            false,
+            None,
+            false,
+            Some(&previous),
+        )
+        .unwrap();
+
+        // Only the changed file was transferred; the unchanged one was
+        // carried over from the previous manifest
+        assert_eq!(incremental.success_count, 1);
+        assert_eq!(
+            incremental.skip_reason_counts.get("unchanged"),
+            Some(&1)
+        );
+        assert_eq!(fs::read(target.join("work/model.py")).unwrap(), b"print('v2, now longer')");
+
+        // The deletion left a tombstone entry and removed the stale copy
+        let updated = manifest::BackupManifest::load(&target).unwrap().unwrap();
+        assert!(updated.get(Path::new("work/scratch.tmp")).unwrap().deleted);
+        assert!(!updated.get(Path::new("notes.txt")).unwrap().deleted);
+        assert!(!target.join("work/scratch.tmp").exists());
+    }
+
+    #[test]
+    fn test_backup_and_verify_clean_tree() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let target = temp.path().join("target");
+        fs::create_dir_all(source.join("work")).unwrap();
+        fs::write(source.join("notes.txt"), b"verified contents").unwrap();
+        fs::write(source.join("work/model.py"), b"print('hello')").unwrap();
+
+        let report = backup_and_verify(&source, &target, &BackupVerifyOptions::default()).unwrap();
+        assert_eq!(report.transfer.success_count, 2);
+        assert_eq!(report.verified_files, 2);
+        assert!(report.mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_backup_and_verify_surfaces_post_copy_mutation() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let target = temp.path().join("target");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("good.txt"), b"stays intact").unwrap();
+        fs::write(source.join("flaky.txt"), b"about to change").unwrap();
+
+        let report = backup_and_verify(&source, &target, &BackupVerifyOptions::default()).unwrap();
+        assert!(report.mismatches.is_empty());
+
+        // A stored file mutated after the copy must surface as a mismatch
+        // on the next verification pass
+        fs::write(target.join("flaky.txt"), b"silently corrupted").unwrap();
+        let backup_manifest = manifest::BackupManifest::load(&target).unwrap().unwrap();
+        let (verified, mismatches) = verify_stored_hashes(&target, &backup_manifest).unwrap();
+        assert_eq!(verified, 1);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].path, "flaky.txt");
+        assert_eq!(
+            mismatches[0].expected,
+            manifest::hash_file_contents(&source.join("flaky.txt")).unwrap()
+        );
+        assert_ne!(mismatches[0].actual, mismatches[0].expected);
+    }
+
+    #[test]
+    fn test_cleanup_old_sessions_verifies_pod_identity_before_deleting() {
+        let temp = TempDir::new().unwrap();
+        let sessions = temp.path().join("sessions");
+        let pod_dir = sessions.join("a1b2c3d4");
+        let current = pod_dir.join("curr0000");
+        let own_old = pod_dir.join("old00001");
+        // Same pod_hash, different pod: the truncation collision
+        let foreign = pod_dir.join("old00002");
+        let unverified = pod_dir.join("old00003");
+        for dir in [&current, &own_old, &foreign, &unverified] {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join("data.bin"), b"session data").unwrap();
+        }
+        fs::write(
+            own_old.join(SESSION_META_FILE_NAME),
+            r#"{"namespace":"teco","pod_name":"nb-test-0"}"#,
+        )
+        .unwrap();
+        fs::write(
+            foreign.join(SESSION_META_FILE_NAME),
+            r#"{"namespace":"other","pod_name":"nb-other-0"}"#,
+        )
+        .unwrap();
+
+        let pod_info = PodInfo {
+            namespace: "teco".to_string(),
+            pod_name: "nb-test-0".to_string(),
+            container_name: "inference".to_string(),
+        };
+
+        let outcome = cleanup_old_sessions(&sessions, "a1b2c3d4", "curr0000", &pod_info, false).unwrap();
+        assert_eq!(outcome.removed, vec![own_old.clone()]);
+        assert_eq!(outcome.foreign, vec![foreign.clone()]);
+        assert_eq!(outcome.unverified_kept, vec![unverified.clone()]);
+        assert!(!own_old.exists());
+        assert!(foreign.exists());
+        assert!(unverified.exists());
+        assert!(current.exists());
+
+        // The metadata-less session goes away only under the legacy
+        // trust-the-hash flag; the collided session still survives
+        let outcome = cleanup_old_sessions(&sessions, "a1b2c3d4", "curr0000", &pod_info, true).unwrap();
+        assert_eq!(outcome.removed, vec![unverified.clone()]);
+        assert_eq!(outcome.foreign, vec![foreign.clone()]);
+        assert!(!unverified.exists());
+        assert!(foreign.exists());
+        assert!(current.exists());
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_token_stops_transfer_after_first_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&source).unwrap();
+        // The security validation canonicalizes both endpoints, so the
+        // target root must exist before the transfer starts
+        fs::create_dir_all(&target).unwrap();
+        fs::write(source.join("a_first.txt"), b"first file").unwrap();
+        fs::write(source.join("z_second.txt"), b"second file").unwrap();
+
+        let token = tokio_util::sync::CancellationToken::new();
+        let watcher_token = token.clone();
+        let first_target = target.join("a_first.txt");
+        // Current-thread runtime: the watcher only gets scheduled at the
+        // transfer's await points, cancels once the first file's bytes
+        // have landed, and the boundary check stops the second file. The
+        // in-flight copy is never torn, so the first file stays complete.
+        let watcher = tokio::spawn(async move {
+            while fs::metadata(&first_target).map(|m| m.len()).unwrap_or(0) != 10 {
+                tokio::task::yield_now().await;
+            }
+            watcher_token.cancel();
+        });
+
+        let result = transfer_data_parallel_cancellable(&source, &target, 300, &token)
+            .await
+            .unwrap();
+        watcher.await.unwrap();
+
+        assert!(result.cancelled);
+        assert_eq!(result.success_count, 1);
+        assert_eq!(fs::read(target.join("a_first.txt")).unwrap(), b"first file");
+        assert!(!target.join("z_second.txt").exists());
+    }
+}
@@ -1,8 +1,8 @@
+use crate::clock::{Clock, SYSTEM_CLOCK};
 use anyhow::{Context, Result};
 use log::{info, warn, debug};
 use std::fs;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +24,9 @@ pub enum BackupStatus {
 pub struct LocklessBackupManager {
     pub operation_name: String,
     pub enable_metadata: bool,
+    /// Time source for metadata timestamps and age thresholds; the real
+    /// clock outside tests
+    clock: &'static dyn Clock,
 }
 
 impl LocklessBackupManager {
@@ -31,6 +34,7 @@ impl LocklessBackupManager {
         Self {
             operation_name,
             enable_metadata: true,
+            clock: &SYSTEM_CLOCK,
         }
     }
 
@@ -39,6 +43,11 @@ impl LocklessBackupManager {
         self
     }
 
+    pub fn with_clock(mut self, clock: &'static dyn Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Create directory without any locking - safe for single-process operations
     pub fn create_directory_lockless(&self, path: &Path) -> Result<()> {
         debug!("Creating directory (lockless): {}", path.display());
@@ -115,10 +124,7 @@ impl LocklessBackupManager {
         match self.read_backup_metadata(&metadata_file) {
             Ok(metadata) => {
                 if metadata.status == BackupStatus::InProgress {
-                    let age_seconds = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs() - metadata.started_at;
+                    let age_seconds = self.clock.unix_seconds().saturating_sub(metadata.started_at);
 
                     // Consider operations older than 30 minutes as stale
                     if age_seconds > 1800 {
@@ -142,17 +148,14 @@ impl LocklessBackupManager {
     /// Write backup operation metadata
     fn write_backup_metadata(&self, metadata_file: &Path, status: BackupStatus) -> Result<()> {
         let metadata = BackupMetadata {
-            started_at: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
+            started_at: self.clock.unix_seconds(),
             process_id: std::process::id(),
             hostname: self.get_hostname(),
             operation: self.operation_name.clone(),
             status,
         };
 
-        let content = serde_json::to_string_pretty(&metadata)
+        let content = crate::result_envelope::render_json(&metadata)
             .context("Failed to serialize backup metadata")?;
 
         fs::write(metadata_file, content)
@@ -187,10 +190,7 @@ impl LocklessBackupManager {
         }
 
         let max_age_seconds = max_age_hours * 3600;
-        let current_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let current_time = self.clock.unix_seconds();
 
         let mut cleaned_count = 0;
 
@@ -198,10 +198,10 @@ impl LocklessBackupManager {
             let entry = entry?;
             let path = entry.path();
 
-            if path.extension().map_or(false, |ext| ext == "backup_meta") {
+            if path.extension().is_some_and(|ext| ext == "backup_meta") {
                 match self.read_backup_metadata(&path) {
                     Ok(metadata) => {
-                        let age = current_time - metadata.started_at;
+                        let age = current_time.saturating_sub(metadata.started_at);
                         
                         // Only clean up completed or failed backups that are old enough
                         if (metadata.status == BackupStatus::Completed || metadata.status == BackupStatus::Failed) 
@@ -329,6 +329,48 @@ mod tests {
         assert_eq!(metadata.operation, "test");
     }
 
+    #[test]
+    fn test_stale_metadata_threshold_under_a_mock_clock() {
+        use std::time::Duration;
+
+        let temp_dir = TempDir::new().unwrap();
+        let test_path = temp_dir.path().join("test_backup");
+        let clock = crate::clock::MockClock::leaked(1_700_000_000);
+        let manager = LocklessBackupManager::new("test".to_string()).with_clock(clock);
+
+        let metadata_file = test_path.with_extension("backup_meta");
+        manager.write_backup_metadata(&metadata_file, BackupStatus::InProgress).unwrap();
+
+        // One second inside the 30-minute window: still concurrent
+        clock.advance(Duration::from_secs(1799));
+        assert!(manager.check_concurrent_backup(&test_path).unwrap().is_some());
+
+        // Two seconds later the metadata is stale and ignored
+        clock.advance(Duration::from_secs(2));
+        assert!(manager.check_concurrent_backup(&test_path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cleanup_age_threshold_under_a_mock_clock() {
+        use std::time::Duration;
+
+        let temp_dir = TempDir::new().unwrap();
+        let clock = crate::clock::MockClock::leaked(1_700_000_000);
+        let manager = LocklessBackupManager::new("test".to_string()).with_clock(clock);
+
+        let metadata_file = temp_dir.path().join("done").with_extension("backup_meta");
+        manager.write_backup_metadata(&metadata_file, BackupStatus::Completed).unwrap();
+
+        // Age must exceed the threshold: exactly one hour is kept
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(manager.cleanup_old_metadata(temp_dir.path(), 1).unwrap(), 0);
+        assert!(metadata_file.exists());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(manager.cleanup_old_metadata(temp_dir.path(), 1).unwrap(), 1);
+        assert!(!metadata_file.exists());
+    }
+
     #[test]
     fn test_concurrent_detection() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,161 @@
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding the log directory (below `--log-dir`)
+pub const LOG_DIR_ENV: &str = "SESSION_MANAGER_LOG_DIR";
+
+/// System log directory tried before falling back to /tmp
+const SYSTEM_LOG_DIR: &str = "/var/log/session-manager";
+
+/// The attached log file, if any. Logging always goes to stderr; the file
+/// sink is attached later, once the session and backup paths are known,
+/// so the log can never be written into the tree being backed up.
+static LOG_FILE: Lazy<Mutex<Option<File>>> = Lazy::new(|| Mutex::new(None));
+
+/// Writes every log line to stderr and, once attached, to the log file.
+/// Sink errors are swallowed: logging must never fail the run.
+struct TeeWriter;
+
+impl Write for TeeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = io::stderr().write_all(buf);
+        if let Some(file) = LOG_FILE.lock().as_mut() {
+            let _ = file.write_all(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = io::stderr().flush();
+        if let Some(file) = LOG_FILE.lock().as_mut() {
+            let _ = file.flush();
+        }
+        Ok(())
+    }
+}
+
+/// Initialize stderr logging immediately; call [`attach_file_sink`] once
+/// the session and backup paths are known
+pub fn init() {
+    env_logger::Builder::new()
+        .target(env_logger::fmt::Target::Pipe(Box::new(TeeWriter)))
+        .filter_level(log::LevelFilter::Debug)
+        .format_timestamp_secs()
+        .init();
+}
+
+/// Log directory candidates in priority order: the CLI flag, the
+/// environment override, the system directory, then /tmp
+fn candidate_log_dirs(cli_log_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(dir) = cli_log_dir {
+        candidates.push(dir.to_path_buf());
+    }
+    if let Ok(dir) = std::env::var(LOG_DIR_ENV) {
+        if !dir.trim().is_empty() {
+            candidates.push(PathBuf::from(dir));
+        }
+    }
+    candidates.push(PathBuf::from(SYSTEM_LOG_DIR));
+    candidates.push(PathBuf::from("/tmp"));
+    candidates
+}
+
+/// Open the log file in the first usable candidate directory, skipping
+/// candidates inside any excluded tree (the session source or the backup
+/// path, where a log file would end up inside the backup itself)
+fn open_log_file_in(
+    candidates: Vec<PathBuf>,
+    excluded: &[&Path],
+    file_name: &str,
+) -> Option<(File, PathBuf)> {
+    for candidate in candidates {
+        if excluded.iter().any(|root| candidate.starts_with(root)) {
+            warn!(
+                "Skipping log directory {} (inside the session or backup tree)",
+                candidate.display()
+            );
+            continue;
+        }
+        if let Err(e) = fs::create_dir_all(&candidate) {
+            debug!("Skipping log directory {}: {}", candidate.display(), e);
+            continue;
+        }
+        let path = candidate.join(file_name);
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => return Some((file, path)),
+            Err(e) => debug!("Cannot open log file {}: {}", path.display(), e),
+        }
+    }
+    None
+}
+
+/// Attach the file sink, choosing the log directory from the priority
+/// list. Infallible from the caller's perspective: when no candidate is
+/// usable, logging stays stderr-only and a warning says so.
+pub fn attach_file_sink(
+    binary_name: &str,
+    cli_log_dir: Option<&Path>,
+    excluded: &[&Path],
+) -> Option<PathBuf> {
+    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
+    let file_name = format!("{}-{}.log", binary_name, timestamp);
+    match open_log_file_in(candidate_log_dirs(cli_log_dir), excluded, &file_name) {
+        Some((file, path)) => {
+            *LOG_FILE.lock() = Some(file);
+            info!("Logging to file: {}", path.display());
+            Some(path)
+        }
+        None => {
+            warn!("No usable log directory found; logging to stderr only");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_unusable_candidate_falls_back_to_next() {
+        let temp = TempDir::new().unwrap();
+        // A plain file blocks create_dir_all, like a read-only mount would
+        let blocked = temp.path().join("blocked");
+        fs::write(&blocked, b"not a directory").unwrap();
+        let writable = temp.path().join("logs");
+
+        let (_, path) = open_log_file_in(
+            vec![blocked.clone(), writable.clone()],
+            &[],
+            "session-test.log",
+        )
+        .unwrap();
+        assert_eq!(path, writable.join("session-test.log"));
+    }
+
+    #[test]
+    fn test_candidates_inside_session_or_backup_are_skipped() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("session");
+        let inside_source = source.join("tmp");
+        let outside = temp.path().join("logs");
+        fs::create_dir_all(&inside_source).unwrap();
+
+        let (_, path) = open_log_file_in(
+            vec![inside_source, outside.clone()],
+            &[&source],
+            "session-test.log",
+        )
+        .unwrap();
+        assert_eq!(path, outside.join("session-test.log"));
+
+        // With every candidate excluded the run stays stderr-only
+        assert!(open_log_file_in(vec![source.clone()], &[&source], "session-test.log").is_none());
+    }
+}
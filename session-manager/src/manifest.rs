@@ -0,0 +1,685 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// File name of the backup manifest stored at the backup root
+pub const MANIFEST_FILE_NAME: &str = ".session-backup-manifest.json";
+
+/// Name prefix shared by the manifest, its temp file and partial shards;
+/// anything starting with it is a manifest artifact, never backup content
+pub const MANIFEST_ARTIFACT_PREFIX: &str = ".session-backup-manifest";
+
+/// Name prefix of partial manifest shards written by the resumable
+/// post-pass (see [`build_manifest_resumable`])
+pub const MANIFEST_SHARD_PREFIX: &str = ".session-backup-manifest.shard-";
+
+/// How many newly hashed files a resumable post-pass accumulates before
+/// flushing a shard
+pub const DEFAULT_MANIFEST_SHARD_SIZE: usize = 1000;
+
+/// Per-file metadata recorded at backup time.
+///
+/// `original_size` and `original_hash` always describe the uncompressed
+/// content, so integrity verification works the same whether the file was
+/// stored raw or compressed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Size of the original (uncompressed) content in bytes
+    pub original_size: u64,
+    /// Size of the file as stored in the backup
+    pub stored_size: u64,
+    /// Whether the stored file is zstd-compressed (named `<name>.zst`)
+    pub compressed: bool,
+    /// Blake3 hash (hex) of the original content
+    pub original_hash: String,
+    /// The source changed while being copied; the stored content may be a
+    /// torn copy, so hash mismatches on restore are expected
+    #[serde(default)]
+    pub unstable: bool,
+    /// Where the content lives when the file was packed with other small
+    /// files instead of stored loose; the manifest doubles as the pack index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<PackLocation>,
+    /// Set when the file was captured as part of a multi-file database
+    /// unit (e.g. "sqlite:<db path>" or "leveldb:<dir path>"), whose
+    /// members were copied together under a stability check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_unit: Option<String>,
+    /// The file existed in the previous backup but is gone from the
+    /// source; incremental backups keep the entry to record the deletion
+    #[serde(default)]
+    pub deleted: bool,
+    /// Birth (creation) time of the original file as RFC3339, where the
+    /// platform reports one. Informational only: Linux offers no portable
+    /// way to set it back on restore.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birth_time: Option<String>,
+    /// Setuid/setgid/sticky mode bits and the `security.capability` xattr
+    /// of the original file, recorded only when the file carries any.
+    /// Restore re-applies them strictly opt-in, for allow-listed paths.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privileged_attrs: Option<PrivilegedAttrs>,
+}
+
+/// Privileged attributes of a backed-up file that a plain permission copy
+/// loses: setuid/setgid/sticky bits and file capabilities (the xattr that
+/// lets ping or similar binaries work without full setuid)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrivilegedAttrs {
+    /// Full Unix permission bits of the original file, including the
+    /// setuid/setgid/sticky bits
+    pub mode: u32,
+    /// Raw `security.capability` xattr value, hex-encoded, when present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<String>,
+}
+
+impl PrivilegedAttrs {
+    /// Decode the hex-encoded capability xattr back to raw bytes; None
+    /// when absent or not valid hex
+    pub fn capability_bytes(&self) -> Option<Vec<u8>> {
+        let hex = self.capabilities.as_deref()?;
+        if hex.len() % 2 != 0 {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect()
+    }
+}
+
+/// Privileged attributes of a file worth recording: Some only when the
+/// file carries setuid/setgid/sticky bits or a `security.capability` xattr
+#[cfg(unix)]
+pub fn privileged_attrs_for(path: &Path) -> Option<PrivilegedAttrs> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let mode = metadata.mode() & 0o7777;
+    let capabilities = read_capability_xattr(path)
+        .map(|bytes| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    if mode & 0o7000 == 0 && capabilities.is_none() {
+        return None;
+    }
+    Some(PrivilegedAttrs { mode, capabilities })
+}
+
+#[cfg(not(unix))]
+pub fn privileged_attrs_for(_path: &Path) -> Option<PrivilegedAttrs> {
+    None
+}
+
+/// Raw `security.capability` xattr of a file, when it carries one
+#[cfg(target_os = "linux")]
+fn read_capability_xattr(path: &Path) -> Option<Vec<u8>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let attr = c"security.capability";
+    // The VFS capability blob is small; 64 bytes covers every version
+    let mut value = [0u8; 64];
+    let len = unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            attr.as_ptr(),
+            value.as_mut_ptr() as *mut libc::c_void,
+            value.len(),
+        )
+    };
+    if len > 0 {
+        Some(value[..len as usize].to_vec())
+    } else {
+        None
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn read_capability_xattr(_path: &Path) -> Option<Vec<u8>> {
+    None
+}
+
+/// Birth (creation) time of a file as RFC3339, where the platform and
+/// filesystem report one
+pub fn birth_time_rfc3339(path: &Path) -> Option<String> {
+    let created = fs::metadata(path).ok()?.created().ok()?;
+    Some(chrono::DateTime::<chrono::Utc>::from(created).to_rfc3339())
+}
+
+/// Position of a packed file's content inside a pack file at the backup root
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PackLocation {
+    /// Pack file name, relative to the backup root
+    pub pack_file: String,
+    /// Byte offset of the content within the pack
+    pub offset: u64,
+    /// Content length in bytes
+    pub length: u64,
+    /// Unix permission bits of the original file
+    pub mode: u32,
+}
+
+/// Layout features a backup may use. A consumer refuses to restore a
+/// backup whose manifest records a feature outside this list: the data
+/// would be silently misread (packed content as loose files, compressed
+/// blobs as plain content). Extend the list whenever a new layout
+/// feature ships.
+pub const SUPPORTED_FEATURES: &[&str] = &["generations", "packs", "compression", "encryption"];
+
+/// Which build produced a backup and which layout features it used;
+/// recorded in the manifest so a restore can check compatibility before
+/// touching any data
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Producer {
+    /// Crate version of the producing binary
+    pub version: String,
+    /// Layout features this backup actually uses, drawn from the
+    /// producer's own [`SUPPORTED_FEATURES`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+impl Producer {
+    /// Features the producer recorded that this build does not
+    /// understand; restoring past them would misread the backup
+    pub fn unknown_features(&self) -> Vec<String> {
+        self.features
+            .iter()
+            .filter(|feature| !SUPPORTED_FEATURES.contains(&feature.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the producing build is newer than this one, comparing the
+    /// numeric version components; a newer producer may have written
+    /// details this build handles imperfectly
+    pub fn newer_than_consumer(&self) -> bool {
+        version_key(&self.version) > version_key(env!("CARGO_PKG_VERSION"))
+    }
+}
+
+/// Numeric components of a version string, for ordering ("1.10.0" after
+/// "1.9.3"); non-numeric fragments are ignored
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Manifest of a backup directory, keyed by path relative to the backup
+/// root (the original, uncompressed name — without any `.zst` suffix)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub entries: BTreeMap<String, ManifestEntry>,
+    /// Entries omitted by --over-quota=trim, relative to the source root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trimmed_for_quota: Vec<crate::quota::TrimmedEntry>,
+    /// Tool provenance, stamped on every save; absent in old backups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<Producer>,
+}
+
+impl BackupManifest {
+    /// Load the manifest from a backup directory; None when absent
+    pub fn load(backup_dir: &Path) -> Result<Option<Self>> {
+        let manifest_path = backup_dir.join(MANIFEST_FILE_NAME);
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read backup manifest: {}", manifest_path.display()))?;
+        let manifest = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse backup manifest: {}", manifest_path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    /// Write the manifest atomically into the backup directory, stamping
+    /// the producing version and the layout features the entries use
+    pub fn save(&mut self, backup_dir: &Path) -> Result<()> {
+        self.stamp_producer();
+        self.save_to(&backup_dir.join(MANIFEST_FILE_NAME))
+    }
+
+    /// Record an extra layout feature not derivable from the entries
+    /// (encryption is applied as a post-pass over the saved files)
+    pub fn record_feature(&mut self, feature: &str) {
+        let producer = self.producer.get_or_insert_with(|| Producer {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            features: Vec::new(),
+        });
+        if !producer.features.iter().any(|f| f == feature) {
+            producer.features.push(feature.to_string());
+        }
+    }
+
+    /// Refresh the producer stamp: this build's version, plus whichever
+    /// layout features the entries actually use. Features recorded
+    /// explicitly (see [`Self::record_feature`]) are preserved.
+    fn stamp_producer(&mut self) {
+        let uses_compression = self.entries.values().any(|entry| entry.compressed);
+        let uses_packs = self.entries.values().any(|entry| entry.pack.is_some());
+        let uses_generations = self.entries.values().any(|entry| entry.deleted);
+        let mut features = self.producer.take().map(|p| p.features).unwrap_or_default();
+        for (feature, used) in [
+            ("generations", uses_generations),
+            ("packs", uses_packs),
+            ("compression", uses_compression),
+        ] {
+            if used && !features.iter().any(|f| f == feature) {
+                features.push(feature.to_string());
+            }
+        }
+        self.producer = Some(Producer {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            features,
+        });
+    }
+
+    /// Write the manifest atomically to an explicit path (the main
+    /// manifest or a post-pass shard)
+    fn save_to(&self, manifest_path: &Path) -> Result<()> {
+        let json = crate::result_envelope::render_json(self).context("Failed to serialize backup manifest")?;
+
+        let temp_path = manifest_path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .with_context(|| format!("Failed to write manifest temp file: {}", temp_path.display()))?;
+        fs::rename(&temp_path, manifest_path)
+            .with_context(|| format!("Failed to move manifest into place: {}", manifest_path.display()))?;
+
+        debug!("Wrote backup manifest with {} entries: {}", self.entries.len(), manifest_path.display());
+        Ok(())
+    }
+
+    /// Load the manifest together with any partial shards left by an
+    /// interrupted post-pass, merged into one view. None when neither the
+    /// main manifest nor any shard exists.
+    pub fn load_with_shards(backup_dir: &Path) -> Result<Option<Self>> {
+        let mut merged = Self::load(backup_dir)?;
+        for shard_path in shard_files(backup_dir)? {
+            let content = fs::read_to_string(&shard_path)
+                .with_context(|| format!("Failed to read manifest shard: {}", shard_path.display()))?;
+            let shard: Self = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse manifest shard: {}", shard_path.display()))?;
+            merged
+                .get_or_insert_with(Self::default)
+                .entries
+                .extend(shard.entries);
+        }
+        Ok(merged)
+    }
+
+    /// Record an entry under its backup-root-relative path
+    pub fn record(&mut self, relative_path: &Path, entry: ManifestEntry) {
+        self.entries
+            .insert(relative_path.to_string_lossy().into_owned(), entry);
+    }
+
+    /// Flag an entry whose source changed during the copy
+    pub fn mark_unstable(&mut self, relative_path: &Path) {
+        if let Some(entry) = self.entries.get_mut(relative_path.to_string_lossy().as_ref()) {
+            entry.unstable = true;
+        }
+    }
+
+    /// Look up an entry by its backup-root-relative path
+    pub fn get(&self, relative_path: &Path) -> Option<&ManifestEntry> {
+        self.entries.get(relative_path.to_string_lossy().as_ref())
+    }
+}
+
+/// Blake3 hash (hex) of a file's content, read in streaming chunks
+pub fn hash_file_contents(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open file for hashing: {}", path.display()))?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read file for hashing: {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Best-effort manifest load for restore and verify paths: a corrupt
+/// manifest is logged and ignored rather than failing the whole restore.
+/// Shards left by an interrupted post-pass are merged in, so both
+/// single-file and sharded manifests are accepted.
+pub fn load_manifest_lenient(backup_dir: &Path) -> Option<BackupManifest> {
+    match BackupManifest::load_with_shards(backup_dir) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            warn!("Ignoring unreadable backup manifest in {}: {}", backup_dir.display(), e);
+            None
+        }
+    }
+}
+
+/// Partial manifest shard files in a backup directory, in index order
+fn shard_files(backup_dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut shards = Vec::new();
+    let entries = match fs::read_dir(backup_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(shards),
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(MANIFEST_SHARD_PREFIX) && name.ends_with(".json") {
+            shards.push(entry.path());
+        }
+    }
+    shards.sort();
+    Ok(shards)
+}
+
+/// Build the manifest for an already-transferred backup (the rsync and
+/// mount-bypass transfers copy without hashing) as a resumable post-pass.
+///
+/// Newly hashed entries are flushed into numbered shard files every
+/// `shard_size` files, so an interrupted run loses at most one shard of
+/// work: the next run merges the shards back in and hashes only what is
+/// still missing. On completion everything is folded into the single
+/// manifest file and the shards are removed. Returns the number of files
+/// hashed by this run.
+pub fn build_manifest_resumable(backup_dir: &Path, shard_size: usize) -> Result<usize> {
+    let shard_size = shard_size.max(1);
+    let mut merged = BackupManifest::load_with_shards(backup_dir)?.unwrap_or_default();
+    let mut next_shard_index = shard_files(backup_dir)?.len();
+
+    let mut pending = BackupManifest::default();
+    let mut hashed = 0usize;
+    for entry in walkdir::WalkDir::new(backup_dir).into_iter().flatten() {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        if name.starts_with(MANIFEST_ARTIFACT_PREFIX) || crate::is_internal_artifact(entry.file_name()) {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(backup_dir) else {
+            continue;
+        };
+        // Trashed cleanups are bookkeeping, not session content
+        if relative.starts_with(crate::direct_restore::TRASH_DIR_NAME) {
+            continue;
+        }
+        if merged.get(relative).is_some() {
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let hash = match hash_file_contents(entry.path()) {
+            Ok(hash) => hash,
+            Err(e) => {
+                warn!("Skipping unhashable backup file {}: {}", entry.path().display(), e);
+                continue;
+            }
+        };
+        pending.record(relative, ManifestEntry {
+            original_size: size,
+            stored_size: size,
+            compressed: false,
+            original_hash: hash,
+            unstable: false,
+            pack: None,
+            db_unit: None,
+            deleted: false,
+            // The transfer preserved neither birth time nor a way to
+            // recover it; only content integrity is recorded here
+            birth_time: None,
+            privileged_attrs: privileged_attrs_for(entry.path()),
+        });
+        hashed += 1;
+
+        if pending.entries.len() >= shard_size {
+            let shard_path = backup_dir.join(format!("{}{:05}.json", MANIFEST_SHARD_PREFIX, next_shard_index));
+            pending.save_to(&shard_path)?;
+            debug!("Flushed manifest shard with {} entries: {}", pending.entries.len(), shard_path.display());
+            merged.entries.extend(std::mem::take(&mut pending.entries));
+            next_shard_index += 1;
+        }
+    }
+
+    // Fold everything into the single manifest and drop the shards; a
+    // crash before this point leaves a consistent sharded state behind
+    merged.entries.extend(pending.entries);
+    merged.save(backup_dir)?;
+    for shard_path in shard_files(backup_dir)? {
+        if let Err(e) = fs::remove_file(&shard_path) {
+            warn!("Failed to remove merged manifest shard {}: {}", shard_path.display(), e);
+        }
+    }
+    Ok(hashed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let mut manifest = BackupManifest::default();
+        manifest.record(
+            Path::new("root/notebook.ipynb"),
+            ManifestEntry {
+                original_size: 1024,
+                stored_size: 128,
+                compressed: true,
+                original_hash: "abc123".to_string(),
+                unstable: false,
+                pack: None,
+                db_unit: None,
+                deleted: false,
+                birth_time: None,
+                privileged_attrs: None,
+            },
+        );
+        manifest.save(temp.path()).unwrap();
+
+        let loaded = BackupManifest::load(temp.path()).unwrap().unwrap();
+        let entry = loaded.get(Path::new("root/notebook.ipynb")).unwrap();
+        assert_eq!(entry.original_size, 1024);
+        assert!(entry.compressed);
+
+        // No temp artifact is left next to the manifest
+        assert!(!temp.path().join(".session-backup-manifest.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_producer_flags_unknown_features_and_newer_versions() {
+        // A feature outside SUPPORTED_FEATURES marks the backup as
+        // produced by a build this one cannot safely read
+        let unknown = Producer {
+            version: "0.1.0".to_string(),
+            features: vec!["packs".to_string(), "sharded-index-v2".to_string()],
+        };
+        assert_eq!(unknown.unknown_features(), vec!["sharded-index-v2".to_string()]);
+        assert!(!unknown.newer_than_consumer());
+
+        // A newer producer with only known features is a warning case
+        let newer = Producer { version: "99.1.0".to_string(), features: vec!["compression".to_string()] };
+        assert!(newer.unknown_features().is_empty());
+        assert!(newer.newer_than_consumer());
+
+        // This build's own version is never "newer"
+        let same = Producer { version: env!("CARGO_PKG_VERSION").to_string(), features: Vec::new() };
+        assert!(!same.newer_than_consumer());
+
+        // Numeric ordering, not lexicographic: 1.10 is newer than 1.9
+        assert!(version_key("1.10.0") > version_key("1.9.3"));
+    }
+
+    #[test]
+    fn test_save_stamps_producer_with_version_and_used_features() {
+        let temp = TempDir::new().unwrap();
+        let mut manifest = BackupManifest::default();
+        manifest.record(
+            Path::new("root/notebook.ipynb"),
+            ManifestEntry {
+                original_size: 1024,
+                stored_size: 128,
+                compressed: true,
+                original_hash: "abc123".to_string(),
+                unstable: false,
+                pack: None,
+                db_unit: None,
+                deleted: false,
+                birth_time: None,
+                privileged_attrs: None,
+            },
+        );
+        manifest.record_feature("encryption");
+        manifest.save(temp.path()).unwrap();
+
+        // The stamp carries this build's version, the features derived
+        // from the entries and the explicitly recorded one
+        let producer = BackupManifest::load(temp.path()).unwrap().unwrap().producer.unwrap();
+        assert_eq!(producer.version, env!("CARGO_PKG_VERSION"));
+        assert!(producer.features.contains(&"compression".to_string()));
+        assert!(producer.features.contains(&"encryption".to_string()));
+        assert!(!producer.features.contains(&"packs".to_string()));
+
+        // Manifests written before the stamp existed still parse
+        let legacy: BackupManifest = serde_json::from_str(r#"{"entries":{}}"#).unwrap();
+        assert!(legacy.producer.is_none());
+    }
+
+    #[test]
+    fn test_missing_manifest_is_none() {
+        let temp = TempDir::new().unwrap();
+        assert!(BackupManifest::load(temp.path()).unwrap().is_none());
+        assert!(load_manifest_lenient(temp.path()).is_none());
+    }
+
+    #[test]
+    fn test_post_pass_builds_manifest_in_shards_and_merges() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("work")).unwrap();
+        for (name, contents) in [
+            ("notes.txt", "alpha"),
+            ("work/a.py", "bravo"),
+            ("work/b.py", "charlie"),
+            ("work/c.py", "delta"),
+            ("last.log", "echo"),
+        ] {
+            fs::write(temp.path().join(name), contents).unwrap();
+        }
+
+        let hashed = build_manifest_resumable(temp.path(), 2).unwrap();
+        assert_eq!(hashed, 5);
+
+        // Everything folded into the single manifest, no shards left over
+        let manifest = BackupManifest::load(temp.path()).unwrap().unwrap();
+        assert_eq!(manifest.entries.len(), 5);
+        assert!(shard_files(temp.path()).unwrap().is_empty());
+
+        // The recorded hash agrees with an independent re-hash
+        let entry = manifest.get(Path::new("work/b.py")).unwrap();
+        assert_eq!(entry.original_hash, blake3::hash(b"charlie").to_hex().to_string());
+        assert_eq!(entry.original_size, 7);
+    }
+
+    #[test]
+    fn test_post_pass_resumes_from_shards_without_rehashing() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("done.txt"), b"already hashed").unwrap();
+        fs::write(temp.path().join("todo.txt"), b"still missing").unwrap();
+
+        // Simulate an interrupted run that flushed one shard: a sentinel
+        // hash proves the entry is carried over instead of re-hashed
+        let mut shard = BackupManifest::default();
+        shard.record(Path::new("done.txt"), ManifestEntry {
+            original_size: 14,
+            stored_size: 14,
+            compressed: false,
+            original_hash: "sentinel".to_string(),
+            unstable: false,
+            pack: None,
+            db_unit: None,
+            deleted: false,
+            birth_time: None,
+            privileged_attrs: None,
+        });
+        shard.save_to(&temp.path().join(format!("{}00000.json", MANIFEST_SHARD_PREFIX))).unwrap();
+
+        // Verify-side loading accepts the sharded state as-is
+        let partial = load_manifest_lenient(temp.path()).unwrap();
+        assert_eq!(partial.get(Path::new("done.txt")).unwrap().original_hash, "sentinel");
+
+        // The resumed run hashes only the missing file and merges both
+        let hashed = build_manifest_resumable(temp.path(), 2).unwrap();
+        assert_eq!(hashed, 1);
+
+        let manifest = BackupManifest::load(temp.path()).unwrap().unwrap();
+        assert_eq!(manifest.get(Path::new("done.txt")).unwrap().original_hash, "sentinel");
+        assert_eq!(
+            manifest.get(Path::new("todo.txt")).unwrap().original_hash,
+            blake3::hash(b"still missing").to_hex().to_string()
+        );
+        assert!(shard_files(temp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_privileged_attrs_recorded_only_for_special_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let plain = temp.path().join("plain.bin");
+        fs::write(&plain, b"tool").unwrap();
+        fs::set_permissions(&plain, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(privileged_attrs_for(&plain).is_none());
+
+        let setuid = temp.path().join("setuid.bin");
+        fs::write(&setuid, b"tool").unwrap();
+        fs::set_permissions(&setuid, fs::Permissions::from_mode(0o4755)).unwrap();
+        let attrs = privileged_attrs_for(&setuid).unwrap();
+        assert_eq!(attrs.mode, 0o4755);
+    }
+
+    #[test]
+    fn test_capability_bytes_round_trip() {
+        let attrs = PrivilegedAttrs {
+            mode: 0o755,
+            capabilities: Some("01000002c025000000000000".to_string()),
+        };
+        assert_eq!(
+            attrs.capability_bytes().unwrap(),
+            vec![0x01, 0x00, 0x00, 0x02, 0xc0, 0x25, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+
+        // Absent and malformed values decode to None
+        assert!(PrivilegedAttrs { mode: 0o755, capabilities: None }.capability_bytes().is_none());
+        assert!(PrivilegedAttrs { mode: 0o755, capabilities: Some("abc".to_string()) }
+            .capability_bytes()
+            .is_none());
+    }
+
+    #[test]
+    fn test_hash_file_contents_is_stable() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("data.txt");
+        fs::write(&path, b"session contents").unwrap();
+        let first = hash_file_contents(&path).unwrap();
+        let second = hash_file_contents(&path).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, blake3::hash(b"session contents").to_hex().to_string());
+    }
+}
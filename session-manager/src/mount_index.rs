@@ -0,0 +1,138 @@
+//! Prefix index over mount points for the native transfer path.
+//!
+//! The ancestor walk in [`crate::is_path_mounted`] hashes every ancestor of
+//! every visited path against the mount set; with hundreds of mounts and
+//! millions of files that alone costs minutes. Building a component trie
+//! once per transfer answers "is this path at or under any mount" with a
+//! single walk down the path's own components.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+/// Component trie over a set of mount points, built once per transfer
+pub struct MountIndex {
+    root: MountNode,
+}
+
+#[derive(Default)]
+struct MountNode {
+    /// A mount point ends exactly at this node
+    terminal: bool,
+    children: HashMap<OsString, MountNode>,
+}
+
+impl MountIndex {
+    pub fn new(mounted_paths: &HashSet<PathBuf>) -> Self {
+        let mut root = MountNode::default();
+        for mount in mounted_paths {
+            let mut node = &mut root;
+            for component in mount.components() {
+                if let Component::Normal(name) = component {
+                    node = node.children.entry(name.to_os_string()).or_default();
+                }
+            }
+            node.terminal = true;
+        }
+        Self { root }
+    }
+
+    /// True when `path` is a mount point or lies beneath one; one trie
+    /// walk instead of one set lookup per ancestor
+    pub fn covers(&self, path: &Path) -> bool {
+        let mut node = &self.root;
+        if node.terminal {
+            return true;
+        }
+        for component in path.components() {
+            if let Component::Normal(name) = component {
+                match node.children.get(name) {
+                    Some(child) => {
+                        node = child;
+                        if node.terminal {
+                            return true;
+                        }
+                    }
+                    None => return false,
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_prefix_index_agrees_with_ancestor_walk() {
+        let mut mounted = HashSet::new();
+        mounted.insert(PathBuf::from("/mnt/data"));
+        mounted.insert(PathBuf::from("/mnt/data/nested"));
+        mounted.insert(PathBuf::from("/proc"));
+        let index = MountIndex::new(&mounted);
+
+        for (path, expected) in [
+            ("/mnt/data", true),
+            ("/mnt/data/sub/file.txt", true),
+            ("/mnt/data/nested/deeper", true),
+            ("/mnt/database", false),
+            ("/mnt", false),
+            ("/proc/1/status", true),
+            ("/home/user", false),
+        ] {
+            let path = Path::new(path);
+            assert_eq!(index.covers(path), expected, "{}", path.display());
+            assert_eq!(crate::is_path_mounted(path, &mounted), expected, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_prefix_index_micro_benchmark_against_ancestor_walk() {
+        let mut mounted = HashSet::new();
+        for i in 0..500 {
+            mounted.insert(PathBuf::from(format!("/mnt/vol-{:03}", i)));
+        }
+        let index = MountIndex::new(&mounted);
+
+        // Synthetic deep file paths, roughly one in ten under a mount
+        let mut paths = Vec::new();
+        for i in 0..2000 {
+            let mut path = PathBuf::from(format!("/data/files/{}", i % 7));
+            for depth in 0..20 {
+                path = path.join(format!("d{}", depth));
+            }
+            paths.push(path.join(format!("f{}.bin", i)));
+            if i % 10 == 0 {
+                paths.push(PathBuf::from(format!("/mnt/vol-{:03}/sub/dir/file{}", i % 500, i)));
+            }
+        }
+
+        let start = Instant::now();
+        let walk_hits: usize = paths.iter().filter(|p| crate::is_path_mounted(p, &mounted)).count();
+        let walk_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        let index_hits: usize = paths.iter().filter(|p| index.covers(p)).count();
+        let index_elapsed = start.elapsed();
+
+        eprintln!(
+            "mount check over {} paths: ancestor walk {:?}, prefix index {:?}",
+            paths.len(),
+            walk_elapsed,
+            index_elapsed
+        );
+
+        assert_eq!(walk_hits, index_hits);
+        // Generous bound: the point is catching a regression back to
+        // per-ancestor hashing, not winning by an exact ratio
+        assert!(
+            index_elapsed <= walk_elapsed.saturating_mul(3),
+            "prefix index ({:?}) much slower than ancestor walk ({:?})",
+            index_elapsed,
+            walk_elapsed
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::Result;
+use log::debug;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A file under the backup source that some process holds open for
+/// writing. Its contents can change between read and backup completion,
+/// so the copy may come out inconsistent (sqlite WALs, jupyter autosave).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OpenFileInfo {
+    /// The open file, as resolved from the fd symlink
+    pub path: PathBuf,
+    /// Process holding the file open
+    pub pid: u32,
+    /// Process name from /proc/<pid>/comm
+    pub process_name: String,
+}
+
+/// How the pre-backup open-file scan reacts to what it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenFileCheck {
+    /// No scan
+    Off,
+    /// Scan and warn; the backup proceeds
+    Warn,
+    /// Scan and abort the backup if anything is open for writing
+    Fail,
+}
+
+/// Enumerate files open for writing under `root` by scanning /proc/*/fd.
+///
+/// Entries that cannot be read (other users' processes, processes that
+/// exited mid-scan) are skipped silently; the scan is advisory and must
+/// never fail the backup on its own.
+#[cfg(target_os = "linux")]
+pub fn scan_open_for_write(root: &Path) -> Result<Vec<OpenFileInfo>> {
+    let mut found = HashSet::new();
+
+    let proc_entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("Cannot read /proc, skipping open file scan: {}", e);
+            return Ok(Vec::new());
+        }
+    };
+
+    for entry in proc_entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u32>().ok())
+        else {
+            continue;
+        };
+
+        let fd_dir = entry.path().join("fd");
+        let fds = match std::fs::read_dir(&fd_dir) {
+            Ok(fds) => fds,
+            // Permission denied on other users' processes is expected
+            Err(e) => {
+                debug!("Skipping /proc/{}/fd: {}", pid, e);
+                continue;
+            }
+        };
+
+        let process_name = std::fs::read_to_string(entry.path().join("comm"))
+            .map(|comm| comm.trim().to_string())
+            .unwrap_or_else(|_| String::from("unknown"));
+
+        for fd_entry in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd_entry.path()) else {
+                continue;
+            };
+            if !target.starts_with(root) {
+                continue;
+            }
+            if !fd_open_for_write(pid, &fd_entry.file_name()) {
+                continue;
+            }
+            found.insert(OpenFileInfo {
+                path: target,
+                pid,
+                process_name: process_name.clone(),
+            });
+        }
+    }
+
+    let mut at_risk: Vec<_> = found.into_iter().collect();
+    at_risk.sort_by(|a, b| a.path.cmp(&b.path).then(a.pid.cmp(&b.pid)));
+    Ok(at_risk)
+}
+
+/// Enumerate processes whose current working directory lies under `root`,
+/// from /proc/<pid>/cwd. A pip or conda run building inside a tree keeps
+/// its cwd there even between file writes, so this catches installers the
+/// open-fd scan would miss mid-operation. Our own process is excluded.
+#[cfg(target_os = "linux")]
+pub fn scan_cwd_under(root: &Path) -> Result<Vec<OpenFileInfo>> {
+    let mut found = Vec::new();
+    let own_pid = std::process::id();
+
+    let proc_entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("Cannot read /proc, skipping cwd scan: {}", e);
+            return Ok(Vec::new());
+        }
+    };
+
+    for entry in proc_entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid == own_pid {
+            continue;
+        }
+
+        let Ok(cwd) = std::fs::read_link(entry.path().join("cwd")) else {
+            continue;
+        };
+        if !cwd.starts_with(root) {
+            continue;
+        }
+
+        let process_name = std::fs::read_to_string(entry.path().join("comm"))
+            .map(|comm| comm.trim().to_string())
+            .unwrap_or_else(|_| String::from("unknown"));
+
+        found.push(OpenFileInfo {
+            path: cwd,
+            pid,
+            process_name,
+        });
+    }
+
+    found.sort_by(|a, b| a.path.cmp(&b.path).then(a.pid.cmp(&b.pid)));
+    Ok(found)
+}
+
+/// Whether the fd's access mode in /proc/<pid>/fdinfo includes writing
+#[cfg(target_os = "linux")]
+fn fd_open_for_write(pid: u32, fd_name: &std::ffi::OsStr) -> bool {
+    let fdinfo_path = PathBuf::from(format!("/proc/{}/fdinfo", pid)).join(fd_name);
+    let Ok(fdinfo) = std::fs::read_to_string(&fdinfo_path) else {
+        return false;
+    };
+    for line in fdinfo.lines() {
+        if let Some(flags) = line.strip_prefix("flags:") {
+            let Ok(flags) = u32::from_str_radix(flags.trim(), 8) else {
+                return false;
+            };
+            let access_mode = flags as i32 & libc::O_ACCMODE;
+            return access_mode == libc::O_WRONLY || access_mode == libc::O_RDWR;
+        }
+    }
+    false
+}
+
+/// /proc is Linux-specific; elsewhere the scan reports nothing
+#[cfg(not(target_os = "linux"))]
+pub fn scan_open_for_write(_root: &Path) -> Result<Vec<OpenFileInfo>> {
+    Ok(Vec::new())
+}
+
+/// /proc is Linux-specific; elsewhere the scan reports nothing
+#[cfg(not(target_os = "linux"))]
+pub fn scan_cwd_under(_root: &Path) -> Result<Vec<OpenFileInfo>> {
+    Ok(Vec::new())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_handles_reported_and_read_handles_ignored() {
+        let temp = TempDir::new().unwrap();
+        let written = temp.path().join("notebook.db-wal");
+        let read_only = temp.path().join("dataset.bin");
+        std::fs::write(&written, b"dirty pages").unwrap();
+        std::fs::write(&read_only, b"static input").unwrap();
+
+        // Keep both handles alive across the scan
+        let _writer = std::fs::OpenOptions::new().append(true).open(&written).unwrap();
+        let _reader = std::fs::File::open(&read_only).unwrap();
+
+        let at_risk = scan_open_for_write(temp.path()).unwrap();
+        let own_pid = std::process::id();
+
+        let written_entry = at_risk
+            .iter()
+            .find(|info| info.path == written)
+            .expect("file open for append must be reported");
+        assert_eq!(written_entry.pid, own_pid);
+        assert!(!written_entry.process_name.is_empty());
+
+        assert!(
+            !at_risk.iter().any(|info| info.path == read_only),
+            "read-only handles must not be reported"
+        );
+    }
+
+    #[test]
+    fn test_scan_outside_open_files_is_empty() {
+        let temp = TempDir::new().unwrap();
+        // Nothing in this fresh directory is open anywhere
+        assert!(scan_open_for_write(temp.path()).unwrap().is_empty());
+    }
+}
@@ -5,7 +5,6 @@ use std::io::{BufReader, Read};
 use memmap2::Mmap;
 use blake3::Hasher;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use rayon::prelude::*;
 
 /// Optimized file reading that chooses strategy based on file size
 pub fn read_file_optimized(path: &Path) -> Result<String> {
@@ -38,15 +37,60 @@ fn read_file_buffered(file: File) -> Result<String> {
     Ok(content)
 }
 
-/// Parallel file hashing using Blake3 for integrity verification
+/// Default file size above which hashing fans out across the rayon pool.
+///
+/// Parallel hashing only pays off once hashing outruns a single core's
+/// memory bandwidth; below the threshold the thread coordination costs
+/// more than it saves, and on slower shared storage the break-even point
+/// is higher still (around 32MB on our NFS). Operators can raise it with
+/// `--parallel-hash-threshold`.
+pub const DEFAULT_PARALLEL_HASH_THRESHOLD: u64 = 10 * 1024 * 1024;
+
+/// Effective threshold; overridable once at startup from the CLI
+static PARALLEL_HASH_THRESHOLD: std::sync::atomic::AtomicU64 =
+    std::sync::atomic::AtomicU64::new(DEFAULT_PARALLEL_HASH_THRESHOLD);
+
+/// Install the process-wide parallel hashing threshold
+pub fn install_parallel_hash_threshold(threshold: u64) {
+    PARALLEL_HASH_THRESHOLD.store(threshold, std::sync::atomic::Ordering::Relaxed);
+}
+
+fn parallel_hash_threshold() -> u64 {
+    PARALLEL_HASH_THRESHOLD.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Hashing path counters, for instrumentation in tests
+static SEQUENTIAL_HASHES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+static PARALLEL_HASHES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// How many files were hashed on the sequential path so far
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn sequential_hash_count() -> u64 {
+    SEQUENTIAL_HASHES.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// How many files were hashed on the parallel path so far
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn parallel_hash_count() -> u64 {
+    PARALLEL_HASHES.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Blake3 file hashing for integrity verification: files above the
+/// configured threshold are hashed across the rayon pool. Both paths
+/// produce the identical blake3 digest.
 pub fn hash_file_parallel(path: &Path) -> Result<String> {
     let file = File::open(path)?;
     let metadata = file.metadata()?;
     let file_size = metadata.len();
-    
-    if file_size > 10 * 1024 * 1024 { // 10MB threshold for parallel hashing
-        hash_file_parallel_chunks(file, file_size)
+
+    // Inside a rayon worker (the restore already fans files out across
+    // the pool) nested update_rayon oversubscribes the pool; hash
+    // sequentially there and keep chunked parallelism for standalone calls
+    if file_size > parallel_hash_threshold() && rayon::current_thread_index().is_none() {
+        PARALLEL_HASHES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        hash_file_parallel_chunks(file)
     } else {
+        SEQUENTIAL_HASHES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         hash_file_sequential(file)
     }
 }
@@ -59,57 +103,145 @@ fn hash_file_sequential(file: File) -> Result<String> {
     Ok(hasher.finalize().to_hex().to_string())
 }
 
-/// Parallel file hashing for large files using chunks
-fn hash_file_parallel_chunks(file: File, file_size: u64) -> Result<String> {
-    const CHUNK_SIZE: u64 = 1024 * 1024; // 1MB chunks
-    let num_chunks = (file_size + CHUNK_SIZE - 1) / CHUNK_SIZE;
-    
+/// Parallel file hashing for large files. `update_rayon` splits the
+/// input across the rayon pool internally while yielding the same digest
+/// as a sequential update.
+fn hash_file_parallel_chunks(file: File) -> Result<String> {
     let mmap = unsafe { Mmap::map(&file)? };
-    
-    // Hash chunks in parallel
-    let chunk_hashes: Result<Vec<_>> = (0..num_chunks)
-        .into_par_iter()
-        .map(|chunk_idx| {
-            let start = (chunk_idx * CHUNK_SIZE) as usize;
-            let end = std::cmp::min(start + CHUNK_SIZE as usize, mmap.len());
-            
-            let mut hasher = Hasher::new();
-            hasher.update(&mmap[start..end]);
-            Ok(hasher.finalize())
-        })
-        .collect();
-    
-    let hashes = chunk_hashes?;
-    
-    // Combine chunk hashes
-    let mut final_hasher = Hasher::new();
-    for hash in hashes {
-        final_hasher.update(hash.as_bytes());
+    let mut hasher = Hasher::new();
+    hasher.update_rayon(&mmap);
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Default copy buffer; small files gain nothing from a larger one
+pub const DEFAULT_COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Buffer used for large sequential files, where 1-4MB buffers markedly
+/// improve throughput on NFS
+pub const LARGE_COPY_BUFFER_SIZE: usize = 2 * 1024 * 1024;
+
+/// File size above which the large buffer is auto-selected
+pub const LARGE_FILE_BUFFER_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Read syscalls issued by the copy loops, for instrumentation in tests
+static READ_SYSCALLS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+fn note_read_syscall() {
+    READ_SYSCALLS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Total read syscalls issued by the copy loops so far
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn read_syscall_count() -> u64 {
+    READ_SYSCALLS.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Pick the copy buffer size for a file of the given size
+pub fn buffer_size_for(file_size: u64) -> usize {
+    if file_size >= LARGE_FILE_BUFFER_THRESHOLD {
+        LARGE_COPY_BUFFER_SIZE
+    } else {
+        DEFAULT_COPY_BUFFER_SIZE
     }
-    
-    Ok(final_hasher.finalize().to_hex().to_string())
 }
 
-/// Async file copying with progress tracking
-pub async fn copy_file_async(src: &Path, dst: &Path) -> Result<u64> {
+/// Copy a file while hashing it from the same buffers used for writing,
+/// so a manifest build costs no second read of the source. Returns the
+/// bytes copied and the blake3 hex digest of the content.
+pub fn copy_file_hashed(src: &Path, dst: &Path) -> Result<(u64, String)> {
+    use std::io::Write;
+
+    let mut src_file = File::open(src)
+        .with_context(|| format!("Failed to open source file: {}", src.display()))?;
+    let file_size = src_file.metadata()?.len();
+    let mut dst_file = File::create(dst)
+        .with_context(|| format!("Failed to create target file: {}", dst.display()))?;
+
+    let mut buffer = vec![0u8; buffer_size_for(file_size)];
+    let mut hasher = Hasher::new();
+    let mut total_copied = 0u64;
+    loop {
+        let bytes_read = src_file.read(&mut buffer)?;
+        note_read_syscall();
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        dst_file.write_all(&buffer[..bytes_read])?;
+        total_copied += bytes_read as u64;
+    }
+    Ok((total_copied, hasher.finalize().to_hex().to_string()))
+}
+
+/// Async twin of [`copy_file_hashed`]: the hash is computed incrementally
+/// from the write buffers instead of re-reading the file afterwards
+pub async fn copy_file_async_hashed(src: &Path, dst: &Path, buffer_size: usize) -> Result<(u64, String)> {
     let mut src_file = tokio::fs::File::open(src).await?;
+    if let Some(parent) = dst.parent() {
+        let parent = parent.to_path_buf();
+        tokio::task::spawn_blocking(move || crate::dir_cache::ensure_dir_exists(&parent)).await??;
+    }
     let mut dst_file = tokio::fs::File::create(dst).await?;
-    
-    let metadata = src_file.metadata().await?;
-    let _file_size = metadata.len();
-    
-    // Create parent directories if needed
+
+    let mut buffer = vec![0u8; buffer_size.max(4096)];
+    let mut hasher = Hasher::new();
+    let mut total_copied = 0u64;
+    loop {
+        let bytes_read = src_file.read(&mut buffer).await?;
+        note_read_syscall();
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        dst_file.write_all(&buffer[..bytes_read]).await?;
+        total_copied += bytes_read as u64;
+    }
+    dst_file.sync_all().await?;
+    Ok((total_copied, hasher.finalize().to_hex().to_string()))
+}
+
+/// Async file copying with progress tracking; the buffer size is
+/// auto-selected from the file size
+pub async fn copy_file_async(src: &Path, dst: &Path) -> Result<u64> {
+    // Large files bypass the page cache when the direct I/O policy is
+    // installed; the aligned-buffer copy is blocking, so hand it off
+    if let Some(policy) = crate::direct_io::installed_policy() {
+        let size = tokio::fs::metadata(src).await?.len();
+        if size >= policy.min_size {
+            if let Some(parent) = dst.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let src = src.to_path_buf();
+            let dst = dst.to_path_buf();
+            return tokio::task::spawn_blocking(move || {
+                crate::direct_io::copy_file_with_policy(&src, &dst, &policy)
+            })
+            .await?;
+        }
+    }
+
+    let file_size = tokio::fs::metadata(src).await?.len();
+    copy_file_async_with_buffer(src, dst, buffer_size_for(file_size)).await
+}
+
+/// Async file copy with an explicit buffer size. Returns the bytes copied
+/// and feeds per-read instrumentation through [`note_read_syscall`].
+pub async fn copy_file_async_with_buffer(src: &Path, dst: &Path, buffer_size: usize) -> Result<u64> {
+    let mut src_file = tokio::fs::File::open(src).await?;
+
+    // Create parent directories if needed (race-safe across parallel workers)
     if let Some(parent) = dst.parent() {
-        tokio::fs::create_dir_all(parent).await?;
+        let parent = parent.to_path_buf();
+        tokio::task::spawn_blocking(move || crate::dir_cache::ensure_dir_exists(&parent)).await??;
     }
-    
-    // Use larger buffer for better performance
-    const BUFFER_SIZE: usize = 64 * 1024; // 64KB buffer
-    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut dst_file = tokio::fs::File::create(dst).await?;
+
+    let mut buffer = vec![0u8; buffer_size.max(4096)];
     let mut total_copied = 0u64;
     
     loop {
         let bytes_read = src_file.read(&mut buffer).await?;
+        note_read_syscall();
         if bytes_read == 0 {
             break;
         }
@@ -120,4 +252,115 @@ pub async fn copy_file_async(src: &Path, dst: &Path) -> Result<u64> {
     
     dst_file.sync_all().await?;
     Ok(total_copied)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_large_buffer_copies_correctly_with_fewer_reads() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("model.bin");
+        let contents: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 253) as u8).collect();
+        std::fs::write(&src, &contents).unwrap();
+
+        // Small buffer: many reads
+        let small_dst = temp.path().join("copy-small.bin");
+        let before = read_syscall_count();
+        let bytes = copy_file_async_with_buffer(&src, &small_dst, 64 * 1024).await.unwrap();
+        let small_reads = read_syscall_count() - before;
+        assert_eq!(bytes, contents.len() as u64);
+
+        // Large buffer: same bytes, far fewer read syscalls
+        let large_dst = temp.path().join("copy-large.bin");
+        let before = read_syscall_count();
+        let bytes = copy_file_async_with_buffer(&src, &large_dst, LARGE_COPY_BUFFER_SIZE).await.unwrap();
+        let large_reads = read_syscall_count() - before;
+        assert_eq!(bytes, contents.len() as u64);
+
+        assert_eq!(std::fs::read(&small_dst).unwrap(), contents);
+        assert_eq!(std::fs::read(&large_dst).unwrap(), contents);
+        assert!(
+            large_reads * 4 < small_reads,
+            "expected far fewer reads with the large buffer ({} vs {})",
+            large_reads,
+            small_reads
+        );
+    }
+
+    #[test]
+    fn test_hash_threshold_selects_path_with_identical_digests() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("checkpoint.bin");
+        let contents: Vec<u8> = (0..2 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&file, &contents).unwrap();
+
+        // Just below the threshold: sequential path
+        install_parallel_hash_threshold(contents.len() as u64);
+        let seq_before = sequential_hash_count();
+        let sequential_digest = hash_file_parallel(&file).unwrap();
+        assert_eq!(sequential_hash_count() - seq_before, 1);
+
+        // Same file above a lowered threshold: parallel path
+        install_parallel_hash_threshold(contents.len() as u64 - 1);
+        let par_before = parallel_hash_count();
+        let parallel_digest = hash_file_parallel(&file).unwrap();
+        assert_eq!(parallel_hash_count() - par_before, 1);
+
+        // The two paths must agree with each other and with plain blake3
+        assert_eq!(sequential_digest, parallel_digest);
+        assert_eq!(sequential_digest, blake3::hash(&contents).to_hex().to_string());
+
+        // From inside a rayon worker (as the parallel restore calls us)
+        // the pool-aware check must fall back to sequential hashing, so
+        // nested parallelism cannot oversubscribe or deadlock the pool
+        use rayon::prelude::*;
+        let nested_before = sequential_hash_count();
+        let nested_digests: Vec<String> = (0..4)
+            .into_par_iter()
+            .map(|_| hash_file_parallel(&file).unwrap())
+            .collect();
+        assert_eq!(sequential_hash_count() - nested_before, 4);
+        assert!(nested_digests.iter().all(|digest| *digest == parallel_digest));
+
+        install_parallel_hash_threshold(DEFAULT_PARALLEL_HASH_THRESHOLD);
+    }
+
+    #[test]
+    fn test_sync_copy_integrated_hash_matches_independent_rehash() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("weights.bin");
+        let contents: Vec<u8> = (0..300_000).map(|i| (i % 249) as u8).collect();
+        std::fs::write(&src, &contents).unwrap();
+
+        let dst = temp.path().join("weights.copy");
+        let (bytes, hash) = copy_file_hashed(&src, &dst).unwrap();
+        assert_eq!(bytes, contents.len() as u64);
+        assert_eq!(std::fs::read(&dst).unwrap(), contents);
+        // The hash fed from the copy buffers must agree with a full re-read
+        assert_eq!(hash, blake3::hash(&contents).to_hex().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_async_copy_integrated_hash_matches_independent_rehash() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("checkpoint.bin");
+        let contents: Vec<u8> = (0..300_000).map(|i| (i % 247) as u8).collect();
+        std::fs::write(&src, &contents).unwrap();
+
+        let dst = temp.path().join("checkpoint.copy");
+        let (bytes, hash) = copy_file_async_hashed(&src, &dst, DEFAULT_COPY_BUFFER_SIZE).await.unwrap();
+        assert_eq!(bytes, contents.len() as u64);
+        assert_eq!(std::fs::read(&dst).unwrap(), contents);
+        assert_eq!(hash, blake3::hash(&contents).to_hex().to_string());
+    }
+
+    #[test]
+    fn test_buffer_size_auto_selection() {
+        assert_eq!(buffer_size_for(4 * 1024), DEFAULT_COPY_BUFFER_SIZE);
+        assert_eq!(buffer_size_for(LARGE_FILE_BUFFER_THRESHOLD), LARGE_COPY_BUFFER_SIZE);
+        assert_eq!(buffer_size_for(u64::MAX), LARGE_COPY_BUFFER_SIZE);
+    }
+}
@@ -0,0 +1,178 @@
+use log::debug;
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Copy ordering policy applied when building the per-directory work
+/// queues of the transfer and restore pipelines, set from `--order`.
+///
+/// Under a hard wall-clock deadline the order decides what a partial run
+/// is worth: a pre-termination backup wants the largest irreplaceable
+/// files first, while a restore wants small config files first so the
+/// user is productive before the bulk data lands. When the deadline cuts
+/// a run short, the result records which priority tier was reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyOrder {
+    /// Directory order as the filesystem returns it
+    #[default]
+    Natural,
+    /// Largest files first: maximizes bytes saved by a cut-short backup
+    LargestFirst,
+    /// Smallest files first: maximizes file count restored before the cut
+    SmallestFirst,
+    /// Dotfiles and files under `etc/` or `home/` first, then the rest
+    ConfigFirst,
+}
+
+impl CopyOrder {
+    /// Sort key for a file at `relative` with `size` bytes: priority tier
+    /// first, then the size rank within the tier. Equal keys keep their
+    /// natural order under a stable sort, so `Natural` and the tier-only
+    /// `ConfigFirst` leave intra-tier order untouched.
+    pub fn sort_key(&self, relative: &Path, size: u64) -> (u8, u64) {
+        match self {
+            CopyOrder::Natural => (0, 0),
+            CopyOrder::LargestFirst => (0, u64::MAX - size),
+            CopyOrder::SmallestFirst => (0, size),
+            CopyOrder::ConfigFirst => (if is_config_path(relative) { 0 } else { 1 }, 0),
+        }
+    }
+
+    /// Human-readable priority tier of a file under this ordering,
+    /// recorded in the result when the deadline expires so operators know
+    /// how far the prioritized queue got
+    pub fn tier(&self, relative: &Path, size: u64) -> &'static str {
+        match self {
+            CopyOrder::Natural => "unordered",
+            CopyOrder::LargestFirst | CopyOrder::SmallestFirst => size_bucket(size),
+            CopyOrder::ConfigFirst => {
+                if is_config_path(relative) {
+                    "config"
+                } else {
+                    "general"
+                }
+            }
+        }
+    }
+}
+
+/// Size bucket label for the size-based orderings
+fn size_bucket(size: u64) -> &'static str {
+    const MIB: u64 = 1024 * 1024;
+    if size >= 64 * MIB {
+        "large (>=64 MiB)"
+    } else if size >= MIB {
+        "medium (>=1 MiB)"
+    } else {
+        "small (<1 MiB)"
+    }
+}
+
+/// True for paths `ConfigFirst` prioritizes: dotfiles anywhere, and
+/// anything under a top-level `etc/` or `home/` directory
+fn is_config_path(relative: &Path) -> bool {
+    if relative
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    {
+        return true;
+    }
+    matches!(
+        relative.components().next(),
+        Some(std::path::Component::Normal(first)) if first == "etc" || first == "home"
+    )
+}
+
+/// Globally installed copy order, set once at binary startup from `--order`
+static COPY_ORDER: AtomicU8 = AtomicU8::new(0);
+
+/// Install the process-wide copy order
+pub fn install(order: CopyOrder) {
+    debug!("Installing copy order: {:?}", order);
+    let value = match order {
+        CopyOrder::Natural => 0,
+        CopyOrder::LargestFirst => 1,
+        CopyOrder::SmallestFirst => 2,
+        CopyOrder::ConfigFirst => 3,
+    };
+    COPY_ORDER.store(value, Ordering::Relaxed);
+}
+
+/// The installed copy order; `Natural` unless `--order` was given
+pub fn installed() -> CopyOrder {
+    match COPY_ORDER.load(Ordering::Relaxed) {
+        1 => CopyOrder::LargestFirst,
+        2 => CopyOrder::SmallestFirst,
+        3 => CopyOrder::ConfigFirst,
+        _ => CopyOrder::Natural,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture() -> Vec<(PathBuf, u64)> {
+        vec![
+            (PathBuf::from("data/model.bin"), 500 * 1024 * 1024),
+            (PathBuf::from(".bashrc"), 200),
+            (PathBuf::from("work/notes.txt"), 4 * 1024 * 1024),
+            (PathBuf::from("etc/app/config.yaml"), 900),
+            (PathBuf::from("work/scratch.log"), 100),
+        ]
+    }
+
+    fn sorted_names(order: CopyOrder) -> Vec<String> {
+        let mut files = fixture();
+        files.sort_by_key(|(path, size)| order.sort_key(path, *size));
+        files
+            .into_iter()
+            .map(|(path, _)| path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_each_policy_orders_the_fixture_queue() {
+        assert_eq!(
+            sorted_names(CopyOrder::Natural),
+            ["data/model.bin", ".bashrc", "work/notes.txt", "etc/app/config.yaml", "work/scratch.log"]
+        );
+        assert_eq!(
+            sorted_names(CopyOrder::LargestFirst),
+            ["data/model.bin", "work/notes.txt", "etc/app/config.yaml", ".bashrc", "work/scratch.log"]
+        );
+        assert_eq!(
+            sorted_names(CopyOrder::SmallestFirst),
+            ["work/scratch.log", ".bashrc", "etc/app/config.yaml", "work/notes.txt", "data/model.bin"]
+        );
+        // Config tier first in natural relative order, then the rest
+        assert_eq!(
+            sorted_names(CopyOrder::ConfigFirst),
+            [".bashrc", "etc/app/config.yaml", "data/model.bin", "work/notes.txt", "work/scratch.log"]
+        );
+    }
+
+    #[test]
+    fn test_tier_labels_follow_the_policy() {
+        let big = 200 * 1024 * 1024;
+        assert_eq!(CopyOrder::Natural.tier(Path::new("a"), big), "unordered");
+        assert_eq!(CopyOrder::LargestFirst.tier(Path::new("a"), big), "large (>=64 MiB)");
+        assert_eq!(CopyOrder::SmallestFirst.tier(Path::new("a"), 10), "small (<1 MiB)");
+        assert_eq!(CopyOrder::ConfigFirst.tier(Path::new("etc/passwd"), 10), "config");
+        assert_eq!(CopyOrder::ConfigFirst.tier(Path::new("data/x.bin"), 10), "general");
+        assert_eq!(CopyOrder::ConfigFirst.tier(Path::new("work/.env"), 10), "config");
+    }
+
+    #[test]
+    fn test_install_round_trips_every_order() {
+        for order in [
+            CopyOrder::LargestFirst,
+            CopyOrder::SmallestFirst,
+            CopyOrder::ConfigFirst,
+            CopyOrder::Natural,
+        ] {
+            install(order);
+            assert_eq!(installed(), order);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use log::info;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default number of slowest files kept in the profile report
+pub const DEFAULT_PROFILE_TOP_N: usize = 50;
+
+/// Per-file timing entry in the profile report
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileTiming {
+    pub path: PathBuf,
+    pub duration_micros: u64,
+}
+
+impl Ord for FileTiming {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.duration_micros
+            .cmp(&other.duration_micros)
+            .then_with(|| self.path.cmp(&other.path))
+    }
+}
+
+impl PartialOrd for FileTiming {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Per-directory aggregate in the profile report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryTiming {
+    pub path: PathBuf,
+    pub total_micros: u64,
+    pub file_count: usize,
+}
+
+/// Hotspot report: the slowest files and the directories dominating the
+/// run, both sorted slowest-first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub slowest_files: Vec<FileTiming>,
+    pub directory_totals: Vec<DirectoryTiming>,
+}
+
+/// Collects per-file copy durations during a restore run.
+///
+/// Memory stays bounded regardless of file count: only the top-N slowest
+/// files are kept, in a min-heap so the cheapest entry is evicted first.
+/// Directory totals aggregate every file into its immediate parent.
+#[derive(Debug)]
+pub struct RestoreProfiler {
+    top_n: usize,
+    slowest: Mutex<BinaryHeap<Reverse<FileTiming>>>,
+    directories: Mutex<HashMap<PathBuf, (Duration, usize)>>,
+}
+
+impl RestoreProfiler {
+    pub fn new(top_n: usize) -> Self {
+        Self {
+            top_n: top_n.max(1),
+            slowest: Mutex::new(BinaryHeap::new()),
+            directories: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record one file's copy duration
+    pub fn record(&self, path: &Path, duration: Duration) {
+        let timing = FileTiming {
+            path: path.to_path_buf(),
+            duration_micros: duration.as_micros() as u64,
+        };
+
+        {
+            let mut slowest = self.slowest.lock();
+            if slowest.len() < self.top_n {
+                slowest.push(Reverse(timing));
+            } else if slowest
+                .peek()
+                .is_some_and(|Reverse(cheapest)| *cheapest < timing)
+            {
+                slowest.pop();
+                slowest.push(Reverse(timing));
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            let mut directories = self.directories.lock();
+            let entry = directories.entry(parent.to_path_buf()).or_insert((Duration::ZERO, 0));
+            entry.0 += duration;
+            entry.1 += 1;
+        }
+    }
+
+    /// Build the sorted report, slowest entries first
+    pub fn report(&self) -> ProfileReport {
+        let mut slowest_files: Vec<FileTiming> = self
+            .slowest
+            .lock()
+            .iter()
+            .map(|Reverse(timing)| timing.clone())
+            .collect();
+        slowest_files.sort_by(|a, b| b.cmp(a));
+
+        let mut directory_totals: Vec<DirectoryTiming> = self
+            .directories
+            .lock()
+            .iter()
+            .map(|(path, (total, file_count))| DirectoryTiming {
+                path: path.clone(),
+                total_micros: total.as_micros() as u64,
+                file_count: *file_count,
+            })
+            .collect();
+        directory_totals.sort_by_key(|d| Reverse(d.total_micros));
+
+        ProfileReport {
+            slowest_files,
+            directory_totals,
+        }
+    }
+
+    /// Log a short hotspot summary at the end of a run
+    pub fn log_summary(&self) {
+        let report = self.report();
+        info!("Restore timing profile (top {} files):", self.top_n);
+        for timing in report.slowest_files.iter().take(10) {
+            info!("  {:>10}us  {}", timing.duration_micros, timing.path.display());
+        }
+        for directory in report.directory_totals.iter().take(5) {
+            info!(
+                "  {:>10}us  {} ({} files)",
+                directory.total_micros,
+                directory.path.display(),
+                directory.file_count
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiler_reports_slowest_files_and_directory_totals() {
+        let profiler = RestoreProfiler::new(3);
+        profiler.record(Path::new("/root/fast.txt"), Duration::from_micros(10));
+        profiler.record(Path::new("/root/slow.bin"), Duration::from_millis(50));
+        profiler.record(Path::new("/root/medium.log"), Duration::from_millis(5));
+        profiler.record(Path::new("/home/user/big.ckpt"), Duration::from_millis(200));
+        profiler.record(Path::new("/home/user/tiny.txt"), Duration::from_micros(1));
+
+        let report = profiler.report();
+
+        // Only top-3 retained, sorted slowest-first with nonzero durations
+        assert_eq!(report.slowest_files.len(), 3);
+        assert_eq!(report.slowest_files[0].path, Path::new("/home/user/big.ckpt"));
+        assert_eq!(report.slowest_files[1].path, Path::new("/root/slow.bin"));
+        assert_eq!(report.slowest_files[2].path, Path::new("/root/medium.log"));
+        assert!(report.slowest_files.iter().all(|t| t.duration_micros > 0));
+
+        // Directory totals aggregate every file, not just the retained ones
+        assert_eq!(report.directory_totals[0].path, Path::new("/home/user"));
+        assert_eq!(report.directory_totals[0].file_count, 2);
+        let root = report
+            .directory_totals
+            .iter()
+            .find(|d| d.path == Path::new("/root"))
+            .unwrap();
+        assert_eq!(root.file_count, 3);
+        assert_eq!(root.total_micros, 55_010);
+    }
+
+    #[test]
+    fn test_profiler_memory_stays_bounded() {
+        let profiler = RestoreProfiler::new(5);
+        for i in 0..10_000 {
+            profiler.record(
+                Path::new(&format!("/data/file-{}", i)),
+                Duration::from_micros(i),
+            );
+        }
+        let report = profiler.report();
+        assert_eq!(report.slowest_files.len(), 5);
+        // The heap keeps the largest durations
+        assert_eq!(report.slowest_files[0].duration_micros, 9_999);
+        assert_eq!(report.slowest_files[4].duration_micros, 9_995);
+    }
+}
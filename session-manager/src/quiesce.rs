@@ -0,0 +1,414 @@
+//! Pre-restore quiesce guard.
+//!
+//! Restoring /opt/conda while a postStart hook is mid `pip install`
+//! interleaves two writers over the same site-packages tree and corrupts
+//! the environment. Before writing anything, the guard looks at each
+//! directory the restore will touch for in-flight package operations:
+//! lock artifacts (conda's pkgs/*.lock, pip build temp dirs, held
+//! dpkg/apt locks) and processes that hold files open for writing or sit
+//! with their cwd inside the tree. The caller either waits for the
+//! activity to drain ([`wait_for_quiesce`]) or skips the busy subtrees.
+//!
+//! [`check_quiesce`] is deliberately standalone with its own result type
+//! so an operator can run the same check by hand before a manual restore.
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::open_files;
+
+/// One reason a restore target is not quiet. `subtree` is the directory
+/// the restore should leave alone until the activity drains.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuiesceIssue {
+    pub subtree: PathBuf,
+    pub reason: String,
+}
+
+/// Outcome of one quiesce check over the restore targets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuiesceReport {
+    pub issues: Vec<QuiesceIssue>,
+}
+
+impl QuiesceReport {
+    pub fn is_quiet(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The distinct subtrees to exclude from the restore
+    pub fn busy_subtrees(&self) -> Vec<PathBuf> {
+        let mut subtrees: Vec<_> = self.issues.iter().map(|i| i.subtree.clone()).collect();
+        subtrees.sort();
+        subtrees.dedup();
+        subtrees
+    }
+}
+
+/// A path shape that marks an in-flight package operation when found
+/// under a restore target
+struct LockIndicator {
+    /// Relative glob locating the lock artifact; `*`/`?` match within a
+    /// single path segment
+    pattern: &'static str,
+    /// How many leading components of `pattern` form the subtree to
+    /// skip; 0 means the whole target is busy
+    scope: usize,
+    /// The artifact exists permanently (dpkg/apt lock files) and only
+    /// indicates activity while some process holds flock on it
+    requires_held_flock: bool,
+}
+
+/// The same operation shows up at different depths depending on which
+/// directory the backup tree rooted it under, so common prefixes are
+/// spelled out per indicator.
+const LOCK_INDICATORS: &[LockIndicator] = &[
+    // conda holds per-package locks in its pkgs cache while extracting
+    LockIndicator { pattern: "pkgs/*.lock", scope: 0, requires_held_flock: false },
+    LockIndicator { pattern: "conda/pkgs/*.lock", scope: 1, requires_held_flock: false },
+    LockIndicator { pattern: "opt/conda/pkgs/*.lock", scope: 2, requires_held_flock: false },
+    // pip stages wheels in temp dirs named after the operation
+    LockIndicator { pattern: "pip-build-*", scope: 0, requires_held_flock: false },
+    LockIndicator { pattern: "pip-install-*", scope: 0, requires_held_flock: false },
+    LockIndicator { pattern: "pip-req-build-*", scope: 0, requires_held_flock: false },
+    LockIndicator { pattern: "pip-ephem-wheel-cache-*", scope: 0, requires_held_flock: false },
+    LockIndicator { pattern: "tmp/pip-build-*", scope: 1, requires_held_flock: false },
+    LockIndicator { pattern: "tmp/pip-install-*", scope: 1, requires_held_flock: false },
+    LockIndicator { pattern: "tmp/pip-req-build-*", scope: 1, requires_held_flock: false },
+    LockIndicator { pattern: "tmp/pip-ephem-wheel-cache-*", scope: 1, requires_held_flock: false },
+    // dpkg/apt lock files exist permanently on Debian-based images; the
+    // files are only meaningful while their flock is held
+    LockIndicator { pattern: "var/lib/dpkg/lock", scope: 0, requires_held_flock: true },
+    LockIndicator { pattern: "var/lib/dpkg/lock-frontend", scope: 0, requires_held_flock: true },
+    LockIndicator { pattern: "var/lib/apt/lists/lock", scope: 0, requires_held_flock: true },
+    LockIndicator { pattern: "var/cache/apt/archives/lock", scope: 0, requires_held_flock: true },
+    LockIndicator { pattern: "lib/dpkg/lock", scope: 0, requires_held_flock: true },
+    LockIndicator { pattern: "lib/dpkg/lock-frontend", scope: 0, requires_held_flock: true },
+];
+
+/// The container-root directories a restore from `backup_path` will
+/// write into: one per top-level backup entry. Bookkeeping entries
+/// (.trash, metadata) never reach the container root and are skipped.
+pub fn restore_targets(backup_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut targets = Vec::new();
+    let entries = std::fs::read_dir(backup_path)
+        .with_context(|| format!("Failed to read backup directory: {}", backup_path.display()))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            targets.push(Path::new("/").join(name));
+        }
+    }
+    targets.sort();
+    Ok(targets)
+}
+
+/// One pass over `targets`: lock artifacts, writers, and processes with
+/// a cwd inside the tree. Targets that do not exist yet are trivially
+/// quiet — the restore creates them from scratch.
+pub fn check_quiesce(targets: &[PathBuf]) -> Result<QuiesceReport> {
+    let mut issues = Vec::new();
+
+    for target in targets {
+        if !target.is_dir() {
+            continue;
+        }
+
+        for indicator in LOCK_INDICATORS {
+            for matched in match_relative_pattern(target, indicator.pattern) {
+                if indicator.requires_held_flock && !flock_is_held(&matched) {
+                    continue;
+                }
+                let subtree = indicator
+                    .pattern
+                    .split('/')
+                    .take(indicator.scope)
+                    .fold(target.clone(), |p, c| p.join(c));
+                issues.push(QuiesceIssue {
+                    subtree,
+                    reason: format!("package operation in flight: {}", matched.display()),
+                });
+            }
+        }
+
+        for open in open_files::scan_open_for_write(target)? {
+            issues.push(QuiesceIssue {
+                subtree: target.clone(),
+                reason: format!(
+                    "{} (pid {}) has {} open for writing",
+                    open.process_name,
+                    open.pid,
+                    open.path.display()
+                ),
+            });
+        }
+
+        for parked in open_files::scan_cwd_under(target)? {
+            issues.push(QuiesceIssue {
+                subtree: target.clone(),
+                reason: format!(
+                    "{} (pid {}) has its working directory at {}",
+                    parked.process_name,
+                    parked.pid,
+                    parked.path.display()
+                ),
+            });
+        }
+    }
+
+    Ok(QuiesceReport { issues })
+}
+
+/// Poll [`check_quiesce`] until the targets are quiet or `wait` elapses;
+/// returns the last report either way, so the caller sees what is still
+/// busy after the deadline
+pub fn wait_for_quiesce(
+    targets: &[PathBuf],
+    wait: Duration,
+    poll_interval: Duration,
+) -> Result<QuiesceReport> {
+    let start = Instant::now();
+    loop {
+        let report = check_quiesce(targets)?;
+        if report.is_quiet() {
+            return Ok(report);
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= wait {
+            return Ok(report);
+        }
+        debug!(
+            "Waiting for {} busy subtree(s) to quiesce ({:?} of {:?} elapsed)",
+            report.busy_subtrees().len(),
+            elapsed,
+            wait
+        );
+        std::thread::sleep(poll_interval.min(wait - elapsed));
+    }
+}
+
+/// Expand a relative glob under `base`, matching `*`/`?` within single
+/// path segments via the shared segment matcher
+fn match_relative_pattern(base: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut candidates = vec![base.to_path_buf()];
+    for component in pattern.split('/') {
+        let mut next = Vec::new();
+        if component.contains('*') || component.contains('?') {
+            for candidate in &candidates {
+                let Ok(entries) = std::fs::read_dir(candidate) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    let lossy = name.to_string_lossy();
+                    if crate::filter::segment_match(component.as_bytes(), lossy.as_bytes()) {
+                        next.push(candidate.join(&name));
+                    }
+                }
+            }
+        } else {
+            for candidate in &candidates {
+                let path = candidate.join(component);
+                if path.symlink_metadata().is_ok() {
+                    next.push(path);
+                }
+            }
+        }
+        candidates = next;
+        if candidates.is_empty() {
+            break;
+        }
+    }
+    candidates.sort();
+    candidates
+}
+
+/// Whether some process currently holds flock on `path`; probed with a
+/// non-blocking exclusive lock that is released immediately on success
+#[cfg(unix)]
+fn flock_is_held(path: &Path) -> bool {
+    use std::os::unix::io::AsRawFd;
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let ret = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if ret == 0 {
+        unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) };
+        return false;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EWOULDBLOCK)
+}
+
+/// flock probing is unix-specific; elsewhere held locks are not detected
+#[cfg(not(unix))]
+fn flock_is_held(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_conda_pkgs_locks_flag_the_environment_busy() {
+        let temp = TempDir::new().unwrap();
+        let pkgs = temp.path().join("conda/pkgs");
+        std::fs::create_dir_all(&pkgs).unwrap();
+        std::fs::write(pkgs.join("numpy-2.0.1.lock"), b"").unwrap();
+        // A non-lock file in the cache is not an indicator
+        std::fs::write(pkgs.join("numpy-2.0.1.tar.bz2"), b"pkg").unwrap();
+
+        let targets = vec![temp.path().to_path_buf()];
+        let report = check_quiesce(&targets).unwrap();
+        assert!(
+            report
+                .issues
+                .iter()
+                .any(|i| i.reason.contains("numpy-2.0.1.lock")),
+            "lock file must be reported: {:?}",
+            report.issues
+        );
+        // The scope is the conda root, not the whole target
+        assert!(report
+            .busy_subtrees()
+            .contains(&temp.path().join("conda")));
+
+        std::fs::remove_file(pkgs.join("numpy-2.0.1.lock")).unwrap();
+        assert!(check_quiesce(&targets).unwrap().is_quiet());
+    }
+
+    #[test]
+    fn test_pip_build_temp_dirs_flag_the_whole_target() {
+        let temp = TempDir::new().unwrap();
+        let tmp = temp.path().join("tmp");
+        std::fs::create_dir_all(tmp.join("pip-req-build-a1b2c3")).unwrap();
+
+        let targets = vec![temp.path().to_path_buf()];
+        let report = check_quiesce(&targets).unwrap();
+        assert!(!report.is_quiet());
+        assert_eq!(report.busy_subtrees(), vec![tmp]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dpkg_lock_file_only_counts_while_flock_is_held() {
+        use std::os::unix::io::AsRawFd;
+
+        let temp = TempDir::new().unwrap();
+        let dpkg = temp.path().join("var/lib/dpkg");
+        std::fs::create_dir_all(&dpkg).unwrap();
+        let lock_path = dpkg.join("lock");
+        std::fs::write(&lock_path, b"").unwrap();
+
+        // The lock file exists permanently on Debian images; existence
+        // alone must not flag the target
+        let targets = vec![temp.path().to_path_buf()];
+        assert!(check_quiesce(&targets).unwrap().is_quiet());
+
+        // Hold the flock the way dpkg does and the target turns busy
+        let holder = std::fs::File::open(&lock_path).unwrap();
+        assert_eq!(
+            unsafe { libc::flock(holder.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) },
+            0
+        );
+        let report = check_quiesce(&targets).unwrap();
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| i.reason.contains("dpkg/lock")));
+        assert_eq!(report.busy_subtrees(), vec![temp.path().to_path_buf()]);
+
+        drop(holder);
+        assert!(check_quiesce(&targets).unwrap().is_quiet());
+    }
+
+    #[test]
+    fn test_wait_for_quiesce_returns_once_the_lock_clears() {
+        let temp = TempDir::new().unwrap();
+        let pkgs = temp.path().join("pkgs");
+        std::fs::create_dir_all(&pkgs).unwrap();
+        let lock = pkgs.join("in-flight.lock");
+        std::fs::write(&lock, b"").unwrap();
+
+        let remover = std::thread::spawn({
+            let lock = lock.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(100));
+                std::fs::remove_file(&lock).unwrap();
+            }
+        });
+
+        let targets = vec![temp.path().to_path_buf()];
+        let start = Instant::now();
+        let report = wait_for_quiesce(
+            &targets,
+            Duration::from_secs(10),
+            Duration::from_millis(20),
+        )
+        .unwrap();
+        remover.join().unwrap();
+
+        assert!(report.is_quiet());
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "must return as soon as the lock clears, not at the deadline"
+        );
+    }
+
+    #[test]
+    fn test_deadline_expiry_reports_what_is_still_busy() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("pip-install-xyz")).unwrap();
+
+        let targets = vec![temp.path().to_path_buf()];
+        let report = wait_for_quiesce(
+            &targets,
+            Duration::from_millis(50),
+            Duration::from_millis(10),
+        )
+        .unwrap();
+        assert!(!report.is_quiet());
+        assert_eq!(report.busy_subtrees(), targets);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_open_writer_under_a_target_flags_it_busy() {
+        let temp = TempDir::new().unwrap();
+        let notebook = temp.path().join("autosave.ipynb");
+        std::fs::write(&notebook, b"{}").unwrap();
+        let _writer = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&notebook)
+            .unwrap();
+
+        let targets = vec![temp.path().to_path_buf()];
+        let report = check_quiesce(&targets).unwrap();
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| i.reason.contains("open for writing")));
+    }
+
+    #[test]
+    fn test_restore_targets_map_backup_entries_to_root_dirs() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("opt")).unwrap();
+        std::fs::create_dir_all(temp.path().join("root")).unwrap();
+        std::fs::create_dir_all(temp.path().join(".trash")).unwrap();
+        std::fs::write(temp.path().join("stray-file"), b"").unwrap();
+
+        let targets = restore_targets(temp.path()).unwrap();
+        assert_eq!(
+            targets,
+            vec![PathBuf::from("/opt"), PathBuf::from("/root")]
+        );
+    }
+}
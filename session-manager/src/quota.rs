@@ -0,0 +1,321 @@
+//! Backup-time quota enforcement.
+//!
+//! Platform policy caps persisted session data per user, but nothing
+//! stops a runaway session from filling the backup volume. With
+//! `--max-backup-bytes` the backup measures the source first; over
+//! quota it either fails or, with `--over-quota=trim`, drops the
+//! biggest entries from an ordered sacrificial list (caches first)
+//! until the remainder fits. Everything omitted is recorded in the
+//! transfer result and the backup manifest.
+//!
+//! The trimming decision ([`plan_trim`]) is pure over a size listing so
+//! it can be unit-tested without touching a filesystem.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::filter::TransferFilter;
+
+/// How the backup reacts when the source exceeds `--max-backup-bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverQuotaPolicy {
+    /// Abort the backup; nothing is written
+    Fail,
+    /// Drop sacrificial entries largest-first until under quota
+    Trim,
+}
+
+/// Default sacrificial globs for `--over-quota=trim`, most expendable
+/// first: regenerable caches before scratch space
+pub const DEFAULT_SACRIFICIAL_PATTERNS: &[&str] = &[
+    "**/.cache",
+    "**/__pycache__",
+    "**/.npm",
+    "**/.Trash",
+    "**/tmp",
+];
+
+/// A source entry eligible for trimming: the topmost path matching a
+/// sacrificial pattern, with its aggregate size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrimCandidate {
+    /// Path relative to the source root
+    pub path: PathBuf,
+    /// Aggregate size of the file or directory subtree
+    pub bytes: u64,
+    /// Position of the matching pattern in the sacrificial list;
+    /// lower is dropped first
+    pub pattern_index: usize,
+}
+
+/// One entry omitted from the backup to fit the quota; recorded in the
+/// transfer result and the backup manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrimmedEntry {
+    /// Path relative to the source root
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// The sacrificial pattern that made the entry expendable
+    pub matched_pattern: String,
+}
+
+/// Outcome of the trimming decision over a measured source
+#[derive(Debug, Clone)]
+pub struct QuotaPlan {
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub trimmed: Vec<TrimmedEntry>,
+}
+
+impl QuotaPlan {
+    /// Source size once the trimmed entries are excluded
+    pub fn remaining_bytes(&self) -> u64 {
+        self.total_bytes
+            .saturating_sub(self.trimmed.iter().map(|t| t.bytes).sum())
+    }
+
+    pub fn within_quota(&self) -> bool {
+        self.remaining_bytes() <= self.max_bytes
+    }
+}
+
+/// Decide what to drop: candidates are taken in sacrificial-list order,
+/// largest first within each pattern, until the remainder fits. Pure
+/// over the listing — no filesystem access. Candidates nested under an
+/// already-trimmed entry are ignored so their bytes are not subtracted
+/// twice.
+pub fn plan_trim(
+    total_bytes: u64,
+    max_bytes: u64,
+    mut candidates: Vec<TrimCandidate>,
+    patterns: &[String],
+) -> QuotaPlan {
+    let mut plan = QuotaPlan {
+        total_bytes,
+        max_bytes,
+        trimmed: Vec::new(),
+    };
+    if total_bytes <= max_bytes {
+        return plan;
+    }
+
+    candidates.sort_by(|a, b| {
+        a.pattern_index
+            .cmp(&b.pattern_index)
+            .then(b.bytes.cmp(&a.bytes))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    for candidate in candidates {
+        if plan.within_quota() {
+            break;
+        }
+        if plan
+            .trimmed
+            .iter()
+            .any(|t| candidate.path.starts_with(&t.path))
+        {
+            continue;
+        }
+        plan.trimmed.push(TrimmedEntry {
+            path: candidate.path,
+            bytes: candidate.bytes,
+            matched_pattern: patterns
+                .get(candidate.pattern_index)
+                .cloned()
+                .unwrap_or_default(),
+        });
+    }
+    plan
+}
+
+/// Measure the source in one walk: its total size and the topmost
+/// entries matching a sacrificial pattern, each with its aggregate
+/// size. Symlinks count their own length and are never followed.
+pub fn measure_source(source: &Path, patterns: &[String]) -> Result<(u64, Vec<TrimCandidate>)> {
+    // One single-pattern filter per list position; a path is a match
+    // for pattern i exactly when that filter would exclude it
+    let filters: Vec<TransferFilter> = patterns
+        .iter()
+        .map(|pattern| TransferFilter::new(std::slice::from_ref(pattern), &[]))
+        .collect();
+    let mut candidates = Vec::new();
+    let total = measure_dir(source, source, &filters, false, &mut candidates)?;
+    Ok((total, candidates))
+}
+
+fn measure_dir(
+    dir: &Path,
+    root: &Path,
+    filters: &[TransferFilter],
+    inside_candidate: bool,
+    candidates: &mut Vec<TrimCandidate>,
+) -> Result<u64> {
+    let mut total = 0u64;
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory for size estimation: {}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        let matched = if inside_candidate {
+            None
+        } else {
+            filters.iter().position(|f| !f.admits(relative))
+        };
+        let bytes = if metadata.is_dir() {
+            measure_dir(&path, root, filters, inside_candidate || matched.is_some(), candidates)?
+        } else {
+            metadata.len()
+        };
+        if let Some(pattern_index) = matched {
+            candidates.push(TrimCandidate {
+                path: relative.to_path_buf(),
+                bytes,
+                pattern_index,
+            });
+        }
+        total += bytes;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(path: &str, bytes: u64, pattern_index: usize) -> TrimCandidate {
+        TrimCandidate {
+            path: PathBuf::from(path),
+            bytes,
+            pattern_index,
+        }
+    }
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_under_quota_trims_nothing() {
+        let plan = plan_trim(
+            10_000,
+            20_000,
+            vec![candidate("work/.cache", 9_000, 0)],
+            &patterns(&["**/.cache"]),
+        );
+        assert!(plan.trimmed.is_empty());
+        assert!(plan.within_quota());
+        assert_eq!(plan.remaining_bytes(), 10_000);
+    }
+
+    #[test]
+    fn test_caches_go_before_later_patterns_and_largest_first() {
+        let pats = patterns(&["**/.cache", "**/tmp"]);
+        // A big tmp dir must not be sacrificed while dropping the two
+        // caches already gets under quota
+        let plan = plan_trim(
+            100,
+            40,
+            vec![
+                candidate("scratch/tmp", 50, 1),
+                candidate("a/.cache", 20, 0),
+                candidate("b/.cache", 45, 0),
+            ],
+            &pats,
+        );
+        let dropped: Vec<_> = plan.trimmed.iter().map(|t| t.path.clone()).collect();
+        assert_eq!(
+            dropped,
+            vec![PathBuf::from("b/.cache"), PathBuf::from("a/.cache")]
+        );
+        assert_eq!(plan.remaining_bytes(), 35);
+        assert!(plan.within_quota());
+        assert_eq!(plan.trimmed[0].matched_pattern, "**/.cache");
+    }
+
+    #[test]
+    fn test_later_patterns_are_sacrificed_only_when_caches_fall_short() {
+        let pats = patterns(&["**/.cache", "**/tmp"]);
+        let plan = plan_trim(
+            100,
+            30,
+            vec![
+                candidate("scratch/tmp", 50, 1),
+                candidate("a/.cache", 25, 0),
+            ],
+            &pats,
+        );
+        let dropped: Vec<_> = plan.trimmed.iter().map(|t| t.path.clone()).collect();
+        assert_eq!(
+            dropped,
+            vec![PathBuf::from("a/.cache"), PathBuf::from("scratch/tmp")]
+        );
+        assert_eq!(plan.remaining_bytes(), 25);
+    }
+
+    #[test]
+    fn test_nested_candidates_are_not_double_counted() {
+        let pats = patterns(&["**/.cache", "**/tmp"]);
+        // tmp lives inside the cache dir; once the parent is dropped the
+        // child's bytes are already gone
+        let plan = plan_trim(
+            100,
+            10,
+            vec![
+                candidate("work/.cache", 60, 0),
+                candidate("work/.cache/tmp", 40, 1),
+                candidate("other/tmp", 35, 1),
+            ],
+            &pats,
+        );
+        let dropped: Vec<_> = plan.trimmed.iter().map(|t| t.path.clone()).collect();
+        assert_eq!(
+            dropped,
+            vec![PathBuf::from("work/.cache"), PathBuf::from("other/tmp")]
+        );
+        assert_eq!(plan.remaining_bytes(), 5);
+    }
+
+    #[test]
+    fn test_exhausting_all_candidates_still_over_quota() {
+        let plan = plan_trim(
+            1_000,
+            10,
+            vec![candidate("a/.cache", 100, 0)],
+            &patterns(&["**/.cache"]),
+        );
+        assert_eq!(plan.trimmed.len(), 1);
+        assert!(!plan.within_quota());
+        assert_eq!(plan.remaining_bytes(), 900);
+    }
+
+    #[test]
+    fn test_measure_source_reports_topmost_matches_with_aggregate_sizes() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let cache = temp.path().join("work/.cache");
+        std::fs::create_dir_all(cache.join("sub")).unwrap();
+        std::fs::write(cache.join("index.bin"), vec![0u8; 300]).unwrap();
+        std::fs::write(cache.join("sub/entry.bin"), vec![0u8; 200]).unwrap();
+        std::fs::write(temp.path().join("work/kept.txt"), vec![0u8; 100]).unwrap();
+
+        let (total, candidates) =
+            measure_source(temp.path(), &patterns(&["**/.cache", "**/sub"])).unwrap();
+        assert_eq!(total, 600);
+        // Only the topmost match is a candidate; `sub` inside the cache
+        // is already covered by its parent
+        assert_eq!(
+            candidates,
+            vec![TrimCandidate {
+                path: PathBuf::from("work/.cache"),
+                bytes: 500,
+                pattern_index: 0,
+            }]
+        );
+    }
+}
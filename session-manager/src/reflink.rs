@@ -0,0 +1,133 @@
+//! Reflink (copy-on-write) file clones via `ioctl(FICLONE)`.
+//!
+//! On CoW filesystems (XFS with reflink, Btrfs) a clone shares extents
+//! with the source, so "copying" a file is near-instant and costs no
+//! space until either side is rewritten. Both the restore engine's
+//! `--reflink` mode and the backup's `--cow` mode go through here; the
+//! backup side additionally probes the destination filesystem once at
+//! startup and installs the process-wide CoW switch only when the probe
+//! clone succeeds.
+
+use log::debug;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+#[cfg(target_os = "linux")]
+nix::ioctl_write_int_bad!(
+    /// `ioctl(FICLONE)`: clone the source fd's extents into the
+    /// destination fd, replacing its content
+    ficlone,
+    nix::libc::FICLONE
+);
+
+/// Clone `src` into `dst` with `ioctl(FICLONE)`. The kernel refuses the
+/// clone on filesystems without reflink support and across devices; any
+/// failure removes the destination stub so the byte-copy fallback starts
+/// clean.
+#[cfg(target_os = "linux")]
+pub fn try_reflink(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let src_file = fs::File::open(src)?;
+    let dst_file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dst)?;
+    if let Err(errno) = unsafe { ficlone(dst_file.as_raw_fd(), src_file.as_raw_fd()) } {
+        drop(dst_file);
+        let _ = fs::remove_file(dst);
+        return Err(io::Error::from(errno));
+    }
+    Ok(())
+}
+
+/// `FICLONE` is Linux-only; everywhere else a reflink is simply
+/// unavailable and callers take their byte-copy fallback
+#[cfg(not(target_os = "linux"))]
+pub fn try_reflink(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflink requires Linux",
+    ))
+}
+
+/// Probe whether `dir`'s filesystem supports reflink by cloning a tiny
+/// temp file pair inside it. Both probe files are removed afterwards.
+pub fn probe_reflink_support(dir: &Path) -> bool {
+    let pid = std::process::id();
+    let src = dir.join(format!(".reflink-probe-src-{}", pid));
+    let dst = dir.join(format!(".reflink-probe-dst-{}", pid));
+    if fs::write(&src, b"reflink probe").is_err() {
+        return false;
+    }
+    let supported = match try_reflink(&src, &dst) {
+        Ok(()) => true,
+        Err(e) => {
+            debug!("Reflink probe in {} failed: {}", dir.display(), e);
+            false
+        }
+    };
+    let _ = fs::remove_file(&src);
+    let _ = fs::remove_file(&dst);
+    supported
+}
+
+/// Whether backup file copies clone instead of copying bytes; set once
+/// at binary startup from `--cow` after a successful probe
+static COW_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Files cloned by the backup copy helpers, for the cloned-vs-copied
+/// report (see [`crate::optimized_io::read_syscall_count`] for the same
+/// counter pattern)
+static CLONED_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Enable or disable CoW clones for the backup copy path
+pub fn install_cow(enabled: bool) {
+    COW_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the backup copy path tries a reflink before copying bytes
+pub fn cow_enabled() -> bool {
+    COW_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Record one successful clone on the backup copy path
+pub(crate) fn note_clone() {
+    CLONED_FILES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Total files cloned by the backup copy path so far
+pub fn files_cloned() -> u64 {
+    CLONED_FILES.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_probe_matches_clone_behavior() {
+        let temp_dir = TempDir::new().unwrap();
+        let supported = probe_reflink_support(temp_dir.path());
+
+        let src = temp_dir.path().join("src.bin");
+        let dst = temp_dir.path().join("dst.bin");
+        fs::write(&src, b"cow payload").unwrap();
+
+        if supported {
+            try_reflink(&src, &dst).unwrap();
+            assert_eq!(fs::read(&dst).unwrap(), b"cow payload");
+        } else {
+            // Not a reflink filesystem: the clone must refuse and leave
+            // no destination stub behind
+            assert!(try_reflink(&src, &dst).is_err());
+            assert!(!dst.exists());
+        }
+        // The probe cleans up after itself either way
+        assert!(!temp_dir.path().join(format!(".reflink-probe-src-{}", std::process::id())).exists());
+    }
+}
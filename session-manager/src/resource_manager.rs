@@ -81,4 +81,117 @@ impl Default for ResourceManager {
     }
 }
 
-use once_cell;
\ No newline at end of file
+/// Process resource limits relevant to parallel file operations
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceMonitor {
+    /// Soft limit on open file descriptors for this process
+    pub max_open_files: usize,
+}
+
+impl ResourceMonitor {
+    /// Read the soft fd limit via getrlimit(RLIMIT_NOFILE); a conservative
+    /// default is used when the syscall fails
+    pub fn detect() -> Self {
+        let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        // SAFETY: getrlimit only writes into the provided struct
+        let max_open_files = if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } == 0 {
+            limit.rlim_cur as usize
+        } else {
+            debug!("getrlimit(RLIMIT_NOFILE) failed; assuming 1024 open files");
+            1024
+        };
+        Self { max_open_files }
+    }
+}
+
+/// Counting semaphore bounding concurrent file opens: on nodes with a low
+/// `ulimit -n` the parallel copy workers block for a permit instead of
+/// failing with "Too many open files"
+#[derive(Debug)]
+pub struct FdSemaphore {
+    permits: parking_lot::Mutex<usize>,
+    released: parking_lot::Condvar,
+}
+
+impl FdSemaphore {
+    /// Size a semaphore from an fd budget: each file copy holds two
+    /// descriptors (source and destination), and headroom is reserved for
+    /// logs, sockets and the runtime itself
+    pub fn from_fd_limit(max_open_files: usize) -> Self {
+        const FD_HEADROOM: usize = 64;
+        Self::new(max_open_files.saturating_sub(FD_HEADROOM) / 2)
+    }
+
+    /// A semaphore with at least one permit, so a tiny configured limit
+    /// serializes copies rather than deadlocking them
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: parking_lot::Mutex::new(permits.max(1)),
+            released: parking_lot::Condvar::new(),
+        }
+    }
+
+    /// Block until a permit is free; the permit is returned on drop
+    pub fn acquire(&self) -> FdPermit<'_> {
+        let mut permits = self.permits.lock();
+        while *permits == 0 {
+            self.released.wait(&mut permits);
+        }
+        *permits -= 1;
+        FdPermit { semaphore: self }
+    }
+}
+
+/// RAII guard for one [`FdSemaphore`] permit
+pub struct FdPermit<'a> {
+    semaphore: &'a FdSemaphore,
+}
+
+impl Drop for FdPermit<'_> {
+    fn drop(&mut self) {
+        *self.semaphore.permits.lock() += 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_fd_semaphore_bounds_concurrency_without_losing_work() {
+        let semaphore = FdSemaphore::new(2);
+        let current = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let completed = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = semaphore.acquire();
+                    let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                    current.fetch_sub(1, Ordering::SeqCst);
+                    completed.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        // Every worker finished, never more than two at once
+        assert_eq!(completed.load(Ordering::SeqCst), 8);
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+
+        // A zero budget still serializes instead of deadlocking
+        let tiny = FdSemaphore::from_fd_limit(0);
+        let _permit = tiny.acquire();
+    }
+
+    #[test]
+    fn test_resource_monitor_reads_a_plausible_fd_limit() {
+        let monitor = ResourceMonitor::detect();
+        assert!(monitor.max_open_files > 0);
+    }
+}
+
@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::direct_restore::{DirectRestoreResult, VerifyResult};
+use crate::TransferResult;
+
+/// Whether report and manifest JSON is pretty-printed. Compact by
+/// default: single-line output survives line-oriented log aggregators.
+static PRETTY_JSON: AtomicBool = AtomicBool::new(false);
+
+/// Install the JSON output style for the process (see --report-pretty)
+pub fn install_pretty_json(pretty: bool) {
+    PRETTY_JSON.store(pretty, Ordering::Relaxed);
+}
+
+/// Serialize report/manifest JSON honoring the installed style
+pub fn render_json<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    if PRETTY_JSON.load(Ordering::Relaxed) {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// Version of the result-file schema emitted by all tools. Bump this when
+/// a field changes meaning; adding optional fields does not require a bump.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Envelopes written before versioning was introduced count as v1
+fn default_schema_version() -> u32 {
+    1
+}
+
+/// The operation-specific payload, tagged so dashboards can dispatch on
+/// `operation` without guessing from field names
+// One envelope exists per process run; the variant size spread is irrelevant
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "operation", content = "result", rename_all = "snake_case")]
+pub enum OperationResult {
+    Backup(TransferResult),
+    Restore(DirectRestoreResult),
+    Cleanup(CleanupResult),
+    Verify(VerifyResult),
+}
+
+/// Result of a trash cleanup run. Identical in shape between dry and
+/// real runs so the two JSON results stay directly comparable.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CleanupResult {
+    /// Trash run directories removed, or selected for removal in dry-run
+    pub purged_runs: usize,
+    /// Whether this was a simulation that removed nothing
+    pub dry_run: bool,
+    /// Total bytes freed, or reclaimable in dry-run
+    pub reclaimable_bytes: u64,
+    /// Per-run breakdown of what was (or would be) removed
+    pub runs: Vec<PurgedRun>,
+}
+
+/// One trash run directory selected by the retention policy
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgedRun {
+    pub path: std::path::PathBuf,
+    pub bytes: u64,
+}
+
+/// Top-level wrapper every tool emits its JSON results through, so field
+/// names cannot drift between session-backup and session-restore
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResultEnvelope {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    /// Emitting binary name
+    #[serde(default)]
+    pub tool: String,
+    /// Emitting binary version (CARGO_PKG_VERSION)
+    #[serde(default)]
+    pub tool_version: String,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    pub payload: OperationResult,
+}
+
+impl ResultEnvelope {
+    /// Start timing an operation; [`EnvelopeTimer::finish`] stamps
+    /// `finished_at` and wraps the payload
+    pub fn start(tool: &str) -> EnvelopeTimer {
+        EnvelopeTimer {
+            tool: tool.to_string(),
+            started_at: Utc::now(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        render_json(self).context("Failed to serialize result envelope")
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        serde_json::from_str(content).context("Failed to parse result envelope")
+    }
+}
+
+/// Captures the operation start so the envelope records both endpoints
+pub struct EnvelopeTimer {
+    tool: String,
+    started_at: DateTime<Utc>,
+}
+
+impl EnvelopeTimer {
+    pub fn finish(self, payload: OperationResult) -> ResultEnvelope {
+        ResultEnvelope {
+            schema_version: SCHEMA_VERSION,
+            tool: self.tool,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: Some(self.started_at),
+            finished_at: Some(Utc::now()),
+            payload,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_round_trip_keeps_schema_fields() {
+        let envelope = ResultEnvelope::start("session-restore")
+            .finish(OperationResult::Cleanup(CleanupResult {
+                purged_runs: 3,
+                ..Default::default()
+            }));
+
+        let json = envelope.to_json().unwrap();
+        let parsed = ResultEnvelope::from_json(&json).unwrap();
+        assert_eq!(parsed.schema_version, SCHEMA_VERSION);
+        assert_eq!(parsed.tool, "session-restore");
+        assert_eq!(parsed.tool_version, env!("CARGO_PKG_VERSION"));
+        assert!(parsed.started_at.unwrap() <= parsed.finished_at.unwrap());
+        match parsed.payload {
+            OperationResult::Cleanup(cleanup) => assert_eq!(cleanup.purged_runs, 3),
+            other => panic!("wrong payload variant: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compact_and_pretty_styles_parse_identically() {
+        let envelope = ResultEnvelope::start("session-backup")
+            .finish(OperationResult::Cleanup(CleanupResult {
+                purged_runs: 1,
+                ..Default::default()
+            }));
+
+        // Compact (the default) stays on one line for log aggregators
+        install_pretty_json(false);
+        let compact = envelope.to_json().unwrap();
+        assert!(!compact.contains('\n'), "{}", compact);
+
+        install_pretty_json(true);
+        let pretty = envelope.to_json().unwrap();
+        assert!(pretty.contains('\n'));
+        install_pretty_json(false);
+
+        let compact_value: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        let pretty_value: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(compact_value, pretty_value);
+    }
+
+    #[test]
+    fn test_v1_sample_still_deserializes_with_defaults() {
+        // A v1 envelope predates tool_version and the timestamps
+        let sample = r#"{
+            "tool": "session-backup",
+            "payload": {
+                "operation": "backup",
+                "result": {"success_count": 10, "error_count": 0, "skipped_count": 2}
+            }
+        }"#;
+
+        let parsed = ResultEnvelope::from_json(sample).unwrap();
+        assert_eq!(parsed.schema_version, 1);
+        assert!(parsed.tool_version.is_empty());
+        assert!(parsed.started_at.is_none() && parsed.finished_at.is_none());
+        match parsed.payload {
+            OperationResult::Backup(backup) => {
+                assert_eq!(backup.success_count, 10);
+                assert_eq!(backup.skipped_count, 2);
+                assert!(backup.errors.is_empty());
+            }
+            other => panic!("wrong payload variant: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Size-based backup retention.
+//!
+//! Keeping the last N sessions bounds their count but not their bytes; a
+//! few fat sessions can still fill the backup volume. `session-backup
+//! cleanup --max-total-bytes` caps the total instead: per-session sizes
+//! are measured, sessions are sorted oldest-first by modification time,
+//! and the oldest are deleted until the total fits the budget. The
+//! current and previous sessions (the two newest) are never deleted,
+//! even when they alone exceed the budget.
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many of the newest sessions the budget may never delete: the
+/// current one and the previous one a rollback would need
+const PROTECTED_NEWEST: usize = 2;
+
+/// One session directory with its measured size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionUsage {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Unix seconds of last modification, the age ordering key
+    pub modified_at: u64,
+}
+
+/// What the budget enforcement deleted, or would delete in dry-run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub total_bytes_before: u64,
+    pub total_bytes_after: u64,
+    pub deleted: Vec<SessionUsage>,
+    pub dry_run: bool,
+}
+
+/// Delete the oldest session directories under `base` until their total
+/// size fits `max_total_bytes`. Hidden entries (the restore trash) and
+/// the checkpoints directory belong to other layout features with their
+/// own retention and are never touched
+pub fn enforce_size_budget(base: &Path, max_total_bytes: u64, dry_run: bool) -> Result<RetentionReport> {
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(base)
+        .with_context(|| format!("Failed to read backup base: {}", base.display()))?
+        .flatten()
+    {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false)
+            || name.starts_with('.')
+            || name == crate::checkpoint::CHECKPOINTS_DIR_NAME
+        {
+            continue;
+        }
+        let (bytes, _) = crate::quota::measure_source(&path, &[])
+            .with_context(|| format!("Failed to measure session size: {}", path.display()))?;
+        let modified_at = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        sessions.push(SessionUsage { path, bytes, modified_at });
+    }
+    // Oldest first; name breaks ties so the order is stable
+    sessions.sort_by(|a, b| a.modified_at.cmp(&b.modified_at).then(a.path.cmp(&b.path)));
+
+    let total_bytes_before: u64 = sessions.iter().map(|s| s.bytes).sum();
+    let mut remaining = total_bytes_before;
+    let mut deleted = Vec::new();
+    let deletable = sessions.len().saturating_sub(PROTECTED_NEWEST);
+    for session in &sessions[..deletable] {
+        if remaining <= max_total_bytes {
+            break;
+        }
+        if dry_run {
+            info!("DRY RUN: would delete session {} ({} bytes)", session.path.display(), session.bytes);
+        } else {
+            fs::remove_dir_all(&session.path)
+                .with_context(|| format!("Failed to delete session: {}", session.path.display()))?;
+            info!("Deleted session {} ({} bytes)", session.path.display(), session.bytes);
+        }
+        remaining -= session.bytes;
+        deleted.push(session.clone());
+    }
+    if remaining > max_total_bytes {
+        warn!(
+            "Backup storage is still {} bytes over the {} byte budget; the current and \
+             previous sessions are never deleted",
+            remaining - max_total_bytes, max_total_bytes
+        );
+    }
+    Ok(RetentionReport {
+        total_bytes_before,
+        total_bytes_after: remaining,
+        deleted,
+        dry_run,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::{set_file_mtime, FileTime};
+    use tempfile::TempDir;
+
+    fn session(base: &Path, name: &str, bytes: usize, age_secs: i64) -> PathBuf {
+        let dir = base.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("data.bin"), vec![0u8; bytes]).unwrap();
+        let now = FileTime::now();
+        set_file_mtime(&dir, FileTime::from_unix_time(now.unix_seconds() - age_secs, 0)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_budget_prunes_oldest_sessions_until_within_budget() {
+        let temp_dir = TempDir::new().unwrap();
+        let s1 = session(temp_dir.path(), "session-1", 100, 4000);
+        let s2 = session(temp_dir.path(), "session-2", 200, 3000);
+        let s3 = session(temp_dir.path(), "session-3", 300, 2000);
+        let s4 = session(temp_dir.path(), "session-4", 400, 1000);
+
+        // 1000 bytes total against an 800 byte budget: the two oldest go
+        let report = enforce_size_budget(temp_dir.path(), 800, false).unwrap();
+        assert_eq!(report.total_bytes_before, 1000);
+        assert_eq!(report.total_bytes_after, 700);
+        let deleted: Vec<_> = report.deleted.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(deleted, vec![s1.clone(), s2.clone()]);
+        assert!(!s1.exists());
+        assert!(!s2.exists());
+        assert!(s3.exists());
+        assert!(s4.exists());
+    }
+
+    #[test]
+    fn test_current_and_previous_sessions_survive_any_budget() {
+        let temp_dir = TempDir::new().unwrap();
+        let s1 = session(temp_dir.path(), "session-1", 100, 3000);
+        let s2 = session(temp_dir.path(), "session-2", 200, 2000);
+        let s3 = session(temp_dir.path(), "session-3", 300, 1000);
+
+        // A budget nothing fits: only the oldest is deletable, the
+        // current and previous sessions stay regardless
+        let report = enforce_size_budget(temp_dir.path(), 10, false).unwrap();
+        assert_eq!(report.deleted.len(), 1);
+        assert!(!s1.exists());
+        assert!(s2.exists());
+        assert!(s3.exists());
+        assert_eq!(report.total_bytes_after, 500);
+    }
+
+    #[test]
+    fn test_dry_run_reports_without_deleting() {
+        let temp_dir = TempDir::new().unwrap();
+        let s1 = session(temp_dir.path(), "session-1", 100, 3000);
+        session(temp_dir.path(), "session-2", 200, 2000);
+        session(temp_dir.path(), "session-3", 300, 1000);
+
+        let report = enforce_size_budget(temp_dir.path(), 500, true).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.deleted.len(), 1);
+        assert!(s1.exists());
+    }
+}
@@ -1,10 +1,9 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::{info, warn, debug};
 use session_manager::*;
 use session_manager::direct_restore::DirectRestoreEngine;
 use std::path::PathBuf;
-use std::fs::OpenOptions;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -19,6 +18,13 @@ struct Args {
     )]
     mappings_file: PathBuf,
 
+    #[arg(
+        long,
+        help = "Fail when the mappings file has duplicate keys instead of warning \
+                and keeping the last value for each"
+    )]
+    strict_mappings: bool,
+
     #[arg(
         long,
         default_value = "/etc/sessions",
@@ -45,48 +51,407 @@ struct Args {
     #[arg(long, default_value = "900", help = "Operation timeout in seconds")]
     timeout: u64,
 
+    #[arg(long, help = "Heartbeat file refreshed during long operations, for liveness probes")]
+    heartbeat_file: Option<PathBuf>,
+
+    #[arg(
+        long = "status-socket",
+        value_name = "PATH",
+        help = "Serve the current phase/progress and, once finished, the final result \
+                envelope as line-delimited JSON over this unix socket (mode 0600, \
+                removed on exit)"
+    )]
+    status_socket: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Directory for the log file; falls back to $SESSION_MANAGER_LOG_DIR, \
+                /var/log/session-manager, then /tmp"
+    )]
+    log_dir: Option<PathBuf>,
+
+
+    #[cfg(feature = "cri")]
+    #[arg(
+        long,
+        help = "Resolve pod identity from this container ID via the containerd CRI socket"
+    )]
+    container_id: Option<String>,
+
+    #[cfg(feature = "cri")]
+    #[arg(
+        long,
+        default_value = session_manager::cri::DEFAULT_CRI_SOCKET,
+        help = "Containerd CRI socket used for --container-id resolution"
+    )]
+    cri_socket: PathBuf,
+
+    #[arg(long, default_value = "30", help = "Heartbeat refresh interval in seconds")]
+    heartbeat_interval: u64,
+
     #[arg(long, help = "Dry run mode - don't actually copy files")]
     dry_run: bool,
+
+    #[arg(long, help = "Treat skipped files (busy, read-only, permission denied) as failures")]
+    strict: bool,
+
+    #[arg(
+        long,
+        help = "Encryption key file; may be given multiple times, first is the current key"
+    )]
+    encryption_key_file: Vec<PathBuf>,
+
+    #[arg(long, help = "Adapt restore concurrency to measured throughput")]
+    adaptive_parallelism: bool,
+
+    #[arg(
+        long,
+        help = "Delete cleaned backup files without a temp rollback copy; faster but a failed deletion cannot be rolled back"
+    )]
+    fast_cleanup: bool,
+
+    #[arg(
+        long,
+        help = "Translate overlayfs whiteout nodes into deletions of the corresponding target paths"
+    )]
+    overlayfs_whiteouts: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ReflinkArg::Never,
+        help = "Copy files with ioctl(FICLONE) reflinks where the filesystem supports them: \
+                auto falls back to a normal copy quietly, always warns on each fallback, \
+                never forces normal copies"
+    )]
+    reflink: ReflinkArg,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OrderArg::Natural,
+        help = "Copy ordering for the restore work queue: config-first restores dotfiles and \
+                etc/home files before bulk data so a deadline-cut restore still leaves the \
+                user productive"
+    )]
+    order: OrderArg,
+
+    #[arg(
+        long = "restore-ext",
+        value_name = "EXT",
+        help = "Only restore files with this extension (case-insensitive, leading dot optional); \
+                may be given multiple times, extensionless files never match"
+    )]
+    restore_ext: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Directory that must never be restored into; may be given multiple times"
+    )]
+    no_restore_dir: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Skip symlinks whose resolved target escapes the directory they are restored into; \
+                in-tree relative links are kept"
+    )]
+    confine_symlinks: bool,
+
+    #[arg(
+        long = "privileged-attr-path",
+        value_name = "DIR",
+        help = "Re-apply recorded setuid/setgid bits and file capabilities to restored files \
+                under this path; may be given multiple times. Off by default: privileged \
+                attributes are recorded as not applied instead."
+    )]
+    privileged_attr_path: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Before restoring, check targets for in-flight package operations \
+                (conda/pip/dpkg locks, open writers) and skip busy subtrees"
+    )]
+    pre_restore_guard: bool,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "pre_restore_guard",
+        help = "With --pre-restore-guard, wait up to this long for busy targets to quiesce before skipping them"
+    )]
+    wait_for_quiesce: Option<u64>,
+
+    #[arg(long, help = "Print the per-category skip reason breakdown in the final report")]
+    verbose_skip_reasons: bool,
+
+    #[arg(
+        long,
+        help = "Pretty-print report JSON for humans; the default is compact \
+                single-line output for log aggregators"
+    )]
+    report_pretty: bool,
+
+    #[arg(
+        long,
+        help = "Only create files absent from the container; existing targets are never touched"
+    )]
+    merge_missing_only: bool,
+
+    #[arg(
+        long,
+        help = "Total retries allowed across the whole run; unset means unlimited"
+    )]
+    retry_budget: Option<u64>,
+
+    #[arg(long, help = "Warm upcoming files with readahead advice during restore")]
+    prefetch: bool,
+
+    #[arg(
+        long,
+        default_value_t = session_manager::prefetch::DEFAULT_PREFETCH_DEPTH,
+        help = "How many files the prefetch pool keeps warmed ahead of the copy workers"
+    )]
+    prefetch_depth: usize,
+
+    #[arg(long, help = "Record per-file copy timings and report restore hotspots")]
+    profile: bool,
+
+    #[arg(
+        long,
+        default_value_t = session_manager::profiling::DEFAULT_PROFILE_TOP_N,
+        help = "How many of the slowest files the timing profile keeps"
+    )]
+    profile_top_n: usize,
+
+    #[arg(long, help = "Rename cleaned backup files into <backup-path>/.trash instead of deleting them")]
+    trash_cleanup: bool,
+
+    #[arg(
+        long,
+        help = "Verify the backup is restorable (readable sources, valid targets) without writing anything"
+    )]
+    dry_run_verify: bool,
+
+    #[arg(
+        long,
+        help = "Confirm restoring onto / outside a container; without it the tool refuses on non-container hosts"
+    )]
+    i_understand_restore_to_root: bool,
+
+    #[arg(
+        long,
+        help = "Read --backup-path directly instead of the derived <namespace>/<pod_hash>/<container> layout"
+    )]
+    flat_backup_layout: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["pre_restore_guard", "dry_run_verify"],
+        help = "Restore from a tar stream instead of a backup directory; \"-\" reads from stdin. \
+                Entries pass through the same path validation as loose backup files"
+    )]
+    from_tar: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with = "from_tar",
+        help = "Restore only this container path (a directory restores its subtree); \
+                may be given multiple times. Packed and compressed copies are resolved \
+                through the backup manifest"
+    )]
+    only: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Proceed even when the backup directory's metadata records a different pod"
+    )]
+    force: bool,
+
+    #[arg(
+        long,
+        requires = "from_pod",
+        requires = "from_container",
+        help = "Restore another pod's backup: namespace of the source pod (requires --from-pod, --from-container and --allow-cross-pod-restore)"
+    )]
+    from_namespace: Option<String>,
+
+    #[arg(
+        long,
+        requires = "from_namespace",
+        requires = "from_container",
+        help = "Restore another pod's backup: pod name of the source pod"
+    )]
+    from_pod: Option<String>,
+
+    #[arg(
+        long,
+        requires = "from_namespace",
+        requires = "from_pod",
+        help = "Restore another pod's backup: container name of the source pod"
+    )]
+    from_container: Option<String>,
+
+    #[arg(
+        long,
+        help = "Confirm a cross-pod restore selected with --from-namespace/--from-pod/--from-container"
+    )]
+    allow_cross_pod_restore: bool,
+
+    #[arg(
+        long,
+        default_value_t = session_manager::DEFAULT_PARALLEL_HASH_THRESHOLD,
+        help = "File size in bytes above which integrity hashing fans out across threads; \
+                raise it on storage where a single core already saturates reads"
+    )]
+    parallel_hash_threshold: u64,
+
+    #[arg(long, default_value = "16", help = "Upper bound on concurrent file operations")]
+    max_parallelism: usize,
+
+    #[arg(
+        long,
+        help = "Cap concurrent file opens below this limit; defaults to the process fd limit from getrlimit"
+    )]
+    max_open_files: Option<usize>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn init_file_logging(binary_name: &str) -> Result<()> {
-    use env_logger::fmt::Target;
-    
-    // Create log file path
-    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-    let log_file_path = format!("/tmp/{}-{}.log", binary_name, timestamp);
-    
-    // Create or open log file
-    let log_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_file_path)
-        .with_context(|| format!("Failed to create log file: {}", log_file_path))?;
-    
-    // Initialize env_logger with file target and debug level
-    env_logger::Builder::new()
-        .target(Target::Pipe(Box::new(log_file)))
-        .filter_level(log::LevelFilter::Debug)
-        .format_timestamp_secs()
-        .init();
-    
-    // Also log to stderr for immediate feedback
-    eprintln!("Logging to file: {}", log_file_path);
-    
-    Ok(())
+/// CLI spelling of [`session_manager::direct_restore::ReflinkMode`]
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ReflinkArg {
+    Auto,
+    Always,
+    Never,
+}
+
+impl From<ReflinkArg> for session_manager::direct_restore::ReflinkMode {
+    fn from(arg: ReflinkArg) -> Self {
+        match arg {
+            ReflinkArg::Auto => Self::Auto,
+            ReflinkArg::Always => Self::Always,
+            ReflinkArg::Never => Self::Never,
+        }
+    }
 }
 
-fn main() -> Result<()> {
-    // Initialize file-based logging to /tmp
-    init_file_logging("session-restore")?;
+/// CLI spelling of [`session_manager::ordering::CopyOrder`]
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum OrderArg {
+    Natural,
+    LargestFirst,
+    SmallestFirst,
+    ConfigFirst,
+}
+
+impl From<OrderArg> for session_manager::ordering::CopyOrder {
+    fn from(arg: OrderArg) -> Self {
+        match arg {
+            OrderArg::Natural => Self::Natural,
+            OrderArg::LargestFirst => Self::LargestFirst,
+            OrderArg::SmallestFirst => Self::SmallestFirst,
+            OrderArg::ConfigFirst => Self::ConfigFirst,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Permanently delete trashed cleanups under <backup-path>/.trash
+    EmptyTrash {
+        /// Only purge trash runs older than this many hours
+        #[arg(long, default_value_t = 0)]
+        retention_hours: u64,
+    },
+
+    /// Retry only the failed files recorded in a prior restore report
+    RetryFromReport {
+        /// Path to the prior restore report JSON file
+        report: PathBuf,
+    },
+
+    /// Named on-demand checkpoints under <backup-path>/checkpoints/
+    Checkpoint {
+        #[command(subcommand)]
+        action: CheckpointAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CheckpointAction {
+    /// Restore the named checkpoint onto the container root; the
+    /// checkpoint itself is preserved instead of being cleaned up
+    Restore {
+        /// Checkpoint name
+        #[arg(long)]
+        name: String,
+    },
+}
+
+fn main() {
+    // Typed error categories anywhere in the chain decide the exit code;
+    // anything untyped keeps the generic failure code
+    if let Err(err) = run() {
+        eprintln!("Error: {:#}", err);
+        std::process::exit(session_manager::error::exit_code_for(&err));
+    }
+}
+
+fn run() -> Result<()> {
+    // Stderr logging first; the file sink is attached once the paths it
+    // must avoid are known
+    session_manager::logging::init();
     let args = Args::parse();
 
+    let envelope_timer = session_manager::result_envelope::ResultEnvelope::start("session-restore");
+
     info!("=== Session Restore Tool Started (Direct Container Root Mode) ===");
     info!("Backup path: {}", args.backup_path.display());
     info!("Timeout: {} seconds", args.timeout);
     info!("Dry run: {}", args.dry_run);
 
+    session_manager::install_parallel_hash_threshold(args.parallel_hash_threshold);
+    session_manager::result_envelope::install_pretty_json(args.report_pretty);
+    session_manager::install_strict_mappings(args.strict_mappings);
+    session_manager::ordering::install(args.order.into());
+
+    if let Some(ref heartbeat_file) = args.heartbeat_file {
+        session_manager::heartbeat::install(heartbeat_file.clone(), args.heartbeat_interval)
+            .context("Failed to install heartbeat file")?;
+    }
+
+    // Held for the rest of the run; dropping it on any exit path stops
+    // the accept loop and removes the socket
+    let _status_server = match args.status_socket {
+        Some(ref socket_path) => Some(
+            session_manager::status_server::StatusServer::start(socket_path)
+                .context("Failed to start status server")?,
+        ),
+        None => None,
+    };
+
+    // The target root is always /: refuse on what looks like a developer
+    // machine unless explicitly confirmed
+    session_manager::direct_restore::check_root_restore_guard(
+        args.i_understand_restore_to_root,
+        session_manager::direct_restore::running_inside_container(),
+    )?;
+
     // Get current pod information
+    #[cfg(feature = "cri")]
+    let pod_info = match args.container_id {
+        Some(ref container_id) => session_manager::cri::resolve_pod_info(&args.cri_socket, container_id)
+            .with_context(|| format!("Failed to resolve pod information for container {}", container_id))?,
+        None => PodInfo::from_args_and_env(
+            args.namespace,
+            args.pod_name,
+            args.container_name,
+        ).with_context(|| "Failed to determine pod information")?,
+    };
+    #[cfg(not(feature = "cri"))]
     let pod_info = PodInfo::from_args_and_env(
         args.namespace,
         args.pod_name,
@@ -98,41 +463,311 @@ fn main() -> Result<()> {
         pod_info.namespace, pod_info.pod_name, pod_info.container_name
     );
 
-    // Validate backup storage directory exists and has content
-    if !args.backup_path.exists() {
-        warn!("Backup storage directory does not exist: {}", args.backup_path.display());
-        info!("=== Session Restore Completed (No Backup Data) ===");
+    // Cross-pod restores select another pod's derived directory explicitly;
+    // everything else (protected paths, trash, retries) behaves the same
+    let source_pod_info = match (&args.from_namespace, &args.from_pod, &args.from_container) {
+        (None, None, None) => pod_info.clone(),
+        (Some(namespace), Some(pod_name), Some(container_name)) => {
+            if !args.allow_cross_pod_restore {
+                anyhow::bail!(
+                    "--from-namespace/--from-pod/--from-container restore another pod's data; \
+                     pass --allow-cross-pod-restore to confirm"
+                );
+            }
+            if args.flat_backup_layout {
+                anyhow::bail!("--from-* selection requires the namespaced backup layout");
+            }
+            warn!(
+                "CROSS-POD RESTORE: restoring backup of {}/{}/{} into pod {}/{}/{}",
+                namespace, pod_name, container_name,
+                pod_info.namespace, pod_info.pod_name, pod_info.container_name
+            );
+            PodInfo {
+                namespace: namespace.clone(),
+                pod_name: pod_name.clone(),
+                container_name: container_name.clone(),
+            }
+        }
+        // clap's `requires` links reject partial triples before we get here
+        _ => unreachable!("--from-* flags require each other"),
+    };
+
+    // Resolve the same derived <namespace>/<pod_hash>/<container> directory
+    // the backup side writes into
+    let backup_path = session_manager::backup_layout::resolve_backup_dir(
+        &args.backup_path,
+        &source_pod_info,
+        args.flat_backup_layout,
+        args.force,
+        false,
+    )?;
+
+    // The log file must not land inside the backup being restored
+    session_manager::logging::attach_file_sink(
+        "session-restore",
+        args.log_dir.as_deref(),
+        &[&args.backup_path],
+    );
+
+    // A tar stream has no backup directory to validate or decrypt, and an
+    // explicitly requested checkpoint reports its own absence as an error
+    let checkpoint_requested = matches!(args.command, Some(Command::Checkpoint { .. }));
+    if args.from_tar.is_none() && !checkpoint_requested {
+        // Validate backup storage directory exists and has content
+        if !backup_path.exists() {
+            warn!("Backup storage directory does not exist: {}", backup_path.display());
+            info!("=== Session Restore Completed (No Backup Data) ===");
+            return Ok(());
+        }
+
+        if is_directory_empty(&backup_path)? {
+            warn!("Backup storage directory is empty: {}", backup_path.display());
+            info!("=== Session Restore Completed (Empty Backup Data) ===");
+            return Ok(());
+        }
+
+        // Show backup storage directory contents before restore
+        debug!("Backup storage directory contents before restore:");
+        show_directory_contents(&backup_path)?;
+    }
+
+    // Create direct restore engine
+    if args.from_tar.is_none() && !args.encryption_key_file.is_empty() && !args.dry_run {
+        let keyring = session_manager::encryption::Keyring::load(&args.encryption_key_file)
+            .context("Failed to load encryption keyring")?;
+        let decrypted = session_manager::encryption::decrypt_backup_dir(&keyring, &backup_path)
+            .context("Failed to decrypt backup files")?;
+        info!("Decrypted {} backup files before restoration", decrypted);
+    }
+
+    // Compatibility gate: refuse a backup whose manifest records layout
+    // features this build does not understand (the data would be
+    // misread); a producer merely newer than this build only warns
+    let mut producer_version: Option<String> = None;
+    if args.from_tar.is_none() && !checkpoint_requested {
+        if let Some(producer) = session_manager::manifest::load_manifest_lenient(&backup_path)
+            .and_then(|manifest| manifest.producer)
+        {
+            info!(
+                "Backup produced by session-manager {} (features: {})",
+                producer.version,
+                if producer.features.is_empty() { "none".to_string() } else { producer.features.join(", ") }
+            );
+            let unknown = producer.unknown_features();
+            if !unknown.is_empty() {
+                if args.force {
+                    warn!(
+                        "Backup uses layout feature(s) this binary does not understand: {}; proceeding due to --force",
+                        unknown.join(", ")
+                    );
+                } else {
+                    anyhow::bail!(
+                        "Backup at {} was produced by version {} and uses layout feature(s) this binary \
+                         does not understand: {}; upgrade the restore tool or pass --force to attempt it anyway",
+                        backup_path.display(), producer.version, unknown.join(", ")
+                    );
+                }
+            } else if producer.newer_than_consumer() {
+                warn!(
+                    "Backup was produced by a newer build ({} > {}); details this build does not know about may be restored imperfectly",
+                    producer.version, env!("CARGO_PKG_VERSION")
+                );
+            }
+            producer_version = Some(producer.version);
+        }
+    }
+
+    // Optional quiesce guard: never restore over an in-flight conda/pip/
+    // dpkg operation; subtrees still busy after the wait are excluded the
+    // same way --no-restore-dir targets are
+    let mut no_restore_dirs = args.no_restore_dir.clone();
+    if args.pre_restore_guard {
+        let targets = session_manager::quiesce::restore_targets(&backup_path)?;
+        let wait = std::time::Duration::from_secs(args.wait_for_quiesce.unwrap_or(0));
+        let report = session_manager::quiesce::wait_for_quiesce(
+            &targets,
+            wait,
+            std::time::Duration::from_secs(2),
+        )?;
+        for issue in &report.issues {
+            warn!(
+                "Pre-restore guard: skipping {} - {}",
+                issue.subtree.display(),
+                issue.reason
+            );
+        }
+        no_restore_dirs.extend(report.busy_subtrees());
+    }
+
+    let restore_engine = DirectRestoreEngine::new(args.dry_run, args.timeout)
+        .with_strict(args.strict)
+        .with_adaptive_parallelism(args.adaptive_parallelism, args.max_parallelism)
+        .with_fast_cleanup(args.fast_cleanup)
+        .with_overlayfs_whiteouts(args.overlayfs_whiteouts)
+        .with_reflink(args.reflink.into())
+        .with_restore_extensions(args.restore_ext.clone())
+        .with_no_restore_dirs(no_restore_dirs)
+        .with_confine_symlinks(args.confine_symlinks)
+        .with_privileged_attr_paths(args.privileged_attr_path.clone())
+        .with_merge_missing_only(args.merge_missing_only)
+        .with_retry_budget(args.retry_budget)
+        .with_prefetch(args.prefetch, args.prefetch_depth)
+        .with_profiling(args.profile, args.profile_top_n)
+        .with_trash_mode(args.trash_cleanup);
+    let restore_engine = match args.max_open_files {
+        Some(limit) => restore_engine.with_max_open_files(limit),
+        None => restore_engine,
+    };
+
+    if args.dry_run_verify {
+        info!("Verifying backup restorability (no writes): {}", backup_path.display());
+        let verification = restore_engine.verify_backup(&backup_path)
+            .with_context(|| "Failed to verify backup")?;
+        let restorable = verification.is_restorable();
+        let issue_count = verification.issues.len();
+        let (verified_files, total_files) = (verification.verified_files, verification.total_files);
+        let envelope = envelope_timer
+            .finish(session_manager::result_envelope::OperationResult::Verify(verification));
+        println!("{}", envelope.to_json()?);
+        if !restorable {
+            warn!("Backup verification found {} issues", issue_count);
+            std::process::exit(1);
+        }
+        info!("Backup verified: {}/{} files restorable", verified_files, total_files);
         return Ok(());
     }
 
-    if is_directory_empty(&args.backup_path)? {
-        warn!("Backup storage directory is empty: {}", args.backup_path.display());
-        info!("=== Session Restore Completed (Empty Backup Data) ===");
+    if let Some(Command::EmptyTrash { retention_hours }) = &args.command {
+        let retention = std::time::Duration::from_secs(retention_hours * 3600);
+        let report = session_manager::direct_restore::purge_trash(&backup_path, retention, args.dry_run)
+            .context("Failed to empty trash")?;
+        info!("{} {} trash run directories under {} ({} bytes)",
+              if report.dry_run { "Would purge" } else { "Purged" }, report.purged_runs,
+              backup_path.join(session_manager::direct_restore::TRASH_DIR_NAME).display(),
+              report.reclaimable_bytes);
+        let envelope = envelope_timer.finish(session_manager::result_envelope::OperationResult::Cleanup(report));
+        println!("{}", envelope.to_json()?);
         return Ok(());
     }
 
-    // Show backup storage directory contents before restore
-    debug!("Backup storage directory contents before restore:");
-    show_directory_contents(&args.backup_path)?;
+    // Filled on the retry path; the new report is written from the final
+    // envelope so both outputs share one schema
+    let mut retry_report_path = None;
+    // Set when the restore was interrupted by a hard error but left a
+    // partial result worth reporting; returned after the report is emitted
+    let mut restore_error: Option<anyhow::Error> = None;
+    let mut result = match args.command {
+        Some(Command::RetryFromReport { ref report }) => {
+            info!("Retrying failed files from prior report: {}", report.display());
 
-    // Create direct restore engine
-    let restore_engine = DirectRestoreEngine::new(args.dry_run, args.timeout);
+            let content = std::fs::read_to_string(report)
+                .with_context(|| format!("Failed to read restore report: {}", report.display()))?;
+            // Current reports are envelopes; reports written before the
+            // schema existed are a bare restore result
+            let prior = match session_manager::result_envelope::ResultEnvelope::from_json(&content) {
+                Ok(envelope) => match envelope.payload {
+                    session_manager::result_envelope::OperationResult::Restore(prior) => prior,
+                    other => anyhow::bail!(
+                        "Report {} is not a restore result: {:?}", report.display(), other
+                    ),
+                },
+                Err(_) => serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse restore report JSON: {}", report.display()))?,
+            };
 
-    // Perform direct container root restoration
-    info!("Starting direct container root restoration from {}...", args.backup_path.display());
+            let result = restore_engine.retry_from_report(&prior, &backup_path)
+                .with_context(|| "Failed to retry files from prior report")?;
+            retry_report_path = Some(report.with_extension("retry.json"));
+            result
+        }
+        Some(Command::EmptyTrash { .. }) => unreachable!("handled above"),
+        Some(Command::Checkpoint { ref action }) => match action {
+            CheckpointAction::Restore { name } => {
+                info!("Restoring checkpoint {} from {}...", name, backup_path.display());
+
+                session_manager::checkpoint::restore_checkpoint(&backup_path, name, restore_engine)
+                    .with_context(|| format!("Failed to restore checkpoint {}", name))?
+            }
+        },
+        None if !args.only.is_empty() => {
+            info!("Starting selective restore of {} paths from {}...", args.only.len(), backup_path.display());
 
-    let result = restore_engine.restore_to_container_root(&args.backup_path)
-        .with_context(|| "Failed to perform direct container root restoration")?;
+            restore_engine.restore_only(&backup_path, &args.only)
+                .with_context(|| "Failed to perform selective restore")?
+        }
+        None => {
+            if let Some(ref tar_source) = args.from_tar {
+                // Streaming restore: every entry goes through the same
+                // validation pipeline as loose backup files
+                if tar_source == std::path::Path::new("-") {
+                    info!("Starting streaming restore from tar archive on stdin...");
+                    restore_engine.restore_from_tar(std::io::stdin().lock())
+                } else {
+                    info!("Starting streaming restore from tar archive {}...", tar_source.display());
+                    let archive = std::fs::File::open(tar_source)
+                        .with_context(|| format!("Failed to open tar archive: {}", tar_source.display()))?;
+                    restore_engine.restore_from_tar(std::io::BufReader::new(archive))
+                }
+                .with_context(|| "Failed to restore from tar stream")?
+            } else {
+                // Perform direct container root restoration
+                info!("Starting direct container root restoration from {}...", backup_path.display());
+
+                match restore_engine.restore_to_container_root(&backup_path) {
+                    Ok(result) => result,
+                    // A hard traversal error carries the progress made so
+                    // far: report it through the envelope as usual, then
+                    // fail once the report is out
+                    Err(err) => match err.downcast::<session_manager::direct_restore::RestoreInterrupted>() {
+                        Ok(interrupted) => {
+                            let session_manager::direct_restore::RestoreInterrupted { partial, source } = interrupted;
+                            warn!("Restore interrupted partway; reporting partial results: {:#}", source);
+                            restore_error = Some(source.context(
+                                "Restore interrupted partway; partial results were reported",
+                            ));
+                            partial
+                        }
+                        Err(err) => {
+                            return Err(err).with_context(|| "Failed to perform direct container root restoration")
+                        }
+                    },
+                }
+            }
+        }
+    };
+
+    // Record who the data belonged to and who ran the restore so reports
+    // of cross-pod restores are auditable after the fact
+    result.source_identity = Some(session_manager::backup_layout::BackupMeta::from_pod_info(&source_pod_info));
+    result.executing_identity = Some(session_manager::backup_layout::BackupMeta::from_pod_info(&pod_info));
+    result.producer_version = producer_version;
 
     // Report results
     info!("=== Direct Container Root Restoration Results ===");
+    info!(
+        "Restored backup of {}/{}/{} as pod {}/{}/{}",
+        source_pod_info.namespace, source_pod_info.pod_name, source_pod_info.container_name,
+        pod_info.namespace, pod_info.pod_name, pod_info.container_name
+    );
     info!("Total files processed: {}", result.total_files);
     info!("Successfully restored: {}", result.successful_files);
     info!("Skipped files: {}", result.skipped_files);
     info!("Failed files: {}", result.failed_files);
     info!("Cleaned backup files: {}", result.cleaned_files);
+    if result.trashed_files > 0 {
+        info!("Trashed (recoverable) backup files: {}", result.trashed_files);
+    }
     info!("Duration: {:?}", result.duration);
 
+    if args.verbose_skip_reasons && !result.skip_reason_counts.is_empty() {
+        info!("Skip reason breakdown:");
+        let mut breakdown: Vec<_> = result.skip_reason_counts.iter().collect();
+        breakdown.sort();
+        for (reason, count) in breakdown {
+            info!("  {}: {}", reason, count);
+        }
+    }
+
     if !result.skipped_details.is_empty() {
         info!("Skipped files details:");
         for skipped in &result.skipped_details {
@@ -151,6 +786,14 @@ fn main() -> Result<()> {
         info!("Successfully cleaned {} backup files after restoration", result.cleaned_files);
     }
 
+    if !result.privileged_attrs_not_applied.is_empty() {
+        warn!(
+            "Privileged attributes (setuid/file capabilities) not applied to {} restored files; \
+             opt in with --privileged-attr-path to re-apply them",
+            result.privileged_attrs_not_applied.len()
+        );
+    }
+
     // Determine overall success
     let success_rate = if result.total_files > 0 {
         (result.successful_files as f64 / result.total_files as f64) * 100.0
@@ -159,9 +802,34 @@ fn main() -> Result<()> {
     };
 
     info!("Restoration success rate: {:.1}%", success_rate);
+    result.log_directory_summaries();
+
+    // Emit the machine-readable result through the shared envelope (and
+    // the retry report file, when retrying from a prior report)
+    let (failed_files, successful_files) = (result.failed_files, result.successful_files);
+    let envelope = envelope_timer
+        .finish(session_manager::result_envelope::OperationResult::Restore(result));
+    let envelope_json = envelope.to_json()?;
+    session_manager::status_server::publish_final(envelope_json.clone());
+    println!("{}", envelope_json);
+    if let Some(report_path) = retry_report_path {
+        std::fs::write(&report_path, &envelope_json)
+            .with_context(|| format!("Failed to write retry report: {}", report_path.display()))?;
+        info!("Wrote retry report: {}", report_path.display());
+    }
+
+    // The partial report is out; now surface the interruption so the
+    // process still exits non-zero
+    if let Some(err) = restore_error {
+        return Err(err);
+    }
+
+    if args.strict && failed_files > 0 {
+        return Err(anyhow::anyhow!("Restoration failed in strict mode: {} files failed", failed_files));
+    }
 
-    if result.failed_files > 0 && result.successful_files == 0 {
-        return Err(anyhow::anyhow!("Restoration failed: {} files failed, 0 succeeded", result.failed_files));
+    if failed_files > 0 && successful_files == 0 {
+        return Err(anyhow::anyhow!("Restoration failed: {} files failed, 0 succeeded", failed_files));
     }
 
     info!("=== Session Restore Completed Successfully ===");
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How the source tree was captured for the backup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    /// Two-phase capture: a hardlink farm froze the namespace first
+    HardlinkFarm,
+    /// The source was copied directly (cross-filesystem staging)
+    DirectCopy,
+}
+
+/// A hardlink farm of the source tree, built before the slow copy so
+/// renames and deletions during the transfer cannot change what is
+/// captured. Removed on drop, so a failed backup never leaves it behind.
+pub struct SnapshotFarm {
+    pub root: PathBuf,
+}
+
+impl Drop for SnapshotFarm {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.root) {
+            if self.root.exists() {
+                warn!("Failed to remove snapshot farm {}: {}", self.root.display(), e);
+            }
+        } else {
+            debug!("Removed snapshot farm: {}", self.root.display());
+        }
+    }
+}
+
+/// Build a hardlink farm of `source` under `staging_base/.snapshot-<ts>`:
+/// directories are recreated, regular files hardlinked (fast and nearly
+/// atomic per file), symlinks recreated as symlinks. Returns None when
+/// the source and staging live on different filesystems (link() fails
+/// with EXDEV), in which case the caller copies directly instead.
+pub fn create_hardlink_farm(
+    source: &Path,
+    staging_base: &Path,
+    excluded: &HashSet<PathBuf>,
+) -> Result<Option<SnapshotFarm>> {
+    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
+    let root = staging_base.join(format!(".snapshot-{}", timestamp));
+    fs::create_dir_all(&root)
+        .with_context(|| format!("Failed to create snapshot farm root: {}", root.display()))?;
+    let farm = SnapshotFarm { root };
+
+    let mut linked = 0usize;
+    for entry in walkdir::WalkDir::new(source).min_depth(1) {
+        let entry = entry.with_context(|| format!("Failed to walk source: {}", source.display()))?;
+        let source_path = entry.path();
+        if excluded.contains(source_path) {
+            debug!("Snapshot farm skipping excluded path: {}", source_path.display());
+            continue;
+        }
+        let relative = source_path.strip_prefix(source).expect("walked under source");
+        let farm_path = farm.root.join(relative);
+
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            fs::create_dir_all(&farm_path)
+                .with_context(|| format!("Failed to create farm directory: {}", farm_path.display()))?;
+        } else if file_type.is_symlink() {
+            let target = fs::read_link(source_path)
+                .with_context(|| format!("Failed to read symlink: {}", source_path.display()))?;
+            std::os::unix::fs::symlink(&target, &farm_path)
+                .with_context(|| format!("Failed to recreate symlink: {}", farm_path.display()))?;
+        } else if let Err(e) = fs::hard_link(source_path, &farm_path) {
+            if e.raw_os_error() == Some(libc::EXDEV) {
+                info!(
+                    "Snapshot staging crosses filesystems at {}; falling back to direct copy",
+                    source_path.display()
+                );
+                // The farm guard removes the partial tree
+                return Ok(None);
+            }
+            return Err(e).with_context(|| {
+                format!("Failed to hardlink {} into the snapshot farm", source_path.display())
+            });
+        } else {
+            linked += 1;
+        }
+    }
+
+    info!("Snapshot farm ready: {} files hardlinked under {}", linked, farm.root.display());
+    Ok(Some(farm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_farm_mirrors_the_source_and_is_removed_on_drop() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let staging = temp.path().join("staging");
+        fs::create_dir_all(source.join("work/deep")).unwrap();
+        fs::create_dir_all(&staging).unwrap();
+        fs::write(source.join("notes.txt"), b"top level").unwrap();
+        fs::write(source.join("work/deep/data.bin"), b"nested contents").unwrap();
+        std::os::unix::fs::symlink("notes.txt", source.join("alias")).unwrap();
+
+        let farm = create_hardlink_farm(&source, &staging, &HashSet::new())
+            .unwrap()
+            .expect("same filesystem must use the farm");
+        let farm_root = farm.root.clone();
+
+        // Content matches and files share the source inode
+        assert_eq!(fs::read(farm_root.join("notes.txt")).unwrap(), b"top level");
+        assert_eq!(fs::read(farm_root.join("work/deep/data.bin")).unwrap(), b"nested contents");
+        assert_eq!(fs::read_link(farm_root.join("alias")).unwrap(), PathBuf::from("notes.txt"));
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(
+            fs::metadata(farm_root.join("notes.txt")).unwrap().ino(),
+            fs::metadata(source.join("notes.txt")).unwrap().ino()
+        );
+
+        // Deleting the source original no longer affects the farm copy
+        fs::remove_file(source.join("notes.txt")).unwrap();
+        assert_eq!(fs::read(farm_root.join("notes.txt")).unwrap(), b"top level");
+
+        // The guard removes the farm even when the backup path fails early
+        drop(farm);
+        assert!(!farm_root.exists());
+    }
+
+    #[test]
+    fn test_cross_filesystem_staging_falls_back() {
+        use std::os::unix::fs::MetadataExt;
+
+        let source_temp = TempDir::new().unwrap();
+        fs::write(source_temp.path().join("data.txt"), b"contents").unwrap();
+
+        // /dev/shm is a separate tmpfs on most Linux hosts; skip when not
+        let staging = Path::new("/dev/shm");
+        if !staging.is_dir()
+            || fs::metadata(staging).unwrap().dev() == fs::metadata(source_temp.path()).unwrap().dev()
+        {
+            return;
+        }
+        let staging_temp = tempfile::Builder::new()
+            .prefix("farm-staging-")
+            .tempdir_in(staging)
+            .unwrap();
+
+        let farm = create_hardlink_farm(source_temp.path(), staging_temp.path(), &HashSet::new()).unwrap();
+        assert!(farm.is_none(), "cross-filesystem staging must fall back");
+        // The partial farm was cleaned up
+        assert_eq!(fs::read_dir(staging_temp.path()).unwrap().count(), 0);
+    }
+}
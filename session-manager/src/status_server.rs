@@ -0,0 +1,212 @@
+//! Local status endpoint for `--status-socket`.
+//!
+//! A node agent asking "is the restore for this pod finished, and did it
+//! succeed" should not have to tail log files. When the flag is given
+//! the binary binds a unix socket and serves a line-delimited JSON
+//! protocol: each connection receives one status line — the current
+//! phase and progress while the operation runs, plus the final result
+//! envelope once it has finished — and is then closed. Access control is
+//! the socket file's permissions (0600); the socket is removed when the
+//! server is dropped on exit.
+//!
+//! The binaries are synchronous, so the accept loop runs as a tokio task
+//! on a dedicated current-thread runtime owned by a background thread.
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+/// Whether a status server is running; the progress notes from the hot
+/// copy loops are dropped after one atomic load when it is not
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Last phase reported through [`crate::heartbeat::beat`]
+static PHASE: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new("starting".to_string()));
+
+/// Last per-operation file count reported through the beat
+static FILES_DONE: AtomicUsize = AtomicUsize::new(0);
+
+/// Final result envelope JSON, set once when the operation finishes
+static FINAL_RESULT: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+/// Record the copy loops' progress for the status line; a no-op unless a
+/// server is running. Called from [`crate::heartbeat::beat`] so the
+/// status socket and the heartbeat file always agree.
+pub(crate) fn note_progress(phase: &str, files_done: usize) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    FILES_DONE.store(files_done, Ordering::Relaxed);
+    // Only rewrite the phase string when it changes; the beat fires per
+    // copied file
+    if *PHASE.read() != phase {
+        *PHASE.write() = phase.to_string();
+    }
+}
+
+/// Publish the final result envelope; connections from here on report
+/// `finished` and carry the envelope
+pub fn publish_final(envelope_json: String) {
+    *FINAL_RESULT.write() = Some(envelope_json);
+}
+
+/// One status line: the current phase and shared progress tallies, plus
+/// the final envelope under `result` once published
+fn current_status_json() -> String {
+    let progress = crate::progress::global().snapshot();
+    let final_result = FINAL_RESULT.read().clone();
+    let mut status = serde_json::json!({
+        "state": if final_result.is_some() { "finished" } else { "running" },
+        "phase": *PHASE.read(),
+        "files_done": FILES_DONE.load(Ordering::Relaxed),
+        "bytes_done": progress.bytes_done,
+        "failures": progress.failures,
+        "skips": progress.skips,
+    });
+    if let Some(envelope_json) = final_result {
+        status["result"] = serde_json::from_str(&envelope_json)
+            .unwrap_or(serde_json::Value::String(envelope_json));
+    }
+    let mut line = status.to_string();
+    line.push('\n');
+    line
+}
+
+/// Running status server; dropping it stops the accept loop and removes
+/// the socket file
+pub struct StatusServer {
+    socket_path: PathBuf,
+    cancel: CancellationToken,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl StatusServer {
+    /// Bind `socket_path` (replacing any stale socket left by a crashed
+    /// predecessor) and start serving status lines
+    pub fn start(socket_path: &Path) -> Result<Self> {
+        let _ = fs::remove_file(socket_path);
+        let listener = std::os::unix::net::UnixListener::bind(socket_path)
+            .with_context(|| format!("Failed to bind status socket: {}", socket_path.display()))?;
+        // The socket file's permissions are the access control
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to set status socket permissions: {}", socket_path.display()))?;
+        }
+        listener
+            .set_nonblocking(true)
+            .context("Failed to make status socket non-blocking")?;
+
+        let cancel = CancellationToken::new();
+        let accept_cancel = cancel.clone();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .context("Failed to build status server runtime")?;
+        let handle = std::thread::Builder::new()
+            .name("status-server".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    let listener = match tokio::net::UnixListener::from_std(listener) {
+                        Ok(listener) => listener,
+                        Err(e) => {
+                            warn!("Status server failed to adopt its socket: {}", e);
+                            return;
+                        }
+                    };
+                    loop {
+                        tokio::select! {
+                            _ = accept_cancel.cancelled() => break,
+                            accepted = listener.accept() => {
+                                let Ok((mut stream, _)) = accepted else { continue };
+                                let line = current_status_json();
+                                if let Err(e) = stream.write_all(line.as_bytes()).await {
+                                    debug!("Status connection dropped mid-write: {}", e);
+                                }
+                                let _ = stream.shutdown().await;
+                            }
+                        }
+                    }
+                });
+            })
+            .context("Failed to spawn status server thread")?;
+
+        ENABLED.store(true, Ordering::Relaxed);
+        debug!("Status server listening on {}", socket_path.display());
+        Ok(Self {
+            socket_path: socket_path.to_path_buf(),
+            cancel,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        ENABLED.store(false, Ordering::Relaxed);
+        self.cancel.cancel();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        if let Err(e) = fs::remove_file(&self.socket_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove status socket {}: {}", self.socket_path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    fn query(socket_path: &Path) -> serde_json::Value {
+        let mut stream = std::os::unix::net::UnixStream::connect(socket_path).unwrap();
+        let mut line = String::new();
+        stream.read_to_string(&mut line).unwrap();
+        serde_json::from_str(line.trim_end()).unwrap()
+    }
+
+    #[test]
+    fn test_status_socket_reports_running_then_final_result() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket_path = temp_dir.path().join("restore-status.sock");
+        let server = StatusServer::start(&socket_path).unwrap();
+
+        // Mid-restore: the operation has not finished, so a connection
+        // sees the running state with the progress fields
+        crate::heartbeat::beat("restore", 3);
+        let status = query(&socket_path);
+        assert_eq!(status["state"], "running");
+        assert!(status.get("phase").is_some());
+        assert!(status.get("files_done").is_some());
+        assert!(status.get("result").is_none());
+
+        // Access control is the socket file's mode
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&socket_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // After completion: the final envelope rides along
+        publish_final(r#"{"tool":"session-restore","success":true}"#.to_string());
+        let status = query(&socket_path);
+        assert_eq!(status["state"], "finished");
+        assert_eq!(status["result"]["success"], true);
+
+        // Dropping the server removes the socket
+        drop(server);
+        assert!(!socket_path.exists());
+    }
+}
@@ -0,0 +1,358 @@
+//! Transfer strategy selection.
+//!
+//! The transfer path historically picked rsync whenever the binary was
+//! present and fell back to tar or the native engine otherwise. On mixed
+//! workloads (thousands of small files vs a few large ones) that static
+//! choice is often wrong. This module adds an auto-tuning mode: copy a
+//! small sample of the source into a scratch directory once per available
+//! strategy, time each run, and pick the fastest for the full transfer.
+//! The decision and the sample timings are recorded on the transfer
+//! result. Sampling only ever writes into its own scratch directory,
+//! never the real destination, and removes the scratch output when done.
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn, debug};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// Cap on the number of files in the measurement sample
+pub const SAMPLE_MAX_FILES: usize = 200;
+
+/// Cap on the total bytes in the measurement sample
+pub const SAMPLE_MAX_BYTES: u64 = 200 * 1024 * 1024;
+
+/// A transfer backend the backup can run with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStrategy {
+    Rsync,
+    Tar,
+    Native,
+}
+
+impl TransferStrategy {
+    /// True when the strategy can run on this host
+    pub fn available(&self) -> bool {
+        match self {
+            TransferStrategy::Rsync => which::which("rsync").is_ok(),
+            TransferStrategy::Tar => which::which("tar").is_ok(),
+            TransferStrategy::Native => true,
+        }
+    }
+}
+
+impl fmt::Display for TransferStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferStrategy::Rsync => write!(f, "rsync"),
+            TransferStrategy::Tar => write!(f, "tar"),
+            TransferStrategy::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// How the caller wants the strategy chosen: measured on a sample, or
+/// pinned to one backend
+#[derive(Debug, Clone, Copy)]
+pub enum StrategyChoice {
+    Auto,
+    Pinned(TransferStrategy),
+}
+
+/// One timed sample run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleTiming {
+    pub strategy: TransferStrategy,
+    pub duration: Duration,
+}
+
+/// The outcome of strategy selection, recorded on the transfer result so
+/// the report shows why a backend was used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyDecision {
+    pub strategy: TransferStrategy,
+    /// True when the strategy was pinned on the command line rather than measured
+    pub pinned: bool,
+    /// Files in the measurement sample (0 when pinned)
+    pub sample_files: usize,
+    /// Total bytes in the measurement sample
+    pub sample_bytes: u64,
+    /// Per-strategy sample timings; empty when measurement was skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample_timings: Vec<SampleTiming>,
+}
+
+impl StrategyDecision {
+    /// A decision forced by --transfer-strategy, with no measurement
+    pub fn pinned(strategy: TransferStrategy) -> Self {
+        StrategyDecision {
+            strategy,
+            pinned: true,
+            sample_files: 0,
+            sample_bytes: 0,
+            sample_timings: Vec::new(),
+        }
+    }
+}
+
+/// Pick the fastest available strategy by timing each one on a small
+/// sample of the source, copied into a scratch directory. Strategies that
+/// cannot honor the exclusion set (the tar pipeline) are not candidates
+/// when exclusions are present.
+pub fn auto_select(source: &Path, excluded_paths: &HashSet<PathBuf>) -> Result<StrategyDecision> {
+    let candidates: Vec<TransferStrategy> = [
+        TransferStrategy::Rsync,
+        TransferStrategy::Tar,
+        TransferStrategy::Native,
+    ]
+    .iter()
+    .copied()
+    .filter(|strategy| strategy.available())
+    .filter(|strategy| *strategy != TransferStrategy::Tar || excluded_paths.is_empty())
+    .collect();
+
+    let (files, sample_bytes) = collect_sample(source, excluded_paths);
+
+    if files.is_empty() || candidates.len() <= 1 {
+        let strategy = candidates.first().copied().unwrap_or(TransferStrategy::Native);
+        info!(
+            "Strategy measurement skipped ({}): using {}",
+            if files.is_empty() { "empty sample" } else { "single candidate" },
+            strategy
+        );
+        return Ok(StrategyDecision {
+            strategy,
+            pinned: false,
+            sample_files: files.len(),
+            sample_bytes,
+            sample_timings: Vec::new(),
+        });
+    }
+
+    // Sampling writes only into this scratch directory, never the real
+    // destination; dropping it removes everything the runs produced
+    let scratch = tempfile::Builder::new()
+        .prefix("transfer-strategy-")
+        .tempdir()
+        .context("Failed to create strategy sampling scratch directory")?;
+
+    // One relative path per line, shared by rsync --files-from and tar -T
+    let list_file = scratch.path().join("sample-files.txt");
+    let mut listing = String::new();
+    for relative in &files {
+        listing.push_str(&relative.to_string_lossy());
+        listing.push('\n');
+    }
+    fs::write(&list_file, listing).context("Failed to write strategy sample file list")?;
+
+    // Read the sample once up front so the first measured strategy does
+    // not pay the cold-cache penalty alone
+    for relative in &files {
+        if let Ok(mut file) = fs::File::open(source.join(relative)) {
+            let _ = io::copy(&mut file, &mut io::sink());
+        }
+    }
+
+    info!(
+        "Measuring {} transfer strategies on a sample of {} files ({} bytes)",
+        candidates.len(), files.len(), sample_bytes
+    );
+
+    let mut sample_timings = Vec::new();
+    for candidate in candidates {
+        match time_strategy(candidate, source, &files, &list_file, scratch.path()) {
+            Ok(duration) => {
+                info!("  {}: {:?}", candidate, duration);
+                sample_timings.push(SampleTiming { strategy: candidate, duration });
+            }
+            Err(e) => {
+                warn!("Strategy {} failed on the sample, excluding it: {}", candidate, e);
+            }
+        }
+    }
+
+    let strategy = sample_timings
+        .iter()
+        .min_by_key(|timing| timing.duration)
+        .map(|timing| timing.strategy)
+        .unwrap_or(TransferStrategy::Native);
+    info!("Selected transfer strategy: {}", strategy);
+
+    Ok(StrategyDecision {
+        strategy,
+        pinned: false,
+        sample_files: files.len(),
+        sample_bytes,
+        sample_timings,
+    })
+}
+
+/// Collect up to [`SAMPLE_MAX_FILES`] / [`SAMPLE_MAX_BYTES`] of regular
+/// files under the source, skipping excluded (mounted) subtrees and this
+/// tool's own artifacts
+fn collect_sample(source: &Path, excluded_paths: &HashSet<PathBuf>) -> (Vec<PathBuf>, u64) {
+    let mut files = Vec::new();
+    let mut bytes = 0u64;
+
+    let walker = WalkDir::new(source)
+        .into_iter()
+        .filter_entry(|entry| !crate::is_path_mounted(entry.path(), excluded_paths));
+    for entry in walker {
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if crate::is_internal_artifact(entry.file_name()) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        let Ok(relative) = entry.path().strip_prefix(source) else { continue };
+        files.push(relative.to_path_buf());
+        bytes += metadata.len();
+        if files.len() >= SAMPLE_MAX_FILES || bytes >= SAMPLE_MAX_BYTES {
+            break;
+        }
+    }
+
+    (files, bytes)
+}
+
+/// Copy the sample into a per-strategy directory under the scratch area
+/// and time it; the output is removed before returning
+fn time_strategy(
+    strategy: TransferStrategy,
+    source: &Path,
+    files: &[PathBuf],
+    list_file: &Path,
+    scratch: &Path,
+) -> Result<Duration> {
+    let destination = scratch.join(strategy.to_string());
+    fs::create_dir_all(&destination)
+        .with_context(|| format!("Failed to create sample destination: {}", destination.display()))?;
+
+    let started = Instant::now();
+    match strategy {
+        TransferStrategy::Rsync => {
+            let status = Command::new("rsync")
+                .arg("-a")
+                .arg(format!("--files-from={}", list_file.display()))
+                .arg(format!("{}/", source.display()))
+                .arg(format!("{}/", destination.display()))
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .context("Failed to run rsync on the sample")?;
+            if !status.success() {
+                bail!("rsync sample run exited with {:?}", status.code());
+            }
+        }
+        TransferStrategy::Tar => {
+            let mut source_cmd = Command::new("tar")
+                .arg("-cf")
+                .arg("-")
+                .arg("-C")
+                .arg(source)
+                .arg("-T")
+                .arg(list_file)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .context("Failed to start tar source for the sample")?;
+            let source_stdout = source_cmd.stdout.take()
+                .ok_or_else(|| anyhow::anyhow!("Failed to get stdout from sample tar source"))?;
+            let status = Command::new("tar")
+                .arg("-xf")
+                .arg("-")
+                .arg("-C")
+                .arg(&destination)
+                .stdin(source_stdout)
+                .stderr(Stdio::null())
+                .status()
+                .context("Failed to run tar extraction for the sample")?;
+            let source_status = source_cmd.wait()
+                .context("Failed to wait for sample tar source")?;
+            if !source_status.success() || !status.success() {
+                bail!(
+                    "tar sample run failed (source: {:?}, target: {:?})",
+                    source_status.code(), status.code()
+                );
+            }
+        }
+        TransferStrategy::Native => {
+            for relative in files {
+                let target = destination.join(relative);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create sample parent: {}", parent.display()))?;
+                }
+                fs::copy(source.join(relative), &target)
+                    .with_context(|| format!("Failed to copy sample file: {}", relative.display()))?;
+            }
+        }
+    }
+    let elapsed = started.elapsed();
+
+    if let Err(e) = fs::remove_dir_all(&destination) {
+        debug!("Failed to remove sample destination {}: {}", destination.display(), e);
+    }
+
+    Ok(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_auto_select_measures_candidates_and_cleans_up() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.txt"), b"alpha").unwrap();
+        fs::create_dir_all(source.path().join("nested")).unwrap();
+        fs::write(source.path().join("nested").join("b.txt"), b"beta").unwrap();
+        // Internal artifacts never enter the sample
+        fs::write(source.path().join("state.backup_meta"), b"meta").unwrap();
+
+        let decision = auto_select(source.path(), &HashSet::new()).unwrap();
+
+        assert!(!decision.pinned);
+        assert_eq!(decision.sample_files, 2);
+        assert_eq!(decision.sample_bytes, 9);
+        assert!(decision.strategy.available());
+        // With more than one candidate every timing maps to a candidate;
+        // with exactly one, measurement is skipped entirely
+        for timing in &decision.sample_timings {
+            assert!(timing.strategy.available());
+        }
+
+        // The sampling scratch directory is removed when selection finishes
+        let leftovers: Vec<_> = fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("transfer-strategy-"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_tar_is_not_a_candidate_with_exclusions() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("a.txt"), b"alpha").unwrap();
+
+        let mut excluded = HashSet::new();
+        excluded.insert(PathBuf::from("/proc"));
+        let decision = auto_select(source.path(), &excluded).unwrap();
+
+        assert_ne!(decision.strategy, TransferStrategy::Tar);
+        for timing in &decision.sample_timings {
+            assert_ne!(timing.strategy, TransferStrategy::Tar);
+        }
+    }
+}
@@ -224,7 +224,7 @@ fn find_current_session(
                 .with_context(|| format!("Invalid created_at timestamp: {}", mapping.created_at))?
                 .with_timezone(&Utc);
 
-            if latest_time.map_or(true, |t| created_at > t) {
+            if latest_time.is_none_or(|t| created_at > t) {
                 latest_time = Some(created_at);
                 best_match = Some((path_key, mapping));
             }
@@ -290,7 +290,7 @@ fn find_available_sessions(
     }
 
     // Sort by modification time (newest first)
-    sessions.sort_by(|a, b| b.mod_time.cmp(&a.mod_time));
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.mod_time));
 
     Ok(sessions)
 }